@@ -3,12 +3,39 @@
 use super::metadata::{build_chapter_info, create_ffmpeg_metadata};
 use crate::bootstrap::ffmpeg as bootstrap_ffmpeg;
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 use std::process::Command;
 use tempfile::TempDir;
 
+/// Default AAC bitrate of the assembled M4B (kbps).
+pub const AAC_BITRATE_KBPS: u32 = 128;
+
+/// Frequency of the tone used to mark gaps (Hz).
+const GAP_TONE_FREQUENCY: u32 = 440;
+
+/// What to insert in place of chunks that have no audio.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum GapFill {
+    /// Insert silence.
+    #[default]
+    Silence,
+    /// Insert a quiet sine tone so gaps are audible when listening back.
+    Tone,
+}
+
+impl std::fmt::Display for GapFill {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GapFill::Silence => write!(f, "silence"),
+            GapFill::Tone => write!(f, "tone"),
+        }
+    }
+}
+
 /// Get the FFmpeg command, preferring bootstrapped version.
-fn ffmpeg_command() -> Command {
+pub(super) fn ffmpeg_command() -> Command {
     if let Ok(path) = bootstrap_ffmpeg::get_ffmpeg_executable() {
         if path.exists() {
             return Command::new(path);
@@ -58,6 +85,83 @@ pub fn get_audio_duration_ms(audio_path: &Path) -> Result<u64> {
     Ok((duration_secs * 1000.0) as u64)
 }
 
+/// Get the (sample_rate, channels) of the first audio stream using ffprobe.
+pub fn get_audio_format(audio_path: &Path) -> Result<(u32, u32)> {
+    let output = ffprobe_command()
+        .args([
+            "-v",
+            "quiet",
+            "-select_streams",
+            "a:0",
+            "-show_entries",
+            "stream=sample_rate,channels",
+            "-of",
+            "default=noprint_wrappers=1:nokey=1",
+        ])
+        .arg(audio_path)
+        .output()
+        .context("Failed to run ffprobe")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("ffprobe failed: {}", stderr);
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut values = stdout.lines().map(|l| l.trim());
+    let sample_rate: u32 = values
+        .next()
+        .unwrap_or_default()
+        .parse()
+        .context("Failed to parse sample rate")?;
+    let channels: u32 = values
+        .next()
+        .unwrap_or_default()
+        .parse()
+        .context("Failed to parse channel count")?;
+
+    Ok((sample_rate, channels))
+}
+
+/// Generate a WAV file to stand in for a missing chunk.
+///
+/// The output uses 16-bit PCM at the given sample rate and channel count so it
+/// can be concatenated losslessly with the synthesized chunks.
+pub fn create_gap_audio(
+    output_path: &Path,
+    fill: GapFill,
+    duration_ms: u64,
+    sample_rate: u32,
+    channels: u32,
+) -> Result<()> {
+    let duration_secs = format!("{:.3}", duration_ms as f64 / 1000.0);
+    let source = match fill {
+        GapFill::Silence => format!(
+            "anullsrc=r={}:cl={}",
+            sample_rate,
+            if channels == 1 { "mono" } else { "stereo" }
+        ),
+        GapFill::Tone => format!(
+            "sine=frequency={}:sample_rate={},volume=0.2",
+            GAP_TONE_FREQUENCY, sample_rate
+        ),
+    };
+
+    let output = ffmpeg_command()
+        .args(["-y", "-f", "lavfi", "-i", &source, "-t", &duration_secs])
+        .args(["-ac", &channels.to_string(), "-c:a", "pcm_s16le"])
+        .arg(output_path)
+        .output()
+        .context("Failed to run ffmpeg gap generation")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("ffmpeg gap generation failed: {}", stderr);
+    }
+
+    Ok(())
+}
+
 /// Concatenate multiple audio files into one.
 ///
 /// Uses FFmpeg's concat demuxer for lossless concatenation of same-format files.
@@ -109,6 +213,7 @@ pub fn concatenate_audio_files(audio_files: &[&Path], output_path: &Path) -> Res
 /// * `title` - Book title
 /// * `author` - Book author
 /// * `cover_image` - Optional path to cover image
+/// * `bitrate_kbps` - AAC bitrate of the output
 pub fn assemble_m4b(
     all_audio_files: &[&Path],
     chapter_boundaries: &[(String, usize)],
@@ -116,6 +221,7 @@ pub fn assemble_m4b(
     title: &str,
     author: &str,
     cover_image: Option<&Path>,
+    bitrate_kbps: u32,
 ) -> Result<()> {
     if all_audio_files.is_empty() {
         anyhow::bail!("No audio files provided");
@@ -169,17 +275,10 @@ pub fn assemble_m4b(
     }
 
     // Add metadata mapping and encoding settings
-    cmd.args([
-        "-map_metadata",
-        "1",
-        "-c:a",
-        "aac",
-        "-b:a",
-        "128k",
-        "-f",
-        "mp4",
-    ])
-    .arg(output_path);
+    cmd.args(["-map_metadata", "1", "-c:a", "aac", "-b:a"])
+        .arg(format!("{}k", bitrate_kbps))
+        .args(["-f", "mp4"])
+        .arg(output_path);
 
     let output = cmd.output().context("Failed to run ffmpeg M4B creation")?;
 
@@ -227,6 +326,18 @@ mod tests {
         let _ = is_ffprobe_available();
     }
 
+    #[test]
+    fn test_gap_fill_serde() {
+        #[derive(Deserialize)]
+        struct Wrapper {
+            fill: GapFill,
+        }
+        let parsed: Wrapper = toml::from_str("fill = \"tone\"").unwrap();
+        assert_eq!(parsed.fill, GapFill::Tone);
+        assert_eq!(GapFill::default(), GapFill::Silence);
+        assert_eq!(GapFill::Tone.to_string(), "tone");
+    }
+
     // Note: Full integration tests for audio assembly would require actual audio files
     // and FFmpeg to be installed. These are better suited for integration tests.
 }
@@ -2,5 +2,8 @@
 
 pub mod assembler;
 mod metadata;
+pub mod voice;
+pub mod wav;
 
-pub use assembler::assemble_m4b;
+pub use assembler::{assemble_m4b, GapFill};
+pub use wav::validate_wav;
@@ -0,0 +1,390 @@
+//! Preparing voice reference clips for cloning.
+//!
+//! Chatterbox conditions on a few seconds of speech. Clips from a recorder
+//! or a ripped audiobook come in any format, with silence at the ends, long
+//! stretches the model never looks at and levels all over the place, so
+//! they're converted into what the model expects: mono 16-bit WAV at its
+//! sample rate, cut to the span of speech (or, for long clips, the best
+//! [`MAX_DURATION_SECS`] of it) and loudness-normalized. Clipping and
+//! background noise can't be fixed here, so they're reported as warnings.
+
+use super::assembler::ffmpeg_command;
+use super::wav::{read_samples, read_wav_info};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tempfile::TempDir;
+
+/// Sample rate Chatterbox works at (Hz).
+pub const SAMPLE_RATE: u32 = 24000;
+
+/// Shortest clip that clones reliably (seconds).
+pub const MIN_DURATION_SECS: f32 = 5.0;
+
+/// Longest clip kept (seconds). The model conditions on at most the first
+/// ten seconds, so longer clips are trimmed to their best stretch instead.
+pub const MAX_DURATION_SECS: f32 = 10.0;
+
+/// Leeway for resampling when checking a prepared clip's length.
+const DURATION_SLACK_MS: u64 = 250;
+
+/// Length of the frames levels are measured over (milliseconds).
+const FRAME_MS: usize = 50;
+
+/// Frames quieter than this are never speech (dBFS).
+const SILENCE_THRESHOLD_DB: f32 = -50.0;
+
+/// How far above the noise floor a frame must be to count as speech (dB).
+const SPEECH_MARGIN_DB: f32 = 10.0;
+
+/// Frames within this much of the loud frames always count as speech, for
+/// clips with few pauses or a lot of noise (dB).
+const SPEECH_RANGE_DB: f32 = 20.0;
+
+/// Silence left before and after the speech (frames).
+const PAD_FRAMES: usize = 4;
+
+/// Sample level counted as clipped.
+const CLIP_LEVEL: f32 = 0.999;
+
+/// Share of clipped samples worth a warning.
+const MAX_CLIPPED_RATIO: f32 = 0.001;
+
+/// Speech-to-noise ratio below which the clone picks up the noise (dB).
+const MIN_SNR_DB: f32 = 20.0;
+
+/// Integrated loudness clips are normalized to (LUFS).
+const TARGET_LOUDNESS: f32 = -20.0;
+
+/// Part of a source clip to use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Segment {
+    /// Offset into the source (seconds).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<f32>,
+    /// Length to take from `start` (seconds).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub length: Option<f32>,
+}
+
+impl Segment {
+    /// Whether the whole source is used.
+    pub fn is_whole(&self) -> bool {
+        self.start.is_none() && self.length.is_none()
+    }
+}
+
+/// Result of preparing a clip.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prepared {
+    /// Length of the prepared clip.
+    pub duration_ms: u64,
+    /// Where the kept stretch starts in the source (or `segment` of it), if
+    /// the clip was too long and had to be trimmed.
+    pub trimmed_from_ms: Option<u64>,
+    /// Problems with the recording itself.
+    pub warnings: Vec<String>,
+}
+
+/// Convert `input` (any format FFmpeg reads) into a prepared reference clip.
+///
+/// Fails if it has less than [`MIN_DURATION_SECS`] of speech.
+pub fn prepare(input: &Path, output: &Path, segment: Segment) -> Result<Prepared> {
+    let temp_dir = TempDir::new()?;
+    let decoded = temp_dir.path().join("decoded.wav");
+    decode(input, &decoded, segment)?;
+
+    let (_, samples) = read_samples(&decoded)?;
+    let analysis = Analysis::new(&samples, SAMPLE_RATE);
+    let (start, frames) = analysis.keep()?;
+
+    let mut cmd = ffmpeg_command();
+    let result = cmd
+        .arg("-y")
+        .args(["-ss", &format_secs(start * FRAME_MS)])
+        .arg("-i")
+        .arg(&decoded)
+        .args(["-t", &format_secs(frames * FRAME_MS)])
+        .args(["-af", &format!("loudnorm=I={}:TP=-1.5:LRA=11", TARGET_LOUDNESS)])
+        .args(["-ar", &SAMPLE_RATE.to_string()])
+        .args(["-map_metadata", "-1", "-fflags", "+bitexact", "-flags:a", "+bitexact"])
+        .args(["-c:a", "pcm_s16le"])
+        .arg(output)
+        .output()
+        .context("Failed to run ffmpeg")?;
+    if !result.status.success() {
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        anyhow::bail!("ffmpeg couldn't normalize {}: {}", input.display(), stderr.trim());
+    }
+
+    let trimmed = frames < analysis.speech_frames();
+    Ok(Prepared {
+        duration_ms: read_wav_info(output)?.duration_ms(),
+        trimmed_from_ms: trimmed.then_some((start * FRAME_MS) as u64),
+        warnings: analysis.warnings(),
+    })
+}
+
+/// Check that `path` is a prepared clip, as [`prepare`] writes them.
+pub fn check_prepared(path: &Path) -> Result<()> {
+    let info = read_wav_info(path)?;
+    if info.channels != 1 || info.sample_rate != SAMPLE_RATE || info.bits_per_sample != 16 {
+        anyhow::bail!(
+            "{} is {}-bit, {} channel(s) at {} Hz, not a prepared voice clip (16-bit mono at {} Hz)",
+            path.display(),
+            info.bits_per_sample,
+            info.channels,
+            info.sample_rate,
+            SAMPLE_RATE
+        );
+    }
+
+    let duration_ms = info.duration_ms();
+    let min_ms = (MIN_DURATION_SECS * 1000.0) as u64;
+    let max_ms = (MAX_DURATION_SECS * 1000.0) as u64 + DURATION_SLACK_MS;
+    if !(min_ms..=max_ms).contains(&duration_ms) {
+        anyhow::bail!(
+            "{} is {:.1}s long, not {}-{}s",
+            path.display(),
+            duration_ms as f32 / 1000.0,
+            MIN_DURATION_SECS,
+            MAX_DURATION_SECS
+        );
+    }
+    Ok(())
+}
+
+/// Decode `segment` of `input` to mono 16-bit WAV at the model's sample rate.
+fn decode(input: &Path, output: &Path, segment: Segment) -> Result<()> {
+    let mut cmd = ffmpeg_command();
+    cmd.arg("-y");
+    if let Some(start) = segment.start {
+        cmd.args(["-ss", &start.to_string()]);
+    }
+    cmd.arg("-i").arg(input);
+    if let Some(length) = segment.length {
+        cmd.args(["-t", &length.to_string()]);
+    }
+
+    let result = cmd
+        .args(["-ac", "1", "-ar", &SAMPLE_RATE.to_string(), "-c:a", "pcm_s16le"])
+        .arg(output)
+        .output()
+        .context("Failed to run ffmpeg")?;
+    if !result.status.success() {
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        anyhow::bail!("ffmpeg couldn't read {}: {}", input.display(), stderr.trim());
+    }
+    Ok(())
+}
+
+fn format_secs(ms: usize) -> String {
+    format!("{:.3}", ms as f64 / 1000.0)
+}
+
+/// Levels of a decoded clip, frame by frame.
+#[derive(Debug)]
+struct Analysis {
+    /// RMS level of each frame (dBFS).
+    levels: Vec<f32>,
+    /// Whether each frame has clipped samples.
+    clipped: Vec<bool>,
+    clipped_ratio: f32,
+    /// Level of the quietest frames, taken as the background.
+    noise_floor: f32,
+    /// Typical level of the loud frames.
+    speech_level: f32,
+}
+
+impl Analysis {
+    fn new(samples: &[f32], sample_rate: u32) -> Self {
+        let frame_len = (sample_rate as usize * FRAME_MS / 1000).max(1);
+        let frames = samples.chunks(frame_len);
+        let levels: Vec<f32> = frames
+            .clone()
+            .map(|frame| {
+                let rms = (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt();
+                (20.0 * rms.log10()).max(-100.0)
+            })
+            .collect();
+        let clipped = frames
+            .map(|frame| frame.iter().any(|s| s.abs() >= CLIP_LEVEL))
+            .collect();
+
+        let clipped_samples = samples.iter().filter(|s| s.abs() >= CLIP_LEVEL).count();
+        let mut sorted = levels.clone();
+        sorted.sort_by(f32::total_cmp);
+        let percentile = |p: usize| sorted.get(sorted.len() * p / 100).copied().unwrap_or(-100.0);
+
+        Analysis {
+            clipped,
+            clipped_ratio: clipped_samples as f32 / samples.len().max(1) as f32,
+            noise_floor: percentile(5),
+            speech_level: percentile(90),
+            levels,
+        }
+    }
+
+    fn is_speech(&self, frame: usize) -> bool {
+        let threshold = (self.noise_floor + SPEECH_MARGIN_DB).min(self.speech_level - SPEECH_RANGE_DB);
+        self.levels[frame] > threshold.max(SILENCE_THRESHOLD_DB)
+    }
+
+    /// First and last speech frame, padded with a little of the silence around them.
+    fn speech_span(&self) -> Option<(usize, usize)> {
+        let first = (0..self.levels.len()).find(|&i| self.is_speech(i))?;
+        let last = (0..self.levels.len()).rev().find(|&i| self.is_speech(i))?;
+        Some((
+            first.saturating_sub(PAD_FRAMES),
+            (last + 1 + PAD_FRAMES).min(self.levels.len()),
+        ))
+    }
+
+    fn speech_frames(&self) -> usize {
+        self.speech_span().map_or(0, |(start, end)| end - start)
+    }
+
+    /// Start and length (in frames) of the stretch to keep.
+    fn keep(&self) -> Result<(usize, usize)> {
+        let (start, end) = self.speech_span().unwrap_or((0, 0));
+        let min_frames = (MIN_DURATION_SECS * 1000.0) as usize / FRAME_MS;
+        let max_frames = (MAX_DURATION_SECS * 1000.0) as usize / FRAME_MS;
+
+        if end - start < min_frames {
+            anyhow::bail!(
+                "Only {:.1}s of speech found; at least {}s is needed for a good clone{}",
+                ((end - start) * FRAME_MS) as f32 / 1000.0,
+                MIN_DURATION_SECS,
+                if self.snr() < MIN_SNR_DB { " (the recording may be too noisy to tell speech apart)" } else { "" }
+            );
+        }
+        if end - start <= max_frames {
+            return Ok((start, end - start));
+        }
+        Ok((self.best_window(start, end, max_frames), max_frames))
+    }
+
+    /// Start of the `len`-frame window in `start..end` with the most clean
+    /// speech: speech frames count for it, clipped frames against it.
+    fn best_window(&self, start: usize, end: usize, len: usize) -> usize {
+        let score = |i: usize| -> i64 {
+            if self.clipped[i] {
+                -1
+            } else {
+                self.is_speech(i) as i64
+            }
+        };
+
+        let mut total: i64 = (start..start + len).map(score).sum();
+        let (mut best, mut best_total) = (start, total);
+        for i in start + 1..=end - len {
+            total += score(i + len - 1) - score(i - 1);
+            if total > best_total {
+                best = i;
+                best_total = total;
+            }
+        }
+        best
+    }
+
+    /// Estimated speech-to-noise ratio (dB).
+    fn snr(&self) -> f32 {
+        self.speech_level - self.noise_floor
+    }
+
+    /// Problems with the recording that preparing it can't fix.
+    fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.clipped_ratio > MAX_CLIPPED_RATIO {
+            warnings.push(format!(
+                "{:.1}% of the samples are clipped, so the clone may sound distorted; record at a lower level",
+                self.clipped_ratio * 100.0
+            ));
+        }
+        let snr = self.snr();
+        if snr < MIN_SNR_DB {
+            warnings.push(format!(
+                "background noise is only {:.0} dB below the speech, so the clone may pick it up; use a cleaner recording",
+                snr
+            ));
+        }
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 1000;
+
+    /// `secs` of a tone at `amplitude` with `noise` added, at [`RATE`].
+    fn tone(secs: f32, amplitude: f32, noise: f32) -> Vec<f32> {
+        (0..(secs * RATE as f32) as usize)
+            .map(|i| {
+                let wobble = if i % 2 == 0 { noise } else { -noise };
+                (i as f32 * 0.3).sin() * amplitude + wobble
+            })
+            .collect()
+    }
+
+    fn clip(parts: &[Vec<f32>]) -> Vec<f32> {
+        parts.concat()
+    }
+
+    #[test]
+    fn test_keep_trims_silence() {
+        let samples = clip(&[tone(2.0, 0.0, 0.0), tone(6.0, 0.5, 0.0), tone(3.0, 0.0, 0.0)]);
+        let analysis = Analysis::new(&samples, RATE);
+        let (start, frames) = analysis.keep().unwrap();
+        assert_eq!(start, 40 - PAD_FRAMES);
+        assert_eq!(frames, 120 + 2 * PAD_FRAMES);
+        assert!(analysis.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_keep_rejects_short_speech() {
+        let samples = clip(&[tone(3.0, 0.5, 0.0), tone(10.0, 0.0, 0.0)]);
+        let err = Analysis::new(&samples, RATE).keep().unwrap_err();
+        assert!(err.to_string().contains("at least 5s"));
+    }
+
+    #[test]
+    fn test_keep_picks_clean_stretch_of_long_clip() {
+        // 8s with long pauses, then 12s of clipped speech, then 10s of clean speech
+        let mut gappy = Vec::new();
+        for _ in 0..4 {
+            gappy.extend(tone(1.0, 0.5, 0.0));
+            gappy.extend(tone(1.0, 0.0, 0.0));
+        }
+        let samples = clip(&[gappy, tone(12.0, 2.0, 0.0), tone(10.0, 0.5, 0.0)]);
+        let samples: Vec<f32> = samples.iter().map(|s| s.clamp(-1.0, 1.0)).collect();
+        let analysis = Analysis::new(&samples, RATE);
+
+        let (start, frames) = analysis.keep().unwrap();
+        assert_eq!(frames, 200);
+        assert_eq!(start, 400);
+        assert!(analysis.warnings()[0].contains("clipped"));
+    }
+
+    #[test]
+    fn test_noisy_clip_warns() {
+        let samples = clip(&[tone(1.0, 0.0, 0.05), tone(6.0, 0.1, 0.05), tone(1.0, 0.0, 0.05)]);
+        let warnings = Analysis::new(&samples, RATE).warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("background noise"));
+    }
+
+    #[test]
+    fn test_check_prepared() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("voice.wav");
+
+        std::fs::write(&path, crate::audio::wav::test_wav(&[0; 24000 * 6])).unwrap();
+        assert!(check_prepared(&path).is_ok());
+        std::fs::write(&path, crate::audio::wav::test_wav(&[0; 24000 * 3])).unwrap();
+        assert!(check_prepared(&path).is_err());
+        std::fs::write(&path, crate::audio::wav::test_wav(&[0; 24000 * 11])).unwrap();
+        assert!(check_prepared(&path).is_err());
+    }
+}
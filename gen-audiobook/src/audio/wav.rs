@@ -0,0 +1,187 @@
+//! Minimal WAV header checks and 16-bit PCM reading.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// Check that a file is a complete, non-empty WAV.
+///
+/// Reads the RIFF chunks and fails if the format is missing or the data
+/// chunk is empty or runs past the end of the file, as it does when a write
+/// was cut short.
+pub fn validate_wav(path: &Path) -> Result<()> {
+    read_wav_info(path).map(|_| ())
+}
+
+/// Format and length of a WAV file's audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WavInfo {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub bits_per_sample: u16,
+    /// Size of the audio data in bytes.
+    pub data_len: usize,
+    /// Where the audio data starts in the file.
+    data_offset: usize,
+}
+
+impl WavInfo {
+    /// Length of the audio in milliseconds.
+    pub fn duration_ms(&self) -> u64 {
+        let bytes_per_second =
+            self.sample_rate as u64 * self.channels as u64 * (self.bits_per_sample as u64 / 8).max(1);
+        self.data_len as u64 * 1000 / bytes_per_second
+    }
+}
+
+/// Read the format of a WAV file, checking it the way [`validate_wav`] does.
+pub fn read_wav_info(path: &Path) -> Result<WavInfo> {
+    let data = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    parse_wav(&data).with_context(|| format!("Invalid WAV file {}", path.display()))
+}
+
+/// Read a 16-bit mono WAV file as samples in [-1, 1].
+pub fn read_samples(path: &Path) -> Result<(WavInfo, Vec<f32>)> {
+    let data = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let info = parse_wav(&data).with_context(|| format!("Invalid WAV file {}", path.display()))?;
+    if info.channels != 1 || info.bits_per_sample != 16 {
+        anyhow::bail!(
+            "{} is {}-bit with {} channels; expected 16-bit mono",
+            path.display(),
+            info.bits_per_sample,
+            info.channels
+        );
+    }
+
+    let samples = data[info.data_offset..info.data_offset + info.data_len]
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / i16::MAX as f32)
+        .collect();
+    Ok((info, samples))
+}
+
+fn parse_wav(data: &[u8]) -> Result<WavInfo> {
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        anyhow::bail!("not a RIFF/WAVE file");
+    }
+
+    let mut format = None;
+    let mut offset = 12;
+    while offset + 8 <= data.len() {
+        let id = &data[offset..offset + 4];
+        let size = u32::from_le_bytes([
+            data[offset + 4],
+            data[offset + 5],
+            data[offset + 6],
+            data[offset + 7],
+        ]) as usize;
+        let body = offset + 8;
+
+        match id {
+            b"fmt " => {
+                if size < 16 || body + size > data.len() {
+                    anyhow::bail!("truncated format chunk");
+                }
+                let channels = u16::from_le_bytes([data[body + 2], data[body + 3]]);
+                let sample_rate = u32::from_le_bytes([
+                    data[body + 4],
+                    data[body + 5],
+                    data[body + 6],
+                    data[body + 7],
+                ]);
+                let bits_per_sample = u16::from_le_bytes([data[body + 14], data[body + 15]]);
+                if channels == 0 || sample_rate == 0 {
+                    anyhow::bail!("invalid format: {} channels at {} Hz", channels, sample_rate);
+                }
+                format = Some((channels, sample_rate, bits_per_sample));
+            }
+            b"data" => {
+                let Some((channels, sample_rate, bits_per_sample)) = format else {
+                    anyhow::bail!("data chunk before format chunk");
+                };
+                if size == 0 {
+                    anyhow::bail!("no audio data");
+                }
+                if body + size > data.len() {
+                    anyhow::bail!(
+                        "truncated: {} of {} audio bytes present",
+                        data.len() - body,
+                        size
+                    );
+                }
+                return Ok(WavInfo {
+                    channels,
+                    sample_rate,
+                    bits_per_sample,
+                    data_len: size,
+                    data_offset: body,
+                });
+            }
+            _ => {}
+        }
+
+        // Chunks are padded to an even size
+        offset = body + size + (size & 1);
+    }
+
+    anyhow::bail!("no data chunk")
+}
+
+#[cfg(test)]
+pub(crate) fn test_wav(samples: &[i16]) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+    let mut wav = Vec::new();
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+    wav.extend_from_slice(&24000u32.to_le_bytes());
+    wav.extend_from_slice(&48000u32.to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_wav() {
+        let wav = test_wav(&[0, 100, -100, 0]);
+        assert!(parse_wav(&wav).is_ok());
+
+        // Cut short while writing
+        assert!(parse_wav(&wav[..wav.len() - 2]).is_err());
+        assert!(parse_wav(&wav[..20]).is_err());
+        // Header only
+        assert!(parse_wav(&test_wav(&[])).is_err());
+        assert!(parse_wav(b"audio for job").is_err());
+    }
+
+    #[test]
+    fn test_wav_info() {
+        let info = parse_wav(&test_wav(&[0; 36000])).unwrap();
+        assert_eq!(info.channels, 1);
+        assert_eq!(info.sample_rate, 24000);
+        assert_eq!(info.bits_per_sample, 16);
+        assert_eq!(info.duration_ms(), 1500);
+    }
+
+    #[test]
+    fn test_read_samples() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("clip.wav");
+        std::fs::write(&path, test_wav(&[0, i16::MAX, -i16::MAX])).unwrap();
+
+        let (info, samples) = read_samples(&path).unwrap();
+        assert_eq!(info.sample_rate, 24000);
+        assert_eq!(samples, vec![0.0, 1.0, -1.0]);
+    }
+}
@@ -0,0 +1,649 @@
+//! Offline setup from an exported bundle, or from a mirror serving one.
+//!
+//! `gen-audio setup export-bundle` packages everything the bootstrap would
+//! otherwise download: the Python and FFmpeg archives, a pip wheelhouse of
+//! the required packages, and optionally the Chatterbox weights.
+//! `gen-audio setup --from` installs from the tarball or its unpacked
+//! directory. The unpacked directory served over HTTP by any static file
+//! server (e.g. `python3 -m http.server`) works as a `mirror_url`.
+//!
+//! Layout:
+//!
+//! ```text
+//! bundle.json      contents, with the size and SHA-256 of each file
+//! python.tar.gz    archives, named as in manifest::archive_name
+//! ffmpeg.tar.xz
+//! requirements.lock  Python package lock the wheelhouse was built from
+//! wheels/          pip wheelhouse
+//! models.tar       Hugging Face hub cache entries (optional)
+//! ```
+
+use super::download::{download_file, part_path};
+use super::lock::{LockFile, LOCK_FILE};
+use super::manifest::{self, archive_name, Artifact, Component, COMPONENTS};
+use super::platform::Platform;
+use super::versions::{get_downloads_dir, get_hf_hub_dir, FFMPEG_VERSION, PYTHON_VERSION};
+use super::{chatterbox_model_dirs, progress, python};
+use crate::config::GenAudioConfig;
+use crate::coordinator::compute_file_sha256;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Name of the bundle's table of contents.
+pub const BUNDLE_MANIFEST: &str = "bundle.json";
+
+/// Directory holding the pip wheelhouse.
+pub const WHEELS_DIR: &str = "wheels";
+
+/// Archive of Hugging Face hub cache entries.
+pub const MODELS_ARCHIVE: &str = "models.tar";
+
+/// Environment variable that overrides the `mirror_url` setting.
+pub const MIRROR_ENV: &str = "GEN_AUDIO_MIRROR_URL";
+
+/// A file in a bundle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleFile {
+    /// Path relative to the bundle root.
+    pub file: String,
+    /// Size in bytes.
+    pub size: u64,
+    /// Lowercase hex SHA-256.
+    pub sha256: String,
+}
+
+impl BundleFile {
+    /// Describe a file already in the bundle directory.
+    fn describe(dir: &Path, file: &str) -> Result<Self> {
+        let path = dir.join(file);
+        Ok(Self {
+            file: file.to_string(),
+            size: std::fs::metadata(&path)?.len(),
+            sha256: compute_file_sha256(&path)?,
+        })
+    }
+
+    /// Expected size and hash, as a download of `url`.
+    fn artifact<'a>(&'a self, url: &'a str) -> Artifact<'a> {
+        Artifact {
+            url,
+            size: Some(self.size),
+            sha256: Some(&self.sha256),
+        }
+    }
+}
+
+/// Contents of a bundle (`bundle.json`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleManifest {
+    /// gen-audio version that exported the bundle.
+    pub gen_audio_version: String,
+    /// Platform the bundle is for (e.g., "Linux-x86_64").
+    pub platform: String,
+    /// When the bundle was exported.
+    pub created_at: DateTime<Utc>,
+    /// Archive for each component, by component name.
+    pub files: BTreeMap<String, BundleFile>,
+    /// File names in the wheelhouse.
+    #[serde(default)]
+    pub wheels: Vec<String>,
+    /// Chatterbox weights, if included.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub models: Option<BundleFile>,
+    /// Lockfile the wheelhouse was built from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lock: Option<BundleFile>,
+}
+
+impl BundleManifest {
+    /// Load `bundle.json` from a bundle directory.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(dir.join(BUNDLE_MANIFEST))
+            .with_context(|| format!("No {} in {}", BUNDLE_MANIFEST, dir.display()))?;
+        serde_json::from_str(&content).context("Failed to parse bundle.json")
+    }
+
+    /// Write `bundle.json` to a bundle directory.
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(dir.join(BUNDLE_MANIFEST), content).context("Failed to write bundle.json")
+    }
+
+    /// Fail unless the bundle was exported for `platform`.
+    pub fn check_platform(&self, platform: &Platform) -> Result<()> {
+        let expected = platform.to_version_string();
+        if self.platform != expected {
+            anyhow::bail!(
+                "Bundle is for {}, but this machine is {}. Export one on a {} machine.",
+                self.platform,
+                expected,
+                expected
+            );
+        }
+        Ok(())
+    }
+
+    /// The archive for a component.
+    fn file(&self, component: Component) -> Result<&BundleFile> {
+        self.files
+            .get(component.as_str())
+            .ok_or_else(|| anyhow::anyhow!("Bundle has no {} archive", component.as_str()))
+    }
+
+    /// What a component's file must match, as a download of `url`.
+    ///
+    /// The pinned manifest wins over the bundle's own record, so a bundle
+    /// can't bring in an archive this version of gen-audio doesn't trust.
+    fn artifact<'a>(&'a self, platform: &Platform, component: Component, url: &'a str) -> Result<Artifact<'a>> {
+        let file = self.file(component)?;
+        let pinned = manifest::artifact(platform, component).filter(|a| a.is_pinned());
+
+        Ok(match pinned {
+            Some(pinned) => Artifact { url, ..pinned },
+            None => file.artifact(url),
+        })
+    }
+}
+
+/// An archive ready to be installed.
+pub struct Fetched {
+    /// Where the archive is.
+    pub path: PathBuf,
+    /// Its SHA-256.
+    pub sha256: String,
+    /// Whether it was downloaded, and can be removed once installed.
+    downloaded: bool,
+}
+
+impl Fetched {
+    /// Remove the archive if it was downloaded for this install.
+    pub fn discard(self) {
+        if self.downloaded {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Where the bootstrap gets its files.
+pub enum Source {
+    /// The download sites in the manifest, and PyPI.
+    Upstream,
+    /// A mirror serving an unpacked bundle over HTTP.
+    Mirror { url: String, bundle: BundleManifest },
+    /// A bundle on disk.
+    Bundle {
+        dir: PathBuf,
+        bundle: BundleManifest,
+        /// Keeps an unpacked tarball around while installing.
+        _unpacked: Option<tempfile::TempDir>,
+    },
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Upstream => write!(f, "upstream download sites"),
+            Source::Mirror { url, .. } => write!(f, "mirror {}", url),
+            Source::Bundle { dir, .. } => write!(f, "bundle {}", dir.display()),
+        }
+    }
+}
+
+impl Source {
+    /// The mirror from `GEN_AUDIO_MIRROR_URL` or the `mirror_url` setting, or
+    /// upstream if neither is set.
+    pub async fn configured() -> Result<Self> {
+        let url = std::env::var(MIRROR_ENV)
+            .ok()
+            .filter(|url| !url.is_empty())
+            .or_else(|| GenAudioConfig::load().ok().and_then(|c| c.mirror_url));
+
+        match url {
+            Some(url) => Self::mirror(&url).await,
+            None => Ok(Source::Upstream),
+        }
+    }
+
+    /// Use a mirror, reading its `bundle.json`.
+    pub async fn mirror(url: &str) -> Result<Self> {
+        let url = url.trim_end_matches('/').to_string();
+        let manifest_url = format!("{}/{}", url, BUNDLE_MANIFEST);
+
+        let response = reqwest::get(&manifest_url)
+            .await
+            .with_context(|| format!("Failed to reach mirror {}", url))?;
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Mirror {} has no {} (HTTP {}). It should serve an unpacked 'gen-audio setup export-bundle'.",
+                url,
+                BUNDLE_MANIFEST,
+                response.status().as_u16()
+            );
+        }
+        let bundle: BundleManifest = serde_json::from_str(&response.text().await?)
+            .with_context(|| format!("Failed to parse {}", manifest_url))?;
+        bundle.check_platform(&Platform::detect()?)?;
+
+        Ok(Source::Mirror { url, bundle })
+    }
+
+    /// Use an exported bundle: a directory, or a tarball (optionally
+    /// gzipped) which is unpacked first.
+    pub fn open_bundle(path: &Path) -> Result<Self> {
+        let (dir, unpacked) = if path.is_dir() {
+            (path.to_path_buf(), None)
+        } else {
+            let downloads_dir = get_downloads_dir()?;
+            std::fs::create_dir_all(&downloads_dir)?;
+            let unpacked = tempfile::tempdir_in(&downloads_dir)?;
+            progress::message(format!("Unpacking {}...", path.display()));
+            unpack_tarball(path, unpacked.path())?;
+            (unpacked.path().to_path_buf(), Some(unpacked))
+        };
+
+        let bundle = BundleManifest::load(&dir)
+            .with_context(|| format!("{} is not a gen-audio bundle", path.display()))?;
+        bundle.check_platform(&Platform::detect()?)?;
+
+        Ok(Source::Bundle {
+            dir,
+            bundle,
+            _unpacked: unpacked,
+        })
+    }
+
+    /// Get a component's archive, downloading into `dir` unless it's in a
+    /// bundle on disk. Returns `None` if the platform doesn't use it.
+    pub async fn fetch(&self, platform: &Platform, component: Component, dir: &Path) -> Result<Option<Fetched>> {
+        let Some(upstream) = manifest::artifact(platform, component) else {
+            return Ok(None);
+        };
+        let description = match component {
+            Component::Python => format!("Downloading Python {}...", PYTHON_VERSION),
+            Component::Ffmpeg => format!("Downloading FFmpeg {}...", FFMPEG_VERSION),
+            Component::Ffprobe => "Downloading FFprobe...".to_string(),
+        };
+
+        let fetched = match self {
+            Source::Upstream => {
+                let path = dir.join(archive_name(platform, component));
+                let sha256 = download_file(&upstream, &path, &description).await?;
+                Fetched { path, sha256, downloaded: true }
+            }
+            Source::Mirror { url, bundle } => {
+                let file_url = format!("{}/{}", url, bundle.file(component)?.file);
+                let artifact = bundle.artifact(platform, component, &file_url)?;
+                let path = dir.join(archive_name(platform, component));
+                let sha256 = download_file(&artifact, &path, &description).await?;
+                Fetched { path, sha256, downloaded: true }
+            }
+            Source::Bundle { dir: bundle_dir, bundle, .. } => {
+                let file = &bundle.file(component)?.file;
+                let path = bundle_dir.join(file);
+                let artifact = bundle.artifact(platform, component, file)?;
+                let sha256 = verify_bundle_file(&path, &artifact)?;
+                Fetched { path, sha256, downloaded: false }
+            }
+        };
+
+        Ok(Some(fetched))
+    }
+
+    /// Where pip should find wheels, if not on PyPI.
+    pub fn find_links(&self) -> Option<String> {
+        match self {
+            Source::Upstream => None,
+            Source::Mirror { url, .. } => Some(format!("{}/{}/", url, WHEELS_DIR)),
+            Source::Bundle { dir, .. } => Some(dir.join(WHEELS_DIR).to_string_lossy().to_string()),
+        }
+    }
+
+    /// The lockfile to install Python packages from: the bundle's, so that
+    /// its wheelhouse has every locked package, or this machine's.
+    pub async fn lock(&self, platform: &Platform) -> Result<LockFile> {
+        let text = match self {
+            Source::Upstream => return LockFile::current(platform),
+            Source::Mirror { url, bundle } => {
+                let Some(lock) = &bundle.lock else {
+                    return LockFile::current(platform);
+                };
+                let lock_url = format!("{}/{}", url, lock.file);
+                let response = reqwest::get(&lock_url)
+                    .await
+                    .and_then(|r| r.error_for_status())
+                    .with_context(|| format!("Failed to download {}", lock_url))?;
+                let bytes = response.bytes().await?;
+                let sha256 = format!("{:x}", Sha256::digest(&bytes));
+                if sha256 != lock.sha256 {
+                    anyhow::bail!(
+                        "Checksum mismatch for {}: expected SHA-256 {}, got {}",
+                        lock_url,
+                        lock.sha256,
+                        sha256
+                    );
+                }
+                String::from_utf8(bytes.to_vec()).with_context(|| format!("{} is not text", lock_url))?
+            }
+            Source::Bundle { dir, bundle, .. } => {
+                let Some(lock) = &bundle.lock else {
+                    return LockFile::current(platform);
+                };
+                let path = dir.join(&lock.file);
+                verify_bundle_file(&path, &lock.artifact(&lock.file))?;
+                std::fs::read_to_string(&path)?
+            }
+        };
+        LockFile::parse(&text, &self.to_string())
+    }
+
+    /// Install the Chatterbox weights into the Hugging Face cache, if the
+    /// bundle has them. Returns whether anything was installed.
+    pub async fn install_models(&self) -> Result<bool> {
+        let fetched = match self {
+            Source::Upstream => return Ok(false),
+            Source::Mirror { url, bundle } => {
+                let Some(models) = &bundle.models else {
+                    return Ok(false);
+                };
+                let models_url = format!("{}/{}", url, models.file);
+                let path = get_downloads_dir()?.join(MODELS_ARCHIVE);
+                let sha256 = download_file(&models.artifact(&models_url), &path, "Downloading Chatterbox weights...").await?;
+                Fetched { path, sha256, downloaded: true }
+            }
+            Source::Bundle { dir, bundle, .. } => {
+                let Some(models) = &bundle.models else {
+                    return Ok(false);
+                };
+                let path = dir.join(&models.file);
+                let sha256 = verify_bundle_file(&path, &models.artifact(&models.file))?;
+                Fetched { path, sha256, downloaded: false }
+            }
+        };
+
+        let hub = get_hf_hub_dir().ok_or_else(|| anyhow::anyhow!("Could not determine the Hugging Face cache directory"))?;
+        std::fs::create_dir_all(&hub)?;
+        progress::detail(format!("Extracting Chatterbox weights to {}...", hub.display()));
+        let file = std::fs::File::open(&fetched.path)?;
+        tar::Archive::new(file)
+            .unpack(&hub)
+            .context("Failed to extract Chatterbox weights")?;
+        fetched.discard();
+
+        Ok(true)
+    }
+}
+
+/// Check a file in a bundle on disk against what's expected of it.
+fn verify_bundle_file(path: &Path, expected: &Artifact<'_>) -> Result<String> {
+    let size = std::fs::metadata(path)
+        .with_context(|| format!("{} is missing from the bundle", expected.url))?
+        .len();
+    if let Some(expected_size) = expected.size.filter(|&s| s != size) {
+        anyhow::bail!(
+            "{} in the bundle is {} bytes, expected {}. The bundle is incomplete or was modified.",
+            expected.url,
+            size,
+            expected_size
+        );
+    }
+
+    let sha256 = compute_file_sha256(path)?;
+    if let Some(expected_sha256) = expected.sha256.filter(|s| !sha256.eq_ignore_ascii_case(s)) {
+        anyhow::bail!(
+            "Checksum mismatch for {} in the bundle: expected SHA-256 {}, got {}",
+            expected.url,
+            expected_sha256,
+            sha256
+        );
+    }
+
+    Ok(sha256)
+}
+
+/// Default file name for an exported bundle.
+pub fn default_bundle_name(platform: &Platform) -> String {
+    format!(
+        "gen-audio-bundle-{}-{}.tar",
+        env!("CARGO_PKG_VERSION"),
+        platform.to_version_string().to_lowercase()
+    )
+}
+
+/// Export a bundle for this platform to `output`, an uncompressed tarball
+/// (most of it is already compressed).
+///
+/// Archives come from the configured source; the wheelhouse is built with
+/// the bootstrapped Python's pip, so `gen-audio setup` must have run.
+pub async fn export_bundle(output: &Path, include_models: bool) -> Result<BundleManifest> {
+    let platform = Platform::detect()?;
+    let source = Source::configured().await?;
+
+    // Stage next to the output, so large files aren't copied across disks
+    let parent = output
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let staging = tempfile::tempdir_in(parent)?;
+    let dir = staging.path();
+
+    let mut files = BTreeMap::new();
+    for component in COMPONENTS {
+        let Some(fetched) = source.fetch(&platform, component, dir).await? else {
+            continue;
+        };
+        let name = archive_name(&platform, component);
+        if fetched.path != dir.join(name) {
+            std::fs::copy(&fetched.path, dir.join(name))?;
+        }
+        files.insert(component.as_str().to_string(), BundleFile::describe(dir, name)?);
+    }
+
+    let lock = source.lock(&platform).await?;
+    std::fs::write(dir.join(LOCK_FILE), lock.to_requirements())?;
+
+    eprintln!("Building wheelhouse (this may take several minutes)...");
+    python::build_wheelhouse(&dir.join(WHEELS_DIR), &lock)?;
+    let mut wheels: Vec<String> = std::fs::read_dir(dir.join(WHEELS_DIR))?
+        .filter_map(|e| e.ok())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect();
+    wheels.sort();
+
+    let models = if include_models {
+        let model_dirs = chatterbox_model_dirs()?;
+        if model_dirs.is_empty() {
+            anyhow::bail!(
+                "No Chatterbox weights in the Hugging Face cache. Convert something once to download them, or leave out --include-models."
+            );
+        }
+        eprintln!("Packing Chatterbox weights...");
+        pack_models(&model_dirs, &dir.join(MODELS_ARCHIVE))?;
+        Some(BundleFile::describe(dir, MODELS_ARCHIVE)?)
+    } else {
+        None
+    };
+
+    let bundle = BundleManifest {
+        gen_audio_version: env!("CARGO_PKG_VERSION").to_string(),
+        platform: platform.to_version_string(),
+        created_at: Utc::now(),
+        files,
+        wheels,
+        models,
+        lock: Some(BundleFile::describe(dir, LOCK_FILE)?),
+    };
+    bundle.save(dir)?;
+
+    eprintln!("Writing {}...", output.display());
+    write_tarball(dir, output)?;
+
+    Ok(bundle)
+}
+
+/// Archive hub cache entries, keeping the cache's snapshot symlinks.
+fn pack_models(model_dirs: &[PathBuf], archive: &Path) -> Result<()> {
+    let mut builder = tar::Builder::new(std::fs::File::create(archive)?);
+    builder.follow_symlinks(false);
+    for model_dir in model_dirs {
+        let name = model_dir.file_name().unwrap_or_default();
+        builder.append_dir_all(name, model_dir)?;
+    }
+    builder.into_inner()?;
+    Ok(())
+}
+
+/// Write the contents of `dir` to a tarball at `output`.
+fn write_tarball(dir: &Path, output: &Path) -> Result<()> {
+    let part = part_path(output);
+    let mut builder = tar::Builder::new(std::fs::File::create(&part)?);
+    builder.follow_symlinks(false);
+    builder.append_dir_all(".", dir)?;
+    builder.into_inner()?.sync_all()?;
+    std::fs::rename(&part, output).context("Failed to move bundle into place")
+}
+
+/// Unpack a tarball, gzipped or not, into `dir`.
+fn unpack_tarball(path: &Path, dir: &Path) -> Result<()> {
+    let mut magic = [0u8; 2];
+    let is_gzip = std::fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .map(|_| magic == [0x1f, 0x8b])
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let file = std::fs::File::open(path)?;
+    let result = if is_gzip {
+        tar::Archive::new(flate2::read::GzDecoder::new(file)).unpack(dir)
+    } else {
+        tar::Archive::new(file).unpack(dir)
+    };
+    result.with_context(|| format!("Failed to unpack {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bootstrap::test_server::serve_dir;
+
+    /// A bundle directory for this platform with made-up archives.
+    fn fake_bundle(dir: &Path) -> BundleManifest {
+        let platform = Platform::detect().unwrap();
+        let mut files = BTreeMap::new();
+        for component in COMPONENTS {
+            if manifest::artifact(&platform, component).is_none() {
+                continue;
+            }
+            let name = archive_name(&platform, component);
+            std::fs::write(dir.join(name), format!("{} archive", component.as_str())).unwrap();
+            files.insert(component.as_str().to_string(), BundleFile::describe(dir, name).unwrap());
+        }
+        std::fs::create_dir_all(dir.join(WHEELS_DIR)).unwrap();
+        std::fs::write(dir.join(LOCK_FILE), "# torch-variant: cpu\ntorch==2.6.0\n").unwrap();
+
+        let bundle = BundleManifest {
+            gen_audio_version: env!("CARGO_PKG_VERSION").to_string(),
+            platform: platform.to_version_string(),
+            created_at: Utc::now(),
+            files,
+            wheels: Vec::new(),
+            models: None,
+            lock: Some(BundleFile::describe(dir, LOCK_FILE).unwrap()),
+        };
+        bundle.save(dir).unwrap();
+        bundle
+    }
+
+    #[tokio::test]
+    async fn test_bundle_from_tarball() {
+        let platform = Platform::detect().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let bundle_dir = dir.path().join("bundle");
+        std::fs::create_dir_all(&bundle_dir).unwrap();
+        fake_bundle(&bundle_dir);
+
+        let tarball = dir.path().join("bundle.tar");
+        write_tarball(&bundle_dir, &tarball).unwrap();
+
+        let unpacked = dir.path().join("unpacked");
+        std::fs::create_dir_all(&unpacked).unwrap();
+        unpack_tarball(&tarball, &unpacked).unwrap();
+        let source = Source::open_bundle(&unpacked).unwrap();
+        assert!(source.find_links().unwrap().ends_with(WHEELS_DIR));
+        let lock = source.lock(&platform).await.unwrap();
+        assert_eq!(lock.version("torch"), Some("2.6.0"));
+        assert!(lock.origin.starts_with("bundle "));
+
+        let python = source
+            .fetch(&platform, Component::Python, dir.path())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&python.path).unwrap(), "python archive");
+        // Files in a bundle on disk stay where they are
+        python.discard();
+        assert!(unpacked.join("python.tar.gz").exists());
+
+        // A modified file is refused
+        std::fs::write(unpacked.join("python.tar.gz"), "python archivf").unwrap();
+        let err = source
+            .fetch(&platform, Component::Python, dir.path())
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("Checksum mismatch"));
+    }
+
+    #[tokio::test]
+    async fn test_mirror_downloads_and_verifies() {
+        let platform = Platform::detect().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let bundle_dir = dir.path().join("mirror");
+        std::fs::create_dir_all(&bundle_dir).unwrap();
+        fake_bundle(&bundle_dir);
+        let server = serve_dir(bundle_dir.clone(), false);
+
+        let source = Source::mirror(&format!("{}/", server.url)).await.unwrap();
+        assert_eq!(source.find_links().unwrap(), format!("{}/wheels/", server.url));
+        let lock = source.lock(&platform).await.unwrap();
+        assert_eq!(lock.variant, crate::bootstrap::lock::TorchVariant::Cpu);
+
+        let downloads = dir.path().join("downloads");
+        let ffmpeg = source
+            .fetch(&platform, Component::Ffmpeg, &downloads)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&ffmpeg.path).unwrap(), "ffmpeg archive");
+        ffmpeg.discard();
+        assert!(std::fs::read_dir(&downloads).unwrap().next().is_none());
+
+        // Changed on the mirror after the bundle was made
+        let name = archive_name(&platform, Component::Python);
+        std::fs::write(bundle_dir.join(name), "something else!").unwrap();
+        let err = source
+            .fetch(&platform, Component::Python, &downloads)
+            .await
+            .err()
+            .unwrap();
+        assert!(format!("{:#}", err).contains("mismatch"));
+        std::fs::write(bundle_dir.join(LOCK_FILE), "torch==9.9.9\n").unwrap();
+        assert!(source.lock(&platform).await.is_err());
+
+        assert!(Source::mirror(&format!("{}/missing", server.url)).await.is_err());
+    }
+
+    #[test]
+    fn test_bundle_platform_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut bundle = fake_bundle(dir.path());
+        assert!(bundle.check_platform(&Platform::detect().unwrap()).is_ok());
+
+        bundle.platform = "Plan9-mips".to_string();
+        let err = bundle.check_platform(&Platform::detect().unwrap()).unwrap_err();
+        assert!(err.to_string().contains("Plan9-mips"));
+    }
+}
@@ -1,13 +1,23 @@
 //! HTTP download with progress reporting and retry logic.
+//!
+//! Files are downloaded to `<destination>.part`. A retry, or a later run,
+//! continues from what is already there with an HTTP Range request, and the
+//! file is only moved into place once its size and hash match the manifest.
 
+use super::manifest::Artifact;
+use super::progress::{self, ProgressEvent};
+use crate::coordinator::compute_file_sha256;
 use anyhow::{Context, Result};
 use futures_util::StreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
 use std::io::Write;
-use std::path::Path;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
+/// How often download progress is reported in JSON mode.
+const JSON_PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
 /// Download-related errors.
 #[derive(Debug, Error)]
 #[allow(dead_code)]
@@ -20,6 +30,19 @@ pub enum DownloadError {
 
     #[error("Network error: {0}")]
     NetworkError(String),
+
+    #[error(
+        "Checksum mismatch for {url}: expected SHA-256 {expected}, got {actual}. \
+         The file changed upstream or was corrupted in transit, and has been deleted"
+    )]
+    ChecksumMismatch {
+        url: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("Size mismatch for {url}: expected {expected} bytes, got {actual}. The file has been deleted")]
+    SizeMismatch { url: String, expected: u64, actual: u64 },
 }
 
 /// Configuration for retry behavior.
@@ -41,17 +64,83 @@ impl Default for RetryConfig {
     }
 }
 
-/// Download a file with progress bar display.
-pub async fn download_file(url: &str, destination: &Path, description: &str) -> Result<()> {
-    download_file_with_retry(url, destination, description, &RetryConfig::default()).await
+/// Download a manifest entry with progress bar display.
+///
+/// Returns the SHA-256 of the downloaded file.
+pub async fn download_file(artifact: &Artifact<'_>, destination: &Path, description: &str) -> Result<String> {
+    download_file_with_retry(artifact, destination, description, &RetryConfig::default()).await
 }
 
-/// Download a file with progress bar and retry logic.
+/// Download a manifest entry with progress bar and retry logic.
+///
+/// The file is checked against the entry's size and SHA-256 (when pinned)
+/// before it is moved to `destination`. A mismatch deletes the download and
+/// is not retried.
 pub async fn download_file_with_retry(
-    url: &str,
+    artifact: &Artifact<'_>,
     destination: &Path,
     description: &str,
     config: &RetryConfig,
+) -> Result<String> {
+    let part = part_path(destination);
+    transfer_with_retry(artifact, &part, description, config).await?;
+
+    let sha256 = verify_download(artifact, &part)?;
+    std::fs::rename(&part, destination).context("Failed to move download into place")?;
+    progress::report(ProgressEvent::Verified {
+        file: file_label(destination),
+        sha256: sha256.clone(),
+        pinned: artifact.is_pinned(),
+    });
+    Ok(sha256)
+}
+
+/// File name used to identify a download in progress events.
+fn file_label(path: &Path) -> String {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    name.strip_suffix(".part").unwrap_or(&name).to_string()
+}
+
+/// Path of the partial download for `destination`.
+pub fn part_path(destination: &Path) -> PathBuf {
+    let mut name = destination.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    destination.with_file_name(name)
+}
+
+/// Check a finished download against the manifest, deleting it on mismatch.
+fn verify_download(artifact: &Artifact<'_>, part: &Path) -> Result<String> {
+    let actual_size = std::fs::metadata(part)?.len();
+    if let Some(expected) = artifact.size.filter(|&size| size != actual_size) {
+        let _ = std::fs::remove_file(part);
+        return Err(DownloadError::SizeMismatch {
+            url: artifact.url.to_string(),
+            expected,
+            actual: actual_size,
+        }
+        .into());
+    }
+
+    let actual = compute_file_sha256(part)?;
+    if let Some(expected) = artifact.sha256.filter(|sha256| !actual.eq_ignore_ascii_case(sha256)) {
+        let _ = std::fs::remove_file(part);
+        return Err(DownloadError::ChecksumMismatch {
+            url: artifact.url.to_string(),
+            expected: expected.to_string(),
+            actual,
+        }
+        .into());
+    }
+
+    Ok(actual)
+}
+
+/// Transfer the file into `part`, resuming and retrying on failure.
+async fn transfer_with_retry(
+    artifact: &Artifact<'_>,
+    part: &Path,
+    description: &str,
+    config: &RetryConfig,
 ) -> Result<()> {
     let mut attempt = 0;
     let mut delay = config.initial_delay;
@@ -59,7 +148,7 @@ pub async fn download_file_with_retry(
     loop {
         attempt += 1;
 
-        match download_file_once(url, destination, description).await {
+        match download_file_once(artifact, part, description).await {
             Ok(()) => return Ok(()),
             Err(e) => {
                 if attempt >= config.max_attempts {
@@ -69,11 +158,11 @@ pub async fn download_file_with_retry(
                     ));
                 }
 
-                eprintln!(
-                    "  Download failed (attempt {}/{}): {}",
+                progress::detail(format!(
+                    "Download failed (attempt {}/{}): {}",
                     attempt, config.max_attempts, e
-                );
-                eprintln!("  Retrying in {:?}...", delay);
+                ));
+                progress::detail(format!("Retrying in {:?}...", delay));
 
                 tokio::time::sleep(delay).await;
                 delay = Duration::from_secs_f32(
@@ -84,13 +173,26 @@ pub async fn download_file_with_retry(
     }
 }
 
-/// Perform a single download attempt.
-async fn download_file_once(url: &str, destination: &Path, description: &str) -> Result<()> {
+/// Perform a single download attempt, continuing a partial file in `part`.
+async fn download_file_once(artifact: &Artifact<'_>, part: &Path, description: &str) -> Result<()> {
+    let url = artifact.url;
+
     // Create parent directory if needed
-    if let Some(parent) = destination.parent() {
+    if let Some(parent) = part.parent() {
         std::fs::create_dir_all(parent)?;
     }
 
+    let mut existing = std::fs::metadata(part).map(|m| m.len()).unwrap_or(0);
+    if let Some(size) = artifact.size {
+        if existing == size {
+            return Ok(());
+        }
+        if existing > size {
+            std::fs::remove_file(part)?;
+            existing = 0;
+        }
+    }
+
     // Build HTTP client with reasonable timeouts
     let client = reqwest::Client::builder()
         .connect_timeout(Duration::from_secs(30))
@@ -98,14 +200,19 @@ async fn download_file_once(url: &str, destination: &Path, description: &str) ->
         .build()
         .context("Failed to create HTTP client")?;
 
-    // Start request
-    let response = client
-        .get(url)
-        .send()
-        .await
-        .context("Failed to connect")?;
+    // Start request, asking for the rest of a partial download
+    let mut request = client.get(url);
+    if existing > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", existing));
+    }
+    let response = request.send().await.context("Failed to connect")?;
 
     let status = response.status();
+    if existing > 0 && status == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+        // Nothing left past what we have; the checks after the download
+        // catch a file that isn't actually complete
+        return Ok(());
+    }
     if !status.is_success() {
         return Err(DownloadError::HttpError {
             status: status.as_u16(),
@@ -114,10 +221,20 @@ async fn download_file_once(url: &str, destination: &Path, description: &str) ->
         .into());
     }
 
-    let total_size = response.content_length();
+    // The server may ignore the range and send the whole file
+    let resumed = existing > 0
+        && status == reqwest::StatusCode::PARTIAL_CONTENT
+        && content_range_start(&response) == Some(existing);
+    if !resumed {
+        existing = 0;
+    }
+
+    let total_size = response.content_length().map(|len| len + existing);
 
-    // Create progress bar
-    let pb = if let Some(size) = total_size {
+    // Create progress bar (JSON mode reports events instead)
+    let pb = if progress::is_json() {
+        ProgressBar::hidden()
+    } else if let Some(size) = total_size {
         let pb = ProgressBar::new(size);
         pb.set_style(
             ProgressStyle::default_bar()
@@ -138,26 +255,59 @@ async fn download_file_once(url: &str, destination: &Path, description: &str) ->
         pb
     };
 
-    // Open destination file
-    let mut file = std::fs::File::create(destination)
-        .context("Failed to create destination file")?;
+    // Open the partial file, appending when resuming
+    let mut file = if resumed {
+        std::fs::OpenOptions::new().append(true).open(part)
+    } else {
+        std::fs::File::create(part)
+    }
+    .context("Failed to create destination file")?;
 
     // Download with streaming
     let mut stream = response.bytes_stream();
-    let mut downloaded: u64 = 0;
+    let mut downloaded: u64 = existing;
+    pb.set_position(downloaded);
+
+    let file_name = file_label(part);
+    let report = |bytes| {
+        progress::report(ProgressEvent::Download {
+            file: file_name.clone(),
+            bytes,
+            total_bytes: total_size,
+        })
+    };
+    report(downloaded);
+    let mut last_report = Instant::now();
 
     while let Some(chunk) = stream.next().await {
         let chunk = chunk.context("Error reading response")?;
         file.write_all(&chunk).context("Failed to write to file")?;
         downloaded += chunk.len() as u64;
         pb.set_position(downloaded);
+
+        if last_report.elapsed() >= JSON_PROGRESS_INTERVAL {
+            report(downloaded);
+            last_report = Instant::now();
+        }
     }
+    report(downloaded);
 
     pb.finish_and_clear();
 
+    if let Some(total) = total_size.filter(|&total| downloaded < total) {
+        anyhow::bail!("Connection closed after {} of {} bytes", downloaded, total);
+    }
+
     Ok(())
 }
 
+/// Start offset from a `Content-Range: bytes <start>-<end>/<total>` header.
+fn content_range_start(response: &reqwest::Response) -> Option<u64> {
+    let value = response.headers().get(reqwest::header::CONTENT_RANGE)?.to_str().ok()?;
+    let range = value.strip_prefix("bytes ")?;
+    range.split('-').next()?.trim().parse().ok()
+}
+
 /// Format bytes for human-readable display.
 pub fn format_bytes(bytes: u64) -> String {
     const KB: u64 = 1024;
@@ -178,6 +328,7 @@ pub fn format_bytes(bytes: u64) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::bootstrap::test_server::{serve_dir, TestServer};
 
     #[test]
     fn test_format_bytes() {
@@ -194,4 +345,96 @@ mod tests {
         assert_eq!(config.max_attempts, 3);
         assert_eq!(config.initial_delay, Duration::from_secs(1));
     }
+
+    fn quick_retries() -> RetryConfig {
+        RetryConfig {
+            initial_delay: Duration::from_millis(10),
+            ..RetryConfig::default()
+        }
+    }
+
+    fn sha256_hex(data: &[u8]) -> String {
+        format!("{:x}", <sha2::Sha256 as sha2::Digest>::digest(data))
+    }
+
+    /// Serve `body` as `/file` and return the server.
+    fn serve(dir: &Path, body: &[u8], cut_first: bool) -> TestServer {
+        let served = dir.join("served");
+        std::fs::create_dir_all(&served).unwrap();
+        std::fs::write(served.join("file"), body).unwrap();
+        serve_dir(served, cut_first)
+    }
+
+    #[tokio::test]
+    async fn test_download_resumes_and_verifies() {
+        let dir = tempfile::tempdir().unwrap();
+        let body: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+        let server = serve(dir.path(), &body, true);
+        let url = format!("{}/file", server.url);
+        let sha256 = sha256_hex(&body);
+        let artifact = Artifact {
+            url: &url,
+            size: Some(body.len() as u64),
+            sha256: Some(&sha256),
+        };
+
+        let destination = dir.path().join("python.tar.gz");
+        let hash = download_file_with_retry(&artifact, &destination, "test", &quick_retries())
+            .await
+            .unwrap();
+
+        assert_eq!(hash, sha256);
+        assert_eq!(std::fs::read(&destination).unwrap(), body);
+        assert!(!part_path(&destination).exists());
+        // The retry asked for the rest instead of starting over
+        let starts: Vec<u64> = server.requests.lock().unwrap().iter().map(|r| r.1).collect();
+        assert_eq!(starts, vec![0, body.len() as u64 / 2]);
+    }
+
+    #[tokio::test]
+    async fn test_download_checksum_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let body = b"not what the manifest says";
+        let server = serve(dir.path(), body, false);
+        let url = format!("{}/file", server.url);
+        let artifact = Artifact {
+            url: &url,
+            size: Some(body.len() as u64),
+            sha256: Some("0000000000000000000000000000000000000000000000000000000000000000"),
+        };
+
+        let destination = dir.path().join("ffmpeg.zip");
+        let err = download_file_with_retry(&artifact, &destination, "test", &quick_retries())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<DownloadError>(),
+            Some(DownloadError::ChecksumMismatch { .. })
+        ));
+        assert!(err.to_string().contains("expected SHA-256 0000"));
+        assert!(!destination.exists());
+        assert!(!part_path(&destination).exists());
+        // A mismatch isn't retried
+        assert_eq!(server.requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_unpinned_download_reports_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let body = b"some archive";
+        let server = serve(dir.path(), body, false);
+        let url = format!("{}/file", server.url);
+        let artifact = Artifact {
+            url: &url,
+            size: None,
+            sha256: None,
+        };
+
+        let destination = dir.path().join("archive");
+        let hash = download_file_with_retry(&artifact, &destination, "test", &quick_retries())
+            .await
+            .unwrap();
+        assert_eq!(hash, sha256_hex(body));
+    }
 }
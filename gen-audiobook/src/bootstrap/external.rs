@@ -0,0 +1,422 @@
+//! Python and FFmpeg provided outside the bootstrap.
+//!
+//! Machines that already have a managed Python environment or a distro
+//! FFmpeg can point gen-audio at them in the `[external]` config section,
+//! or with environment variables, which take precedence:
+//!
+//! | Setting       | Variable                | Points at                                   |
+//! |---------------|-------------------------|---------------------------------------------|
+//! | `python_venv` | `GEN_AUDIO_PYTHON_VENV` | a venv (or conda env) with chatterbox-tts   |
+//! | `python_home` | `GEN_AUDIO_PYTHON_HOME` | PYTHONHOME for the embedded interpreter     |
+//! | `ffmpeg`      | `GEN_AUDIO_FFMPEG`      | ffmpeg, or a command name looked up on PATH |
+//! | `ffprobe`     | `GEN_AUDIO_FFPROBE`     | ffprobe; defaults to the one next to ffmpeg |
+//!
+//! External components are validated instead of downloaded, and gen-audio
+//! never changes them: it won't create, replace or pip install into an
+//! external environment.
+
+use super::versions::PYTHON_VERSION;
+use crate::config::GenAudioConfig;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// Oldest FFmpeg major version accepted for an external binary.
+pub const MIN_FFMPEG_MAJOR: u32 = 4;
+
+/// The `[external]` config section.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExternalConfig {
+    /// Python environment with chatterbox-tts installed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub python_venv: Option<PathBuf>,
+
+    /// PYTHONHOME for the embedded interpreter.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub python_home: Option<PathBuf>,
+
+    /// FFmpeg binary, or a command name to look up on PATH.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ffmpeg: Option<PathBuf>,
+
+    /// FFprobe binary, or a command name to look up on PATH.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ffprobe: Option<PathBuf>,
+}
+
+/// One externally provided component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExternalComponent {
+    PythonVenv,
+    PythonHome,
+    Ffmpeg,
+    Ffprobe,
+}
+
+impl ExternalComponent {
+    /// All components, in display order.
+    pub const ALL: [ExternalComponent; 4] = [
+        ExternalComponent::PythonVenv,
+        ExternalComponent::PythonHome,
+        ExternalComponent::Ffmpeg,
+        ExternalComponent::Ffprobe,
+    ];
+
+    /// Key in the `[external]` config section.
+    pub fn key(&self) -> &'static str {
+        match self {
+            ExternalComponent::PythonVenv => "python_venv",
+            ExternalComponent::PythonHome => "python_home",
+            ExternalComponent::Ffmpeg => "ffmpeg",
+            ExternalComponent::Ffprobe => "ffprobe",
+        }
+    }
+
+    /// Environment variable that overrides the setting.
+    pub fn env_var(&self) -> &'static str {
+        match self {
+            ExternalComponent::PythonVenv => "GEN_AUDIO_PYTHON_VENV",
+            ExternalComponent::PythonHome => "GEN_AUDIO_PYTHON_HOME",
+            ExternalComponent::Ffmpeg => "GEN_AUDIO_FFMPEG",
+            ExternalComponent::Ffprobe => "GEN_AUDIO_FFPROBE",
+        }
+    }
+}
+
+impl ExternalConfig {
+    /// The configured path for a component.
+    pub fn get(&self, component: ExternalComponent) -> Option<&PathBuf> {
+        match component {
+            ExternalComponent::PythonVenv => self.python_venv.as_ref(),
+            ExternalComponent::PythonHome => self.python_home.as_ref(),
+            ExternalComponent::Ffmpeg => self.ffmpeg.as_ref(),
+            ExternalComponent::Ffprobe => self.ffprobe.as_ref(),
+        }
+    }
+
+    /// Set or clear the path for a component.
+    pub fn set(&mut self, component: ExternalComponent, path: Option<PathBuf>) {
+        let slot = match component {
+            ExternalComponent::PythonVenv => &mut self.python_venv,
+            ExternalComponent::PythonHome => &mut self.python_home,
+            ExternalComponent::Ffmpeg => &mut self.ffmpeg,
+            ExternalComponent::Ffprobe => &mut self.ffprobe,
+        };
+        *slot = path;
+    }
+
+    /// Apply environment overrides, looked up with `env`.
+    fn with_env(mut self, env: impl Fn(&str) -> Option<String>) -> Self {
+        for component in ExternalComponent::ALL {
+            if let Some(value) = env(component.env_var()).filter(|v| !v.is_empty()) {
+                self.set(component, Some(PathBuf::from(value)));
+            }
+        }
+        self
+    }
+
+    /// Whether nothing is provided externally.
+    pub fn is_empty(&self) -> bool {
+        ExternalComponent::ALL.iter().all(|&c| self.get(c).is_none())
+    }
+
+    /// Whether the Python environment is provided externally.
+    pub fn has_python(&self) -> bool {
+        self.python_venv.is_some()
+    }
+
+    /// Whether FFmpeg (and with it FFprobe) is provided externally.
+    pub fn has_ffmpeg(&self) -> bool {
+        self.ffmpeg.is_some()
+    }
+
+    /// Python executable of the external environment.
+    pub fn venv_python(&self) -> Option<PathBuf> {
+        Some(self.python_venv.as_ref()?.join("bin").join("python"))
+    }
+
+    /// The external FFmpeg binary.
+    pub fn ffmpeg(&self) -> Option<PathBuf> {
+        self.ffmpeg.as_deref().map(resolve_command)
+    }
+
+    /// The external FFprobe binary: as configured, or next to FFmpeg.
+    pub fn ffprobe(&self) -> Option<PathBuf> {
+        match &self.ffprobe {
+            Some(ffprobe) => Some(resolve_command(ffprobe)),
+            None => Some(self.ffmpeg()?.with_file_name("ffprobe")),
+        }
+    }
+
+    /// Check every configured component, failing with what to fix.
+    pub fn validate(&self) -> Result<()> {
+        self.validate_components(&ExternalComponent::ALL)
+    }
+
+    /// Check some of the components, if they are configured.
+    pub fn validate_components(&self, components: &[ExternalComponent]) -> Result<()> {
+        let check = |component: &ExternalComponent| match component {
+            ExternalComponent::PythonVenv => self.python_venv.as_deref().map(|venv| check_python_venv(venv).map(drop)),
+            ExternalComponent::PythonHome => self.python_home.as_deref().map(check_python_home),
+            ExternalComponent::Ffmpeg => self.ffmpeg().map(|path| check_ffmpeg_binary("ffmpeg", &path).map(drop)),
+            ExternalComponent::Ffprobe => self.ffprobe().map(|path| check_ffmpeg_binary("ffprobe", &path).map(drop)),
+        };
+        let problems: Vec<String> = components
+            .iter()
+            .filter_map(check)
+            .filter_map(|result| result.err())
+            .map(|e| format!("{:#}", e))
+            .collect();
+
+        if !problems.is_empty() {
+            anyhow::bail!(
+                "External dependencies are misconfigured:\n  - {}\nFix them, or remove the setting ('gen-audio config set-external <component>') to let gen-audio install its own.",
+                problems.join("\n  - ")
+            );
+        }
+        Ok(())
+    }
+}
+
+static EXTERNAL: OnceLock<ExternalConfig> = OnceLock::new();
+
+/// External components for this process: the `[external]` config section
+/// with environment overrides applied.
+pub fn external() -> &'static ExternalConfig {
+    EXTERNAL.get_or_init(|| {
+        GenAudioConfig::load()
+            .map(|config| config.external)
+            .unwrap_or_default()
+            .with_env(|name| std::env::var(name).ok())
+    })
+}
+
+/// Resolve a bare command name on PATH; paths are returned as given.
+fn resolve_command(command: &Path) -> PathBuf {
+    if command.components().count() != 1 {
+        return command.to_path_buf();
+    }
+    std::env::var_os("PATH")
+        .and_then(|path| {
+            std::env::split_paths(&path)
+                .map(|dir| dir.join(command))
+                .find(|candidate| candidate.is_file())
+        })
+        .unwrap_or_else(|| command.to_path_buf())
+}
+
+/// Check an external Python environment. Returns its Python version.
+fn check_python_venv(venv: &Path) -> Result<String> {
+    let python = venv.join("bin").join("python");
+    if !python.exists() {
+        anyhow::bail!(
+            "python_venv {} has no bin/python. Point it at the environment's directory (the one containing bin/).",
+            venv.display()
+        );
+    }
+
+    // Print the version before importing, so it's known even if the import fails
+    let output = Command::new(&python)
+        .args([
+            "-c",
+            "import sys; print('%d.%d.%d' % sys.version_info[:3], flush=True); import chatterbox",
+        ])
+        .output()
+        .with_context(|| format!("Failed to run {}", python.display()))?;
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+    let required = required_python_minor();
+    if !version.starts_with(&format!("{}.", required)) {
+        anyhow::bail!(
+            "python_venv {} has Python {}, but gen-audio embeds Python {} and needs a {} environment.",
+            venv.display(),
+            if version.is_empty() { "of unknown version" } else { &version },
+            required,
+            required
+        );
+    }
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!(
+            "chatterbox can't be imported in python_venv {} ({}). Install it with '{} -m pip install chatterbox-tts'.",
+            venv.display(),
+            stderr.trim().lines().last().unwrap_or("no error output"),
+            python.display()
+        );
+    }
+
+    Ok(version)
+}
+
+/// Check a PYTHONHOME for the embedded interpreter.
+fn check_python_home(home: &Path) -> Result<()> {
+    let stdlib = home.join("lib").join(format!("python{}", required_python_minor()));
+    if !stdlib.join("os.py").exists() {
+        anyhow::bail!(
+            "python_home {} has no Python {} standard library ({} is missing). Point it at the installation prefix, e.g. /usr or the directory containing lib/.",
+            home.display(),
+            required_python_minor(),
+            stdlib.join("os.py").display()
+        );
+    }
+    Ok(())
+}
+
+/// Check an external FFmpeg or FFprobe binary. Returns its version.
+fn check_ffmpeg_binary(name: &str, path: &Path) -> Result<String> {
+    let output = Command::new(path)
+        .arg("-version")
+        .output()
+        .with_context(|| format!("{} {} can't be run. Set it to the binary's full path.", name, path.display()))?;
+    if !output.status.success() {
+        anyhow::bail!("{} {} -version failed", name, path.display());
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (major, version) = parse_ffmpeg_version(&stdout)
+        .ok_or_else(|| anyhow::anyhow!("{} {} doesn't look like {}", name, path.display(), name))?;
+    if major.is_some_and(|major| major < MIN_FFMPEG_MAJOR) {
+        anyhow::bail!(
+            "{} {} is version {}; gen-audio needs {} or newer.",
+            name,
+            path.display(),
+            version,
+            MIN_FFMPEG_MAJOR
+        );
+    }
+    Ok(version)
+}
+
+/// Parse the first line of `ffmpeg -version` (or `ffprobe -version`).
+///
+/// Returns the major version, if it is a release, and the version string.
+/// Git builds ("N-112345-g...") have no major version.
+fn parse_ffmpeg_version(output: &str) -> Option<(Option<u32>, String)> {
+    let line = output.lines().next()?;
+    let mut words = line.split_whitespace();
+    let _name = words.next()?;
+    if words.next()? != "version" {
+        return None;
+    }
+    let version = words.next()?.to_string();
+    let digits: String = version
+        .trim_start_matches('n')
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    Some((digits.parse().ok(), version))
+}
+
+/// "3.11" for the embedded Python.
+fn required_python_minor() -> String {
+    PYTHON_VERSION.rsplitn(2, '.').last().unwrap_or(PYTHON_VERSION).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn script(path: &Path, body: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn test_env_overrides_config() {
+        let config = ExternalConfig {
+            ffmpeg: Some(PathBuf::from("/usr/bin/ffmpeg")),
+            python_home: Some(PathBuf::from("/opt/python")),
+            ..Default::default()
+        };
+        let external = config.with_env(|name| match name {
+            "GEN_AUDIO_FFMPEG" => Some("/opt/ffmpeg/bin/ffmpeg".to_string()),
+            "GEN_AUDIO_PYTHON_HOME" => Some(String::new()),
+            _ => None,
+        });
+        assert_eq!(external.ffmpeg(), Some(PathBuf::from("/opt/ffmpeg/bin/ffmpeg")));
+        assert_eq!(external.ffprobe(), Some(PathBuf::from("/opt/ffmpeg/bin/ffprobe")));
+        // An empty variable doesn't clear the setting
+        assert_eq!(external.python_home, Some(PathBuf::from("/opt/python")));
+        assert!(!external.has_python());
+        assert!(ExternalConfig::default().is_empty());
+    }
+
+    #[test]
+    fn test_parse_ffmpeg_version() {
+        assert_eq!(
+            parse_ffmpeg_version("ffmpeg version 6.1.1-3ubuntu5 Copyright (c) 2000-2023\n"),
+            Some((Some(6), "6.1.1-3ubuntu5".to_string()))
+        );
+        assert_eq!(
+            parse_ffmpeg_version("ffprobe version n7.0.2 Copyright"),
+            Some((Some(7), "n7.0.2".to_string()))
+        );
+        assert_eq!(
+            parse_ffmpeg_version("ffmpeg version N-112345-gabcdef Copyright"),
+            Some((None, "N-112345-gabcdef".to_string()))
+        );
+        assert_eq!(parse_ffmpeg_version("Python 3.11.9"), None);
+    }
+
+    #[test]
+    fn test_validate_ffmpeg() {
+        let dir = tempfile::tempdir().unwrap();
+        let ffmpeg = dir.path().join("bin").join("ffmpeg");
+        script(&ffmpeg, "echo 'ffmpeg version 6.1.1 Copyright'");
+        script(&dir.path().join("bin").join("ffprobe"), "echo 'ffprobe version 3.4.8 Copyright'");
+
+        let external = ExternalConfig {
+            ffmpeg: Some(ffmpeg),
+            ..Default::default()
+        };
+        let err = external.validate().unwrap_err().to_string();
+        assert!(err.contains("ffprobe"));
+        assert!(err.contains("3.4.8"));
+        assert!(!err.contains("6.1.1"));
+
+        script(&dir.path().join("bin").join("ffprobe"), "echo 'ffprobe version 6.1.1 Copyright'");
+        external.validate().unwrap();
+    }
+
+    #[test]
+    fn test_validate_python_venv() {
+        let dir = tempfile::tempdir().unwrap();
+        let python = dir.path().join("bin").join("python");
+        let external = ExternalConfig {
+            python_venv: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+
+        script(&python, &format!("echo {}", PYTHON_VERSION));
+        assert_eq!(check_python_venv(dir.path()).unwrap(), PYTHON_VERSION);
+        external.validate().unwrap();
+
+        script(&python, "echo 3.12.1");
+        assert!(external.validate().unwrap_err().to_string().contains("3.12.1"));
+
+        script(
+            &python,
+            &format!(
+                "echo {}; echo \"ModuleNotFoundError: No module named 'chatterbox'\" >&2; exit 1",
+                PYTHON_VERSION
+            ),
+        );
+        let err = external.validate().unwrap_err().to_string();
+        assert!(err.contains("No module named 'chatterbox'"));
+        assert!(err.contains("pip install chatterbox-tts"));
+
+        let empty = tempfile::tempdir().unwrap();
+        assert!(check_python_venv(empty.path()).unwrap_err().to_string().contains("bin/python"));
+    }
+
+    #[test]
+    fn test_resolve_command() {
+        assert_eq!(resolve_command(Path::new("/opt/ffmpeg")), PathBuf::from("/opt/ffmpeg"));
+        assert_eq!(resolve_command(Path::new("sh")).file_name().unwrap(), "sh");
+        assert!(resolve_command(Path::new("sh")).is_absolute());
+    }
+}
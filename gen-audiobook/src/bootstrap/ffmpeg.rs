@@ -1,52 +1,30 @@
 //! FFmpeg download and extraction.
 
-use super::download::download_file;
-use super::platform::{Arch, Os, Platform};
-use super::versions::{get_ffmpeg_dir, FFMPEG_VERSION};
+use super::external::external;
+use super::manifest::Component;
+use super::platform::{Os, Platform};
+use super::progress;
+use super::versions::get_ffmpeg_dir;
 use anyhow::{Context, Result};
 use std::io::Read;
 use std::path::{Path, PathBuf};
 
-/// Get the download URL for FFmpeg.
-pub fn get_ffmpeg_download_url(platform: &Platform) -> &'static str {
-    match (platform.os, platform.arch) {
-        (Os::MacOs, Arch::Aarch64) => {
-            "https://ffmpeg.martin-riedl.de/redirect/latest/macos/arm64/release/ffmpeg.zip"
-        }
-        (Os::MacOs, Arch::X86_64) => {
-            "https://ffmpeg.martin-riedl.de/redirect/latest/macos/amd64/release/ffmpeg.zip"
-        }
-        (Os::Linux, Arch::X86_64) => {
-            "https://johnvansickle.com/ffmpeg/releases/ffmpeg-release-amd64-static.tar.xz"
-        }
-        (Os::Linux, Arch::Aarch64) => {
-            "https://johnvansickle.com/ffmpeg/releases/ffmpeg-release-arm64-static.tar.xz"
-        }
-    }
-}
-
-/// Get the download URL for FFprobe (macOS only, Linux bundles it).
-pub fn get_ffprobe_download_url(platform: &Platform) -> Option<&'static str> {
-    match (platform.os, platform.arch) {
-        (Os::MacOs, Arch::Aarch64) => {
-            Some("https://ffmpeg.martin-riedl.de/redirect/latest/macos/arm64/release/ffprobe.zip")
-        }
-        (Os::MacOs, Arch::X86_64) => {
-            Some("https://ffmpeg.martin-riedl.de/redirect/latest/macos/amd64/release/ffprobe.zip")
-        }
-        // Linux static builds include ffprobe
-        (Os::Linux, _) => None,
-    }
-}
-
-/// Get the path to the bootstrapped FFmpeg executable.
+/// Get the path to the FFmpeg executable: the external one if configured,
+/// otherwise the bootstrapped one.
 pub fn get_ffmpeg_executable() -> Result<PathBuf> {
+    if let Some(ffmpeg) = external().ffmpeg() {
+        return Ok(ffmpeg);
+    }
     let ffmpeg_dir = get_ffmpeg_dir()?;
     Ok(ffmpeg_dir.join("ffmpeg"))
 }
 
-/// Get the path to the bootstrapped FFprobe executable.
+/// Get the path to the FFprobe executable: the external one if configured,
+/// otherwise the bootstrapped one.
 pub fn get_ffprobe_executable() -> Result<PathBuf> {
+    if let Some(ffprobe) = external().ffprobe() {
+        return Ok(ffprobe);
+    }
     let ffmpeg_dir = get_ffmpeg_dir()?;
     Ok(ffmpeg_dir.join("ffprobe"))
 }
@@ -81,53 +59,34 @@ pub fn is_ffprobe_installed() -> Result<bool> {
     Ok(output.status.success())
 }
 
-/// Download and install FFmpeg.
-pub async fn install_ffmpeg(platform: &Platform) -> Result<(PathBuf, PathBuf)> {
+/// Extract a downloaded FFmpeg or FFprobe archive into the FFmpeg directory.
+///
+/// On macOS each binary comes in its own zip; on Linux one tar.xz holds
+/// both. Call [`finish_ffmpeg_install`] once all archives are extracted.
+pub fn install_ffmpeg_archive(platform: &Platform, component: Component, archive_path: &Path) -> Result<()> {
     let ffmpeg_dir = get_ffmpeg_dir()?;
     std::fs::create_dir_all(&ffmpeg_dir)?;
 
-    // Download FFmpeg
-    let ffmpeg_url = get_ffmpeg_download_url(platform);
-    let temp_dir = tempfile::tempdir()?;
-
-    match platform.os {
-        Os::MacOs => {
-            // macOS: Separate downloads for ffmpeg and ffprobe (zip files)
-            let ffmpeg_archive = temp_dir.path().join("ffmpeg.zip");
-            download_file(
-                ffmpeg_url,
-                &ffmpeg_archive,
-                &format!("Downloading FFmpeg {}...", FFMPEG_VERSION),
-            )
-            .await?;
-
-            eprintln!("  Extracting FFmpeg...");
-            extract_zip_single_binary(&ffmpeg_archive, &ffmpeg_dir.join("ffmpeg"))?;
-
-            // Download ffprobe separately
-            if let Some(ffprobe_url) = get_ffprobe_download_url(platform) {
-                let ffprobe_archive = temp_dir.path().join("ffprobe.zip");
-                download_file(ffprobe_url, &ffprobe_archive, "Downloading FFprobe...")
-                    .await?;
-
-                eprintln!("  Extracting FFprobe...");
-                extract_zip_single_binary(&ffprobe_archive, &ffmpeg_dir.join("ffprobe"))?;
-            }
+    match (platform.os, component) {
+        (Os::MacOs, Component::Ffmpeg) => {
+            progress::detail("Extracting FFmpeg...");
+            extract_zip_single_binary(archive_path, &ffmpeg_dir.join("ffmpeg"))
+        }
+        (Os::MacOs, Component::Ffprobe) => {
+            progress::detail("Extracting FFprobe...");
+            extract_zip_single_binary(archive_path, &ffmpeg_dir.join("ffprobe"))
         }
-        Os::Linux => {
-            // Linux: Single tar.xz with both ffmpeg and ffprobe
-            let archive_path = temp_dir.path().join("ffmpeg.tar.xz");
-            download_file(
-                ffmpeg_url,
-                &archive_path,
-                &format!("Downloading FFmpeg {}...", FFMPEG_VERSION),
-            )
-            .await?;
-
-            eprintln!("  Extracting FFmpeg...");
-            extract_ffmpeg_tar_xz(&archive_path, &ffmpeg_dir)?;
+        (Os::Linux, Component::Ffmpeg) => {
+            progress::detail("Extracting FFmpeg...");
+            extract_ffmpeg_tar_xz(archive_path, &ffmpeg_dir)
         }
+        _ => anyhow::bail!("No {} archive is used on {}", component.as_str(), platform),
     }
+}
+
+/// Make the extracted binaries executable and check that FFmpeg runs.
+pub fn finish_ffmpeg_install() -> Result<PathBuf> {
+    let ffmpeg_dir = get_ffmpeg_dir()?;
 
     // Set executable permissions
     #[cfg(unix)]
@@ -150,7 +109,6 @@ pub async fn install_ffmpeg(platform: &Platform) -> Result<(PathBuf, PathBuf)> {
     }
 
     let ffmpeg_path = get_ffmpeg_executable()?;
-    let ffprobe_path = get_ffprobe_executable()?;
 
     // Verify installation
     if !ffmpeg_path.exists() {
@@ -171,9 +129,9 @@ pub async fn install_ffmpeg(platform: &Platform) -> Result<(PathBuf, PathBuf)> {
         .next()
         .unwrap_or("unknown")
         .to_string();
-    eprintln!("  Installed {}", version_line);
+    progress::detail(format!("Installed {}", version_line));
 
-    Ok((ffmpeg_path, ffprobe_path))
+    Ok(ffmpeg_path)
 }
 
 /// Extract a single binary from a zip file (macOS FFmpeg distribution).
@@ -250,6 +208,8 @@ fn extract_ffmpeg_tar_xz(archive_path: &Path, destination: &Path) -> Result<()>
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::bootstrap::manifest;
+    use crate::bootstrap::platform::Arch;
 
     #[test]
     fn test_ffmpeg_urls() {
@@ -257,14 +217,14 @@ mod tests {
             os: Os::MacOs,
             arch: Arch::Aarch64,
         };
-        let url = get_ffmpeg_download_url(&macos_arm);
+        let url = manifest::artifact(&macos_arm, Component::Ffmpeg).unwrap().url;
         assert!(url.contains("arm64"));
 
         let linux_x64 = Platform {
             os: Os::Linux,
             arch: Arch::X86_64,
         };
-        let url = get_ffmpeg_download_url(&linux_x64);
+        let url = manifest::artifact(&linux_x64, Component::Ffmpeg).unwrap().url;
         assert!(url.contains("amd64"));
     }
 
@@ -274,12 +234,12 @@ mod tests {
             os: Os::MacOs,
             arch: Arch::Aarch64,
         };
-        assert!(get_ffprobe_download_url(&macos).is_some());
+        assert!(manifest::artifact(&macos, Component::Ffprobe).is_some());
 
         let linux = Platform {
             os: Os::Linux,
             arch: Arch::X86_64,
         };
-        assert!(get_ffprobe_download_url(&linux).is_none());
+        assert!(manifest::artifact(&linux, Component::Ffprobe).is_none());
     }
 }
@@ -0,0 +1,368 @@
+//! Locked Python dependency set.
+//!
+//! Packages are installed from a lockfile in pip's requirements format: one
+//! `name==version` per package, optionally followed by `--hash` options. A
+//! lockfile for each torch variant is built into the binary (see `locks/`).
+//! `gen-audio setup --upgrade --lock` resolves the newest versions and
+//! writes them, with hashes, to `bootstrap/requirements.lock` in the data
+//! directory, which is used instead from then on.
+//!
+//! When every entry has a hash, pip runs with `--require-hashes`, so every
+//! package, dependencies included, must match exactly. A lockfile without
+//! hashes only pins the versions listed in it.
+
+use super::platform::{Os, Platform};
+use super::python::REQUIRED_PACKAGES;
+use super::versions::get_bootstrap_dir;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Environment variable that overrides the detected torch variant.
+pub const TORCH_VARIANT_ENV: &str = "GEN_AUDIO_TORCH_VARIANT";
+
+/// Name of the local lockfile in the bootstrap directory and in bundles.
+pub const LOCK_FILE: &str = "requirements.lock";
+
+/// Which build of torch to install.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TorchVariant {
+    /// PyPI's builds (MPS on macOS).
+    Default,
+    /// CPU-only Linux wheels, without the CUDA libraries.
+    Cpu,
+    /// Linux wheels for CUDA 12.4.
+    Cuda,
+}
+
+impl TorchVariant {
+    /// Name used in lockfiles and `GEN_AUDIO_TORCH_VARIANT`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TorchVariant::Default => "default",
+            TorchVariant::Cpu => "cpu",
+            TorchVariant::Cuda => "cuda",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "default" => Some(TorchVariant::Default),
+            "cpu" => Some(TorchVariant::Cpu),
+            "cuda" => Some(TorchVariant::Cuda),
+            _ => None,
+        }
+    }
+
+    /// Pick the variant for this machine: CUDA on Linux with an NVIDIA
+    /// driver, CPU-only on other Linux machines, PyPI's builds on macOS.
+    pub fn detect(platform: &Platform) -> Result<Self> {
+        if let Ok(value) = std::env::var(TORCH_VARIANT_ENV) {
+            return Self::parse(&value).ok_or_else(|| {
+                anyhow::anyhow!("{}={} is not one of default, cpu, cuda", TORCH_VARIANT_ENV, value)
+            });
+        }
+
+        Ok(match platform.os {
+            Os::MacOs => TorchVariant::Default,
+            Os::Linux if has_nvidia_driver() => TorchVariant::Cuda,
+            Os::Linux => TorchVariant::Cpu,
+        })
+    }
+
+    /// Index torch is installed from, ahead of PyPI.
+    pub fn index_url(&self) -> Option<&'static str> {
+        match self {
+            TorchVariant::Default => None,
+            TorchVariant::Cpu => Some("https://download.pytorch.org/whl/cpu"),
+            TorchVariant::Cuda => Some("https://download.pytorch.org/whl/cu124"),
+        }
+    }
+
+    /// pip options selecting the indexes for this variant.
+    pub fn pip_index_args(&self) -> Vec<String> {
+        match self.index_url() {
+            Some(url) => vec![
+                "--index-url".to_string(),
+                url.to_string(),
+                "--extra-index-url".to_string(),
+                "https://pypi.org/simple".to_string(),
+            ],
+            None => Vec::new(),
+        }
+    }
+
+    fn built_in_lock(&self) -> &'static str {
+        match self {
+            TorchVariant::Default => include_str!("../../locks/default.txt"),
+            TorchVariant::Cpu => include_str!("../../locks/cpu.txt"),
+            TorchVariant::Cuda => include_str!("../../locks/cuda.txt"),
+        }
+    }
+}
+
+impl std::fmt::Display for TorchVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Whether an NVIDIA kernel driver is loaded (Linux only).
+pub fn has_nvidia_driver() -> bool {
+    std::path::Path::new("/proc/driver/nvidia/version").exists()
+}
+
+/// One locked package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockedPackage {
+    /// Normalized package name (e.g., "chatterbox-tts").
+    pub name: String,
+    /// Exact version.
+    pub version: String,
+    /// Accepted hashes, as `sha256:<hex>`.
+    pub hashes: Vec<String>,
+}
+
+/// A parsed lockfile.
+#[derive(Debug, Clone)]
+pub struct LockFile {
+    /// Torch variant the lock was resolved for.
+    pub variant: TorchVariant,
+    /// Locked packages, sorted by name.
+    pub packages: Vec<LockedPackage>,
+    /// Where the lock came from, for messages.
+    pub origin: String,
+}
+
+impl LockFile {
+    /// The lockfile to install from: the local one if it was resolved for
+    /// this machine's torch variant, otherwise the built-in one.
+    pub fn current(platform: &Platform) -> Result<Self> {
+        let variant = TorchVariant::detect(platform)?;
+        let path = local_lock_path()?;
+        if path.exists() {
+            let text = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            let lock = Self::parse(&text, &path.display().to_string())?;
+            if lock.variant == variant {
+                return Ok(lock);
+            }
+        }
+        Self::built_in(variant)
+    }
+
+    /// The lockfile shipped with this binary for a torch variant.
+    pub fn built_in(variant: TorchVariant) -> Result<Self> {
+        Self::parse(variant.built_in_lock(), "built-in")
+    }
+
+    /// Parse a lockfile. Its variant comes from the `# torch-variant:`
+    /// header, and is `default` if there is none.
+    pub fn parse(text: &str, origin: &str) -> Result<Self> {
+        let mut variant = TorchVariant::Default;
+        let mut packages = Vec::new();
+
+        // Join continuation lines first
+        let joined = text.replace("\\\r\n", " ").replace("\\\n", " ");
+        for line in joined.lines() {
+            let line = line.trim();
+            if let Some(comment) = line.strip_prefix('#') {
+                if let Some(name) = comment.trim().strip_prefix("torch-variant:") {
+                    variant = TorchVariant::parse(name)
+                        .ok_or_else(|| anyhow::anyhow!("Unknown torch variant '{}' in {}", name.trim(), origin))?;
+                }
+                continue;
+            }
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut parts = line.split_whitespace();
+            let requirement = parts.next().unwrap_or_default();
+            let (name, version) = requirement
+                .split_once("==")
+                .ok_or_else(|| anyhow::anyhow!("Lock entry '{}' in {} is not pinned with ==", requirement, origin))?;
+            let hashes = parts
+                .filter_map(|option| option.strip_prefix("--hash="))
+                .map(str::to_string)
+                .collect();
+            packages.push(LockedPackage {
+                name: normalize_name(name),
+                version: version.to_string(),
+                hashes,
+            });
+        }
+
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(Self {
+            variant,
+            packages,
+            origin: origin.to_string(),
+        })
+    }
+
+    /// Build a lockfile from the JSON report of
+    /// `pip install --dry-run --ignore-installed --report`.
+    pub fn from_pip_report(report: &str, variant: TorchVariant) -> Result<Self> {
+        let report: serde_json::Value = serde_json::from_str(report).context("Failed to parse pip report")?;
+        let items = report["install"]
+            .as_array()
+            .ok_or_else(|| anyhow::anyhow!("pip report lists nothing to install"))?;
+
+        let mut packages = Vec::new();
+        for item in items {
+            let name = item["metadata"]["name"].as_str().context("pip report entry has no name")?;
+            let version = item["metadata"]["version"].as_str().context("pip report entry has no version")?;
+            let archive = &item["download_info"]["archive_info"];
+            let sha256 = archive["hashes"]["sha256"]
+                .as_str()
+                .or_else(|| archive["hash"].as_str().and_then(|h| h.strip_prefix("sha256=")));
+            packages.push(LockedPackage {
+                name: normalize_name(name),
+                version: version.to_string(),
+                hashes: sha256.map(|h| format!("sha256:{}", h)).into_iter().collect(),
+            });
+        }
+
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(Self {
+            variant,
+            packages,
+            origin: "resolved".to_string(),
+        })
+    }
+
+    /// Write the lock in requirements format.
+    pub fn to_requirements(&self) -> String {
+        let mut text = String::new();
+        text.push_str("# gen-audio Python lock\n");
+        text.push_str(&format!("# torch-variant: {}\n", self.variant));
+        text.push_str("# Regenerate with 'gen-audio setup --upgrade --lock'.\n");
+        for package in &self.packages {
+            text.push_str(&format!("{}=={}", package.name, package.version));
+            for hash in &package.hashes {
+                text.push_str(&format!(" \\\n    --hash={}", hash));
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Whether every package has a hash, so pip can check all of them.
+    pub fn is_hashed(&self) -> bool {
+        !self.packages.is_empty() && self.packages.iter().all(|p| !p.hashes.is_empty())
+    }
+
+    /// Locked version of a package.
+    pub fn version(&self, name: &str) -> Option<&str> {
+        let name = normalize_name(name);
+        self.packages
+            .iter()
+            .find(|p| p.name == name)
+            .map(|p| p.version.as_str())
+    }
+
+    /// Locked versions of the required packages, by name.
+    pub fn required_versions(&self) -> BTreeMap<String, String> {
+        REQUIRED_PACKAGES
+            .iter()
+            .filter_map(|name| Some((name.to_string(), self.version(name)?.to_string())))
+            .collect()
+    }
+
+    /// Save as the local lockfile, used by later installs on this machine.
+    pub fn save_local(&self) -> Result<PathBuf> {
+        let path = local_lock_path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, self.to_requirements()).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+/// Path of the local lockfile written by `setup --upgrade --lock`.
+pub fn local_lock_path() -> Result<PathBuf> {
+    Ok(get_bootstrap_dir()?.join(LOCK_FILE))
+}
+
+/// Normalize a package name as pip compares them (PEP 503).
+pub fn normalize_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    for c in name.trim().chars() {
+        let c = if matches!(c, '_' | '.') { '-' } else { c.to_ascii_lowercase() };
+        if !(c == '-' && normalized.ends_with('-')) {
+            normalized.push(c);
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_built_in_locks() {
+        for variant in [TorchVariant::Default, TorchVariant::Cpu, TorchVariant::Cuda] {
+            let lock = LockFile::built_in(variant).unwrap();
+            assert_eq!(lock.variant, variant);
+            for package in REQUIRED_PACKAGES {
+                assert!(lock.version(package).is_some(), "{} not locked for {}", package, variant);
+            }
+        }
+        assert_eq!(TorchVariant::Default.pip_index_args(), Vec::<String>::new());
+        assert!(TorchVariant::Cpu.pip_index_args()[1].ends_with("/whl/cpu"));
+    }
+
+    #[test]
+    fn test_lock_roundtrip() {
+        let text = "# torch-variant: cuda\n\
+                    Torch==2.6.0+cu124 \\\n    --hash=sha256:aaa \\\n    --hash=sha256:bbb\n\
+                    chatterbox_tts==0.1.2 --hash=sha256:ccc\n";
+        let lock = LockFile::parse(text, "test").unwrap();
+        assert_eq!(lock.variant, TorchVariant::Cuda);
+        assert_eq!(lock.packages.len(), 2);
+        assert_eq!(lock.version("chatterbox-tts"), Some("0.1.2"));
+        assert_eq!(lock.packages[1].hashes, ["sha256:aaa", "sha256:bbb"]);
+        assert!(lock.is_hashed());
+
+        let reparsed = LockFile::parse(&lock.to_requirements(), "test").unwrap();
+        assert_eq!(reparsed.packages, lock.packages);
+        assert_eq!(reparsed.variant, TorchVariant::Cuda);
+
+        assert!(LockFile::parse("torch>=2.0\n", "test").is_err());
+        assert!(!LockFile::parse("torch==2.6.0\n", "test").unwrap().is_hashed());
+    }
+
+    #[test]
+    fn test_from_pip_report() {
+        let report = r#"{
+            "version": "1",
+            "install": [
+                {
+                    "metadata": {"name": "torch", "version": "2.6.0+cpu"},
+                    "download_info": {"url": "https://download.pytorch.org/whl/cpu/torch.whl",
+                                      "archive_info": {"hash": "sha256=abc", "hashes": {"sha256": "abc"}}}
+                },
+                {
+                    "metadata": {"name": "Numpy", "version": "1.26.4"},
+                    "download_info": {"url": "https://files.example/numpy.whl",
+                                      "archive_info": {"hash": "sha256=def"}}
+                }
+            ]
+        }"#;
+        let lock = LockFile::from_pip_report(report, TorchVariant::Cpu).unwrap();
+        assert_eq!(lock.packages[0].name, "numpy");
+        assert_eq!(lock.packages[0].hashes, ["sha256:def"]);
+        assert_eq!(lock.version("torch"), Some("2.6.0+cpu"));
+        assert!(lock.is_hashed());
+        assert_eq!(lock.required_versions().get("torch").map(String::as_str), Some("2.6.0+cpu"));
+    }
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("Chatterbox_TTS"), "chatterbox-tts");
+        assert_eq!(normalize_name("zope.interface"), "zope-interface");
+        assert_eq!(normalize_name("a__b"), "a-b");
+    }
+}
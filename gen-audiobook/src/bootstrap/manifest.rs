@@ -0,0 +1,170 @@
+//! Pinned download manifest for bootstrapped components.
+//!
+//! Every download is listed here with its URL, size and SHA-256, per
+//! platform. Downloads are checked against these values before anything is
+//! extracted, so a changed or corrupted file is caught instead of installed.
+//!
+//! Entries without a `sha256` are not pinned yet: they are downloaded and
+//! their hash is recorded in `versions.json`, but nothing is verified. To pin
+//! one, fill in `size` and `sha256` from the release's published checksums
+//! (python-build-standalone ships a `SHA256SUMS` file with each release) or
+//! from a download you have checked by hand. Don't copy them from a
+//! `versions.json` of unknown origin.
+
+use super::platform::{Arch, Os, Platform};
+
+/// A bootstrapped component that is downloaded as a single file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Component {
+    /// Portable Python archive.
+    Python,
+    /// FFmpeg archive (includes ffprobe on Linux).
+    Ffmpeg,
+    /// FFprobe archive (macOS only).
+    Ffprobe,
+}
+
+impl Component {
+    /// Name used in messages and as the key in `versions.json`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Component::Python => "python",
+            Component::Ffmpeg => "ffmpeg",
+            Component::Ffprobe => "ffprobe",
+        }
+    }
+}
+
+/// One downloadable file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Artifact<'a> {
+    /// Where to download it from. Must point at a fixed release, not a
+    /// "latest" redirect.
+    pub url: &'a str,
+    /// Expected size in bytes, if pinned.
+    pub size: Option<u64>,
+    /// Expected lowercase hex SHA-256, if pinned.
+    pub sha256: Option<&'a str>,
+}
+
+impl Artifact<'_> {
+    /// Whether the download is verified against a known hash.
+    pub fn is_pinned(&self) -> bool {
+        self.sha256.is_some()
+    }
+}
+
+const fn unpinned(url: &'static str) -> Artifact<'static> {
+    Artifact {
+        url,
+        size: None,
+        sha256: None,
+    }
+}
+
+// python-build-standalone 20241206, CPython 3.11.11 (install_only).
+// Must match PYTHON_VERSION and PYTHON_RELEASE_TAG in versions.rs.
+// TODO: pin size and sha256 from the release's SHA256SUMS.
+const PYTHON_MACOS_AARCH64: Artifact<'static> = unpinned(
+    "https://github.com/astral-sh/python-build-standalone/releases/download/20241206/cpython-3.11.11+20241206-aarch64-apple-darwin-install_only.tar.gz",
+);
+const PYTHON_MACOS_X86_64: Artifact<'static> = unpinned(
+    "https://github.com/astral-sh/python-build-standalone/releases/download/20241206/cpython-3.11.11+20241206-x86_64-apple-darwin-install_only.tar.gz",
+);
+const PYTHON_LINUX_X86_64: Artifact<'static> = unpinned(
+    "https://github.com/astral-sh/python-build-standalone/releases/download/20241206/cpython-3.11.11+20241206-x86_64-unknown-linux-gnu-install_only.tar.gz",
+);
+const PYTHON_LINUX_AARCH64: Artifact<'static> = unpinned(
+    "https://github.com/astral-sh/python-build-standalone/releases/download/20241206/cpython-3.11.11+20241206-aarch64-unknown-linux-gnu-install_only.tar.gz",
+);
+
+// Static FFmpeg builds.
+// TODO: these still point at each provider's current release. Replace them
+// with versioned URLs for FFMPEG_VERSION and pin size and sha256; until then
+// a new upstream release is installed without notice.
+const FFMPEG_MACOS_AARCH64: Artifact<'static> =
+    unpinned("https://ffmpeg.martin-riedl.de/redirect/latest/macos/arm64/release/ffmpeg.zip");
+const FFMPEG_MACOS_X86_64: Artifact<'static> =
+    unpinned("https://ffmpeg.martin-riedl.de/redirect/latest/macos/amd64/release/ffmpeg.zip");
+const FFPROBE_MACOS_AARCH64: Artifact<'static> =
+    unpinned("https://ffmpeg.martin-riedl.de/redirect/latest/macos/arm64/release/ffprobe.zip");
+const FFPROBE_MACOS_X86_64: Artifact<'static> =
+    unpinned("https://ffmpeg.martin-riedl.de/redirect/latest/macos/amd64/release/ffprobe.zip");
+const FFMPEG_LINUX_X86_64: Artifact<'static> =
+    unpinned("https://johnvansickle.com/ffmpeg/releases/ffmpeg-release-amd64-static.tar.xz");
+const FFMPEG_LINUX_AARCH64: Artifact<'static> =
+    unpinned("https://johnvansickle.com/ffmpeg/releases/ffmpeg-release-arm64-static.tar.xz");
+
+/// Look up the download for a component on a platform.
+///
+/// Returns `None` when the platform doesn't need it (FFprobe on Linux comes
+/// in the FFmpeg archive).
+pub fn artifact(platform: &Platform, component: Component) -> Option<Artifact<'static>> {
+    let artifact = match (component, platform.os, platform.arch) {
+        (Component::Python, Os::MacOs, Arch::Aarch64) => PYTHON_MACOS_AARCH64,
+        (Component::Python, Os::MacOs, Arch::X86_64) => PYTHON_MACOS_X86_64,
+        (Component::Python, Os::Linux, Arch::X86_64) => PYTHON_LINUX_X86_64,
+        (Component::Python, Os::Linux, Arch::Aarch64) => PYTHON_LINUX_AARCH64,
+        (Component::Ffmpeg, Os::MacOs, Arch::Aarch64) => FFMPEG_MACOS_AARCH64,
+        (Component::Ffmpeg, Os::MacOs, Arch::X86_64) => FFMPEG_MACOS_X86_64,
+        (Component::Ffmpeg, Os::Linux, Arch::X86_64) => FFMPEG_LINUX_X86_64,
+        (Component::Ffmpeg, Os::Linux, Arch::Aarch64) => FFMPEG_LINUX_AARCH64,
+        (Component::Ffprobe, Os::MacOs, Arch::Aarch64) => FFPROBE_MACOS_AARCH64,
+        (Component::Ffprobe, Os::MacOs, Arch::X86_64) => FFPROBE_MACOS_X86_64,
+        (Component::Ffprobe, Os::Linux, _) => return None,
+    };
+    Some(artifact)
+}
+
+/// File name a component's archive is saved under, locally and in bundles.
+pub fn archive_name(platform: &Platform, component: Component) -> &'static str {
+    match (component, platform.os) {
+        (Component::Python, _) => "python.tar.gz",
+        (Component::Ffmpeg, Os::MacOs) => "ffmpeg.zip",
+        (Component::Ffmpeg, Os::Linux) => "ffmpeg.tar.xz",
+        (Component::Ffprobe, _) => "ffprobe.zip",
+    }
+}
+
+/// All components, in install order.
+pub const COMPONENTS: [Component; 3] = [Component::Python, Component::Ffmpeg, Component::Ffprobe];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bootstrap::versions::{PYTHON_RELEASE_TAG, PYTHON_VERSION};
+
+    const PLATFORMS: [Platform; 4] = [
+        Platform { os: Os::MacOs, arch: Arch::Aarch64 },
+        Platform { os: Os::MacOs, arch: Arch::X86_64 },
+        Platform { os: Os::Linux, arch: Arch::X86_64 },
+        Platform { os: Os::Linux, arch: Arch::Aarch64 },
+    ];
+
+    #[test]
+    fn test_python_entries_match_pinned_version() {
+        for platform in &PLATFORMS {
+            let python = artifact(platform, Component::Python).unwrap();
+            assert!(python.url.contains(&format!("/{}/", PYTHON_RELEASE_TAG)));
+            assert!(python.url.contains(&format!("cpython-{}+", PYTHON_VERSION)));
+            assert!(python.url.contains(platform.python_platform_string()));
+        }
+    }
+
+    #[test]
+    fn test_pinned_entries_are_well_formed() {
+        for platform in &PLATFORMS {
+            for component in COMPONENTS {
+                let Some(entry) = artifact(platform, component) else {
+                    continue;
+                };
+                assert!(entry.url.starts_with("https://"));
+                if let Some(sha256) = entry.sha256 {
+                    assert_eq!(sha256.len(), 64, "{}", entry.url);
+                    assert!(sha256.chars().all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase()));
+                    assert!(entry.size.is_some(), "{} is pinned without a size", entry.url);
+                }
+            }
+        }
+    }
+}
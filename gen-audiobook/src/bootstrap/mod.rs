@@ -4,18 +4,37 @@
 //! - Portable Python from python-build-standalone
 //! - Static FFmpeg/FFprobe binaries
 //! - Python virtual environment with Chatterbox TTS dependencies
+//!
+//! Files come from the upstream sites, a configured mirror, or an exported
+//! bundle (see [`bundle`]).
 
+pub mod bundle;
 pub mod download;
+pub mod external;
 pub mod ffmpeg;
+pub mod lock;
+pub mod manifest;
+pub mod models;
 pub mod platform;
+pub mod progress;
 pub mod python;
+#[cfg(test)]
+mod test_server;
 pub mod versions;
 
 use anyhow::{Context, Result};
+use bundle::Source;
+use lock::LockFile;
 use platform::Platform;
-use std::io::{self, Write};
+use std::collections::BTreeMap;
+use std::io::{self, IsTerminal, Write};
 use std::path::PathBuf;
-use versions::{get_data_dir, InstalledVersions, FFMPEG_VERSION, PYTHON_RELEASE_TAG, PYTHON_VERSION};
+use std::sync::OnceLock;
+use manifest::Component;
+use versions::{
+    get_data_dir, get_downloads_dir, InstalledVersions, FFMPEG_VERSION, PYTHON_RELEASE_TAG,
+    PYTHON_VERSION,
+};
 
 /// Paths to bootstrapped components.
 pub struct BootstrapPaths {
@@ -29,11 +48,55 @@ pub struct BootstrapPaths {
     pub ffprobe: PathBuf,
 }
 
+/// Environment variable that gives consent to downloads, like `--yes`.
+pub const ASSUME_YES_ENV: &str = "GEN_AUDIO_ASSUME_YES";
+
+/// Whether setup may download dependencies without asking.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Consent {
+    /// Ask on the terminal.
+    #[default]
+    Ask,
+    /// Go ahead without asking (`--yes` or `GEN_AUDIO_ASSUME_YES`).
+    AssumeYes,
+    /// Fail instead of asking (`--non-interactive`).
+    Refuse,
+}
+
+impl Consent {
+    /// Consent from the command line flags and `GEN_AUDIO_ASSUME_YES`.
+    pub fn from_flags(yes: bool, non_interactive: bool) -> Self {
+        Self::resolve(yes, non_interactive, std::env::var(ASSUME_YES_ENV).ok().as_deref())
+    }
+
+    fn resolve(yes: bool, non_interactive: bool, env: Option<&str>) -> Self {
+        let env_yes = env.is_some_and(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "yes"));
+        if yes || env_yes {
+            Consent::AssumeYes
+        } else if non_interactive {
+            Consent::Refuse
+        } else {
+            Consent::Ask
+        }
+    }
+}
+
+static CONSENT: OnceLock<Consent> = OnceLock::new();
+
+/// Set how consent is obtained for this process. Only the first call has an
+/// effect.
+pub fn set_consent(consent: Consent) {
+    let _ = CONSENT.set(consent);
+}
+
 /// Bootstrap status indicating what needs to be done.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum BootstrapStatus {
     /// Everything is ready to use.
     Ready,
+    /// Everything is ready to use, and some of it is provided externally
+    /// (see [`external`]).
+    External,
     /// Full bootstrap is needed (first run).
     NeedsFullBootstrap,
     /// Only Python packages need to be installed.
@@ -42,38 +105,57 @@ pub enum BootstrapStatus {
     PlatformChanged,
 }
 
+impl BootstrapStatus {
+    /// Whether nothing needs to be installed.
+    pub fn is_ready(&self) -> bool {
+        matches!(self, BootstrapStatus::Ready | BootstrapStatus::External)
+    }
+}
+
 /// Check the current bootstrap status.
+///
+/// Externally provided components are validated rather than checked for
+/// installation, and a broken one is an error: downloading wouldn't fix it.
 pub fn check_status() -> Result<BootstrapStatus> {
     let platform = Platform::detect()?;
     let data_dir = get_data_dir()?;
     let versions = InstalledVersions::load(&data_dir)?;
+    let external = external::external();
+    external.validate()?;
 
     // Check if platform changed
-    if versions.platform.is_some() && !versions.platform_matches(&platform.to_version_string()) {
+    let all_external = external.has_python() && external.has_ffmpeg();
+    if !all_external && versions.platform.is_some() && !versions.platform_matches(&platform.to_version_string()) {
         return Ok(BootstrapStatus::PlatformChanged);
     }
 
     // Check if Python is installed
-    if !python::is_python_installed()? {
+    if !external.has_python() && !python::is_python_installed()? {
         return Ok(BootstrapStatus::NeedsFullBootstrap);
     }
 
     // Check if FFmpeg is installed
-    if !ffmpeg::is_ffmpeg_installed()? {
+    if !external.has_ffmpeg() && !ffmpeg::is_ffmpeg_installed()? {
         return Ok(BootstrapStatus::NeedsFullBootstrap);
     }
 
-    // Check if venv exists
-    if !python::is_venv_ready()? {
-        return Ok(BootstrapStatus::NeedsPackages);
-    }
+    if !external.has_python() {
+        // Check if venv exists
+        if !python::is_venv_ready()? {
+            return Ok(BootstrapStatus::NeedsPackages);
+        }
 
-    // Check if Chatterbox is installed
-    if !python::is_chatterbox_installed()? {
-        return Ok(BootstrapStatus::NeedsPackages);
+        // Check if Chatterbox is installed
+        if !python::is_chatterbox_installed()? {
+            return Ok(BootstrapStatus::NeedsPackages);
+        }
     }
 
-    Ok(BootstrapStatus::Ready)
+    if external.is_empty() {
+        Ok(BootstrapStatus::Ready)
+    } else {
+        Ok(BootstrapStatus::External)
+    }
 }
 
 /// Ensure the bootstrap is complete, running it if needed.
@@ -83,7 +165,7 @@ pub async fn ensure_bootstrapped() -> Result<BootstrapPaths> {
     let status = check_status()?;
 
     match status {
-        BootstrapStatus::Ready => {
+        BootstrapStatus::Ready | BootstrapStatus::External => {
             // Already bootstrapped
         }
         BootstrapStatus::NeedsFullBootstrap => {
@@ -91,21 +173,33 @@ pub async fn ensure_bootstrapped() -> Result<BootstrapPaths> {
             if !confirm_bootstrap()? {
                 anyhow::bail!("Bootstrap cancelled by user");
             }
-            run_full_bootstrap().await?;
+            run_full_bootstrap(&Source::configured().await?).await?;
         }
         BootstrapStatus::NeedsPackages => {
-            eprintln!("Python packages need to be installed...\n");
-            install_packages()?;
+            progress::message("Python packages need to be installed...");
+            install_packages(&Source::configured().await?).await?;
         }
         BootstrapStatus::PlatformChanged => {
-            eprintln!("Platform has changed, reinstalling dependencies...\n");
+            progress::message("Platform has changed, reinstalling dependencies...");
             if !confirm_bootstrap()? {
                 anyhow::bail!("Bootstrap cancelled by user");
             }
-            run_full_bootstrap().await?;
+            run_full_bootstrap(&Source::configured().await?).await?;
         }
     }
 
+    bootstrap_paths()
+}
+
+/// Install everything from `source`, whatever is already installed.
+///
+/// Used for `gen-audio setup --from`, so it doesn't ask for confirmation.
+pub async fn install_from(source: &Source) -> Result<BootstrapPaths> {
+    run_full_bootstrap(source).await?;
+    bootstrap_paths()
+}
+
+fn bootstrap_paths() -> Result<BootstrapPaths> {
     Ok(BootstrapPaths {
         python: python::get_venv_python()?,
         ffmpeg: ffmpeg::get_ffmpeg_executable()?,
@@ -114,7 +208,26 @@ pub async fn ensure_bootstrapped() -> Result<BootstrapPaths> {
 }
 
 /// Show confirmation prompt for first-run bootstrap.
+///
+/// Never reads stdin unless consent is `Ask`, and treats a closed stdin as
+/// a refusal rather than a yes, so runs over SSH or in CI don't hang or
+/// download unasked.
 fn confirm_bootstrap() -> Result<bool> {
+    let hint = format!("Rerun with --yes or set {}=1 to allow it.", ASSUME_YES_ENV);
+    match CONSENT.get().copied().unwrap_or_default() {
+        Consent::AssumeYes => {
+            progress::message("Installing dependencies for gen-audio (~2.1 GB download)...");
+            return Ok(true);
+        }
+        Consent::Refuse => {
+            anyhow::bail!(
+                "gen-audio needs a one-time setup (~2.1 GB download), but --non-interactive was given. {}",
+                hint
+            );
+        }
+        Consent::Ask => {}
+    }
+
     eprintln!();
     eprintln!("gen-audiobook requires a one-time setup (~2.1 GB download):");
     eprintln!("  - Python {} (~25 MB)", PYTHON_VERSION);
@@ -128,62 +241,155 @@ fn confirm_bootstrap() -> Result<bool> {
     io::stderr().flush()?;
 
     let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
+    if io::stdin().read_line(&mut input)? == 0 {
+        eprintln!();
+        let reason = if io::stdin().is_terminal() { "No answer" } else { "No terminal to ask on" };
+        anyhow::bail!("{}: setup not confirmed. {}", reason, hint);
+    }
 
     let input = input.trim().to_lowercase();
     Ok(input.is_empty() || input == "y" || input == "yes")
 }
 
 /// Run the full bootstrap process.
-async fn run_full_bootstrap() -> Result<()> {
+async fn run_full_bootstrap(source: &Source) -> Result<()> {
     let platform = Platform::detect()?;
     let data_dir = get_data_dir()?;
+    // Kept across runs, so an interrupted download resumes
+    let downloads_dir = get_downloads_dir()?;
     let mut versions = InstalledVersions::load(&data_dir)?;
 
-    eprintln!();
-
-    // Step 1: Download Python
-    eprintln!("[1/4] Downloading Python {}...", PYTHON_VERSION);
-    python::install_python(&platform).await?;
-    versions.set_python(PYTHON_VERSION, PYTHON_RELEASE_TAG);
-    versions.set_platform(&platform.to_version_string());
-    versions.save(&data_dir)?;
+    if !matches!(source, Source::Upstream) {
+        progress::message(format!("Installing from {}", source));
+    }
 
-    // Step 2: Create venv
-    eprintln!();
-    eprintln!("[2/4] Setting up Python environment...");
-    python::create_venv()?;
+    // External components are left alone
+    let external = external::external();
+    let install_python = !external.has_python();
+    let install_ffmpeg = !external.has_ffmpeg();
+    if let Some(venv) = external.python_venv.as_ref().filter(|_| !install_python) {
+        progress::message(format!("Using the Python environment at {}", venv.display()));
+    }
+    if let Some(ffmpeg) = external.ffmpeg().filter(|_| !install_ffmpeg) {
+        progress::message(format!("Using FFmpeg at {}", ffmpeg.display()));
+    }
+    let total = if install_python { 3 } else { 0 } + if install_ffmpeg { 1 } else { 0 };
+    let mut step = 0;
+    let mut next_step = |name: String| {
+        step += 1;
+        progress::step(step, total, name);
+    };
+
+    if install_python {
+        // Install Python
+        next_step(format!("Installing Python {}...", PYTHON_VERSION));
+        let archive = source
+            .fetch(&platform, Component::Python, &downloads_dir)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("No Python download for {}", platform))?;
+        python::install_python_archive(&archive.path)?;
+        versions.set_python(PYTHON_VERSION, PYTHON_RELEASE_TAG);
+        versions.set_hash(Component::Python.as_str(), &archive.sha256);
+        versions.set_platform(&platform.to_version_string());
+        versions.save(&data_dir)?;
+        archive.discard();
+
+        // Create venv
+        next_step("Setting up Python environment...".to_string());
+        python::create_venv()?;
+    }
 
-    // Step 3: Download FFmpeg
-    eprintln!();
-    eprintln!("[3/4] Downloading FFmpeg {}...", FFMPEG_VERSION);
-    ffmpeg::install_ffmpeg(&platform).await?;
-    versions.set_ffmpeg(FFMPEG_VERSION);
-    versions.save(&data_dir)?;
+    if install_ffmpeg {
+        next_step(format!("Installing FFmpeg {}...", FFMPEG_VERSION));
+        for component in [Component::Ffmpeg, Component::Ffprobe] {
+            let Some(archive) = source.fetch(&platform, component, &downloads_dir).await? else {
+                continue;
+            };
+            ffmpeg::install_ffmpeg_archive(&platform, component, &archive.path)?;
+            versions.set_hash(component.as_str(), &archive.sha256);
+            archive.discard();
+        }
+        ffmpeg::finish_ffmpeg_install()?;
+        versions.set_ffmpeg(FFMPEG_VERSION);
+        versions.set_platform(&platform.to_version_string());
+        versions.save(&data_dir)?;
+    }
 
-    // Step 4: Install packages
-    eprintln!();
-    eprintln!("[4/4] Installing Chatterbox TTS... (this may take several minutes)");
-    install_packages()?;
+    if install_python {
+        next_step("Installing Chatterbox TTS... (this may take several minutes)".to_string());
+        install_packages(source).await?;
+    }
+    source.install_models().await?;
 
-    eprintln!();
-    eprintln!("Setup complete! Starting conversion...");
-    eprintln!();
+    if !progress::is_json() {
+        eprintln!();
+        eprintln!("Setup complete! Starting conversion...");
+        eprintln!();
+    }
 
     Ok(())
 }
 
-/// Install Python packages into the venv.
-fn install_packages() -> Result<()> {
-    python::install_packages(|msg| {
-        eprintln!("  {}", msg);
-    })?;
+/// Install the locked Python packages into the venv.
+pub async fn install_packages(source: &Source) -> Result<()> {
+    ensure_managed_python()?;
+    let lock = source.lock(&Platform::detect()?).await?;
+    install_locked(&lock, source)
+}
+
+/// Resolve the newest package versions, save them as this machine's
+/// lockfile, and install them. Returns the lockfile's path.
+pub async fn relock_packages(source: &Source) -> Result<PathBuf> {
+    ensure_managed_python()?;
+    let platform = Platform::detect()?;
+    let variant = lock::TorchVariant::detect(&platform)?;
+    progress::detail(format!("Resolving the newest packages for torch variant {}...", variant));
+    let resolved = python::resolve_lock(variant, source.find_links().as_deref())?;
+    if !resolved.is_hashed() {
+        progress::detail("Some packages have no published hash, so only versions are locked.");
+    }
+    let path = resolved.save_local()?;
+    progress::detail(format!("Wrote {}", path.display()));
+
+    install_locked(&LockFile::current(&platform)?, source)?;
+    Ok(path)
+}
+
+/// Fail if the Python environment is external, which gen-audio doesn't
+/// install packages into.
+fn ensure_managed_python() -> Result<()> {
+    match external::external().venv_python() {
+        Some(python) => anyhow::bail!(
+            "The Python environment {} is managed outside gen-audio. Install packages with its own pip, e.g. '{} -m pip install chatterbox-tts'.",
+            python.parent().and_then(|bin| bin.parent()).unwrap_or(&python).display(),
+            python.display()
+        ),
+        None => Ok(()),
+    }
+}
+
+/// Installed versions of the required Python packages, as recorded when
+/// they were installed. Empty if that isn't known.
+pub fn installed_packages() -> BTreeMap<String, String> {
+    get_data_dir()
+        .and_then(|dir| InstalledVersions::load(&dir))
+        .map(|versions| versions.packages)
+        .unwrap_or_default()
+}
+
+fn install_locked(lock: &LockFile, source: &Source) -> Result<()> {
+    python::install_packages(lock, source.find_links().as_deref(), |msg| progress::detail(msg))?;
 
     // Verify Chatterbox is installed
     if !python::is_chatterbox_installed()? {
         anyhow::bail!("Chatterbox installation verification failed");
     }
 
+    let data_dir = get_data_dir()?;
+    let mut versions = InstalledVersions::load(&data_dir)?;
+    versions.set_packages(lock, python::installed_versions()?);
+    versions.save(&data_dir)?;
+
     Ok(())
 }
 
@@ -205,29 +411,37 @@ pub fn clean_all(include_models: bool) -> Result<CleanupStats> {
 
     // Optionally remove HuggingFace model cache for Chatterbox
     if include_models {
-        if let Some(cache_dir) = dirs::cache_dir() {
-            let hf_cache = cache_dir.join("huggingface").join("hub");
-            if hf_cache.exists() {
-                // Find and remove Chatterbox models
-                for entry in std::fs::read_dir(&hf_cache)? {
-                    let entry = entry?;
-                    let name = entry.file_name();
-                    let name_str = name.to_string_lossy();
-
-                    if name_str.contains("ResembleAI") || name_str.contains("chatterbox") {
-                        let size = dir_size(&entry.path()).unwrap_or(0);
-                        std::fs::remove_dir_all(entry.path())?;
-                        stats.models_size += size;
-                        stats.models_removed = true;
-                    }
-                }
-            }
+        for path in chatterbox_model_dirs()? {
+            let size = dir_size(&path).unwrap_or(0);
+            std::fs::remove_dir_all(&path)?;
+            stats.models_size += size;
+            stats.models_removed = true;
         }
     }
 
     Ok(stats)
 }
 
+/// Chatterbox model directories in the HuggingFace hub cache.
+pub fn chatterbox_model_dirs() -> Result<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+    let Some(hf_cache) = versions::get_hf_hub_dir().filter(|d| d.exists()) else {
+        return Ok(dirs);
+    };
+
+    for entry in std::fs::read_dir(&hf_cache)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name_str = name.to_string_lossy();
+
+        if name_str.contains("ResembleAI") || name_str.contains("chatterbox") {
+            dirs.push(entry.path());
+        }
+    }
+
+    Ok(dirs)
+}
+
 /// Statistics about cleanup operation.
 #[derive(Default)]
 pub struct CleanupStats {
@@ -270,15 +484,29 @@ pub fn get_info() -> Result<String> {
     let platform = Platform::detect()?;
     let data_dir = get_data_dir()?;
     let versions = InstalledVersions::load(&data_dir)?;
-    let status = check_status()?;
+    let status = match check_status() {
+        Ok(status) => format!("{:?}", status),
+        Err(e) => format!("error: {:#}", e),
+    };
 
     let mut info = String::new();
 
     info.push_str(&format!("Platform: {}\n", platform));
     info.push_str(&format!("Data directory: {:?}\n", data_dir));
-    info.push_str(&format!("Bootstrap status: {:?}\n", status));
+    info.push_str(&format!("Bootstrap status: {}\n", status));
     info.push_str("\n");
 
+    let external = external::external();
+    if !external.is_empty() {
+        info.push_str("External components:\n");
+        for component in external::ExternalComponent::ALL {
+            if let Some(path) = external.get(component) {
+                info.push_str(&format!("  {}: {}\n", component.key(), path.display()));
+            }
+        }
+        info.push('\n');
+    }
+
     if let Some(ref v) = versions.python_version {
         info.push_str(&format!("Python version: {}\n", v));
     } else {
@@ -291,6 +519,51 @@ pub fn get_info() -> Result<String> {
         info.push_str("FFmpeg: not installed\n");
     }
 
+    for component in manifest::COMPONENTS {
+        let Some(sha256) = versions.hashes.get(component.as_str()) else {
+            continue;
+        };
+        let pinned = manifest::artifact(&platform, component).is_some_and(|a| a.is_pinned());
+        info.push_str(&format!(
+            "{} archive SHA-256: {}{}\n",
+            component.as_str(),
+            sha256,
+            if pinned { " (verified)" } else { " (not pinned)" }
+        ));
+    }
+
+    info.push('\n');
+    match LockFile::current(&platform) {
+        Ok(lock) => {
+            info.push_str(&format!(
+                "Package lock: {} (torch variant {}, {})\n",
+                lock.origin,
+                lock.variant,
+                if lock.is_hashed() { "hashes verified" } else { "versions only" }
+            ));
+            let required = lock.required_versions();
+            for (name, version) in &required {
+                info.push_str(&format!("  {} {}\n", name, version));
+            }
+            if lock.packages.len() > required.len() {
+                info.push_str(&format!("  and {} dependencies\n", lock.packages.len() - required.len()));
+            }
+        }
+        Err(e) => info.push_str(&format!("Package lock: {:#}\n", e)),
+    }
+    if !versions.packages.is_empty() {
+        info.push_str(&format!(
+            "Installed packages (torch variant {}, from {} lock):\n",
+            versions.torch_variant.as_deref().unwrap_or("unknown"),
+            versions.lock.as_deref().unwrap_or("unknown")
+        ));
+        for (name, version) in &versions.packages {
+            info.push_str(&format!("  {} {}\n", name, version));
+        }
+    }
+
+    info.push_str(&format!("Model weights: {}\n", models::describe_installed()));
+
     info.push_str("\n");
     info.push_str(&python::get_env_info()?);
 
@@ -306,3 +579,21 @@ pub fn get_info() -> Result<String> {
 
     Ok(info)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consent_resolve() {
+        assert_eq!(Consent::resolve(false, false, None), Consent::Ask);
+        assert_eq!(Consent::resolve(true, false, None), Consent::AssumeYes);
+        assert_eq!(Consent::resolve(false, true, None), Consent::Refuse);
+        // Explicit permission wins over --non-interactive
+        assert_eq!(Consent::resolve(true, true, None), Consent::AssumeYes);
+        assert_eq!(Consent::resolve(false, true, Some("1")), Consent::AssumeYes);
+        assert_eq!(Consent::resolve(false, false, Some("Yes")), Consent::AssumeYes);
+        assert_eq!(Consent::resolve(false, false, Some("0")), Consent::Ask);
+        assert_eq!(Consent::resolve(false, true, Some("")), Consent::Refuse);
+    }
+}
@@ -0,0 +1,606 @@
+//! Chatterbox model weights in the Hugging Face cache.
+//!
+//! `from_pretrained` downloads the weights on first use, which on a worker
+//! can take longer than the job timeout. `gen-audio models fetch` downloads
+//! them ahead of time instead: the revision is resolved to a commit through
+//! the Hub API, each file is checked against the hash the Hub publishes for
+//! it (the LFS SHA-256, or the git blob id for small files), and the result
+//! is recorded in `versions.json`. The backend then loads that snapshot with
+//! `from_local`, so later runs don't depend on what `main` points to.
+//!
+//! Files are laid out like `huggingface_hub`'s own cache
+//! (`models--<org>--<name>/{blobs,snapshots,refs}`), so the weights are
+//! shared with other tools.
+
+use super::download;
+use super::manifest::Artifact;
+use super::progress;
+use super::versions::{get_data_dir, get_hf_hub_dir, InstalledVersions};
+use crate::coordinator::compute_file_sha256;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Hugging Face repo with the Chatterbox weights.
+pub const MODEL_REPO: &str = "ResembleAI/chatterbox";
+
+/// Files `ChatterboxTTS.from_local` loads.
+pub const MODEL_FILES: &[&str] = &[
+    "ve.safetensors",
+    "t3_cfg.safetensors",
+    "s3gen.safetensors",
+    "tokenizer.json",
+    "conds.pt",
+];
+
+/// Revision fetched when none is given.
+pub const DEFAULT_REVISION: &str = "main";
+
+/// Environment variable with the revision to fetch when none is given.
+/// `workers setup` uses it to give workers the coordinator's weights.
+pub const REVISION_ENV: &str = "GEN_AUDIO_MODEL_REVISION";
+
+/// Hub to download from; same variable as `huggingface_hub`.
+const ENDPOINT_ENV: &str = "HF_ENDPOINT";
+const DEFAULT_ENDPOINT: &str = "https://huggingface.co";
+
+/// Model weights fetched by `gen-audio models fetch`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstalledModel {
+    /// Hugging Face repo, e.g. "ResembleAI/chatterbox".
+    pub repo: String,
+    /// Revision asked for: a branch, tag or commit.
+    pub revision: String,
+    /// Commit the revision resolved to.
+    pub commit: String,
+    /// Snapshot directory holding the files.
+    pub path: PathBuf,
+    /// SHA-256 of each file, by name.
+    pub files: BTreeMap<String, String>,
+    /// When the files were fetched.
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl InstalledModel {
+    /// First characters of the commit, for display.
+    pub fn short_commit(&self) -> &str {
+        &self.commit[..self.commit.len().min(10)]
+    }
+
+    /// Whether every file is in the snapshot directory. Doesn't hash them;
+    /// see [`verify`].
+    pub fn is_complete(&self) -> bool {
+        self.files.keys().all(|name| self.path.join(name).is_file())
+    }
+}
+
+/// A file in a revision, as listed by the Hub API.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct RemoteFile {
+    #[serde(rename = "rfilename")]
+    name: String,
+    /// Git blob id (SHA-1 of the git object).
+    #[serde(rename = "blobId")]
+    blob_id: Option<String>,
+    size: Option<u64>,
+    /// Set for files stored in LFS.
+    lfs: Option<RemoteLfs>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct RemoteLfs {
+    sha256: String,
+    size: u64,
+}
+
+impl RemoteFile {
+    /// Name of the blob in the cache: the LFS SHA-256, or the git blob id,
+    /// as `huggingface_hub` names them.
+    fn blob_name(&self) -> Option<&str> {
+        self.lfs.as_ref().map(|lfs| lfs.sha256.as_str()).or(self.blob_id.as_deref())
+    }
+}
+
+/// A revision resolved through `GET /api/models/<repo>/revision/<rev>?blobs=true`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct RemoteRevision {
+    /// Commit hash.
+    sha: String,
+    #[serde(default)]
+    siblings: Vec<RemoteFile>,
+}
+
+impl RemoteRevision {
+    fn file(&self, name: &str) -> Option<&RemoteFile> {
+        self.siblings.iter().find(|f| f.name == name)
+    }
+}
+
+/// The recorded model, if one was fetched.
+pub fn installed() -> Option<InstalledModel> {
+    let data_dir = get_data_dir().ok()?;
+    InstalledVersions::load(&data_dir).ok()?.model
+}
+
+/// Directory of `repo` in the hub cache: `models--<org>--<name>`.
+pub fn repo_dir(hub: &Path, repo: &str) -> PathBuf {
+    hub.join(format!("models--{}", repo.replace('/', "--")))
+}
+
+/// Revision from [`REVISION_ENV`], if set.
+fn revision_from_env() -> Option<String> {
+    std::env::var(REVISION_ENV).ok().filter(|r| !r.is_empty())
+}
+
+/// Download the weights at `revision` into the hub cache and record them.
+///
+/// Without a revision, [`REVISION_ENV`] is used, then the commit already
+/// pinned (so a plain fetch never moves the pin), then `main`.
+pub async fn fetch(revision: Option<&str>) -> Result<InstalledModel> {
+    let hub = get_hf_hub_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not determine the Hugging Face cache; set HF_HUB_CACHE"))?;
+    let endpoint = std::env::var(ENDPOINT_ENV)
+        .ok()
+        .filter(|e| !e.is_empty())
+        .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string());
+    let revision = revision
+        .map(str::to_string)
+        .or_else(revision_from_env)
+        .or_else(|| installed().map(|model| model.commit))
+        .unwrap_or_else(|| DEFAULT_REVISION.to_string());
+
+    let model = fetch_from(&endpoint, &hub, MODEL_REPO, &revision).await?;
+
+    let data_dir = get_data_dir()?;
+    let mut versions = InstalledVersions::load(&data_dir)?;
+    versions.model = Some(model.clone());
+    versions.save(&data_dir)?;
+    Ok(model)
+}
+
+/// Fetch the weights unless the recorded ones are all there (and match
+/// [`REVISION_ENV`], if set).
+///
+/// Without network access, a complete snapshot already in the cache (from
+/// an earlier `from_pretrained`, or a bundle) is good enough: the backend
+/// falls back to loading it by repo name.
+pub async fn ensure_fetched() -> Result<()> {
+    let wanted = revision_from_env();
+    let current = installed().filter(|model| {
+        model.is_complete()
+            && wanted
+                .as_ref()
+                .is_none_or(|r| *r == model.commit || *r == model.revision)
+    });
+    if let Some(model) = current {
+        progress::message(format!("Model weights: {} at {}", model.repo, model.short_commit()));
+        return Ok(());
+    }
+
+    progress::message(format!("Downloading the {} model weights...", MODEL_REPO));
+    match fetch(None).await {
+        Ok(model) => {
+            progress::message(format!("Model weights: {} at {}", model.repo, model.short_commit()));
+            Ok(())
+        }
+        Err(e) if !complete_snapshots().is_empty() => {
+            progress::message(format!(
+                "Couldn't fetch the model weights ({:#}); using the ones already in the cache",
+                e
+            ));
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+async fn fetch_from(endpoint: &str, hub: &Path, repo: &str, revision: &str) -> Result<InstalledModel> {
+    let endpoint = endpoint.trim_end_matches('/');
+    let remote = resolve(endpoint, repo, revision).await?;
+
+    let repo_dir = repo_dir(hub, repo);
+    let blobs = repo_dir.join("blobs");
+    let snapshot = repo_dir.join("snapshots").join(&remote.sha);
+    let staging = repo_dir.join(".incomplete").join(&remote.sha);
+    for dir in [&blobs, &snapshot, &staging] {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+
+    let mut files = BTreeMap::new();
+    for (i, name) in MODEL_FILES.iter().enumerate() {
+        let file = remote.file(name).ok_or_else(|| {
+            anyhow::anyhow!("{} at {} has no {}; is it a Chatterbox revision?", repo, revision, name)
+        })?;
+        let blob_name = file
+            .blob_name()
+            .ok_or_else(|| anyhow::anyhow!("The Hub didn't list a hash for {}", name))?;
+        let blob = blobs.join(blob_name);
+
+        progress::step(i as u32 + 1, MODEL_FILES.len() as u32, format!("Fetching {}...", name));
+        let cached = blob.is_file().then(|| check_blob(&blob, file).ok()).flatten();
+        let sha256 = if let Some(sha256) = cached {
+            progress::detail("Already downloaded");
+            sha256
+        } else {
+            let url = format!("{}/{}/resolve/{}/{}", endpoint, repo, remote.sha, name);
+            let artifact = Artifact {
+                url: &url,
+                size: file.lfs.as_ref().map(|lfs| lfs.size).or(file.size),
+                sha256: file.lfs.as_ref().map(|lfs| lfs.sha256.as_str()),
+            };
+            let staged = staging.join(name);
+            let mut sha256 = download::download_file(&artifact, &staged, name).await?;
+            // LFS files were checked against their SHA-256 while downloading
+            if file.lfs.is_none() {
+                sha256 = check_blob(&staged, file).inspect_err(|_| {
+                    let _ = std::fs::remove_file(&staged);
+                })?;
+            }
+            std::fs::rename(&staged, &blob).context("Failed to move download into the cache")?;
+            sha256
+        };
+
+        link_snapshot_file(&snapshot, name, blob_name)?;
+        files.insert(name.to_string(), sha256);
+    }
+
+    let _ = std::fs::remove_dir_all(repo_dir.join(".incomplete"));
+    if revision != remote.sha {
+        let refs = repo_dir.join("refs");
+        std::fs::create_dir_all(refs.join(revision).parent().unwrap_or(&refs))?;
+        std::fs::write(refs.join(revision), &remote.sha).context("Failed to write the revision ref")?;
+    }
+
+    Ok(InstalledModel {
+        repo: repo.to_string(),
+        revision: revision.to_string(),
+        commit: remote.sha,
+        path: snapshot,
+        files,
+        fetched_at: Utc::now(),
+    })
+}
+
+/// Resolve `revision` to a commit and its file list.
+async fn resolve(endpoint: &str, repo: &str, revision: &str) -> Result<RemoteRevision> {
+    let url = format!(
+        "{}/api/models/{}/revision/{}?blobs=true",
+        endpoint,
+        repo,
+        revision.replace('/', "%2F")
+    );
+    let response = reqwest::get(&url)
+        .await
+        .with_context(|| format!("Failed to reach {}", endpoint))?;
+    if !response.status().is_success() {
+        anyhow::bail!(
+            "Couldn't resolve {} at revision '{}' (HTTP {})",
+            repo,
+            revision,
+            response.status().as_u16()
+        );
+    }
+    serde_json::from_str(&response.text().await?).with_context(|| format!("Failed to parse {}", url))
+}
+
+/// Check a file against what the Hub lists for it: LFS files by SHA-256,
+/// small files by git blob id. Returns the file's SHA-256.
+fn check_blob(path: &Path, file: &RemoteFile) -> Result<String> {
+    let sha256 = compute_file_sha256(path)?;
+    match (&file.lfs, &file.blob_id) {
+        (Some(lfs), _) if sha256 != lfs.sha256 => {
+            anyhow::bail!("{}: expected SHA-256 {}, got {}", file.name, lfs.sha256, sha256);
+        }
+        (None, Some(blob_id)) => {
+            let actual = git_blob_id(path)?;
+            if actual != *blob_id {
+                anyhow::bail!("{}: expected git blob {}, got {}", file.name, blob_id, actual);
+            }
+        }
+        _ => {}
+    }
+    Ok(sha256)
+}
+
+/// Git's object id for a file's contents: SHA-1 of `blob <len>\0<contents>`.
+fn git_blob_id(path: &Path) -> Result<String> {
+    use sha1::{Digest, Sha1};
+
+    let contents = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut hasher = Sha1::new();
+    hasher.update(format!("blob {}\0", contents.len()).as_bytes());
+    hasher.update(&contents);
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Point `snapshot/name` at `../../blobs/<blob>`, replacing what's there.
+fn link_snapshot_file(snapshot: &Path, name: &str, blob: &str) -> Result<()> {
+    let link = snapshot.join(name);
+    if link.symlink_metadata().is_ok() {
+        std::fs::remove_file(&link).with_context(|| format!("Failed to replace {}", link.display()))?;
+    }
+    std::os::unix::fs::symlink(Path::new("../../blobs").join(blob), &link)
+        .with_context(|| format!("Failed to link {}", link.display()))
+}
+
+/// Check every recorded file against its recorded SHA-256. Returns the
+/// problems found.
+pub fn verify(model: &InstalledModel) -> Result<Vec<String>> {
+    let mut problems = Vec::new();
+    for (name, expected) in &model.files {
+        let path = model.path.join(name);
+        if !path.is_file() {
+            problems.push(format!("{} is missing", name));
+            continue;
+        }
+        progress::detail(format!("Checking {}...", name));
+        let actual = compute_file_sha256(&path)?;
+        if actual != *expected {
+            problems.push(format!("{}: expected SHA-256 {}, got {}", name, expected, actual));
+        }
+    }
+    Ok(problems)
+}
+
+/// A snapshot of the Chatterbox repo in the hub cache.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub commit: String,
+    pub path: PathBuf,
+    /// Total size of the files it points to.
+    pub size: u64,
+    /// Whether it has every file in [`MODEL_FILES`].
+    pub complete: bool,
+}
+
+/// Snapshots of the Chatterbox repo in the hub cache.
+pub fn snapshots() -> Vec<Snapshot> {
+    let Some(hub) = get_hf_hub_dir() else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(repo_dir(&hub, MODEL_REPO).join("snapshots")) else {
+        return Vec::new();
+    };
+
+    let mut snapshots: Vec<_> = entries
+        .flatten()
+        .filter(|e| e.path().is_dir())
+        .map(|e| {
+            let path = e.path();
+            let size = MODEL_FILES
+                .iter()
+                .filter_map(|name| std::fs::metadata(path.join(name)).ok())
+                .map(|m| m.len())
+                .sum();
+            Snapshot {
+                commit: e.file_name().to_string_lossy().into_owned(),
+                complete: MODEL_FILES.iter().all(|name| path.join(name).is_file()),
+                size,
+                path,
+            }
+        })
+        .collect();
+    snapshots.sort_by(|a, b| a.commit.cmp(&b.commit));
+    snapshots
+}
+
+fn complete_snapshots() -> Vec<Snapshot> {
+    snapshots().into_iter().filter(|s| s.complete).collect()
+}
+
+/// Remove the recorded snapshot, or with `all` every cached Chatterbox
+/// snapshot, and forget the recorded model. Blobs still used by a remaining
+/// snapshot are kept. Returns the bytes freed.
+pub fn remove(all: bool) -> Result<u64> {
+    let data_dir = get_data_dir()?;
+    let mut versions = InstalledVersions::load(&data_dir)?;
+    let hub = get_hf_hub_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not determine the Hugging Face cache"))?;
+    let repo_dir = repo_dir(&hub, MODEL_REPO);
+
+    let freed = if all {
+        let size = dir_size(&repo_dir);
+        if repo_dir.exists() {
+            std::fs::remove_dir_all(&repo_dir)
+                .with_context(|| format!("Failed to remove {}", repo_dir.display()))?;
+        }
+        size
+    } else {
+        let model = versions
+            .model
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No model weights were fetched with 'gen-audio models fetch'"))?;
+        remove_snapshot(&repo_dir, &model.path)?
+    };
+
+    versions.model = None;
+    versions.save(&data_dir)?;
+    Ok(freed)
+}
+
+/// Remove one snapshot and the blobs no other snapshot links to.
+fn remove_snapshot(repo_dir: &Path, snapshot: &Path) -> Result<u64> {
+    let blobs_of = |dir: &Path| -> HashSet<PathBuf> {
+        std::fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|e| std::fs::read_link(e.path()).ok())
+            .filter_map(|target| target.file_name().map(|n| repo_dir.join("blobs").join(n)))
+            .collect()
+    };
+
+    let mut in_use = HashSet::new();
+    for other in std::fs::read_dir(repo_dir.join("snapshots")).into_iter().flatten().flatten() {
+        if other.path() != snapshot {
+            in_use.extend(blobs_of(&other.path()));
+        }
+    }
+
+    let mut freed = 0;
+    for blob in blobs_of(snapshot).difference(&in_use) {
+        freed += std::fs::metadata(blob).map(|m| m.len()).unwrap_or(0);
+        let _ = std::fs::remove_file(blob);
+    }
+    if snapshot.exists() {
+        std::fs::remove_dir_all(snapshot).with_context(|| format!("Failed to remove {}", snapshot.display()))?;
+    }
+    Ok(freed)
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|e| match e.file_type() {
+            Ok(t) if t.is_dir() => dir_size(&e.path()),
+            Ok(t) if t.is_file() => e.metadata().map(|m| m.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// One line describing the recorded model, for `gen-audio info`.
+pub fn describe_installed() -> String {
+    match installed() {
+        Some(model) => format!(
+            "{} at {} ({}), {}",
+            model.repo,
+            model.short_commit(),
+            model.revision,
+            if model.is_complete() { "complete" } else { "files missing" }
+        ),
+        None if !complete_snapshots().is_empty() => {
+            "not pinned (cached by an earlier run; pin with 'gen-audio models fetch')".to_string()
+        }
+        None => "not downloaded (fetched on first use, or with 'gen-audio models fetch')".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bootstrap::test_server::serve_dir;
+
+    const COMMIT: &str = "0123456789abcdef0123456789abcdef01234567";
+
+    /// A fake Hub serving a revision of `repo` with the model files, plus the
+    /// API listing for `main`. LFS hashes are listed for the .safetensors
+    /// files and git blob ids for the rest.
+    fn fake_hub(dir: &Path, repo: &str, corrupt: Option<&str>) {
+        let mut siblings = Vec::new();
+        for name in MODEL_FILES {
+            let contents = format!("weights for {}", name);
+            let file = dir.join(repo).join("resolve").join(COMMIT).join(name);
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(&file, &contents).unwrap();
+
+            let listed = if corrupt == Some(*name) { "other contents".to_string() } else { contents.clone() };
+            let listing = dir.join("listed");
+            std::fs::write(&listing, &listed).unwrap();
+            let mut sibling = serde_json::json!({
+                "rfilename": name,
+                "blobId": git_blob_id(&listing).unwrap(),
+                "size": contents.len(),
+            });
+            if name.ends_with(".safetensors") {
+                sibling["lfs"] = serde_json::json!({
+                    "sha256": compute_file_sha256(&listing).unwrap(),
+                    "size": contents.len(),
+                });
+            }
+            siblings.push(sibling);
+        }
+
+        let api = dir.join("api/models").join(repo).join("revision");
+        std::fs::create_dir_all(&api).unwrap();
+        let listing = serde_json::json!({ "sha": COMMIT, "siblings": siblings });
+        std::fs::write(api.join("main?blobs=true"), listing.to_string()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fetch_lays_out_hub_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let served = dir.path().join("hub-server");
+        fake_hub(&served, MODEL_REPO, None);
+        let server = serve_dir(served, false);
+        let hub = dir.path().join("cache");
+
+        let model = fetch_from(&server.url, &hub, MODEL_REPO, "main").await.unwrap();
+        assert_eq!(model.commit, COMMIT);
+        assert_eq!(model.path, repo_dir(&hub, MODEL_REPO).join("snapshots").join(COMMIT));
+        assert!(model.is_complete());
+        assert_eq!(
+            std::fs::read_to_string(model.path.join("conds.pt")).unwrap(),
+            "weights for conds.pt"
+        );
+        assert!(std::fs::symlink_metadata(model.path.join("conds.pt")).unwrap().is_symlink());
+        assert_eq!(
+            std::fs::read_to_string(repo_dir(&hub, MODEL_REPO).join("refs/main")).unwrap(),
+            COMMIT
+        );
+        assert!(verify(&model).unwrap().is_empty());
+
+        // A second fetch reuses the blobs
+        let requests = server.requests.lock().unwrap().len();
+        fetch_from(&server.url, &hub, MODEL_REPO, "main").await.unwrap();
+        assert_eq!(server.requests.lock().unwrap().len(), requests + 1);
+
+        // Changed files are caught
+        let blob = std::fs::canonicalize(model.path.join("tokenizer.json")).unwrap();
+        std::fs::write(&blob, "tampered").unwrap();
+        let problems = verify(&model).unwrap();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("tokenizer.json: expected SHA-256"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_rejects_files_not_matching_hub() {
+        let dir = tempfile::tempdir().unwrap();
+        let served = dir.path().join("hub-server");
+        fake_hub(&served, MODEL_REPO, Some("tokenizer.json"));
+        let server = serve_dir(served, false);
+        let hub = dir.path().join("cache");
+
+        let err = fetch_from(&server.url, &hub, MODEL_REPO, "main").await.unwrap_err();
+        assert!(err.to_string().starts_with("tokenizer.json: expected git blob"), "{}", err);
+
+        let err = fetch_from(&server.url, &hub, MODEL_REPO, "v2").await.unwrap_err();
+        assert!(err.to_string().contains("HTTP 404"), "{}", err);
+    }
+
+    #[test]
+    fn test_remove_snapshot_keeps_shared_blobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_dir(dir.path(), MODEL_REPO);
+        std::fs::create_dir_all(repo.join("blobs")).unwrap();
+        std::fs::write(repo.join("blobs/shared"), "shared").unwrap();
+        std::fs::write(repo.join("blobs/own"), "own").unwrap();
+
+        let old = repo.join("snapshots/old");
+        let new = repo.join("snapshots/new");
+        for snapshot in [&old, &new] {
+            std::fs::create_dir_all(snapshot).unwrap();
+            link_snapshot_file(snapshot, "conds.pt", "shared").unwrap();
+        }
+        link_snapshot_file(&old, "ve.safetensors", "own").unwrap();
+
+        assert_eq!(remove_snapshot(&repo, &old).unwrap(), 3);
+        assert!(!old.exists());
+        assert!(!repo.join("blobs/own").exists());
+        assert_eq!(std::fs::read_to_string(new.join("conds.pt")).unwrap(), "shared");
+    }
+
+    #[test]
+    fn test_git_blob_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hello");
+        std::fs::write(&path, "hello\n").unwrap();
+        // `echo hello | git hash-object --stdin`
+        assert_eq!(git_blob_id(&path).unwrap(), "ce013625030ba8dba906f756967f9e9ca394464a");
+    }
+}
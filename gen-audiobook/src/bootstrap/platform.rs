@@ -82,6 +82,7 @@ impl Platform {
     /// Get the python-build-standalone platform string.
     ///
     /// Examples: "aarch64-apple-darwin", "x86_64-unknown-linux-gnu"
+    #[allow(dead_code)]
     pub fn python_platform_string(&self) -> &'static str {
         match (self.os, self.arch) {
             (Os::MacOs, Arch::Aarch64) => "aarch64-apple-darwin",
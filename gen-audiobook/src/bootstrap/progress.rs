@@ -0,0 +1,194 @@
+//! Progress reporting for bootstrap steps and downloads.
+//!
+//! Bootstrap output goes through [`report`]. In the default human mode it is
+//! printed to stderr as before; with `--progress=json` every step, message
+//! and download update is written to stdout as one JSON object per line, so
+//! a coordinator running `worker install` remotely can relay it.
+
+use super::download::format_bytes;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::sync::OnceLock;
+
+/// How bootstrap progress is shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ProgressMode {
+    /// Messages and progress bars on stderr.
+    #[default]
+    Human,
+    /// JSON lines on stdout.
+    Json,
+}
+
+static MODE: OnceLock<ProgressMode> = OnceLock::new();
+
+/// Set the progress mode for this process. Only the first call has an effect.
+pub fn set_mode(mode: ProgressMode) {
+    let _ = MODE.set(mode);
+}
+
+/// The progress mode for this process.
+pub fn mode() -> ProgressMode {
+    MODE.get().copied().unwrap_or_default()
+}
+
+/// Whether progress is reported as JSON.
+pub fn is_json() -> bool {
+    mode() == ProgressMode::Json
+}
+
+/// One progress event.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    /// A numbered setup step started.
+    Step { step: u32, total: u32, name: String },
+    /// Something worth telling the user.
+    Message { text: String },
+    /// Bytes of `file` downloaded so far. Sent when a download starts, as it
+    /// goes, and when it ends.
+    Download {
+        file: String,
+        bytes: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        total_bytes: Option<u64>,
+    },
+    /// A download finished and was checked.
+    Verified { file: String, sha256: String, pinned: bool },
+    /// Setup finished.
+    Done,
+    /// Setup failed.
+    Error { message: String },
+}
+
+impl std::fmt::Display for ProgressEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProgressEvent::Step { step, total, name } => write!(f, "[{}/{}] {}", step, total, name),
+            ProgressEvent::Message { text } => write!(f, "{}", text),
+            ProgressEvent::Download {
+                file,
+                bytes,
+                total_bytes: Some(total),
+            } => write!(f, "{}: {} / {}", file, format_bytes(*bytes), format_bytes(*total)),
+            ProgressEvent::Download { file, bytes, .. } => write!(f, "{}: {}", file, format_bytes(*bytes)),
+            ProgressEvent::Verified { file, sha256, pinned } => write!(
+                f,
+                "{}: SHA-256 {}{}",
+                file,
+                sha256,
+                if *pinned { " (verified)" } else { " (not pinned)" }
+            ),
+            ProgressEvent::Done => write!(f, "Done"),
+            ProgressEvent::Error { message } => write!(f, "Error: {}", message),
+        }
+    }
+}
+
+/// Report an event.
+///
+/// In human mode, downloads and checksums are left to the progress bar and
+/// only steps and messages are printed.
+pub fn report(event: ProgressEvent) {
+    match mode() {
+        ProgressMode::Json => {
+            if let Ok(line) = serde_json::to_string(&event) {
+                let mut stdout = std::io::stdout().lock();
+                let _ = writeln!(stdout, "{}", line);
+                let _ = stdout.flush();
+            }
+        }
+        ProgressMode::Human => match event {
+            ProgressEvent::Step { .. } => {
+                eprintln!();
+                eprintln!("{}", event);
+            }
+            ProgressEvent::Message { text } => eprintln!("{}", text),
+            ProgressEvent::Error { message } => eprintln!("{}", message),
+            _ => {}
+        },
+    }
+}
+
+/// Report the start of a numbered setup step.
+pub fn step(step: u32, total: u32, name: impl Into<String>) {
+    report(ProgressEvent::Step {
+        step,
+        total,
+        name: name.into(),
+    });
+}
+
+/// Report a message. In human mode it is printed as given.
+pub fn message(text: impl Into<String>) {
+    report(ProgressEvent::Message { text: text.into() });
+}
+
+/// Report a detail within a step. In human mode it is indented.
+pub fn detail(text: impl AsRef<str>) {
+    let text = text.as_ref();
+    if is_json() {
+        message(text);
+    } else {
+        eprintln!("  {}", text);
+    }
+}
+
+/// Report how a command ended: `done`, or `error` with the full error chain.
+/// Nothing is printed in human mode, where the error is shown by `main`.
+pub fn finish<T>(result: &anyhow::Result<T>) {
+    if !is_json() {
+        return;
+    }
+    match result {
+        Ok(_) => report(ProgressEvent::Done),
+        Err(e) => report(ProgressEvent::Error {
+            message: format!("{:#}", e),
+        }),
+    }
+}
+
+/// Parse one line of JSON progress output.
+pub fn parse_event(line: &str) -> Option<ProgressEvent> {
+    serde_json::from_str(line.trim()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_json() {
+        let event = ProgressEvent::Download {
+            file: "python.tar.gz".to_string(),
+            bytes: 1024,
+            total_bytes: Some(4096),
+        };
+        let line = serde_json::to_string(&event).unwrap();
+        assert_eq!(
+            line,
+            r#"{"event":"download","file":"python.tar.gz","bytes":1024,"total_bytes":4096}"#
+        );
+        assert_eq!(parse_event(&line), Some(event));
+
+        assert_eq!(parse_event(r#"{"event":"done"}"#), Some(ProgressEvent::Done));
+        assert_eq!(parse_event("Installing Chatterbox TTS..."), None);
+    }
+
+    #[test]
+    fn test_event_display() {
+        let step = ProgressEvent::Step {
+            step: 1,
+            total: 4,
+            name: "Installing Python 3.11.11...".to_string(),
+        };
+        assert_eq!(step.to_string(), "[1/4] Installing Python 3.11.11...");
+
+        let download = ProgressEvent::Download {
+            file: "ffmpeg.tar.xz".to_string(),
+            bytes: 1024 * 1024,
+            total_bytes: Some(2 * 1024 * 1024),
+        };
+        assert_eq!(download.to_string(), "ffmpeg.tar.xz: 1.0 MB / 2.0 MB");
+    }
+}
@@ -1,33 +1,18 @@
 //! Python download, extraction, and virtual environment setup.
 
-use super::download::download_file;
-use super::platform::Platform;
-use super::versions::{
-    get_python_dir, get_venv_dir, PYTHON_RELEASE_TAG, PYTHON_VERSION,
-};
+use super::lock::{normalize_name, LockFile, TorchVariant};
+use super::progress;
+use super::versions::{get_python_dir, get_venv_dir};
 use anyhow::{Context, Result};
 use flate2::read::GzDecoder;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use tar::Archive;
 
-/// Python packages required for Chatterbox TTS.
-pub const REQUIRED_PACKAGES: &[&str] = &[
-    "torch",
-    "torchaudio",
-    "soundfile",
-    "chatterbox-tts @ git+https://github.com/resemble-ai/chatterbox.git",
-];
-
-/// Get the download URL for the portable Python build.
-pub fn get_python_download_url(platform: &Platform) -> String {
-    format!(
-        "https://github.com/astral-sh/python-build-standalone/releases/download/{tag}/cpython-{version}+{tag}-{arch}-install_only.tar.gz",
-        tag = PYTHON_RELEASE_TAG,
-        version = PYTHON_VERSION,
-        arch = platform.python_platform_string(),
-    )
-}
+/// Python packages required for Chatterbox TTS. Their versions, and those
+/// of their dependencies, come from the lockfile (see [`super::lock`]).
+pub const REQUIRED_PACKAGES: &[&str] = &["chatterbox-tts", "soundfile", "torch", "torchaudio"];
 
 /// Get the path to the Python executable in the bootstrap directory.
 pub fn get_python_executable() -> Result<PathBuf> {
@@ -92,26 +77,13 @@ pub fn is_chatterbox_installed() -> Result<bool> {
     Ok(output.status.success())
 }
 
-/// Download and install the portable Python build.
-pub async fn install_python(platform: &Platform) -> Result<PathBuf> {
+/// Install the portable Python build from a downloaded archive.
+pub fn install_python_archive(archive_path: &Path) -> Result<PathBuf> {
     let python_dir = get_python_dir()?;
-    let url = get_python_download_url(platform);
-
-    // Create temp file for download
-    let temp_dir = tempfile::tempdir()?;
-    let archive_path = temp_dir.path().join("python.tar.gz");
-
-    // Download
-    download_file(
-        &url,
-        &archive_path,
-        &format!("Downloading Python {}...", PYTHON_VERSION),
-    )
-    .await?;
 
     // Extract
-    eprintln!("  Extracting Python...");
-    extract_tar_gz(&archive_path, &python_dir)?;
+    progress::detail("Extracting Python...");
+    extract_tar_gz(archive_path, &python_dir)?;
 
     // Verify installation
     let python_path = get_python_executable()?;
@@ -133,17 +105,20 @@ pub async fn install_python(platform: &Platform) -> Result<PathBuf> {
     }
 
     let version = String::from_utf8_lossy(&output.stdout);
-    eprintln!("  Installed {}", version.trim());
+    progress::detail(format!("Installed {}", version.trim()));
 
     Ok(python_path)
 }
 
 /// Create a virtual environment using the bootstrapped Python.
 pub fn create_venv() -> Result<()> {
+    if super::external::external().has_python() {
+        anyhow::bail!("The Python environment is managed outside gen-audio; not replacing it");
+    }
     let python_path = get_python_executable()?;
     let venv_path = get_venv_dir()?;
 
-    eprintln!("  Creating virtual environment...");
+    progress::detail("Creating virtual environment...");
 
     // Remove existing venv if present
     if venv_path.exists() {
@@ -161,18 +136,24 @@ pub fn create_venv() -> Result<()> {
         anyhow::bail!("Failed to create venv: {}", stderr);
     }
 
-    eprintln!("  Virtual environment created.");
+    progress::detail("Virtual environment created.");
     Ok(())
 }
 
 /// Install a package using pip.
-pub fn pip_install(package: &str, upgrade: bool) -> Result<()> {
+///
+/// With `find_links` (a wheelhouse directory or URL), pip installs from
+/// there only, without contacting an index.
+pub fn pip_install(package: &str, upgrade: bool, find_links: Option<&str>) -> Result<()> {
     let pip_path = get_venv_pip()?;
 
     let mut args = vec!["install"];
     if upgrade {
         args.push("--upgrade");
     }
+    if let Some(links) = find_links {
+        args.extend(["--no-index", "--find-links", links]);
+    }
     args.push(package);
 
     let output = Command::new(&pip_path)
@@ -188,22 +169,125 @@ pub fn pip_install(package: &str, upgrade: bool) -> Result<()> {
     Ok(())
 }
 
-/// Install all required packages into the venv.
-pub fn install_packages(progress_callback: impl Fn(&str)) -> Result<()> {
+/// Install the locked packages into the venv, from a wheelhouse if
+/// `find_links` is given, otherwise from the lock's torch index and PyPI.
+pub fn install_packages(lock: &LockFile, find_links: Option<&str>, progress_callback: impl Fn(&str)) -> Result<()> {
     // Upgrade pip first
     progress_callback("Upgrading pip...");
-    pip_install("pip", true)?;
-
-    // Install each package
-    for (i, package) in REQUIRED_PACKAGES.iter().enumerate() {
-        let package_name = package.split_whitespace().next().unwrap_or(package);
-        progress_callback(&format!(
-            "Installing {} ({}/{})...",
-            package_name,
-            i + 1,
-            REQUIRED_PACKAGES.len()
-        ));
-        pip_install(package, false)?;
+    pip_install("pip", true, find_links)?;
+
+    let required: Vec<String> = lock
+        .required_versions()
+        .iter()
+        .map(|(name, version)| format!("{} {}", name, version))
+        .collect();
+    progress_callback(&format!(
+        "Installing {} (torch variant {}, {} lock{})...",
+        required.join(", "),
+        lock.variant,
+        lock.origin,
+        if lock.is_hashed() { "" } else { ", versions only" }
+    ));
+
+    let requirements = tempfile::NamedTempFile::new()?;
+    std::fs::write(requirements.path(), lock.to_requirements())?;
+
+    let mut command = Command::new(get_venv_pip()?);
+    command.arg("install").arg("-r").arg(requirements.path());
+    if lock.is_hashed() {
+        command.arg("--require-hashes");
+    }
+    match find_links {
+        Some(links) => command.args(["--no-index", "--find-links", links]),
+        None => command.args(lock.variant.pip_index_args()),
+    };
+
+    let output = command.output().context("Failed to run pip install")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("pip install from the {} lock failed: {}", lock.origin, stderr);
+    }
+
+    Ok(())
+}
+
+/// Resolve the newest versions of the required packages and their
+/// dependencies, with hashes, without installing anything.
+pub fn resolve_lock(variant: TorchVariant, find_links: Option<&str>) -> Result<LockFile> {
+    let report_dir = tempfile::tempdir()?;
+    let report = report_dir.path().join("report.json");
+
+    let mut command = Command::new(get_venv_python()?);
+    command
+        .args(["-m", "pip", "install", "--dry-run", "--ignore-installed", "--quiet", "--report"])
+        .arg(&report);
+    match find_links {
+        Some(links) => command.args(["--no-index", "--find-links", links]),
+        None => command.args(variant.pip_index_args()),
+    };
+    command.args(REQUIRED_PACKAGES);
+
+    let output = command.output().context("Failed to run pip")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Resolving packages failed: {}", stderr);
+    }
+
+    let report = std::fs::read_to_string(&report).context("pip wrote no report")?;
+    LockFile::from_pip_report(&report, variant)
+}
+
+/// Installed versions of the required packages, by name.
+pub fn installed_versions() -> Result<BTreeMap<String, String>> {
+    let output = Command::new(get_venv_python()?)
+        .args(["-m", "pip", "list", "--format=json"])
+        .output()
+        .context("Failed to run pip list")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("pip list failed: {}", stderr);
+    }
+
+    let installed: Vec<serde_json::Value> =
+        serde_json::from_slice(&output.stdout).context("Failed to parse pip list output")?;
+    Ok(installed
+        .iter()
+        .filter_map(|p| Some((normalize_name(p["name"].as_str()?), p["version"].as_str()?.to_string())))
+        .filter(|(name, _)| REQUIRED_PACKAGES.contains(&name.as_str()))
+        .collect())
+}
+
+/// Build wheels for pip and every locked package into `wheel_dir`.
+pub fn build_wheelhouse(wheel_dir: &Path, lock: &LockFile) -> Result<()> {
+    let python_path = get_venv_python()?;
+    if !python_path.exists() {
+        anyhow::bail!("Python environment not set up. Run 'gen-audio setup' first.");
+    }
+
+    let requirements = tempfile::NamedTempFile::new()?;
+    std::fs::write(requirements.path(), lock.to_requirements())?;
+
+    // pip itself isn't in the lock, and --require-hashes covers a whole run
+    let runs: [(Vec<&std::ffi::OsStr>, bool); 2] = [
+        (vec!["pip".as_ref()], false),
+        (vec!["-r".as_ref(), requirements.path().as_os_str()], lock.is_hashed()),
+    ];
+    for (args, require_hashes) in runs {
+        let mut command = Command::new(&python_path);
+        command
+            .args(["-m", "pip", "wheel", "--wheel-dir"])
+            .arg(wheel_dir)
+            .args(lock.variant.pip_index_args())
+            .args(args);
+        if require_hashes {
+            command.arg("--require-hashes");
+        }
+
+        let output = command.output().context("Failed to run pip wheel")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("pip wheel failed: {}", stderr);
+        }
     }
 
     Ok(())
@@ -256,7 +340,9 @@ pub fn get_env_info() -> Result<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::bootstrap::platform::{Arch, Os};
+    use crate::bootstrap::manifest::{self, Component};
+    use crate::bootstrap::platform::{Arch, Os, Platform};
+    use crate::bootstrap::versions::PYTHON_VERSION;
 
     #[test]
     fn test_python_download_url() {
@@ -264,12 +350,19 @@ mod tests {
             os: Os::MacOs,
             arch: Arch::Aarch64,
         };
-        let url = get_python_download_url(&platform);
+        let url = manifest::artifact(&platform, Component::Python).unwrap().url;
         assert!(url.contains("aarch64-apple-darwin"));
         assert!(url.contains("install_only"));
         assert!(url.contains(PYTHON_VERSION));
     }
 
+    #[test]
+    fn test_required_packages_are_normalized() {
+        for package in REQUIRED_PACKAGES {
+            assert_eq!(normalize_name(package), *package);
+        }
+    }
+
     #[test]
     fn test_python_paths() {
         let python_exec = get_python_executable().unwrap();
@@ -0,0 +1,69 @@
+//! Minimal static HTTP server standing in for download hosts and mirrors.
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// A server for the files in one directory.
+pub struct TestServer {
+    /// Base URL, without a trailing slash.
+    pub url: String,
+    /// Path and range start of each request, in order.
+    pub requests: Arc<Mutex<Vec<(String, u64)>>>,
+}
+
+/// Serve `dir` over HTTP on localhost, honoring `Range: bytes=N-`.
+///
+/// With `cut_first`, the first response sends only half its body before
+/// closing the connection, like a dropped download.
+pub fn serve_dir(dir: PathBuf, cut_first: bool) -> TestServer {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let seen = requests.clone();
+
+    std::thread::spawn(move || {
+        for (i, stream) in listener.incoming().enumerate() {
+            let Ok(mut stream) = stream else { continue };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut request_line = String::new();
+            let _ = reader.read_line(&mut request_line);
+            let path = request_line.split_whitespace().nth(1).unwrap_or("/").to_string();
+            let mut start = 0u64;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                    break;
+                }
+                if let Some(range) = line.to_lowercase().strip_prefix("range: bytes=") {
+                    start = range.trim().trim_end_matches('-').parse().unwrap_or(0);
+                }
+            }
+            seen.lock().unwrap().push((path.clone(), start));
+
+            let Ok(body) = std::fs::read(dir.join(path.trim_start_matches('/'))) else {
+                let _ = stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+                continue;
+            };
+
+            let rest = &body[(start as usize).min(body.len())..];
+            let head = if start > 0 {
+                format!(
+                    "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\nConnection: close\r\n\r\n",
+                    rest.len(),
+                    start,
+                    body.len().saturating_sub(1),
+                    body.len()
+                )
+            } else {
+                format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", rest.len())
+            };
+            let sent = if cut_first && i == 0 { &rest[..rest.len() / 2] } else { rest };
+            let _ = stream.write_all(head.as_bytes());
+            let _ = stream.write_all(sent);
+        }
+    });
+
+    TestServer { url, requests }
+}
@@ -1,8 +1,11 @@
 //! Version constants and tracking for bootstrapped components.
 
+use super::lock::LockFile;
+use super::models::InstalledModel;
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 /// Pinned Python version for reproducible builds.
@@ -32,6 +35,27 @@ pub struct InstalledVersions {
 
     /// When the installation occurred.
     pub installed_at: Option<DateTime<Utc>>,
+
+    /// SHA-256 of each downloaded archive that was installed, by component
+    /// (e.g., "python", "ffmpeg").
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hashes: BTreeMap<String, String>,
+
+    /// Torch variant the Python packages were installed for (e.g., "cpu").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub torch_variant: Option<String>,
+
+    /// Lockfile the Python packages were installed from (e.g., "built-in").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lock: Option<String>,
+
+    /// Installed versions of the required Python packages, by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub packages: BTreeMap<String, String>,
+
+    /// Model weights fetched with `gen-audio models fetch`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<InstalledModel>,
 }
 
 impl InstalledVersions {
@@ -95,6 +119,22 @@ impl InstalledVersions {
     pub fn set_platform(&mut self, platform: &str) {
         self.platform = Some(platform.to_string());
     }
+
+    /// Record the Python packages installed from a lockfile.
+    pub fn set_packages(&mut self, lock: &LockFile, installed: BTreeMap<String, String>) {
+        self.torch_variant = Some(lock.variant.to_string());
+        self.lock = Some(if lock.is_hashed() {
+            lock.origin.clone()
+        } else {
+            format!("{}, versions only", lock.origin)
+        });
+        self.packages = installed;
+    }
+
+    /// Record the SHA-256 of an installed download.
+    pub fn set_hash(&mut self, component: &str, sha256: &str) {
+        self.hashes.insert(component.to_string(), sha256.to_string());
+    }
 }
 
 /// Get the data directory for gen-audio.
@@ -123,8 +163,35 @@ pub fn get_ffmpeg_dir() -> Result<PathBuf> {
     Ok(get_bootstrap_dir()?.join("ffmpeg"))
 }
 
+/// Get the directory partial and pending downloads are kept in.
+pub fn get_downloads_dir() -> Result<PathBuf> {
+    Ok(get_bootstrap_dir()?.join("downloads"))
+}
+
+/// Get the Hugging Face hub cache, where model weights are downloaded.
+///
+/// Follows the same lookup as `huggingface_hub`: `HF_HUB_CACHE`, then
+/// `HF_HOME/hub`, then `huggingface/hub` under `XDG_CACHE_HOME` or `~/.cache`.
+pub fn get_hf_hub_dir() -> Option<PathBuf> {
+    let var = |name| std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+
+    if let Some(dir) = var("HF_HUB_CACHE") {
+        return Some(dir);
+    }
+    if let Some(home) = var("HF_HOME") {
+        return Some(home.join("hub"));
+    }
+    let cache = var("XDG_CACHE_HOME").or_else(|| dirs::home_dir().map(|h| h.join(".cache")))?;
+    Some(cache.join("huggingface").join("hub"))
+}
+
 /// Get the venv directory.
+///
+/// This is the external environment if one is configured.
 pub fn get_venv_dir() -> Result<PathBuf> {
+    if let Some(venv) = &super::external::external().python_venv {
+        return Ok(venv.clone());
+    }
     Ok(get_data_dir()?.join("venv"))
 }
 
@@ -153,4 +220,19 @@ mod tests {
         assert!(versions.platform_matches("macOS-aarch64"));
         assert!(!versions.platform_matches("Linux-x86_64"));
     }
+
+    #[test]
+    fn test_hashes_roundtrip() {
+        // Files written before hashes were recorded still load
+        let old: InstalledVersions = serde_json::from_str(r#"{"python_version": "3.11.11"}"#).unwrap();
+        assert!(old.hashes.is_empty());
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut versions = InstalledVersions::default();
+        versions.set_hash("ffmpeg", "abc123");
+        versions.save(dir.path()).unwrap();
+
+        let loaded = InstalledVersions::load(dir.path()).unwrap();
+        assert_eq!(loaded.hashes.get("ffmpeg").map(String::as_str), Some("abc123"));
+    }
 }
@@ -1,7 +1,13 @@
 //! gen-audio configuration management for Chatterbox TTS.
 
+use crate::audio::assembler::AAC_BITRATE_KBPS;
+use crate::audio::GapFill;
+use crate::bootstrap::external::ExternalConfig;
+use crate::text::chunker::DEFAULT_TARGET_SIZE;
+use crate::tts::QualityThresholds;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
 
@@ -9,6 +15,7 @@ use std::path::PathBuf;
 const DEFAULT_EXAGGERATION: f32 = 0.5;
 const DEFAULT_CFG: f32 = 0.5;
 const DEFAULT_TEMPERATURE: f32 = 0.8;
+const DEFAULT_GAP_DURATION_MS: u64 = 1000;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GenAudioConfig {
@@ -20,6 +27,11 @@ pub struct GenAudioConfig {
     #[serde(default)]
     pub device: Option<String>,
 
+    /// Command used to play previews, e.g. "afplay" or "ffplay -nodisp -autoexit".
+    /// The audio file path is appended as the last argument.
+    #[serde(default)]
+    pub player: Option<String>,
+
     /// Expressiveness/exaggeration (0.25-2.0)
     #[serde(default = "default_exaggeration")]
     pub exaggeration: f32,
@@ -35,6 +47,135 @@ pub struct GenAudioConfig {
     /// Target chunk size for text processing
     #[serde(default = "default_chunk_size")]
     pub chunk_size: usize,
+
+    /// What to insert for missing chunks when assembling with --allow-gaps
+    #[serde(default)]
+    pub gap_fill: GapFill,
+
+    /// Duration of each inserted gap in milliseconds
+    #[serde(default = "default_gap_duration_ms")]
+    pub gap_duration_ms: u64,
+
+    /// AAC bitrate of the assembled M4B (kbps)
+    #[serde(default = "default_bitrate_kbps")]
+    pub bitrate_kbps: u32,
+
+    /// Base URL of a mirror serving an exported setup bundle, used instead
+    /// of the upstream download sites and PyPI. `GEN_AUDIO_MIRROR_URL`
+    /// overrides it.
+    #[serde(default)]
+    pub mirror_url: Option<String>,
+
+    /// Quality checks applied to each synthesized chunk
+    #[serde(default)]
+    pub quality: QualityThresholds,
+
+    /// Python and FFmpeg to use instead of bootstrapping them
+    #[serde(default, skip_serializing_if = "ExternalConfig::is_empty")]
+    pub external: ExternalConfig,
+
+    /// Named sets of settings, selected with `--profile`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// A named bundle of settings, e.g. `[profiles.thriller]`.
+///
+/// Each value that is set replaces the top-level one; command-line flags
+/// still override both. Chatterbox is the only backend and M4B the only
+/// output format, so those are covered by `device` and `bitrate_kbps`.
+/// A `[profiles.<name>.quality]` table replaces `[quality]` as a whole.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    /// Voice reference audio, or the name of a voice in the library
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voice_ref: Option<PathBuf>,
+
+    /// Device to synthesize on (mps, cuda, cpu)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exaggeration: Option<f32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cfg: Option<f32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_size: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bitrate_kbps: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gap_fill: Option<GapFill>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gap_duration_ms: Option<u64>,
+
+    /// Quality checks, with unset thresholds at their defaults
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quality: Option<QualityThresholds>,
+}
+
+impl Profile {
+    /// Check a profile name is usable as `--profile <NAME>` and a TOML key.
+    pub fn validate_name(name: &str) -> Result<()> {
+        let valid = !name.is_empty()
+            && !name.starts_with('-')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+        if !valid {
+            anyhow::bail!("Invalid profile name '{}': use letters, digits, '-' or '_'", name);
+        }
+        Ok(())
+    }
+
+    /// Out-of-range values, described for an error message.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut range = |name: &str, value: Option<f32>, min: f32, max: f32| {
+            if let Some(value) = value.filter(|v| !(min..=max).contains(v)) {
+                problems.push(format!("{} {} is outside {}-{}", name, value, min, max));
+            }
+        };
+        range("exaggeration", self.exaggeration, 0.25, 2.0);
+        range("cfg", self.cfg, 0.0, 1.0);
+        range("temperature", self.temperature, 0.05, 5.0);
+
+        if let Some(device) = self.device.as_deref().filter(|d| !["mps", "cuda", "cpu"].contains(d)) {
+            problems.push(format!("device \"{}\" isn't one of mps, cuda or cpu", device));
+        }
+        if self.chunk_size == Some(0) {
+            problems.push("chunk_size is 0".to_string());
+        }
+        if self.bitrate_kbps == Some(0) {
+            problems.push("bitrate_kbps is 0".to_string());
+        }
+        problems
+    }
+
+    /// Replace the settings this profile sets.
+    fn apply(&self, config: &mut GenAudioConfig) {
+        if let Some(voice_ref) = &self.voice_ref {
+            config.voice_ref = Some(voice_ref.clone());
+        }
+        if let Some(device) = &self.device {
+            config.device = Some(device.clone());
+        }
+        config.exaggeration = self.exaggeration.unwrap_or(config.exaggeration);
+        config.cfg = self.cfg.unwrap_or(config.cfg);
+        config.temperature = self.temperature.unwrap_or(config.temperature);
+        config.chunk_size = self.chunk_size.unwrap_or(config.chunk_size);
+        config.bitrate_kbps = self.bitrate_kbps.unwrap_or(config.bitrate_kbps);
+        config.gap_fill = self.gap_fill.unwrap_or(config.gap_fill);
+        config.gap_duration_ms = self.gap_duration_ms.unwrap_or(config.gap_duration_ms);
+        if let Some(quality) = &self.quality {
+            config.quality = quality.clone();
+        }
+    }
 }
 
 fn default_exaggeration() -> f32 {
@@ -50,7 +191,15 @@ fn default_temperature() -> f32 {
 }
 
 fn default_chunk_size() -> usize {
-    280
+    DEFAULT_TARGET_SIZE
+}
+
+fn default_gap_duration_ms() -> u64 {
+    DEFAULT_GAP_DURATION_MS
+}
+
+fn default_bitrate_kbps() -> u32 {
+    AAC_BITRATE_KBPS
 }
 
 impl Default for GenAudioConfig {
@@ -58,10 +207,18 @@ impl Default for GenAudioConfig {
         Self {
             voice_ref: None,
             device: None,
+            player: None,
             exaggeration: default_exaggeration(),
             cfg: default_cfg(),
             temperature: default_temperature(),
             chunk_size: default_chunk_size(),
+            gap_fill: GapFill::default(),
+            gap_duration_ms: default_gap_duration_ms(),
+            bitrate_kbps: default_bitrate_kbps(),
+            mirror_url: None,
+            quality: QualityThresholds::default(),
+            external: ExternalConfig::default(),
+            profiles: BTreeMap::new(),
         }
     }
 }
@@ -101,6 +258,21 @@ impl GenAudioConfig {
         fs::write(&path, content)?;
         Ok(())
     }
+
+    /// These settings with a profile's values in place of the top-level ones.
+    pub fn with_profile(mut self, name: Option<&str>) -> Result<Self> {
+        let Some(name) = name else {
+            return Ok(self);
+        };
+        let profile = self.profiles.get(name).cloned().ok_or_else(|| {
+            anyhow::anyhow!(
+                "No profile named '{}' (see 'gen-audio config profile list')",
+                name
+            )
+        })?;
+        profile.apply(&mut self);
+        Ok(self)
+    }
 }
 
 #[cfg(test)]
@@ -115,6 +287,8 @@ mod tests {
         assert_eq!(config.temperature, 0.8);
         assert!(config.voice_ref.is_none());
         assert!(config.device.is_none());
+        assert_eq!(config.gap_fill, GapFill::Silence);
+        assert_eq!(config.gap_duration_ms, 1000);
     }
 
     #[test]
@@ -130,16 +304,24 @@ mod tests {
         let toml_str = r#"
 voice_ref = "/path/to/voice.wav"
 device = "mps"
+player = "ffplay -nodisp -autoexit"
 exaggeration = 0.7
 cfg = 0.3
 temperature = 1.0
+gap_fill = "tone"
+gap_duration_ms = 2500
+mirror_url = "http://mirror.internal/gen-audio"
 "#;
         let config: GenAudioConfig = toml::from_str(toml_str).unwrap();
         assert_eq!(config.voice_ref, Some(PathBuf::from("/path/to/voice.wav")));
         assert_eq!(config.device, Some("mps".to_string()));
+        assert_eq!(config.player.as_deref(), Some("ffplay -nodisp -autoexit"));
         assert_eq!(config.exaggeration, 0.7);
         assert_eq!(config.cfg, 0.3);
         assert_eq!(config.temperature, 1.0);
+        assert_eq!(config.gap_fill, GapFill::Tone);
+        assert_eq!(config.gap_duration_ms, 2500);
+        assert_eq!(config.mirror_url.as_deref(), Some("http://mirror.internal/gen-audio"));
     }
 
     #[test]
@@ -149,5 +331,110 @@ temperature = 1.0
         assert_eq!(config.exaggeration, 0.5);
         assert_eq!(config.cfg, 0.5);
         assert_eq!(config.temperature, 0.8);
+        assert!(config.quality.enabled);
+    }
+
+    #[test]
+    fn test_parse_quality_section() {
+        let toml_str = r#"
+[quality]
+max_silence_ms = 3000
+max_regenerations = 4
+"#;
+        let config: GenAudioConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.quality.max_silence_ms, 3000);
+        assert_eq!(config.quality.max_regenerations, 4);
+        // Unspecified thresholds keep their defaults
+        assert_eq!(config.quality.chars_per_second, 15.0);
+    }
+
+    #[test]
+    fn test_parse_external_section() {
+        let toml_str = r#"
+[external]
+python_venv = "/opt/venvs/tts"
+ffmpeg = "ffmpeg"
+"#;
+        let config: GenAudioConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.external.python_venv, Some(PathBuf::from("/opt/venvs/tts")));
+        assert_eq!(config.external.ffmpeg, Some(PathBuf::from("ffmpeg")));
+        assert!(config.external.python_home.is_none());
+
+        // An empty section isn't written back
+        let written = toml::to_string_pretty(&GenAudioConfig::default()).unwrap();
+        assert!(!written.contains("[external]"));
+    }
+
+    #[test]
+    fn test_profile_overrides_top_level() {
+        let toml_str = r#"
+exaggeration = 0.6
+cfg = 0.4
+voice_ref = "narrator"
+
+[quality]
+max_regenerations = 5
+
+[profiles.thriller]
+exaggeration = 0.9
+voice_ref = "villain"
+gap_fill = "tone"
+bitrate_kbps = 96
+
+[profiles.thriller.quality]
+max_silence_ms = 4000
+"#;
+        let config: GenAudioConfig = toml::from_str(toml_str).unwrap();
+        let thriller = config.clone().with_profile(Some("thriller")).unwrap();
+        assert_eq!(thriller.exaggeration, 0.9);
+        assert_eq!(thriller.voice_ref, Some(PathBuf::from("villain")));
+        assert_eq!(thriller.gap_fill, GapFill::Tone);
+        assert_eq!(thriller.bitrate_kbps, 96);
+        // The quality table is replaced whole
+        assert_eq!(thriller.quality.max_silence_ms, 4000);
+        assert_eq!(thriller.quality.max_regenerations, 2);
+        // Unset values keep the top-level ones, then the defaults
+        assert_eq!(thriller.cfg, 0.4);
+        assert_eq!(thriller.temperature, 0.8);
+        assert_eq!(thriller.chunk_size, DEFAULT_TARGET_SIZE);
+
+        let plain = config.clone().with_profile(None).unwrap();
+        assert_eq!(plain.exaggeration, 0.6);
+        assert_eq!(plain.quality.max_regenerations, 5);
+        assert!(config.with_profile(Some("kids")).is_err());
+    }
+
+    #[test]
+    fn test_profiles_round_trip() {
+        let mut config = GenAudioConfig::default();
+        config.profiles.insert(
+            "kids".to_string(),
+            Profile {
+                temperature: Some(0.6),
+                chunk_size: Some(200),
+                quality: Some(QualityThresholds {
+                    enabled: false,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
+
+        let written = toml::to_string_pretty(&config).unwrap();
+        assert!(written.contains("[profiles.kids]"));
+        let read: GenAudioConfig = toml::from_str(&written).unwrap();
+        assert_eq!(read.profiles, config.profiles);
+    }
+
+    #[test]
+    fn test_profile_problems() {
+        assert!(Profile::default().problems().is_empty());
+        let profile = Profile {
+            exaggeration: Some(3.0),
+            device: Some("tpu".to_string()),
+            chunk_size: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(profile.problems().len(), 3);
     }
 }
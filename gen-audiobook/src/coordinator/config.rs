@@ -43,8 +43,14 @@ impl WorkersConfig {
         let content = std::fs::read_to_string(&config_path)
             .with_context(|| format!("Failed to read {}", config_path.display()))?;
 
-        toml::from_str(&content)
-            .with_context(|| format!("Failed to parse {}", config_path.display()))
+        let config: Self = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", config_path.display()))?;
+
+        for worker in &config.workers {
+            worker.validate()?;
+        }
+
+        Ok(config)
     }
 
     /// Save configuration to the default location.
@@ -116,9 +122,18 @@ pub struct WorkerDefaults {
     #[serde(default = "default_retry_attempts")]
     pub retry_attempts: u32,
 
-    /// Maximum concurrent jobs per worker.
+    /// Maximum concurrent jobs per worker. A worker with a `worker serve`
+    /// session runs one at a time.
     #[serde(default = "default_max_concurrent")]
     pub max_concurrent_jobs: u32,
+
+    /// Seconds between status checks of each worker during a run.
+    #[serde(default = "default_heartbeat")]
+    pub heartbeat_secs: u64,
+
+    /// Failed status checks, or jobs not answered, in a row before a worker stops getting jobs.
+    #[serde(default = "default_unhealthy_after")]
+    pub unhealthy_after: u32,
 }
 
 impl Default for WorkerDefaults {
@@ -128,6 +143,8 @@ impl Default for WorkerDefaults {
             job_timeout_secs: default_job_timeout(),
             retry_attempts: default_retry_attempts(),
             max_concurrent_jobs: default_max_concurrent(),
+            heartbeat_secs: default_heartbeat(),
+            unhealthy_after: default_unhealthy_after(),
         }
     }
 }
@@ -148,16 +165,43 @@ fn default_max_concurrent() -> u32 {
     1
 }
 
+fn default_heartbeat() -> u64 {
+    30
+}
+
+fn default_unhealthy_after() -> u32 {
+    3
+}
+
+/// How the coordinator reaches a worker.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportKind {
+    /// A remote host over SSH.
+    #[default]
+    Ssh,
+    /// A subprocess on this machine.
+    Local,
+    /// Any command prefix, e.g. `docker exec -i tts1`.
+    Command,
+}
+
 /// Configuration for a single worker.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkerConfig {
     /// Unique name for this worker.
     pub name: String,
 
+    /// How to reach the worker (default: ssh).
+    #[serde(default)]
+    pub transport: TransportKind,
+
     /// SSH hostname or IP address.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub host: String,
 
     /// SSH username.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub user: String,
 
     /// SSH port (default: 22).
@@ -179,6 +223,13 @@ pub struct WorkerConfig {
 
     /// Override max concurrent jobs for this worker.
     pub max_concurrent_jobs: Option<u32>,
+
+    /// Command prefix for the `command` transport.
+    pub command_prefix: Option<String>,
+
+    /// Path to gen-audio on the worker (default: `gen-audio` on the PATH,
+    /// or this binary for local workers).
+    pub gen_audio_path: Option<String>,
 }
 
 fn default_port() -> u16 {
@@ -194,6 +245,7 @@ impl WorkerConfig {
     pub fn new(name: impl Into<String>, host: impl Into<String>, user: impl Into<String>) -> Self {
         Self {
             name: name.into(),
+            transport: TransportKind::Ssh,
             host: host.into(),
             user: user.into(),
             port: default_port(),
@@ -202,6 +254,25 @@ impl WorkerConfig {
             ssh_timeout_secs: None,
             job_timeout_secs: None,
             max_concurrent_jobs: None,
+            command_prefix: None,
+            gen_audio_path: None,
+        }
+    }
+
+    /// Create a worker that runs as a subprocess on this machine.
+    pub fn local(name: impl Into<String>) -> Self {
+        Self {
+            transport: TransportKind::Local,
+            ..Self::new(name, "", "")
+        }
+    }
+
+    /// Create a worker reached through a command prefix such as `docker exec -i tts1`.
+    pub fn command(name: impl Into<String>, prefix: impl Into<String>) -> Self {
+        Self {
+            transport: TransportKind::Command,
+            command_prefix: Some(prefix.into()),
+            ..Self::new(name, "", "")
         }
     }
 
@@ -223,13 +294,59 @@ impl WorkerConfig {
         self
     }
 
+    /// Set the path to gen-audio on the worker.
+    pub fn with_gen_audio_path(mut self, path: impl Into<String>) -> Self {
+        self.gen_audio_path = Some(path.into());
+        self
+    }
+
+    /// Check that the settings needed by the transport are present.
+    pub fn validate(&self) -> Result<()> {
+        match self.transport {
+            TransportKind::Ssh if self.host.is_empty() => {
+                anyhow::bail!("Worker '{}' has no host", self.name)
+            }
+            TransportKind::Command
+                if self.command_prefix.as_deref().is_none_or(|p| p.trim().is_empty()) =>
+            {
+                anyhow::bail!("Worker '{}' has no command_prefix", self.name)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Command that runs gen-audio on the worker.
+    pub fn gen_audio_command(&self) -> String {
+        if let Some(ref path) = self.gen_audio_path {
+            return path.clone();
+        }
+
+        match self.transport {
+            TransportKind::Local => std::env::current_exe()
+                .map(|exe| format!("'{}'", exe.to_string_lossy().replace('\'', "'\\''")))
+                .unwrap_or_else(|_| "gen-audio".to_string()),
+            _ => "gen-audio".to_string(),
+        }
+    }
+
+    /// Short description of where the worker runs.
+    pub fn location(&self) -> String {
+        match self.transport {
+            TransportKind::Ssh => format!("{}@{}:{}", self.user, self.host, self.port),
+            TransportKind::Local => "local subprocess".to_string(),
+            TransportKind::Command => format!(
+                "via `{}`",
+                self.command_prefix.as_deref().unwrap_or_default()
+            ),
+        }
+    }
+
     /// Get effective SSH timeout (worker override or default).
     pub fn ssh_timeout(&self, defaults: &WorkerDefaults) -> u64 {
         self.ssh_timeout_secs.unwrap_or(defaults.ssh_timeout_secs)
     }
 
     /// Get effective job timeout.
-    #[allow(dead_code)]
     pub fn job_timeout(&self, defaults: &WorkerDefaults) -> u64 {
         self.job_timeout_secs.unwrap_or(defaults.job_timeout_secs)
     }
@@ -339,5 +456,39 @@ port = 12345
         assert_eq!(config.workers.len(), 2);
         assert_eq!(config.workers[0].name, "gpu1");
         assert_eq!(config.workers[1].port, 12345);
+        assert_eq!(config.workers[1].transport, TransportKind::Ssh);
+    }
+
+    #[test]
+    fn test_parse_other_transports() {
+        let toml = r#"
+[[workers]]
+name = "cores"
+transport = "local"
+max_concurrent_jobs = 4
+
+[[workers]]
+name = "container"
+transport = "command"
+command_prefix = "docker exec -i tts1"
+gen_audio_path = "/usr/local/bin/gen-audio"
+"#;
+
+        let config: WorkersConfig = toml::from_str(toml).unwrap();
+        let local = &config.workers[0];
+        assert_eq!(local.transport, TransportKind::Local);
+        assert!(local.validate().is_ok());
+        assert!(local.gen_audio_command().starts_with('\''));
+
+        let container = &config.workers[1];
+        assert_eq!(container.transport, TransportKind::Command);
+        assert_eq!(container.gen_audio_command(), "/usr/local/bin/gen-audio");
+        assert_eq!(container.location(), "via `docker exec -i tts1`");
+
+        let mut missing = WorkerConfig::command("bad", " ");
+        assert!(missing.validate().is_err());
+        missing.command_prefix = None;
+        assert!(missing.validate().is_err());
+        assert!(WorkerConfig::new("bad", "", "root").validate().is_err());
     }
 }
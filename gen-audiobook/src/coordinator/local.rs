@@ -0,0 +1,135 @@
+//! Local subprocess transport.
+//!
+//! Runs worker commands on this machine, so a multi-core or multi-GPU box
+//! can host several workers next to the coordinator.
+
+use super::transport::Transport;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Transport for a worker running as a local subprocess.
+#[derive(Debug)]
+pub struct LocalTransport {
+    /// Command timeout.
+    timeout: Duration,
+}
+
+impl LocalTransport {
+    /// Create a new local transport.
+    pub fn new(timeout_secs: u64) -> Self {
+        Self {
+            timeout: Duration::from_secs(timeout_secs),
+        }
+    }
+}
+
+/// Expand a leading `~/`, as the worker's shell would.
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()
+            .map(|home| home.join(rest))
+            .unwrap_or_else(|| PathBuf::from(path)),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Copy a file, creating the destination directory.
+async fn copy(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+
+    tokio::fs::copy(from, to)
+        .await
+        .with_context(|| format!("Failed to copy {} to {}", from.display(), to.display()))?;
+    Ok(())
+}
+
+#[async_trait]
+impl Transport for LocalTransport {
+    fn command(&self, command: &str) -> Command {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    }
+
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    async fn upload(&self, local: &Path, remote: &str) -> Result<()> {
+        copy(local, &expand_home(remote)).await
+    }
+
+    async fn download(&self, remote: &str, local: &Path) -> Result<()> {
+        copy(&expand_home(remote), local).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_exec() {
+        let transport = LocalTransport::new(5);
+        transport.test_connection().await.unwrap();
+
+        let output = transport.exec("echo hello && echo world").await.unwrap();
+        assert_eq!(output, "hello\nworld\n");
+
+        let output = transport.exec_with_input("cat", b"input").await.unwrap();
+        assert_eq!(output, b"input");
+
+        assert!(transport.exec("exit 1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_local_exec_lines() {
+        let transport = LocalTransport::new(5);
+        let mut lines = Vec::new();
+        transport
+            .exec_lines("echo one; echo two; test -t 0 || echo closed", &mut |line| {
+                lines.push(line.to_string())
+            })
+            .await
+            .unwrap();
+        assert_eq!(lines, ["one", "two", "closed"]);
+
+        let err = transport
+            .exec_lines("echo partial; echo broken >&2; exit 3", &mut |_| {})
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("broken"));
+    }
+
+    #[tokio::test]
+    async fn test_local_file_transfer() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source.wav");
+        std::fs::write(&source, b"audio").unwrap();
+
+        let transport = LocalTransport::new(5);
+        let remote = dir.path().join("Application Support").join("copy.wav");
+        let remote = remote.to_string_lossy();
+        transport.upload(&source, &remote).await.unwrap();
+        assert!(transport.file_exists(&remote).await.unwrap());
+
+        let hash = transport.sha256(&remote).await.unwrap().unwrap();
+        assert_eq!(hash.len(), 64);
+        let missing = dir.path().join("missing.wav");
+        assert_eq!(transport.sha256(&missing.to_string_lossy()).await.unwrap(), None);
+
+        let back = dir.path().join("back.wav");
+        transport.download(&remote, &back).await.unwrap();
+        assert_eq!(std::fs::read(&back).unwrap(), b"audio");
+
+        transport.remove(&remote).await.unwrap();
+        assert!(!transport.file_exists(&remote).await.unwrap());
+    }
+}
@@ -3,18 +3,27 @@
 //! The coordinator manages workers and distributes jobs across them.
 
 pub mod config;
+pub mod local;
 pub mod pool;
+pub mod prefix;
+pub mod provision;
 pub mod scheduler;
+pub mod session;
 pub mod ssh;
+pub mod transport;
 
-pub use config::{WorkerConfig, WorkersConfig};
-pub use pool::WorkerPool;
-pub use scheduler::{create_jobs, JobScheduler};
-pub use ssh::SshConnection;
+pub use config::{TransportKind, WorkerConfig, WorkersConfig};
+pub use pool::{Worker, WorkerPool, LOCAL_WORKER_NAME};
+pub use scheduler::{adopt_finished_audio, create_jobs, JobScheduler};
+pub use transport::Transport;
 
 use anyhow::{Context, Result};
 use clap::Subcommand;
-use std::path::PathBuf;
+use std::path::Path;
+
+/// Timeout for copying gen-audio to a worker and running `worker install`,
+/// which downloads Python and the model.
+const INSTALL_TIMEOUT_SECS: u64 = 3600;
 
 /// Workers management subcommands.
 #[derive(Subcommand, Debug)]
@@ -27,7 +36,17 @@ pub enum WorkersCommand {
         /// Unique name for this worker.
         name: String,
         /// SSH hostname or IP address.
-        host: String,
+        #[arg(required_unless_present_any = ["local", "exec"])]
+        host: Option<String>,
+        /// Run the worker as a subprocess on this machine.
+        #[arg(long, conflicts_with_all = ["host", "exec"])]
+        local: bool,
+        /// Reach the worker through a command prefix, e.g. "docker exec -i tts1".
+        #[arg(long, value_name = "PREFIX", conflicts_with = "host")]
+        exec: Option<String>,
+        /// Path to gen-audio on the worker.
+        #[arg(long)]
+        gen_audio_path: Option<String>,
         /// SSH username.
         #[arg(short, long, default_value = "root")]
         user: String,
@@ -55,9 +74,27 @@ pub enum WorkersCommand {
     },
 
     /// Set up a worker remotely (install gen-audio and dependencies).
+    ///
+    /// If gen-audio is missing or a different version, and the worker's
+    /// platform matches this machine's, this gen-audio binary is copied to it.
     Setup {
         /// Name of worker to set up.
         name: String,
+
+        /// Don't copy this gen-audio binary to the worker.
+        #[arg(long)]
+        no_push: bool,
+    },
+
+    /// Check a worker's health with `gen-audio doctor`, including a test
+    /// synthesis on each of its devices.
+    Doctor {
+        /// Name of worker to check.
+        name: String,
+
+        /// Skip the test synthesis.
+        #[arg(long)]
+        no_synthesis: bool,
     },
 }
 
@@ -70,12 +107,32 @@ pub async fn handle_workers_command(cmd: &WorkersCommand) -> Result<()> {
         WorkersCommand::Add {
             name,
             host,
+            local,
+            exec,
+            gen_audio_path,
             user,
             port,
             ssh_key,
             priority,
         } => {
-            add_worker(name, host, user, *port, ssh_key.clone(), *priority)
+            let worker = if *local {
+                WorkerConfig::local(name)
+            } else if let Some(prefix) = exec {
+                WorkerConfig::command(name, prefix)
+            } else {
+                let mut worker = WorkerConfig::new(name, host.as_deref().unwrap_or_default(), user)
+                    .with_port(*port);
+                if let Some(key) = ssh_key {
+                    worker = worker.with_ssh_key(key);
+                }
+                worker
+            };
+
+            let mut worker = worker.with_priority(*priority);
+            if let Some(path) = gen_audio_path {
+                worker = worker.with_gen_audio_path(path);
+            }
+            add_worker(worker)
         }
         WorkersCommand::Remove { name } => {
             remove_worker(name)
@@ -83,8 +140,11 @@ pub async fn handle_workers_command(cmd: &WorkersCommand) -> Result<()> {
         WorkersCommand::Test { name } => {
             test_workers(name.as_deref()).await
         }
-        WorkersCommand::Setup { name } => {
-            setup_worker(name).await
+        WorkersCommand::Setup { name, no_push } => {
+            setup_worker(name, !*no_push).await
+        }
+        WorkersCommand::Doctor { name, no_synthesis } => {
+            doctor_worker(name, !*no_synthesis).await
         }
     }
 }
@@ -98,6 +158,8 @@ fn list_workers() -> Result<()> {
         println!();
         println!("Add a worker with:");
         println!("  gen-audio workers add <name> <host> -u <user>");
+        println!("  gen-audio workers add <name> --local");
+        println!("  gen-audio workers add <name> --exec \"docker exec -i <container>\"");
         return Ok(());
     }
 
@@ -106,9 +168,17 @@ fn list_workers() -> Result<()> {
 
     for worker in &config.workers {
         println!("  {} (priority {})", worker.name, worker.priority);
-        println!("    Host: {}@{}:{}", worker.user, worker.host, worker.port);
-        if let Some(ref key) = worker.ssh_key {
-            println!("    SSH key: {}", key);
+        match worker.transport {
+            TransportKind::Ssh => {
+                println!("    Host: {}", worker.location());
+                if let Some(ref key) = worker.ssh_key {
+                    println!("    SSH key: {}", key);
+                }
+            }
+            _ => println!("    Runs: {}", worker.location()),
+        }
+        if let Some(ref path) = worker.gen_audio_path {
+            println!("    gen-audio: {}", path);
         }
         println!();
     }
@@ -117,23 +187,11 @@ fn list_workers() -> Result<()> {
 }
 
 /// Add a new worker.
-fn add_worker(
-    name: &str,
-    host: &str,
-    user: &str,
-    port: u16,
-    ssh_key: Option<String>,
-    priority: u32,
-) -> Result<()> {
-    let mut config = WorkersConfig::load()?;
+fn add_worker(worker: WorkerConfig) -> Result<()> {
+    worker.validate()?;
 
-    let mut worker = WorkerConfig::new(name, host, user)
-        .with_port(port)
-        .with_priority(priority);
-
-    if let Some(key) = ssh_key {
-        worker = worker.with_ssh_key(key);
-    }
+    let mut config = WorkersConfig::load()?;
+    let name = worker.name.clone();
 
     config.add_worker(worker);
     config.save()?;
@@ -209,18 +267,40 @@ async fn test_workers(name: Option<&str>) -> Result<()> {
     Ok(())
 }
 
-/// Set up a worker remotely.
-async fn setup_worker(name: &str) -> Result<()> {
+/// Run `gen-audio doctor` on a worker and print its report.
+async fn doctor_worker(name: &str, synthesis: bool) -> Result<()> {
     let config = WorkersConfig::load()?;
-
     let worker_config = config
         .get_worker(name)
         .ok_or_else(|| anyhow::anyhow!("Worker '{}' not found", name))?;
 
+    println!("Checking worker '{}'...", name);
+    println!();
+
+    // The test synthesis downloads the model on first use
+    let conn = transport::connect(worker_config, INSTALL_TIMEOUT_SECS);
+    let report = provision::worker_doctor(conn.as_ref(), &worker_config.gen_audio_command(), synthesis).await?;
+    report.print();
+
+    if !report.ok {
+        anyhow::bail!("Worker '{}' failed {} check(s)", name, report.failures());
+    }
+    Ok(())
+}
+
+/// Set up a worker remotely.
+async fn setup_worker(name: &str, push: bool) -> Result<()> {
+    let mut config = WorkersConfig::load()?;
+
+    let mut worker_config = config
+        .get_worker(name)
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("Worker '{}' not found", name))?;
+
     println!("Setting up worker '{}'...", name);
     println!();
 
-    let conn = SshConnection::new(worker_config.clone(), 60);
+    let conn = transport::connect(&worker_config, 60);
 
     // Test connection
     print!("Testing connection... ");
@@ -237,21 +317,76 @@ async fn setup_worker(name: &str) -> Result<()> {
         println!("Found: {}", gpu);
     }
 
-    // Check if gen-audio is installed
+    // Check the platform, to know whether this binary runs there
+    print!("Checking platform... ");
+    let platform = provision::remote_platform(conn.as_ref()).await?;
+    let this_platform = provision::Platform::current();
+    println!("{}", platform);
+
+    // Check if gen-audio is installed and matches this one
     print!("Checking for gen-audio... ");
-    let install_check = conn.exec("which gen-audio 2>/dev/null || echo 'NOT_FOUND'").await?;
-    if install_check.trim() == "NOT_FOUND" {
-        println!("NOT INSTALLED");
-        println!();
-        println!("To install gen-audio on the worker, SSH in and run:");
-        println!("  cargo install --git https://github.com/your/repo gen-audiobook");
-        println!("  gen-audio worker install");
-    } else {
+    let installed = provision::worker_status(conn.as_ref(), &worker_config.gen_audio_command()).await;
+    let up_to_date = match &installed {
+        Ok(status) => {
+            println!("{}", status.gen_audio_version);
+            provision::version_mismatches(status).is_empty()
+        }
+        Err(_) => {
+            println!("NOT INSTALLED");
+            false
+        }
+    };
+
+    // Copies and installs can take a while on a slow link
+    let slow_conn = transport::connect(&worker_config, INSTALL_TIMEOUT_SECS);
+
+    let can_push = push && worker_config.transport != TransportKind::Local && platform == this_platform;
+    if !up_to_date && can_push {
+        print!("Copying gen-audio {} to {}... ", env!("CARGO_PKG_VERSION"), provision::REMOTE_BINARY);
+        let exe = std::env::current_exe().context("Failed to locate the gen-audio binary")?;
+        provision::push_binary(slow_conn.as_ref(), &exe, provision::REMOTE_BINARY).await?;
         println!("OK");
 
-        // Run worker install
+        if worker_config.gen_audio_path.as_deref() != Some(provision::REMOTE_BINARY) {
+            worker_config = worker_config.with_gen_audio_path(provision::REMOTE_BINARY);
+            config.add_worker(worker_config.clone());
+            config.save()?;
+            println!("Set gen_audio_path = \"{}\"", provision::REMOTE_BINARY);
+        }
+    } else if installed.is_err() {
+        println!();
+        if push && platform != this_platform {
+            println!(
+                "This gen-audio is built for {}, so it can't be copied to the worker.",
+                this_platform
+            );
+        }
+        println!(
+            "Install gen-audio {} on the worker, then run this again.",
+            env!("CARGO_PKG_VERSION")
+        );
+        println!("If it isn't on PATH there, set gen_audio_path (--gen-audio-path) for the worker.");
+        return Ok(());
+    }
+
+    let gen_audio = worker_config.gen_audio_command();
+
+    // Run worker install. A worker running this version reports its
+    // progress as it goes; an older one is left to print when it's done.
+    if up_to_date || can_push {
+        // Pin the worker to the same model weights as this coordinator
+        let install_command = match crate::bootstrap::models::installed() {
+            Some(model) => format!("{}={} {}", crate::bootstrap::models::REVISION_ENV, model.commit, gen_audio),
+            None => gen_audio.clone(),
+        };
+        println!("Running worker install...");
+        match provision::install_with_progress(slow_conn.as_ref(), &install_command).await {
+            Ok(()) => println!("Worker install OK"),
+            Err(e) => println!("Worker install FAILED: {:#}", e),
+        }
+    } else {
         print!("Running worker install... ");
-        let install_result = conn.exec("gen-audio worker install 2>&1").await;
+        let install_result = slow_conn.exec(&format!("{} worker install 2>&1", gen_audio)).await;
         match install_result {
             Ok(output) => {
                 println!("OK");
@@ -265,11 +400,53 @@ async fn setup_worker(name: &str) -> Result<()> {
         }
     }
 
+    // Check the result against this coordinator
+    print!("Verifying worker... ");
+    let status = provision::worker_status(conn.as_ref(), &gen_audio).await?;
+    if status.ready {
+        println!("ready ({})", status.device);
+    } else {
+        println!("NOT READY");
+    }
+
+    let mismatches = provision::version_mismatches(&status);
+    if mismatches.is_empty() {
+        println!(
+            "Worker matches this coordinator (gen-audio {}, protocol {})",
+            status.gen_audio_version, status.protocol_version
+        );
+    } else {
+        println!("Version mismatch:");
+        for mismatch in &mismatches {
+            println!("  {}", mismatch);
+        }
+    }
+
+    // Different torch or Chatterbox releases can make chunks sound different
+    let mut expected = crate::bootstrap::installed_packages();
+    if expected.is_empty() {
+        let platform = crate::bootstrap::platform::Platform::detect()?;
+        expected = crate::bootstrap::lock::LockFile::current(&platform)?.required_versions();
+    }
+    let package_mismatches = provision::package_mismatches(&status, &expected);
+    if !package_mismatches.is_empty() {
+        println!("Python packages differ from this coordinator's (audio may not match):");
+        for mismatch in &package_mismatches {
+            println!("  {}", mismatch);
+        }
+        println!("Run 'gen-audio setup --upgrade' on both to install the same lock.");
+    }
+
     Ok(())
 }
 
-/// Compute SHA256 hash of a file.
-pub fn compute_file_hash(path: &PathBuf) -> Result<String> {
+/// Compute a short SHA256 hash of a file, used to name it on workers.
+pub fn compute_file_hash(path: &Path) -> Result<String> {
+    Ok(compute_file_sha256(path)?[..16].to_string())
+}
+
+/// Compute the full hex SHA256 of a file.
+pub fn compute_file_sha256(path: &Path) -> Result<String> {
     use sha2::{Digest, Sha256};
     use std::io::Read;
 
@@ -288,5 +465,5 @@ pub fn compute_file_hash(path: &PathBuf) -> Result<String> {
     }
 
     let hash = hasher.finalize();
-    Ok(format!("{:x}", hash)[..16].to_string())
+    Ok(format!("{:x}", hash))
 }
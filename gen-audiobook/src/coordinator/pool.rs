@@ -1,41 +1,104 @@
 //! Worker pool management for distributed processing.
 
 use super::config::{WorkerConfig, WorkerDefaults, WorkersConfig};
-use super::ssh::SshConnection;
+use super::session::WorkerSession;
+use super::transport::{self, Transport};
+use crate::tts::TtsBackend;
+use crate::worker::executor;
 use crate::worker::protocol::{TtsJob, TtsResult, WorkerStatus};
 use anyhow::{Context, Result};
 use std::collections::{HashMap, HashSet};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Where voice references go on workers too old to report their data directories.
+const LEGACY_VOICES_DIR: &str = "~/.gen-audio/worker/voices";
+
+/// Name of the coordinator's own worker, added with `--include-local`.
+pub const LOCAL_WORKER_NAME: &str = "local";
+
+/// Synthesis running inside the coordinator process.
+struct InProcess {
+    /// Backend on the coordinator's own device.
+    backend: Box<dyn TtsBackend>,
+    /// Voice reference, used directly rather than uploaded by hash.
+    voice_ref: Option<PathBuf>,
+    /// Where audio is written until the scheduler collects it.
+    output_dir: PathBuf,
+}
 
 /// A managed worker in the pool.
 pub struct Worker {
     /// Worker configuration.
     pub config: WorkerConfig,
-    /// SSH connection.
-    pub connection: SshConnection,
+    /// Transport used to reach the worker.
+    pub transport: Box<dyn Transport>,
     /// Current status.
     pub status: Option<WorkerStatus>,
-    /// Jobs currently assigned to this worker.
-    pub active_jobs: HashSet<String>,
+    /// Jobs currently running on this worker.
+    active_jobs: Mutex<HashSet<String>>,
     /// Whether connection is established.
     pub connected: bool,
+    /// Persistent `worker serve` session, if one is running.
+    pub session: Option<WorkerSession>,
+    /// Backend for a worker running inside this process.
+    in_process: Option<InProcess>,
 }
 
 impl Worker {
     /// Create a new worker.
     pub fn new(config: WorkerConfig, defaults: &WorkerDefaults) -> Self {
         let timeout = config.ssh_timeout(defaults);
-        let connection = SshConnection::new(config.clone(), timeout);
+        let transport = transport::connect(&config, timeout);
 
         Self {
             config,
-            connection,
+            transport,
             status: None,
-            active_jobs: HashSet::new(),
+            active_jobs: Mutex::new(HashSet::new()),
             connected: false,
+            session: None,
+            in_process: None,
         }
     }
 
+    /// Create a worker that synthesizes in this process with `backend`.
+    ///
+    /// It takes jobs from the same queue as remote workers, one at a time
+    /// since the model generates one chunk at a time. Audio is written to
+    /// `output_dir` and collected from there like a local worker's.
+    /// In-process jobs can't be interrupted, so the job timeout doesn't apply
+    /// to them.
+    pub fn in_process(
+        backend: Box<dyn TtsBackend>,
+        voice_ref: Option<PathBuf>,
+        output_dir: PathBuf,
+        defaults: &WorkerDefaults,
+    ) -> Result<Self> {
+        std::fs::create_dir_all(&output_dir)
+            .with_context(|| format!("Failed to create {}", output_dir.display()))?;
+
+        let mut config = WorkerConfig::local(LOCAL_WORKER_NAME);
+        config.max_concurrent_jobs = Some(1);
+
+        Ok(Self {
+            status: Some(WorkerStatus::ready(backend.device(), 0)),
+            connected: true,
+            in_process: Some(InProcess {
+                backend,
+                voice_ref,
+                output_dir,
+            }),
+            ..Self::new(config, defaults)
+        })
+    }
+
+    /// Whether the worker runs inside this process.
+    pub fn is_in_process(&self) -> bool {
+        self.in_process.is_some()
+    }
+
     /// Get worker name.
     pub fn name(&self) -> &str {
         &self.config.name
@@ -48,7 +111,20 @@ impl Worker {
 
     /// Get number of active jobs.
     pub fn active_job_count(&self) -> usize {
-        self.active_jobs.len()
+        self.active_jobs.lock().unwrap().len()
+    }
+
+    /// Jobs this worker runs at once.
+    ///
+    /// A `worker serve` session shares one model, which generates one chunk
+    /// at a time, so it takes one job at a time whatever the configuration
+    /// says. Without a session each job is its own process.
+    pub fn max_concurrent(&self, defaults: &WorkerDefaults) -> u32 {
+        if self.session.is_some() {
+            1
+        } else {
+            self.config.max_concurrent(defaults)
+        }
     }
 
     /// Check if worker can accept more jobs.
@@ -56,71 +132,150 @@ impl Worker {
         if !self.is_ready() {
             return false;
         }
-        let max = self.config.max_concurrent(defaults);
-        self.active_jobs.len() < max as usize
+        self.active_job_count() < self.max_concurrent(defaults) as usize
     }
 
     /// Connect and get status.
     pub async fn connect(&mut self) -> Result<()> {
         // Test connection
-        self.connection.test_connection().await
+        self.transport.test_connection().await
             .with_context(|| format!("Failed to connect to worker '{}'", self.name()))?;
 
         self.connected = true;
 
-        // Get worker status
-        let output = self.connection.exec("gen-audio worker status").await
+        let status = self.check_status().await?;
+        self.status = Some(status);
+        Ok(())
+    }
+
+    /// Ask the worker for its current status.
+    pub async fn check_status(&self) -> Result<WorkerStatus> {
+        if self.is_in_process() {
+            return self.status.clone().context("In-process worker has no status");
+        }
+
+        let output = self.transport.exec(&self.gen_audio("worker status")).await
             .with_context(|| format!("Failed to get status from worker '{}'", self.name()))?;
 
-        let status: WorkerStatus = serde_json::from_str(&output)
-            .with_context(|| format!("Failed to parse status from worker '{}'", self.name()))?;
+        serde_json::from_str(&output)
+            .with_context(|| format!("Failed to parse status from worker '{}'", self.name()))
+    }
+
+    /// Start a persistent `worker serve` session.
+    ///
+    /// Without one, each job runs in its own `worker run` SSH process.
+    pub async fn start_session(&mut self, startup_timeout: u64) -> Result<()> {
+        let command = self.transport.command(&self.gen_audio("worker serve"));
+        let (session, status) =
+            WorkerSession::start(self.name(), command, Duration::from_secs(startup_timeout))
+                .await?;
 
         self.status = Some(status);
+        self.session = Some(session);
         Ok(())
     }
 
-    /// Check if voice reference is uploaded.
-    pub async fn has_voice_ref(&self, hash: &str) -> Result<bool> {
-        let remote_path = format!("~/.gen-audio/worker/voices/{}.wav", hash);
-        self.connection.file_exists(&remote_path).await
+    /// Command line running a gen-audio subcommand on this worker.
+    fn gen_audio(&self, subcommand: &str) -> String {
+        format!("{} {}", self.config.gen_audio_command(), subcommand)
     }
 
-    /// Upload voice reference file.
-    pub async fn upload_voice_ref(&self, local_path: &Path, hash: &str) -> Result<()> {
-        // Create voices directory
-        self.connection.mkdir("~/.gen-audio/worker/voices").await?;
+    /// Protocol version negotiated with this worker.
+    pub fn protocol_version(&self) -> u32 {
+        self.status
+            .as_ref()
+            .map(WorkerStatus::negotiated_version)
+            .unwrap_or(1)
+    }
 
-        let remote_path = format!("~/.gen-audio/worker/voices/{}.wav", hash);
-        self.connection.upload(local_path, &remote_path).await
-            .with_context(|| format!("Failed to upload voice reference to '{}'", self.name()))
+    /// Directory the worker reads voice references from, as it reported.
+    fn voices_dir(&self) -> &str {
+        self.status
+            .as_ref()
+            .and_then(|s| s.voices_dir.as_deref())
+            .unwrap_or(LEGACY_VOICES_DIR)
+    }
+
+    /// Path of a voice reference on the worker.
+    fn voice_path(&self, hash: &str) -> String {
+        format!("{}/{}.wav", self.voices_dir().trim_end_matches('/'), hash)
+    }
+
+    /// Check if an intact copy of a voice reference is on the worker.
+    pub async fn has_voice_ref(&self, hash: &str, sha256: &str) -> Result<bool> {
+        let remote_sha256 = self.transport.sha256(&self.voice_path(hash)).await?;
+        Ok(remote_sha256.as_deref() == Some(sha256))
+    }
+
+    /// Upload voice reference file and check it arrived intact.
+    pub async fn upload_voice_ref(&self, local_path: &Path, hash: &str, sha256: &str) -> Result<()> {
+        // Create voices directory
+        self.transport.mkdir(self.voices_dir()).await?;
+
+        let remote_path = self.voice_path(hash);
+        self.transport.upload(local_path, &remote_path).await
+            .with_context(|| format!("Failed to upload voice reference to '{}'", self.name()))?;
+
+        let remote_sha256 = self.transport.sha256(&remote_path).await?;
+        if remote_sha256.as_deref() != Some(sha256) {
+            let _ = self.transport.remove(&remote_path).await;
+            anyhow::bail!(
+                "Voice reference on '{}' is damaged after upload (SHA-256 {}, expected {})",
+                self.name(),
+                remote_sha256.as_deref().unwrap_or("missing"),
+                sha256
+            );
+        }
+        Ok(())
     }
 
     /// Submit a job to this worker.
-    pub async fn submit_job(&mut self, job: &TtsJob, job_timeout: u64) -> Result<TtsResult> {
+    ///
+    /// Takes `&self` so several jobs can run on one worker at once.
+    pub async fn submit_job(&self, job: &TtsJob, job_timeout: u64) -> Result<TtsResult> {
         let job_id = job.job_id.clone();
 
-        // Serialize job
-        let job_json = serde_json::to_string(job)
-            .context("Failed to serialize job")?;
-
         // Track active job
-        self.active_jobs.insert(job_id.clone());
-
-        // Execute job
-        let result = self.execute_job(&job_json, job_timeout).await;
+        self.active_jobs.lock().unwrap().insert(job_id.clone());
+
+        // Speak the highest protocol version the worker understands
+        let mut job = job.clone();
+        job.version = self.protocol_version();
+
+        // Execute job, through the persistent session while it is alive
+        let result = if let Some(local) = &self.in_process {
+            Ok(Self::run_in_process(local, &job).await)
+        } else {
+            match self.session.as_ref().filter(|s| !s.is_closed()) {
+                Some(session) => session.submit(&job, Duration::from_secs(job_timeout)).await,
+                None => self.execute_job(&job, job_timeout).await,
+            }
+        };
 
         // Remove from active jobs
-        self.active_jobs.remove(&job_id);
+        self.active_jobs.lock().unwrap().remove(&job_id);
 
         result
     }
 
-    /// Execute job and parse result.
-    async fn execute_job(&self, job_json: &str, timeout: u64) -> Result<TtsResult> {
-        // Create a connection with job timeout
-        let conn = SshConnection::new(self.config.clone(), timeout);
+    /// Synthesize a job in this process, leaving its audio in the output directory.
+    async fn run_in_process(local: &InProcess, job: &TtsJob) -> TtsResult {
+        let options = executor::job_tts_options(job, local.voice_ref.clone());
+        let output_path = local.output_dir.join(format!("{}.wav", job.job_id));
+        executor::synthesize_job(local.backend.as_ref(), job, &options, &output_path, Instant::now())
+            .await
+    }
+
+    /// Execute job in its own `worker run` process and parse the result.
+    async fn execute_job(&self, job: &TtsJob, timeout: u64) -> Result<TtsResult> {
+        // Serialize job
+        let job_json = serde_json::to_string(job)
+            .context("Failed to serialize job")?;
 
-        let output = conn.exec_with_input("gen-audio worker run", job_json.as_bytes()).await
+        // Create a transport with job timeout
+        let conn = transport::connect(&self.config, timeout);
+
+        let output = conn.exec_with_input(&self.gen_audio("worker run"), job_json.as_bytes()).await
             .with_context(|| format!("Job execution failed on worker '{}'", self.name()))?;
 
         let result: TtsResult = serde_json::from_slice(&output)
@@ -131,12 +286,12 @@ impl Worker {
 
     /// Download result audio file.
     pub async fn download_audio(&self, remote_path: &str, local_path: &Path) -> Result<()> {
-        self.connection.download(remote_path, local_path).await
+        self.transport.download(remote_path, local_path).await
     }
 
     /// Clean up remote audio file.
     pub async fn cleanup_audio(&self, remote_path: &str) -> Result<()> {
-        self.connection.remove(remote_path).await
+        self.transport.remove(remote_path).await
     }
 }
 
@@ -196,6 +351,9 @@ impl WorkerPool {
         let mut results = Vec::new();
 
         for worker in &mut self.workers {
+            if worker.is_in_process() {
+                continue;
+            }
             let result = worker.connect().await;
             results.push((worker.name().to_string(), result));
         }
@@ -203,6 +361,22 @@ impl WorkerPool {
         results
     }
 
+    /// Start a persistent session on every ready worker.
+    pub async fn start_sessions(&mut self) -> Vec<(String, Result<()>)> {
+        let mut results = Vec::new();
+        let timeout = self.defaults.job_timeout_secs;
+
+        for worker in &mut self.workers {
+            if !worker.is_ready() || worker.is_in_process() {
+                continue;
+            }
+            let result = worker.start_session(timeout).await;
+            results.push((worker.name().to_string(), result));
+        }
+
+        results
+    }
+
     /// Get list of ready workers.
     pub fn ready_workers(&self) -> Vec<&Worker> {
         self.workers.iter().filter(|w| w.is_ready()).collect()
@@ -217,30 +391,30 @@ impl WorkerPool {
             .collect()
     }
 
-    /// Get an available worker (round-robin by priority).
-    pub fn get_available_worker(&mut self) -> Option<&mut Worker> {
-        // Sort by (priority, active_jobs) to prefer lower priority and less loaded workers
-        self.workers.sort_by_key(|w| (w.config.priority, w.active_job_count()));
-
-        self.workers
-            .iter_mut()
-            .find(|w| w.can_accept_job(&self.defaults))
-    }
-
     /// Get a worker by name.
     pub fn get_worker(&self, name: &str) -> Option<&Worker> {
         self.workers.iter().find(|w| w.name() == name)
     }
 
     /// Get a mutable worker by name.
+    #[allow(dead_code)]
     pub fn get_worker_mut(&mut self, name: &str) -> Option<&mut Worker> {
         self.workers.iter_mut().find(|w| w.name() == name)
     }
 
     /// Ensure voice reference is uploaded to all ready workers.
+    ///
+    /// Each worker gets it in the voices directory it reported, checked
+    /// against the file's full SHA-256. Workers use the clip as it is, so it
+    /// must already be prepared (see [`crate::voices::prepare_reference`]).
     pub async fn ensure_voice_ref(&mut self, local_path: &Path, hash: &str) -> Result<()> {
+        crate::audio::voice::check_prepared(local_path)
+            .context("Voice reference isn't ready to upload to workers")?;
+        let sha256 = super::compute_file_sha256(local_path)?;
+
         for worker in &mut self.workers {
-            if !worker.is_ready() {
+            // An in-process worker reads the voice reference where it is
+            if !worker.is_ready() || worker.is_in_process() {
                 continue;
             }
 
@@ -254,22 +428,36 @@ impl WorkerPool {
             }
 
             // Check if already exists on worker
-            if worker.has_voice_ref(hash).await.unwrap_or(false) {
+            if worker.has_voice_ref(hash, &sha256).await.unwrap_or(false) {
                 worker_voices.insert(hash.to_string());
                 continue;
             }
 
             // Upload
-            worker.upload_voice_ref(local_path, hash).await?;
+            worker.upload_voice_ref(local_path, hash, &sha256).await?;
             worker_voices.insert(hash.to_string());
         }
 
         Ok(())
     }
 
-    /// Get job timeout setting.
-    pub fn job_timeout(&self) -> u64 {
-        self.defaults.job_timeout_secs
+    /// Add a worker that isn't in the configuration.
+    pub fn add_worker(&mut self, worker: Worker) -> Result<()> {
+        if self.get_worker(worker.name()).is_some() {
+            anyhow::bail!("A worker named '{}' is already in the pool", worker.name());
+        }
+        self.workers.push(worker);
+        Ok(())
+    }
+
+    /// Take the workers out of the pool.
+    pub fn into_workers(self) -> Vec<Worker> {
+        self.workers
+    }
+
+    /// Default settings for the pool's workers.
+    pub fn defaults(&self) -> &WorkerDefaults {
+        &self.defaults
     }
 
     /// Get summary of pool status.
@@ -332,4 +520,43 @@ mod tests {
         assert_eq!(status.connected, 0);
         assert_eq!(status.ready, 0);
     }
+
+    #[tokio::test]
+    async fn test_voice_ref_goes_to_reported_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let voices = dir.path().join("Application Support").join("voices");
+        let voice = dir.path().join("voice.wav");
+        std::fs::write(&voice, b"RIFF voice").unwrap();
+        let sha256 = crate::coordinator::compute_file_sha256(&voice).unwrap();
+
+        let mut worker = Worker::new(WorkerConfig::local("w"), &WorkerDefaults::default());
+        worker.status = Some(WorkerStatus::ready("cpu", 1).with_data_dirs(&voices, dir.path()));
+        assert!(!worker.has_voice_ref("abc", &sha256).await.unwrap());
+
+        worker.upload_voice_ref(&voice, "abc", &sha256).await.unwrap();
+        assert_eq!(std::fs::read(voices.join("abc.wav")).unwrap(), b"RIFF voice");
+        assert!(worker.has_voice_ref("abc", &sha256).await.unwrap());
+
+        // A copy that doesn't match is removed rather than left for jobs to use
+        let wrong = "0".repeat(64);
+        assert!(!worker.has_voice_ref("abc", &wrong).await.unwrap());
+        assert!(worker.upload_voice_ref(&voice, "abc", &wrong).await.is_err());
+        assert!(!voices.join("abc.wav").exists());
+    }
+
+    #[tokio::test]
+    async fn test_only_prepared_voice_refs_are_uploaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let voice = dir.path().join("voice.wav");
+        let mut pool = WorkerPool::new(&WorkersConfig::default());
+
+        std::fs::write(&voice, crate::audio::wav::test_wav(&[0; 24000 * 6])).unwrap();
+        pool.ensure_voice_ref(&voice, "abc").await.unwrap();
+
+        // Too short to clone from, as an unprepared clip might be
+        std::fs::write(&voice, crate::audio::wav::test_wav(&[0; 24000])).unwrap();
+        assert!(pool.ensure_voice_ref(&voice, "abc").await.is_err());
+        std::fs::write(&voice, b"ID3 mp3 data").unwrap();
+        assert!(pool.ensure_voice_ref(&voice, "abc").await.is_err());
+    }
 }
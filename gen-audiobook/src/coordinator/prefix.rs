@@ -0,0 +1,120 @@
+//! Command-prefix transport.
+//!
+//! Runs worker commands through an arbitrary prefix such as
+//! `docker exec -i tts1` or `podman exec -i tts1`. Files are moved by piping
+//! them through `cat`, so the prefix only needs to forward stdin and stdout.
+
+use super::transport::{quote_path, Transport};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::path::Path;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Transport for a worker reached through a command prefix.
+#[derive(Debug)]
+pub struct PrefixTransport {
+    /// Program and arguments placed before `sh -c <command>`.
+    prefix: Vec<String>,
+    /// Command timeout.
+    timeout: Duration,
+}
+
+impl PrefixTransport {
+    /// Create a transport from a whitespace-separated prefix.
+    pub fn new(prefix: &str, timeout_secs: u64) -> Self {
+        Self {
+            prefix: prefix.split_whitespace().map(str::to_string).collect(),
+            timeout: Duration::from_secs(timeout_secs),
+        }
+    }
+}
+
+/// Directory part of a worker path, if any.
+fn remote_parent(remote: &str) -> Option<&str> {
+    remote
+        .rsplit_once('/')
+        .map(|(parent, _)| parent)
+        .filter(|parent| !parent.is_empty())
+}
+
+#[async_trait]
+impl Transport for PrefixTransport {
+    fn command(&self, command: &str) -> Command {
+        // An empty prefix is rejected when the config is loaded
+        let (program, args) = match self.prefix.split_first() {
+            Some((program, args)) => (program.as_str(), args),
+            None => ("", &[][..]),
+        };
+
+        let mut cmd = Command::new(program);
+        cmd.args(args).arg("sh").arg("-c").arg(command);
+        cmd
+    }
+
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    async fn upload(&self, local: &Path, remote: &str) -> Result<()> {
+        let data = tokio::fs::read(local)
+            .await
+            .with_context(|| format!("Failed to read {}", local.display()))?;
+
+        let command = match remote_parent(remote) {
+            Some(parent) => format!(
+                "mkdir -p {} && cat > {}",
+                quote_path(parent),
+                quote_path(remote)
+            ),
+            None => format!("cat > {}", quote_path(remote)),
+        };
+        self.exec_with_input(&command, &data)
+            .await
+            .context("Upload failed")?;
+        Ok(())
+    }
+
+    async fn download(&self, remote: &str, local: &Path) -> Result<()> {
+        let data = self
+            .exec_with_input(&format!("cat {}", quote_path(remote)), &[])
+            .await
+            .context("Download failed")?;
+
+        tokio::fs::write(local, data)
+            .await
+            .with_context(|| format!("Failed to write {}", local.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_prefix_transport() {
+        // `env` stands in for `docker exec -i <container>`
+        let transport = PrefixTransport::new("env GEN_AUDIO_TEST=1", 5);
+        let output = transport.exec("echo $GEN_AUDIO_TEST").await.unwrap();
+        assert_eq!(output.trim(), "1");
+
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source.wav");
+        std::fs::write(&source, b"audio").unwrap();
+
+        let remote = dir.path().join("container").join("copy.wav");
+        let remote = remote.to_string_lossy();
+        transport.upload(&source, &remote).await.unwrap();
+
+        let back = dir.path().join("back.wav");
+        transport.download(&remote, &back).await.unwrap();
+        assert_eq!(std::fs::read(&back).unwrap(), b"audio");
+    }
+
+    #[test]
+    fn test_remote_parent() {
+        assert_eq!(remote_parent("~/voices/a.wav"), Some("~/voices"));
+        assert_eq!(remote_parent("/a.wav"), None);
+        assert_eq!(remote_parent("a.wav"), None);
+    }
+}
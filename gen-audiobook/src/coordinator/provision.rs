@@ -0,0 +1,311 @@
+//! Provisioning gen-audio on workers.
+//!
+//! `workers setup` copies the coordinator's own binary to a worker whose
+//! platform matches this one, so a new machine needs nothing installed
+//! beforehand. The worker's status is then checked against the coordinator's
+//! version and protocol.
+
+use super::transport::{quote_path, Transport};
+use crate::doctor::Report;
+use crate::bootstrap::progress::{self, ProgressEvent};
+use crate::worker::protocol::{WorkerStatus, PROTOCOL_VERSION};
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+/// Where a pushed binary is installed on the worker.
+pub const REMOTE_BINARY: &str = "~/.gen-audio/bin/gen-audio";
+
+/// Operating system and CPU architecture, as Rust names them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Platform {
+    /// Operating system (`linux`, `macos`).
+    pub os: String,
+    /// CPU architecture (`x86_64`, `aarch64`).
+    pub arch: String,
+}
+
+impl Platform {
+    /// Platform this binary was built for.
+    pub fn current() -> Self {
+        Self {
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+        }
+    }
+
+    /// Parse the output of `uname -s -m`.
+    pub fn from_uname(output: &str) -> Option<Self> {
+        let mut parts = output.split_whitespace();
+        let (os, arch) = (parts.next()?, parts.next()?);
+
+        let os = match os {
+            "Darwin" => "macos".to_string(),
+            other => other.to_lowercase(),
+        };
+        let arch = match arch {
+            "amd64" => "x86_64",
+            "arm64" => "aarch64",
+            other => other,
+        };
+
+        Some(Self {
+            os,
+            arch: arch.to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.os, self.arch)
+    }
+}
+
+/// Detect a worker's platform.
+pub async fn remote_platform(conn: &dyn Transport) -> Result<Platform> {
+    let output = conn.exec("uname -s -m").await?;
+    Platform::from_uname(&output)
+        .ok_or_else(|| anyhow::anyhow!("Unrecognized uname output: {}", output.trim()))
+}
+
+/// Copy a gen-audio binary to `remote` on the worker and make it executable.
+///
+/// The file is uploaded under a temporary name and moved into place, so a
+/// failed upload never replaces a working binary.
+pub async fn push_binary(conn: &dyn Transport, local: &Path, remote: &str) -> Result<()> {
+    if let Some((parent, _)) = remote.rsplit_once('/') {
+        conn.mkdir(parent).await?;
+    }
+
+    let part = format!("{}.part", remote);
+    conn.upload(local, &part)
+        .await
+        .with_context(|| format!("Failed to upload {}", local.display()))?;
+    let (part, remote) = (quote_path(&part), quote_path(remote));
+    conn.exec(&format!("chmod +x {} && mv -f {} {}", part, part, remote))
+        .await
+        .context("Failed to install the uploaded binary")?;
+    Ok(())
+}
+
+/// Ask a worker for its status using the given gen-audio command.
+pub async fn worker_status(conn: &dyn Transport, gen_audio: &str) -> Result<WorkerStatus> {
+    let output = conn.exec(&format!("{} worker status", gen_audio)).await?;
+    serde_json::from_str(&output).context("Failed to parse worker status")
+}
+
+/// Run `gen-audio doctor` on a worker and return its report.
+///
+/// A report with failed checks is still returned: doctor's exit status only
+/// says whether one failed.
+pub async fn worker_doctor(conn: &dyn Transport, gen_audio: &str, synthesis: bool) -> Result<Report> {
+    let flags = if synthesis { "--json" } else { "--json --no-synthesis" };
+    let output = conn.exec(&format!("{} doctor {} || true", gen_audio, flags)).await?;
+    serde_json::from_str(&output)
+        .context("Failed to parse the doctor report; the worker's gen-audio may be too old to have 'doctor'")
+}
+
+/// Run `worker install` non-interactively, printing the worker's setup steps
+/// and downloads as they happen.
+///
+/// The worker must run a gen-audio that understands `--progress=json`.
+pub async fn install_with_progress(conn: &dyn Transport, gen_audio: &str) -> Result<()> {
+    let mut failure = None;
+    let mut on_download_line = false;
+    let result = conn
+        .exec_lines(
+            &format!("{} worker install --yes --progress=json", gen_audio),
+            &mut |line| {
+                let event = progress::parse_event(line);
+                if on_download_line && !matches!(event, Some(ProgressEvent::Download { .. })) {
+                    println!();
+                    on_download_line = false;
+                }
+                match event {
+                    Some(event @ ProgressEvent::Download { .. }) => {
+                        print!("\r  {:<60}", event.to_string());
+                        let _ = std::io::stdout().flush();
+                        on_download_line = true;
+                    }
+                    Some(ProgressEvent::Done) => {}
+                    Some(ProgressEvent::Error { message }) => failure = Some(message),
+                    Some(event) => println!("  {}", event),
+                    None if line.trim().is_empty() => {}
+                    None => println!("  {}", line),
+                }
+            },
+        )
+        .await;
+    if on_download_line {
+        println!();
+    }
+
+    match (result, failure) {
+        (Err(_), Some(message)) => anyhow::bail!("{}", message),
+        (result, _) => result,
+    }
+}
+
+/// Differences between a worker's Python packages and `expected`, by
+/// public version: `2.6.0+cpu` and `2.6.0+cu124` are the same release.
+/// Packages missing on either side aren't compared.
+pub fn package_mismatches(status: &WorkerStatus, expected: &BTreeMap<String, String>) -> Vec<String> {
+    let public = |version: &str| version.split('+').next().unwrap_or(version).to_string();
+    status
+        .packages
+        .iter()
+        .filter_map(|(name, version)| {
+            let wanted = expected.get(name)?;
+            (public(version) != public(wanted))
+                .then(|| format!("{} {} on the worker, {} here", name, version, wanted))
+        })
+        .collect()
+}
+
+/// Differences between a worker's gen-audio and the coordinator's.
+pub fn version_mismatches(status: &WorkerStatus) -> Vec<String> {
+    let mut mismatches = Vec::new();
+
+    let version = env!("CARGO_PKG_VERSION");
+    if status.gen_audio_version != version {
+        mismatches.push(format!(
+            "gen-audio {} on the worker, {} here",
+            status.gen_audio_version, version
+        ));
+    }
+
+    if status.protocol_version != PROTOCOL_VERSION {
+        mismatches.push(format!(
+            "protocol version {} on the worker, {} here{}",
+            status.protocol_version,
+            PROTOCOL_VERSION,
+            if status.protocol_version < PROTOCOL_VERSION {
+                " (older features are used with it)"
+            } else {
+                ""
+            }
+        ));
+    }
+
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coordinator::local::LocalTransport;
+
+    #[tokio::test]
+    async fn test_install_with_progress_reports_worker_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("gen-audio");
+        std::fs::write(
+            &script,
+            concat!(
+                "#!/bin/sh\n",
+                "echo '{\"event\":\"step\",\"step\":1,\"total\":4,\"name\":\"Installing Python...\"}'\n",
+                "echo '{\"event\":\"download\",\"file\":\"python.tar.gz\",\"bytes\":10}'\n",
+                "echo '{\"event\":\"error\",\"message\":\"No space left on device\"}'\n",
+                "echo 'Error: No space left on device' >&2\n",
+                "exit 1\n",
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::os::unix::fs::PermissionsExt::from_mode(0o755)).unwrap();
+
+        let transport = LocalTransport::new(5);
+        let err = install_with_progress(&transport, &script.to_string_lossy())
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "No space left on device");
+    }
+
+    #[tokio::test]
+    async fn test_worker_doctor() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("gen-audio");
+        std::fs::write(
+            &script,
+            concat!(
+                "#!/bin/sh\n",
+                "echo '{\"ok\":false,\"checks\":[{\"name\":\"ffmpeg\",\"status\":\"fail\",",
+                "\"detail\":\"no AAC encoder\",\"fix\":\"reinstall\"}]}'\n",
+                "echo 'Error: 1 check(s) failed' >&2\n",
+                "exit 1\n",
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::os::unix::fs::PermissionsExt::from_mode(0o755)).unwrap();
+
+        let transport = LocalTransport::new(5);
+        let report = worker_doctor(&transport, &script.to_string_lossy(), false).await.unwrap();
+        assert!(!report.ok);
+        assert_eq!(report.checks[0].name, "ffmpeg");
+
+        let err = worker_doctor(&transport, "true", false).await.unwrap_err();
+        assert!(err.to_string().contains("too old"));
+    }
+
+    #[test]
+    fn test_platform_from_uname() {
+        let linux = Platform::from_uname("Linux x86_64\n").unwrap();
+        assert_eq!(linux.to_string(), "linux-x86_64");
+
+        let mac = Platform::from_uname("Darwin arm64").unwrap();
+        assert_eq!(mac.to_string(), "macos-aarch64");
+
+        assert_ne!(Platform::from_uname("SunOS i86pc").unwrap(), Platform::current());
+        assert!(Platform::from_uname("Linux").is_none());
+    }
+
+    #[test]
+    fn test_version_mismatches() {
+        let status = WorkerStatus::ready("cuda", 1);
+        assert!(version_mismatches(&status).is_empty());
+
+        let mut old = status.clone();
+        old.gen_audio_version = "0.0.1".to_string();
+        old.protocol_version = 1;
+        let mismatches = version_mismatches(&old);
+        assert_eq!(mismatches.len(), 2);
+        assert!(mismatches[0].contains("0.0.1"));
+    }
+
+    #[test]
+    fn test_package_mismatches() {
+        let expected: BTreeMap<String, String> = [("torch", "2.6.0"), ("chatterbox-tts", "0.1.2")]
+            .into_iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect();
+
+        let mut status = WorkerStatus::ready("cuda", 1);
+        assert!(package_mismatches(&status, &expected).is_empty());
+
+        status.packages.insert("torch".to_string(), "2.6.0+cu124".to_string());
+        status.packages.insert("soundfile".to_string(), "0.13.1".to_string());
+        assert!(package_mismatches(&status, &expected).is_empty());
+
+        status.packages.insert("chatterbox-tts".to_string(), "0.1.1".to_string());
+        assert_eq!(
+            package_mismatches(&status, &expected),
+            ["chatterbox-tts 0.1.1 on the worker, 0.1.2 here"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_push_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("gen-audio");
+        std::fs::write(&binary, "#!/bin/sh\necho pushed\n").unwrap();
+
+        let conn = LocalTransport::new(5);
+        let remote = dir.path().join("worker").join("bin").join("gen-audio");
+        let remote = remote.to_string_lossy();
+        push_binary(&conn, &binary, &remote).await.unwrap();
+
+        assert_eq!(conn.exec(&quote_path(&remote)).await.unwrap(), "pushed\n");
+        assert!(!conn.file_exists(&format!("{}.part", remote)).await.unwrap());
+    }
+}
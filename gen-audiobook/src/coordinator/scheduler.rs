@@ -1,12 +1,37 @@
 //! Job scheduler for distributed TTS processing.
+//!
+//! Jobs go to the worker expected to finish them soonest: workers are tried
+//! in `priority` order, and within a priority by their measured time per job.
+//! Once the queue is empty, a job still running on a slow worker may be
+//! duplicated on an idle faster one, and whichever copy finishes first wins.
+//!
+//! Each job runs in its own task against a shared handle to its worker, and
+//! the scheduler alone tracks how many slots are in use, so no lock is held
+//! while a job runs and every worker slot can be busy at once.
+//!
+//! Workers are checked with `gen-audio worker status` every `heartbeat_secs`.
+//! After `unhealthy_after` failures in a row, from checks or from jobs that
+//! couldn't reach the worker or timed out, a worker stops getting jobs and
+//! its running jobs are queued again. It is checked again after a backoff
+//! that doubles each time, and rejoins once it answers. Once every worker
+//! has been down long enough for its backoff to reach the maximum, with
+//! nothing left running, the queued jobs fail rather than waiting forever.
 
-use super::pool::WorkerPool;
-use crate::worker::protocol::{JobStatus, TtsJob, TtsJobOptions, TtsResult};
-use anyhow::Result;
-use std::collections::VecDeque;
-use std::path::PathBuf;
+use super::config::WorkerDefaults;
+use super::pool::{Worker, WorkerPool};
+use crate::worker::protocol::{JobStatus, TtsJob, TtsJobOptions, TtsResult, WorkerStatus};
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// First wait before checking an unhealthy worker again.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(10);
+
+/// Longest wait between checks of an unhealthy worker.
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
 
 /// Progress information for the scheduler.
 #[derive(Debug, Clone)]
@@ -20,6 +45,8 @@ pub struct SchedulerProgress {
     pub in_flight: usize,
     /// Number of failed jobs.
     pub failed: usize,
+    /// Number of speculative copies of straggling jobs currently running.
+    pub speculative: usize,
     /// Per-worker statistics.
     pub workers: Vec<WorkerProgress>,
 }
@@ -36,6 +63,8 @@ pub struct WorkerProgress {
     pub in_flight: usize,
     /// Average time per job in milliseconds.
     pub avg_time_ms: u64,
+    /// Whether the worker is getting jobs.
+    pub healthy: bool,
 }
 
 /// A job that is currently in flight.
@@ -44,12 +73,94 @@ struct InFlightJob {
     job: TtsJob,
     /// Worker handling this job.
     worker_name: String,
+    /// When the job was sent to the worker.
+    started: Instant,
+    /// Whether this is a speculative copy of a job already running elsewhere.
+    speculative: bool,
+    /// Set once the job no longer depends on this copy: another copy has
+    /// finished, or the worker went unhealthy and the job was queued again.
+    /// The worker may still be busy with it, but its result is discarded.
+    superseded: bool,
+}
+
+/// Where a job's result came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Origin {
+    /// Reported by the worker after running the job. A failure here is about
+    /// the job (unusable text, a failed quality check), not the worker.
+    Worker,
+    /// Made up by the coordinator because the worker couldn't be reached or
+    /// the connection broke, which counts against the worker's health.
+    Transport,
+}
+
+/// Whether a worker is getting jobs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Health {
+    Healthy,
+    /// Not getting jobs until a status check at `retry_at` succeeds.
+    Unhealthy { retry_at: Instant, backoff: Duration },
+}
+
+/// A worker and its scheduling settings.
+struct WorkerSlots {
+    /// Worker name.
+    name: String,
+    /// Priority (lower = preferred).
+    priority: u32,
+    /// Maximum jobs at once.
+    max_concurrent: usize,
+    /// Job timeout in seconds.
+    job_timeout: u64,
+    /// The worker, shared with the tasks running its jobs.
+    worker: Arc<Worker>,
+    /// Current health.
+    health: Health,
+    /// Failed status checks and unanswered jobs in a row.
+    consecutive_failures: u32,
+    /// Whether a status check is running.
+    checking: bool,
+    /// When the last status check started.
+    last_check: Instant,
+}
+
+impl WorkerSlots {
+    /// Wrap a ready worker.
+    fn new(worker: Worker, defaults: &WorkerDefaults) -> Self {
+        Self {
+            name: worker.name().to_string(),
+            priority: worker.config.priority,
+            max_concurrent: worker.max_concurrent(defaults) as usize,
+            job_timeout: worker.config.job_timeout(defaults),
+            worker: Arc::new(worker),
+            health: Health::Healthy,
+            consecutive_failures: 0,
+            checking: false,
+            last_check: Instant::now(),
+        }
+    }
+
+    /// Whether the worker is getting jobs.
+    fn is_healthy(&self) -> bool {
+        self.health == Health::Healthy
+    }
+
+    /// Whether a status check is due.
+    fn check_due(&self, heartbeat: Duration, now: Instant) -> bool {
+        if self.checking {
+            return false;
+        }
+        match self.health {
+            Health::Healthy => now.duration_since(self.last_check) >= heartbeat,
+            Health::Unhealthy { retry_at, .. } => now >= retry_at,
+        }
+    }
 }
 
 /// Job scheduler that distributes work across workers.
 pub struct JobScheduler {
-    /// Worker pool.
-    pool: Arc<Mutex<WorkerPool>>,
+    /// Workers that were ready when the scheduler was created.
+    workers: Vec<WorkerSlots>,
     /// Pending jobs waiting to be assigned.
     pending: VecDeque<TtsJob>,
     /// Jobs currently in flight.
@@ -66,6 +177,16 @@ pub struct JobScheduler {
     worker_stats: std::collections::HashMap<String, WorkerStats>,
     /// Temporary directory for downloaded audio.
     temp_dir: PathBuf,
+    /// Worker each retried job last failed on, to try a different one.
+    last_failed_on: HashMap<String, String>,
+    /// Time between status checks of healthy workers.
+    heartbeat: Duration,
+    /// Failures in a row before a worker is marked unhealthy.
+    unhealthy_after: u32,
+    /// First wait before checking an unhealthy worker again.
+    initial_backoff: Duration,
+    /// Longest wait between checks; a worker still down by then is given up on.
+    max_backoff: Duration,
 }
 
 /// Statistics for a single worker.
@@ -75,11 +196,26 @@ struct WorkerStats {
     total_time_ms: u64,
 }
 
+impl WorkerStats {
+    /// Average wall-clock time per job, once at least one has completed.
+    fn avg_time_ms(&self) -> Option<u64> {
+        (self.completed > 0).then(|| self.total_time_ms / self.completed as u64)
+    }
+}
+
 impl JobScheduler {
-    /// Create a new scheduler.
+    /// Create a new scheduler for the pool's ready workers.
     pub fn new(pool: WorkerPool, temp_dir: PathBuf) -> Self {
+        let defaults = pool.defaults().clone();
+        let workers = pool
+            .into_workers()
+            .into_iter()
+            .filter(|w| w.is_ready())
+            .map(|w| WorkerSlots::new(w, &defaults))
+            .collect();
+
         Self {
-            pool: Arc::new(Mutex::new(pool)),
+            workers,
             pending: VecDeque::new(),
             in_flight: Vec::new(),
             completed: Vec::new(),
@@ -88,6 +224,11 @@ impl JobScheduler {
             retry_counts: std::collections::HashMap::new(),
             worker_stats: std::collections::HashMap::new(),
             temp_dir,
+            last_failed_on: HashMap::new(),
+            heartbeat: Duration::from_secs(defaults.heartbeat_secs),
+            unhealthy_after: defaults.unhealthy_after,
+            initial_backoff: INITIAL_BACKOFF,
+            max_backoff: MAX_BACKOFF,
         }
     }
 
@@ -101,113 +242,256 @@ impl JobScheduler {
     /// Get current progress.
     pub fn progress(&self) -> SchedulerProgress {
         let workers: Vec<WorkerProgress> = self
-            .worker_stats
+            .workers
             .iter()
-            .map(|(name, stats)| {
-                let in_flight = self
-                    .in_flight
-                    .iter()
-                    .filter(|j| j.worker_name == *name)
-                    .count();
-
-                let avg_time_ms = if stats.completed > 0 {
-                    stats.total_time_ms / stats.completed as u64
-                } else {
-                    0
-                };
-
+            .map(|w| {
+                let stats = self.worker_stats.get(&w.name);
                 WorkerProgress {
-                    name: name.clone(),
-                    completed: stats.completed,
-                    in_flight,
-                    avg_time_ms,
+                    name: w.name.clone(),
+                    completed: stats.map_or(0, |s| s.completed),
+                    in_flight: self.jobs_on(&w.name),
+                    avg_time_ms: stats.and_then(WorkerStats::avg_time_ms).unwrap_or(0),
+                    healthy: w.is_healthy(),
                 }
             })
             .collect();
 
+        let in_flight = self.active_job_ids().len();
+
         SchedulerProgress {
-            total_jobs: self.pending.len() + self.in_flight.len() + self.completed.len() + self.failed.len(),
+            total_jobs: self.pending.len() + in_flight + self.completed.len() + self.failed.len(),
             completed: self.completed.len(),
-            in_flight: self.in_flight.len(),
+            in_flight,
             failed: self.failed.len(),
+            speculative: self
+                .in_flight
+                .iter()
+                .filter(|j| j.speculative && !j.superseded)
+                .count(),
             workers,
         }
     }
 
+    /// IDs of jobs with at least one copy still running.
+    fn active_job_ids(&self) -> HashSet<&str> {
+        self.in_flight
+            .iter()
+            .filter(|j| !j.superseded)
+            .map(|j| j.job.job_id.as_str())
+            .collect()
+    }
+
+    /// Jobs occupying a worker's slots, including superseded copies it is
+    /// still busy with.
+    fn jobs_on(&self, worker_name: &str) -> usize {
+        self.in_flight
+            .iter()
+            .filter(|j| j.worker_name == worker_name)
+            .count()
+    }
+
+    /// Look up a worker by name.
+    fn worker(&self, name: &str) -> Option<&WorkerSlots> {
+        self.workers.iter().find(|w| w.name == name)
+    }
+
+    /// Look up a worker by name, mutably.
+    fn worker_mut(&mut self, name: &str) -> Option<&mut WorkerSlots> {
+        self.workers.iter_mut().find(|w| w.name == name)
+    }
+
+    /// Measured time per job for a worker.
+    fn avg_time_ms(&self, worker_name: &str) -> Option<u64> {
+        self.worker_stats.get(worker_name)?.avg_time_ms()
+    }
+
+    /// Pick a healthy worker with a free slot for the next job.
+    ///
+    /// Lower priority values win outright. Within a priority, workers that
+    /// haven't completed a job yet go first so their speed gets measured, then
+    /// the fastest, then the least loaded. `avoid` is skipped unless it is the
+    /// only healthy worker.
+    fn pick_worker(&self, avoid: Option<&str>) -> Option<String> {
+        let others_healthy = self
+            .workers
+            .iter()
+            .any(|w| w.is_healthy() && Some(w.name.as_str()) != avoid);
+
+        self.workers
+            .iter()
+            .filter(|w| w.is_healthy() && self.jobs_on(&w.name) < w.max_concurrent)
+            .filter(|w| !(others_healthy && Some(w.name.as_str()) == avoid))
+            .min_by_key(|w| {
+                (
+                    w.priority,
+                    self.avg_time_ms(&w.name).unwrap_or(0),
+                    self.jobs_on(&w.name),
+                )
+            })
+            .map(|w| w.name.clone())
+    }
+
+    /// Pick a straggling job to duplicate on an idle worker.
+    ///
+    /// Only used once nothing is queued. A worker with no jobs running takes
+    /// a copy of a job if, going by measured times, it would finish it before
+    /// the worker already running it. Each job is duplicated at most once.
+    fn pick_speculative(&self) -> Option<(TtsJob, String)> {
+        let now = Instant::now();
+        let mut best: Option<(Duration, &InFlightJob, &WorkerSlots)> = None;
+
+        for worker in &self.workers {
+            if !worker.is_healthy() || self.jobs_on(&worker.name) > 0 {
+                continue;
+            }
+            let Some(fast_ms) = self.avg_time_ms(&worker.name) else {
+                continue;
+            };
+            let fast = Duration::from_millis(fast_ms);
+
+            for running in self.in_flight.iter().filter(|j| !j.superseded) {
+                let copies = self
+                    .in_flight
+                    .iter()
+                    .filter(|j| j.job.job_id == running.job.job_id && !j.superseded)
+                    .count();
+                if copies > 1 {
+                    continue;
+                }
+
+                // Without a measurement, assume the job is halfway through
+                let elapsed = now.duration_since(running.started);
+                let remaining = match self.avg_time_ms(&running.worker_name) {
+                    Some(ms) => Duration::from_millis(ms).saturating_sub(elapsed),
+                    None => elapsed,
+                };
+
+                if fast < remaining && best.is_none_or(|(r, _, _)| remaining > r) {
+                    best = Some((remaining, running, worker));
+                }
+            }
+        }
+
+        best.map(|(_, running, worker)| (running.job.clone(), worker.name.clone()))
+    }
+
+    /// Send a job to a worker in the background.
+    fn dispatch(
+        &mut self,
+        job: TtsJob,
+        worker_name: String,
+        speculative: bool,
+        tx: &mpsc::Sender<(String, TtsResult, Origin)>,
+    ) {
+        self.in_flight.push(InFlightJob {
+            job: job.clone(),
+            worker_name: worker_name.clone(),
+            started: Instant::now(),
+            speculative,
+            superseded: false,
+        });
+
+        let tx = tx.clone();
+        let target = self
+            .worker(&worker_name)
+            .map(|w| (Arc::clone(&w.worker), w.job_timeout));
+
+        tokio::spawn(async move {
+            let result = match target {
+                Some((worker, job_timeout)) => worker.submit_job(&job, job_timeout).await,
+                None => Err(anyhow::anyhow!("Worker not found")),
+            };
+
+            let (result, origin) = match result {
+                Ok(r) => (r, Origin::Worker),
+                Err(e) => (TtsResult::failure(&job.job_id, e.to_string()), Origin::Transport),
+            };
+
+            let _ = tx.send((worker_name, result, origin)).await;
+        });
+    }
+
     /// Run the scheduler until all jobs complete.
-    pub async fn run_to_completion<F>(&mut self, mut on_progress: F) -> Result<Vec<TtsResult>>
+    ///
+    /// `on_result` sees each job's final result as soon as it arrives, so the
+    /// caller can record it before the run ends.
+    pub async fn run_to_completion<F, R>(
+        &mut self,
+        mut on_progress: F,
+        mut on_result: R,
+    ) -> Result<Vec<TtsResult>>
     where
         F: FnMut(SchedulerProgress),
+        R: FnMut(&TtsResult) -> Result<()>,
     {
-        // Create channel for results
-        let (tx, mut rx) = mpsc::channel::<(String, TtsResult)>(32);
+        // Create channels for results and status checks
+        let (tx, mut rx) = mpsc::channel::<(String, TtsResult, Origin)>(32);
+        let (check_tx, mut check_rx) = mpsc::channel::<(String, Result<WorkerStatus>)>(32);
 
         loop {
-            // Check if we're done
-            if self.pending.is_empty() && self.in_flight.is_empty() && self.failed.is_empty() {
+            // Retry failed jobs ahead of new ones
+            while let Some(job) = self.failed.pop() {
+                self.pending.push_front(job);
+            }
+
+            // Check if we're done. Superseded copies still running are abandoned.
+            if self.pending.is_empty() && self.active_job_ids().is_empty() {
                 break;
             }
 
-            // Try to assign pending jobs to available workers
-            while !self.pending.is_empty() {
-                let mut pool = self.pool.lock().await;
-                if let Some(worker) = pool.get_available_worker() {
-                    if let Some(job) = self.pending.pop_front() {
-                        let job_id = job.job_id.clone();
-                        let worker_name = worker.name().to_string();
-                        let job_timeout = pool.job_timeout();
-
-                        // Track in-flight job
-                        self.in_flight.push(InFlightJob {
-                            job: job.clone(),
-                            worker_name: worker_name.clone(),
-                        });
-
-                        // Spawn job execution
-                        let tx = tx.clone();
-                        let pool_clone = Arc::clone(&self.pool);
-
-                        tokio::spawn(async move {
-                            let result = {
-                                let mut pool = pool_clone.lock().await;
-                                if let Some(worker) = pool.get_worker_mut(&worker_name) {
-                                    worker.submit_job(&job, job_timeout).await
-                                } else {
-                                    Err(anyhow::anyhow!("Worker not found"))
-                                }
-                            };
-
-                            let result = match result {
-                                Ok(r) => r,
-                                Err(e) => TtsResult::failure(&job_id, e.to_string()),
-                            };
-
-                            let _ = tx.send((worker_name, result)).await;
-                        });
-                    } else {
-                        break;
+            self.start_checks(&check_tx);
+
+            // Assign pending jobs to the best available workers. A retried
+            // job may wait for a worker other than the one it failed on.
+            let mut i = 0;
+            while i < self.pending.len() {
+                let avoid = self.last_failed_on.get(&self.pending[i].job_id).cloned();
+                match self.pick_worker(avoid.as_deref()) {
+                    Some(worker_name) => {
+                        if let Some(job) = self.pending.remove(i) {
+                            self.dispatch(job, worker_name, false, &tx);
+                        }
                     }
-                } else {
-                    break;
+                    None if avoid.is_some() => i += 1,
+                    None => break,
                 }
             }
 
-            // Retry failed jobs if workers are available
-            while !self.failed.is_empty() {
-                let mut pool = self.pool.lock().await;
-                if pool.get_available_worker().is_some() {
-                    if let Some(job) = self.failed.pop() {
-                        self.pending.push_front(job);
-                    }
-                } else {
-                    break;
+            // With nothing queued, put idle fast workers on the stragglers
+            if self.pending.is_empty() {
+                while let Some((job, worker_name)) = self.pick_speculative() {
+                    eprintln!(
+                        "Job {} is straggling, also running it on {}",
+                        job.job_id, worker_name
+                    );
+                    self.dispatch(job, worker_name, true, &tx);
+                    on_progress(self.progress());
+                }
+            }
+
+            // Nobody left to run the queue: give up on it instead of waiting forever
+            if self.active_job_ids().is_empty() && self.all_workers_given_up() {
+                let finished = self.completed.len();
+                self.fail_pending();
+                for result in &self.completed[finished..] {
+                    on_result(result)?;
                 }
+                on_progress(self.progress());
+                continue;
             }
 
             // Wait for a result with timeout
             tokio::select! {
-                Some((worker_name, result)) = rx.recv() => {
-                    self.handle_result(worker_name, result).await?;
+                Some((worker_name, result, origin)) = rx.recv() => {
+                    let finished = self.completed.len();
+                    self.handle_result(worker_name, result, origin).await?;
+                    for result in &self.completed[finished..] {
+                        on_result(result)?;
+                    }
+                    on_progress(self.progress());
+                }
+                Some((worker_name, status)) = check_rx.recv() => {
+                    self.handle_check(&worker_name, status);
                     on_progress(self.progress());
                 }
                 _ = tokio::time::sleep(std::time::Duration::from_millis(100)) => {
@@ -219,87 +503,289 @@ impl JobScheduler {
         Ok(std::mem::take(&mut self.completed))
     }
 
+    /// Whether every worker has stayed down through its re-checks until the
+    /// backoff reached its maximum, with no check still running.
+    fn all_workers_given_up(&self) -> bool {
+        self.workers.iter().all(|w| {
+            !w.checking
+                && matches!(w.health, Health::Unhealthy { backoff, .. } if backoff >= self.max_backoff)
+        })
+    }
+
+    /// Fail every queued job because no worker can take it.
+    fn fail_pending(&mut self) {
+        let names: Vec<_> = self.workers.iter().map(|w| w.name.as_str()).collect();
+        let error = if names.is_empty() {
+            "No workers available".to_string()
+        } else {
+            format!("All workers are unhealthy ({})", names.join(", "))
+        };
+        eprintln!("{}; failing {} queued job(s)", error, self.pending.len());
+
+        let failures: Vec<_> = self
+            .pending
+            .drain(..)
+            .map(|job| TtsResult::failure(&job.job_id, error.clone()))
+            .collect();
+        self.completed.extend(failures);
+    }
+
+    /// Start status checks on workers that are due one.
+    fn start_checks(&mut self, check_tx: &mpsc::Sender<(String, Result<WorkerStatus>)>) {
+        let now = Instant::now();
+        let heartbeat = self.heartbeat;
+
+        for slots in self.workers.iter_mut().filter(|w| w.check_due(heartbeat, now)) {
+            slots.checking = true;
+            slots.last_check = now;
+
+            let name = slots.name.clone();
+            let worker = Arc::clone(&slots.worker);
+            let check_tx = check_tx.clone();
+            tokio::spawn(async move {
+                let status = worker.check_status().await;
+                let _ = check_tx.send((name, status)).await;
+            });
+        }
+    }
+
+    /// Handle the outcome of a status check.
+    fn handle_check(&mut self, worker_name: &str, status: Result<WorkerStatus>) {
+        let Some(slots) = self.worker_mut(worker_name) else {
+            return;
+        };
+        slots.checking = false;
+
+        let error = match status {
+            Ok(status) if status.ready => None,
+            Ok(_) => Some("worker is not ready".to_string()),
+            Err(e) => Some(format!("{:#}", e)),
+        };
+
+        match error {
+            Some(error) => self.record_failure(worker_name, &error),
+            None => {
+                slots.consecutive_failures = 0;
+                if !slots.is_healthy() {
+                    slots.health = Health::Healthy;
+                    eprintln!("Worker {} is back, giving it jobs again", worker_name);
+                }
+            }
+        }
+    }
+
+    /// Count a failed status check, or a job that didn't get through, against a worker.
+    fn record_failure(&mut self, worker_name: &str, error: &str) {
+        let unhealthy_after = self.unhealthy_after;
+        let (initial_backoff, max_backoff) = (self.initial_backoff, self.max_backoff);
+        let Some(slots) = self.worker_mut(worker_name) else {
+            return;
+        };
+        slots.consecutive_failures += 1;
+
+        match slots.health {
+            Health::Healthy if slots.consecutive_failures >= unhealthy_after => {
+                slots.health = Health::Unhealthy {
+                    retry_at: Instant::now() + initial_backoff,
+                    backoff: initial_backoff,
+                };
+                eprintln!(
+                    "Worker {} is unhealthy after {} failures ({}), checking again in {}s",
+                    worker_name,
+                    slots.consecutive_failures,
+                    error,
+                    initial_backoff.as_secs()
+                );
+                self.requeue_jobs_on(worker_name);
+            }
+            Health::Healthy => {}
+            // Still down: wait longer before the next check
+            Health::Unhealthy { backoff, .. } => {
+                let backoff = (backoff * 2).min(max_backoff);
+                slots.health = Health::Unhealthy {
+                    retry_at: Instant::now() + backoff,
+                    backoff,
+                };
+            }
+        }
+    }
+
+    /// Queue the jobs running on a worker again, unless another copy runs elsewhere.
+    fn requeue_jobs_on(&mut self, worker_name: &str) {
+        let mut requeue = Vec::new();
+        for entry in self.in_flight.iter_mut() {
+            if entry.worker_name == worker_name && !entry.superseded {
+                entry.superseded = true;
+                requeue.push(entry.job.clone());
+            }
+        }
+
+        for job in requeue {
+            if !self.active_job_ids().contains(job.job_id.as_str()) {
+                self.last_failed_on.insert(job.job_id.clone(), worker_name.to_string());
+                self.pending.push_front(job);
+            }
+        }
+    }
+
     /// Handle a completed job result.
-    async fn handle_result(&mut self, worker_name: String, result: TtsResult) -> Result<()> {
-        // Find and remove the in-flight job
+    async fn handle_result(
+        &mut self,
+        worker_name: String,
+        result: TtsResult,
+        origin: Origin,
+    ) -> Result<()> {
+        // Find and remove this worker's copy of the job
         let job_idx = self
             .in_flight
             .iter()
-            .position(|j| j.job.job_id == result.job_id);
+            .position(|j| j.job.job_id == result.job_id && j.worker_name == worker_name);
 
-        let job = if let Some(idx) = job_idx {
-            self.in_flight.remove(idx).job
-        } else {
+        let Some(idx) = job_idx else {
             return Ok(());
         };
+        let entry = self.in_flight.remove(idx);
 
-        match result.status {
-            JobStatus::Completed => {
-                // Update worker stats
-                let stats = self.worker_stats.entry(worker_name.clone()).or_default();
-                stats.completed += 1;
-                if let Some(ms) = result.duration_ms {
-                    stats.total_time_ms += ms;
-                }
+        // A job the worker ran and answered, even with a failure, shows it is
+        // up; one it never answered doesn't
+        if result.status == JobStatus::Timeout || origin == Origin::Transport {
+            let error = result.error.as_deref().unwrap_or("unknown");
+            self.record_failure(&worker_name, error);
+        } else if let Some(slots) = self.worker_mut(&worker_name) {
+            slots.consecutive_failures = 0;
+        }
 
-                // Download the audio file if path is provided
-                if let Some(ref remote_path) = result.audio_path {
-                    let local_path = self.temp_dir.join(format!("{}.wav", result.job_id));
+        // Another copy already finished this job
+        if entry.superseded {
+            if result.status == JobStatus::Completed {
+                self.discard_audio(&worker_name, &result).await;
+            }
+            return Ok(());
+        }
 
-                    let download_result = {
-                        let pool = self.pool.lock().await;
-                        if let Some(worker) = pool.get_worker(&worker_name) {
-                            worker.download_audio(remote_path, &local_path).await
-                        } else {
-                            Err(anyhow::anyhow!("Worker not found"))
-                        }
-                    };
+        let other_copy_running = self
+            .in_flight
+            .iter()
+            .any(|j| j.job.job_id == result.job_id && !j.superseded);
 
-                    if let Err(e) = download_result {
-                        eprintln!("Warning: Failed to download audio for {}: {}", result.job_id, e);
+        match result.status {
+            JobStatus::Completed => {
+                // A chunk only counts as done once its audio is here intact
+                let mut result = result;
+                if let Err(e) = self.fetch_audio(&worker_name, &mut result).await {
+                    let error = format!("{:#}", e);
+                    self.record_failure(&worker_name, &error);
+                    if !other_copy_running {
+                        let failure = TtsResult::failure(&result.job_id, error);
+                        self.retry_or_fail(entry.job, &worker_name, failure);
                     }
+                    return Ok(());
+                }
 
-                    // Clean up remote file
-                    let _ = {
-                        let pool = self.pool.lock().await;
-                        if let Some(worker) = pool.get_worker(&worker_name) {
-                            worker.cleanup_audio(remote_path).await
-                        } else {
-                            Ok(())
-                        }
-                    };
+                // First result wins
+                for other in self.in_flight.iter_mut() {
+                    if other.job.job_id == result.job_id {
+                        other.superseded = true;
+                    }
                 }
 
+                // Update worker stats
+                let stats = self.worker_stats.entry(worker_name.clone()).or_default();
+                stats.completed += 1;
+                stats.total_time_ms += entry.started.elapsed().as_millis() as u64;
+
                 self.completed.push(result);
             }
+            // The other copy may still succeed
+            JobStatus::Failed | JobStatus::Timeout if other_copy_running => {}
             JobStatus::Failed | JobStatus::Timeout => {
-                // Check if we should retry
-                let retry_count = self.retry_counts.entry(job.job_id.clone()).or_insert(0);
-                *retry_count += 1;
-
-                if *retry_count < self.max_retries {
-                    eprintln!(
-                        "Job {} failed (attempt {}), retrying: {}",
-                        job.job_id,
-                        retry_count,
-                        result.error.as_deref().unwrap_or("unknown")
-                    );
-                    self.failed.push(job);
-                } else {
-                    eprintln!(
-                        "Job {} failed after {} attempts: {}",
-                        job.job_id,
-                        self.max_retries,
-                        result.error.as_deref().unwrap_or("unknown")
-                    );
-                    self.completed.push(result);
-                }
+                self.retry_or_fail(entry.job, &worker_name, result)
             }
         }
 
         Ok(())
     }
 
+    /// Remove the audio of a result that lost to another copy of its job.
+    async fn discard_audio(&self, worker_name: &str, result: &TtsResult) {
+        // Inline audio is simply dropped
+        let Some(ref remote_path) = result.audio_path else {
+            return;
+        };
+
+        if let Some(slots) = self.worker(worker_name) {
+            let _ = slots.worker.cleanup_audio(remote_path).await;
+        }
+    }
+
+    /// Write a completed job's audio to the temp directory.
+    ///
+    /// Inline audio is checked against its checksum. Workers on protocol
+    /// version 1 leave the file on disk, so it is downloaded and removed.
+    ///
+    /// The audio is written under a temporary name and renamed into place, so
+    /// an interrupted run never leaves a partial `<job_id>.wav` behind.
+    async fn fetch_audio(&self, worker_name: &str, result: &mut TtsResult) -> Result<()> {
+        let local_path = self.temp_dir.join(format!("{}.wav", result.job_id));
+        let part_path = self.temp_dir.join(format!("{}.wav.part", result.job_id));
+
+        if let Some(audio) = result.decode_audio()? {
+            std::fs::write(&part_path, audio)
+                .with_context(|| format!("Failed to write {}", part_path.display()))?;
+            std::fs::rename(&part_path, &local_path)
+                .with_context(|| format!("Failed to write {}", local_path.display()))?;
+            // No need to keep the encoded copy around
+            result.audio_data = None;
+            return Ok(());
+        }
+
+        let remote_path = result
+            .audio_path
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("Worker returned no audio"))?;
+
+        let worker = &self
+            .worker(worker_name)
+            .ok_or_else(|| anyhow::anyhow!("Worker not found"))?
+            .worker;
+
+        worker
+            .download_audio(&remote_path, &part_path)
+            .await
+            .context("Failed to download audio")?;
+        std::fs::rename(&part_path, &local_path)
+            .with_context(|| format!("Failed to write {}", local_path.display()))?;
+
+        // Clean up remote file
+        let _ = worker.cleanup_audio(&remote_path).await;
+        Ok(())
+    }
+
+    /// Queue a failed job for retry, or give up on it after too many attempts.
+    fn retry_or_fail(&mut self, job: TtsJob, worker_name: &str, result: TtsResult) {
+        let retry_count = self.retry_counts.entry(job.job_id.clone()).or_insert(0);
+        *retry_count += 1;
+
+        if *retry_count < self.max_retries {
+            eprintln!(
+                "Job {} failed (attempt {}), retrying: {}",
+                job.job_id,
+                retry_count,
+                result.error.as_deref().unwrap_or("unknown")
+            );
+            self.last_failed_on.insert(job.job_id.clone(), worker_name.to_string());
+            self.failed.push(job);
+        } else {
+            eprintln!(
+                "Job {} failed after {} attempts: {}",
+                job.job_id,
+                self.max_retries,
+                result.error.as_deref().unwrap_or("unknown")
+            );
+            self.completed.push(result);
+        }
+    }
+
     /// Get completed results grouped by chapter.
     #[allow(dead_code)]
     pub fn results_by_chapter(&self) -> std::collections::HashMap<usize, Vec<&TtsResult>> {
@@ -323,28 +809,22 @@ impl JobScheduler {
 }
 
 /// Parse chapter number from job ID.
-#[allow(dead_code)]
-fn parse_chapter_from_job_id(job_id: &str) -> Option<usize> {
+pub(crate) fn parse_chapter_from_job_id(job_id: &str) -> Option<usize> {
     // Format: session_chXXX_ckYYYY
-    let parts: Vec<&str> = job_id.split('_').collect();
-    for part in parts {
-        if part.starts_with("ch") {
-            return part[2..].parse().ok();
-        }
-    }
-    None
+    job_id
+        .split('_')
+        .find_map(|part| part.strip_prefix("ch"))?
+        .parse()
+        .ok()
 }
 
 /// Parse chunk number from job ID.
-#[allow(dead_code)]
-fn parse_chunk_from_job_id(job_id: &str) -> Option<usize> {
-    let parts: Vec<&str> = job_id.split('_').collect();
-    for part in parts {
-        if part.starts_with("ck") {
-            return part[2..].parse().ok();
-        }
-    }
-    None
+pub(crate) fn parse_chunk_from_job_id(job_id: &str) -> Option<usize> {
+    job_id
+        .split('_')
+        .find_map(|part| part.strip_prefix("ck"))?
+        .parse()
+        .ok()
 }
 
 /// Create TTS jobs from text chunks.
@@ -361,6 +841,36 @@ pub fn create_jobs(
         .collect()
 }
 
+/// Split off jobs whose audio is already in `temp_dir`.
+///
+/// A coordinator interrupted mid-run leaves finished chunks as
+/// `<job_id>.wav` files that never made it into the session. Returns the
+/// jobs still to run and the ones whose audio was found. Files that aren't a
+/// complete WAV are removed, and their jobs run again.
+pub fn adopt_finished_audio(temp_dir: &Path, jobs: Vec<TtsJob>) -> (Vec<TtsJob>, Vec<TtsJob>) {
+    let mut remaining = Vec::new();
+    let mut adopted = Vec::new();
+
+    for job in jobs {
+        let path = temp_dir.join(format!("{}.wav", job.job_id));
+        if !path.exists() {
+            remaining.push(job);
+            continue;
+        }
+
+        match crate::audio::validate_wav(&path) {
+            Ok(()) => adopted.push(job),
+            Err(e) => {
+                eprintln!("Discarding leftover audio for {}: {:#}", job.job_id, e);
+                let _ = std::fs::remove_file(&path);
+                remaining.push(job);
+            }
+        }
+    }
+
+    (remaining, adopted)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -386,4 +896,626 @@ mod tests {
         assert_eq!(jobs[0].chunk_id, 0);
         assert_eq!(jobs[2].chapter_id, 1);
     }
+
+    #[test]
+    fn test_adopt_finished_audio() {
+        let dir = tempfile::tempdir().unwrap();
+        let jobs = create_jobs(
+            "sess",
+            &(0..4).map(|i| (0, i, String::new())).collect::<Vec<_>>(),
+            TtsJobOptions::default(),
+        );
+        let path = |job: &TtsJob| dir.path().join(format!("{}.wav", job.job_id));
+
+        let wav = crate::audio::wav::test_wav(&[0, 100, -100, 0]);
+        std::fs::write(path(&jobs[0]), &wav).unwrap();
+        std::fs::write(path(&jobs[2]), &wav[..wav.len() - 2]).unwrap();
+        std::fs::write(dir.path().join(format!("{}.wav.part", jobs[3].job_id)), &wav).unwrap();
+
+        let (remaining, adopted) = adopt_finished_audio(dir.path(), jobs.clone());
+        let ids = |jobs: &[TtsJob]| jobs.iter().map(|j| j.job_id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&adopted), ids(&jobs[..1]));
+        assert_eq!(ids(&remaining), ids(&jobs[1..]));
+
+        // The truncated file is gone so a fresh copy can take its place
+        assert!(path(&jobs[0]).exists());
+        assert!(!path(&jobs[2]).exists());
+    }
+
+    /// A stand-in for `gen-audio` on a protocol version 1 worker: answers
+    /// `worker status`, and `worker run`/`worker serve` by writing a WAV file
+    /// for each job and returning its path. `serve` runs jobs concurrently.
+    ///
+    /// With `RUNNING` set, each job takes `DELAY` seconds and appends the
+    /// number of jobs running at its start to `$RUNNING.counts`. With `FAIL`
+    /// set, every job fails. With `BROKEN` set, `worker run` exits without
+    /// answering, as when the connection drops. With `DOWN` naming a file
+    /// that exists, every command fails, as when the host is unreachable.
+    const FAKE_GEN_AUDIO: &str = r#"
+if [ -n "$DOWN" ] && [ -e "$DOWN" ]; then
+  exit 1
+fi
+status='{"ready":true,"device":"cpu","gen_audio_version":"test","chatterbox_installed":true,"jobs_in_progress":0,"available_disk_mb":1}'
+answer() {
+  id=$(printf '%s' "$1" | sed 's/.*"job_id":"\([^"]*\)".*/\1/')
+  if [ -n "$RUNNING" ]; then
+    touch "$RUNNING/$id"
+    ls "$RUNNING" | wc -l >> "$RUNNING.counts"
+    sleep "$DELAY"
+    rm "$RUNNING/$id"
+  fi
+  if [ -n "$BROKEN" ]; then
+    exit 1
+  fi
+  if [ -n "$FAIL" ]; then
+    printf '{"version":1,"job_id":"%s","status":"failed","error":"boom","completed_at":"2024-01-01T00:00:00Z"}\n' "$id"
+    return
+  fi
+  printf 'audio for %s' "$id" > "$OUT/$id.wav"
+  printf '{"version":1,"job_id":"%s","status":"completed","audio_path":"%s","completed_at":"2024-01-01T00:00:00Z"}\n' "$id" "$OUT/$id.wav"
+}
+case "$2" in
+  status) echo "$status" ;;
+  run) answer "$(cat)" ;;
+  serve)
+    echo "$status"
+    while IFS= read -r line; do answer "$line" & done
+    wait ;;
+esac
+"#;
+
+    /// Local workers running the fake gen-audio, each with a session if asked
+    /// and its own extra environment.
+    async fn fake_pool(
+        dir: &std::path::Path,
+        env: &str,
+        workers: &[(&str, bool, &str)],
+        max_concurrent: u32,
+    ) -> WorkerPool {
+        use crate::coordinator::config::{WorkerConfig, WorkersConfig};
+
+        let script = dir.join("gen-audio.sh");
+        std::fs::write(&script, FAKE_GEN_AUDIO).unwrap();
+        let out = dir.join("worker-out");
+        std::fs::create_dir_all(&out).unwrap();
+
+        let mut config = WorkersConfig {
+            workers: workers
+                .iter()
+                .map(|(name, _, worker_env)| {
+                    let gen_audio = format!(
+                        "OUT='{}' {} {} sh '{}'",
+                        out.display(),
+                        env,
+                        worker_env,
+                        script.display()
+                    );
+                    WorkerConfig::local(*name).with_gen_audio_path(&gen_audio)
+                })
+                .collect(),
+            ..WorkersConfig::default()
+        };
+        config.defaults.max_concurrent_jobs = max_concurrent;
+
+        let mut pool = WorkerPool::new(&config);
+        for (name, result) in pool.connect_all().await {
+            result.unwrap_or_else(|e| panic!("{}: {}", name, e));
+        }
+        for (name, session, _) in workers {
+            if *session {
+                pool.get_worker_mut(name).unwrap().start_session(5).await.unwrap();
+            }
+        }
+        pool
+    }
+
+    #[tokio::test]
+    async fn test_workers_run_jobs_in_parallel() {
+        let dir = tempfile::tempdir().unwrap();
+        let running = dir.path().join("running");
+        std::fs::create_dir(&running).unwrap();
+        let env = format!("RUNNING='{}' DELAY=0.4", running.display());
+
+        // 2 workers x 2 slots, but the one served through a session takes one
+        // job at a time
+        let pool = fake_pool(dir.path(), &env, &[("a", true, ""), ("b", false, "")], 2).await;
+        let temp_dir = dir.path().join("coordinator");
+        std::fs::create_dir(&temp_dir).unwrap();
+
+        let chunks: Vec<_> = (0..8).map(|i| (0, i, format!("Chunk {}", i))).collect();
+        let mut scheduler = JobScheduler::new(pool, temp_dir);
+        scheduler.enqueue(create_jobs("sess", &chunks, TtsJobOptions::default()));
+
+        let start = Instant::now();
+        let results = scheduler.run_to_completion(|_| {}, |_| Ok(())).await.unwrap();
+        let elapsed = start.elapsed();
+        assert_eq!(results.len(), 8);
+        assert!(results.iter().all(|r| r.status == JobStatus::Completed));
+
+        let counts = std::fs::read_to_string(dir.path().join("running.counts")).unwrap();
+        let max_running = counts.lines().filter_map(|l| l.trim().parse::<usize>().ok()).max();
+        assert_eq!(max_running, Some(3), "jobs running at once: {:?}", counts);
+
+        // Three rounds of 0.4s, far from the 3.2s of running one at a time
+        assert!(elapsed < Duration::from_millis(2000), "took {:?}", elapsed);
+    }
+
+    #[tokio::test]
+    async fn test_distributed_run_with_local_workers() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("worker-out");
+        let temp_dir = dir.path().join("coordinator");
+        std::fs::create_dir(&temp_dir).unwrap();
+
+        // Session on one worker only, so both execution paths are covered
+        let pool = fake_pool(dir.path(), "", &[("a", true, ""), ("b", false, "")], 1).await;
+
+        let chunks: Vec<_> = (0..4).map(|i| (0, i, format!("Chunk {}", i))).collect();
+        let jobs = create_jobs("sess", &chunks, TtsJobOptions::default());
+
+        let mut scheduler = JobScheduler::new(pool, temp_dir.clone());
+        scheduler.enqueue(jobs.clone());
+        let mut recorded = Vec::new();
+        let results = scheduler
+            .run_to_completion(
+                |_| {},
+                |result| {
+                    // Each result's audio is in place by the time it is reported
+                    assert!(temp_dir.join(format!("{}.wav", result.job_id)).exists());
+                    recorded.push(result.job_id.clone());
+                    Ok(())
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 4);
+        let returned: Vec<_> = results.iter().map(|r| r.job_id.clone()).collect();
+        assert_eq!(recorded, returned);
+
+        // A speculative copy abandoned at the end of the run may still write its file
+        let abandoned: HashSet<_> = scheduler.in_flight.iter().map(|j| j.job.job_id.clone()).collect();
+        for job in &jobs {
+            let audio = std::fs::read(temp_dir.join(format!("{}.wav", job.job_id))).unwrap();
+            assert_eq!(audio, format!("audio for {}", job.job_id).into_bytes());
+            // Downloaded files are removed from the worker
+            if !abandoned.contains(&job.job_id) {
+                assert!(!out.join(format!("{}.wav", job.job_id)).exists());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failing_worker_is_taken_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let temp_dir = dir.path().join("coordinator");
+        std::fs::create_dir(&temp_dir).unwrap();
+
+        let pool = fake_pool(dir.path(), "", &[("bad", false, "BROKEN=1"), ("good", false, "")], 1).await;
+
+        let chunks: Vec<_> = (0..6).map(|i| (0, i, format!("Chunk {}", i))).collect();
+        let mut scheduler = JobScheduler::new(pool, temp_dir);
+        scheduler.enqueue(create_jobs("sess", &chunks, TtsJobOptions::default()));
+        let results = scheduler.run_to_completion(|_| {}, |_| Ok(())).await.unwrap();
+
+        // Jobs that failed on the bad worker were retried on the good one
+        assert_eq!(results.len(), 6);
+        assert!(results.iter().all(|r| r.status == JobStatus::Completed));
+
+        let progress = scheduler.progress();
+        let health: Vec<_> = progress.workers.iter().map(|w| (w.name.as_str(), w.healthy)).collect();
+        assert_eq!(health, [("bad", false), ("good", true)]);
+        assert_eq!(progress.workers[1].completed, 6);
+    }
+
+    #[tokio::test]
+    async fn test_only_worker_rejoins_after_an_outage() {
+        let dir = tempfile::tempdir().unwrap();
+        let temp_dir = dir.path().join("coordinator");
+        std::fs::create_dir(&temp_dir).unwrap();
+
+        let down = dir.path().join("down");
+        let env = format!("DOWN='{}'", down.display());
+        let pool = fake_pool(dir.path(), "", &[("only", false, &env)], 1).await;
+
+        // Unreachable for a moment, then back before its first re-check
+        std::fs::write(&down, "").unwrap();
+        let outage = {
+            let down = down.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(150));
+                std::fs::remove_file(down).unwrap();
+            })
+        };
+
+        let chunks: Vec<_> = (0..4).map(|i| (0, i, format!("Chunk {}", i))).collect();
+        let mut scheduler = JobScheduler::new(pool, temp_dir);
+        scheduler.unhealthy_after = 1;
+        scheduler.initial_backoff = Duration::from_millis(400);
+        scheduler.enqueue(create_jobs("sess", &chunks, TtsJobOptions::default()));
+        let results = tokio::time::timeout(
+            Duration::from_secs(5),
+            scheduler.run_to_completion(|_| {}, |_| Ok(())),
+        )
+        .await
+        .expect("run should go on once the worker is back")
+        .unwrap();
+        outage.join().unwrap();
+
+        assert_eq!(results.len(), 4);
+        assert!(results.iter().all(|r| r.status == JobStatus::Completed));
+        assert!(scheduler.progress().workers[0].healthy);
+    }
+
+    #[tokio::test]
+    async fn test_run_ends_when_every_worker_is_down() {
+        let dir = tempfile::tempdir().unwrap();
+        let temp_dir = dir.path().join("coordinator");
+        std::fs::create_dir(&temp_dir).unwrap();
+
+        let down = dir.path().join("down");
+        let env = format!("DOWN='{}'", down.display());
+        let pool = fake_pool(dir.path(), "", &[("bad", false, &env)], 1).await;
+        std::fs::write(&down, "").unwrap();
+
+        let chunks: Vec<_> = (0..4).map(|i| (0, i, format!("Chunk {}", i))).collect();
+        let mut scheduler = JobScheduler::new(pool, temp_dir);
+        // Re-checked after 50ms and 100ms, then given up on at 200ms
+        scheduler.initial_backoff = Duration::from_millis(50);
+        scheduler.max_backoff = Duration::from_millis(200);
+        scheduler.enqueue(create_jobs("sess", &chunks, TtsJobOptions::default()));
+        let mut reported = 0;
+        let results = tokio::time::timeout(
+            Duration::from_secs(5),
+            scheduler.run_to_completion(|_| {}, |_| {
+                reported += 1;
+                Ok(())
+            }),
+        )
+        .await
+        .expect("run should end once its only worker is down")
+        .unwrap();
+
+        assert_eq!(results.len(), 4);
+        assert_eq!(reported, 4);
+        assert!(results.iter().all(|r| r.status == JobStatus::Failed));
+        let stranded: Vec<_> = results
+            .iter()
+            .filter(|r| r.error.as_deref() == Some("All workers are unhealthy (bad)"))
+            .collect();
+        assert!(!stranded.is_empty());
+    }
+
+    /// A backend that writes the job text as its audio.
+    struct EchoBackend;
+
+    #[async_trait::async_trait]
+    impl crate::tts::TtsBackend for EchoBackend {
+        async fn synthesize(
+            &self,
+            text: &str,
+            output_path: &Path,
+            _options: &crate::tts::TtsOptions,
+        ) -> Result<crate::tts::QualityMetrics> {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            std::fs::write(output_path, format!("local audio for {}", text))?;
+            Ok(Default::default())
+        }
+
+        async fn synthesize_with_retry(
+            &self,
+            text: &str,
+            output_path: &Path,
+            options: &crate::tts::TtsOptions,
+            _max_retries: u32,
+        ) -> Result<crate::tts::SynthesisReport> {
+            let quality = self.synthesize(text, output_path, options).await?;
+            Ok(crate::tts::SynthesisReport {
+                quality,
+                seed: options.seed.unwrap_or(0),
+            })
+        }
+
+        fn device(&self) -> &str {
+            "cpu"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_in_process_worker_shares_the_queue() {
+        use crate::coordinator::pool::LOCAL_WORKER_NAME;
+
+        let dir = tempfile::tempdir().unwrap();
+        let temp_dir = dir.path().join("coordinator");
+        let output_dir = temp_dir.join(LOCAL_WORKER_NAME);
+
+        let running = dir.path().join("running");
+        std::fs::create_dir(&running).unwrap();
+        let env = format!("RUNNING='{}' DELAY=0.3", running.display());
+
+        // The remote worker is the slower one here
+        let mut pool = fake_pool(dir.path(), &env, &[("remote", false, "")], 1).await;
+        let local = Worker::in_process(
+            Box::new(EchoBackend),
+            None,
+            output_dir.clone(),
+            pool.defaults(),
+        )
+        .unwrap();
+        pool.add_worker(local).unwrap();
+
+        // Neither a connection nor a session is attempted for it
+        assert!(pool.connect_all().await.iter().all(|(name, _)| name == "remote"));
+
+        let chunks: Vec<_> = (0..6).map(|i| (0, i, format!("Chunk {}", i))).collect();
+        let jobs = create_jobs("sess", &chunks, TtsJobOptions::default());
+        let mut scheduler = JobScheduler::new(pool, temp_dir.clone());
+        scheduler.enqueue(jobs.clone());
+        let mut both_busy = false;
+        let results = scheduler
+            .run_to_completion(
+                |progress| {
+                    both_busy |= progress.workers.iter().all(|w| w.in_flight > 0);
+                },
+                |_| Ok(()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 6);
+        assert!(results.iter().all(|r| r.status == JobStatus::Completed));
+
+        // Both ran jobs at the same time
+        assert!(both_busy);
+        let progress = scheduler.progress();
+        assert_eq!(progress.workers[1].name, LOCAL_WORKER_NAME);
+        assert!(progress.workers[1].completed > 0);
+
+        // Every chunk's audio is collected, wherever it was made
+        for job in &jobs {
+            assert!(temp_dir.join(format!("{}.wav", job.job_id)).exists());
+        }
+        assert_eq!(std::fs::read_dir(&output_dir).unwrap().count(), 0);
+    }
+
+    /// A scheduler over made-up workers, without a pool behind it.
+    fn test_scheduler(workers: &[(&str, u32, usize)]) -> JobScheduler {
+        use crate::coordinator::config::{WorkerConfig, WorkersConfig};
+
+        let config = WorkersConfig::default();
+        let pool = WorkerPool::new(&config);
+        let mut scheduler = JobScheduler::new(pool, std::env::temp_dir());
+        scheduler.workers = workers
+            .iter()
+            .map(|&(name, priority, max_concurrent)| {
+                let worker = Worker::new(WorkerConfig::local(name), &config.defaults);
+                WorkerSlots {
+                    priority,
+                    max_concurrent,
+                    job_timeout: 5,
+                    ..WorkerSlots::new(worker, &config.defaults)
+                }
+            })
+            .collect();
+        scheduler
+    }
+
+    fn set_avg(scheduler: &mut JobScheduler, worker: &str, avg_ms: u64) {
+        let stats = scheduler.worker_stats.entry(worker.to_string()).or_default();
+        stats.completed = 1;
+        stats.total_time_ms = avg_ms;
+    }
+
+    fn run_on(scheduler: &mut JobScheduler, job: &TtsJob, worker: &str, started: Instant) {
+        scheduler.in_flight.push(InFlightJob {
+            job: job.clone(),
+            worker_name: worker.to_string(),
+            started,
+            speculative: scheduler.in_flight.iter().any(|j| j.job.job_id == job.job_id),
+            superseded: false,
+        });
+    }
+
+    #[test]
+    fn test_pick_worker() {
+        let mut scheduler = test_scheduler(&[("cpu", 1, 1), ("gpu", 1, 2), ("backup", 2, 1)]);
+        set_avg(&mut scheduler, "cpu", 10_000);
+        set_avg(&mut scheduler, "gpu", 1_000);
+        let jobs = create_jobs(
+            "sess",
+            &(0..4).map(|i| (0, i, String::new())).collect::<Vec<_>>(),
+            TtsJobOptions::default(),
+        );
+        let now = Instant::now();
+
+        // Fastest worker first, up to its limit
+        assert_eq!(scheduler.pick_worker(None).as_deref(), Some("gpu"));
+        run_on(&mut scheduler, &jobs[0], "gpu", now);
+        run_on(&mut scheduler, &jobs[1], "gpu", now);
+
+        // Then the slower one at the same priority, before a lower priority
+        assert_eq!(scheduler.pick_worker(None).as_deref(), Some("cpu"));
+        run_on(&mut scheduler, &jobs[2], "cpu", now);
+        assert_eq!(scheduler.pick_worker(None).as_deref(), Some("backup"));
+        run_on(&mut scheduler, &jobs[3], "backup", now);
+        assert_eq!(scheduler.pick_worker(None), None);
+
+        // Unmeasured workers are tried before measured ones
+        let mut scheduler = test_scheduler(&[("gpu", 1, 1), ("new", 1, 1)]);
+        set_avg(&mut scheduler, "gpu", 1_000);
+        assert_eq!(scheduler.pick_worker(None).as_deref(), Some("new"));
+
+        // A retried job goes elsewhere unless no other worker is healthy
+        assert_eq!(scheduler.pick_worker(Some("new")).as_deref(), Some("gpu"));
+        run_on(&mut scheduler, &jobs[0], "gpu", now);
+        assert_eq!(scheduler.pick_worker(Some("new")), None);
+        scheduler.record_failure("gpu", "down");
+        scheduler.record_failure("gpu", "down");
+        scheduler.record_failure("gpu", "down");
+        assert_eq!(scheduler.pick_worker(Some("new")).as_deref(), Some("new"));
+    }
+
+    #[test]
+    fn test_unhealthy_worker_and_readmission() {
+        let mut scheduler = test_scheduler(&[("flaky", 1, 2), ("steady", 1, 2)]);
+        let jobs = create_jobs(
+            "sess",
+            &(0..2).map(|i| (0, i, String::new())).collect::<Vec<_>>(),
+            TtsJobOptions::default(),
+        );
+        run_on(&mut scheduler, &jobs[0], "flaky", Instant::now());
+        run_on(&mut scheduler, &jobs[1], "flaky", Instant::now());
+        run_on(&mut scheduler, &jobs[1], "steady", Instant::now());
+
+        // A success in between resets the count
+        scheduler.handle_check("flaky", Err(anyhow::anyhow!("ssh: timed out")));
+        scheduler.handle_check("flaky", Err(anyhow::anyhow!("ssh: timed out")));
+        scheduler.handle_check("flaky", Ok(WorkerStatus::ready("cpu", 1)));
+        scheduler.handle_check("flaky", Err(anyhow::anyhow!("ssh: timed out")));
+        assert!(scheduler.worker("flaky").unwrap().is_healthy());
+
+        // The threshold takes it out and requeues the job only it was running
+        scheduler.handle_check("flaky", Err(anyhow::anyhow!("ssh: timed out")));
+        scheduler.handle_check("flaky", Err(anyhow::anyhow!("ssh: timed out")));
+        assert!(!scheduler.worker("flaky").unwrap().is_healthy());
+        assert_eq!(scheduler.pending.len(), 1);
+        assert_eq!(scheduler.pending[0].job_id, jobs[0].job_id);
+        assert_eq!(scheduler.active_job_ids().len(), 1);
+        assert!(scheduler.retry_counts.is_empty());
+        assert_eq!(scheduler.pick_worker(None).as_deref(), Some("steady"));
+        assert!(!scheduler.progress().workers[0].healthy);
+
+        // Each failed check while down doubles the wait
+        scheduler.handle_check("flaky", Err(anyhow::anyhow!("ssh: timed out")));
+        let Health::Unhealthy { backoff, retry_at } = scheduler.worker("flaky").unwrap().health
+        else {
+            panic!("worker should be unhealthy");
+        };
+        assert_eq!(backoff, INITIAL_BACKOFF * 2);
+        let slots = scheduler.worker("flaky").unwrap();
+        assert!(!slots.check_due(scheduler.heartbeat, Instant::now()));
+        assert!(slots.check_due(scheduler.heartbeat, retry_at));
+
+        // A ready status brings it back
+        scheduler.handle_check("flaky", Ok(WorkerStatus::ready("cpu", 1)));
+        assert!(scheduler.worker("flaky").unwrap().is_healthy());
+    }
+
+    #[tokio::test]
+    async fn test_job_failures_dont_count_against_the_worker() {
+        let mut scheduler = test_scheduler(&[("gpu", 1, 1)]);
+        let jobs = create_jobs(
+            "sess",
+            &(0..4).map(|i| (0, i, String::new())).collect::<Vec<_>>(),
+            TtsJobOptions::default(),
+        );
+
+        // Hard chunks failing their quality checks leave the worker in use
+        for job in &jobs[..3] {
+            run_on(&mut scheduler, job, "gpu", Instant::now());
+            let failed = TtsResult::failure(
+                &job.job_id,
+                "Quality check failed after 2 regeneration(s): clipped",
+            );
+            scheduler.handle_result("gpu".to_string(), failed, Origin::Worker).await.unwrap();
+        }
+        assert!(scheduler.worker("gpu").unwrap().is_healthy());
+        assert_eq!(scheduler.worker("gpu").unwrap().consecutive_failures, 0);
+        assert_eq!(scheduler.failed.len(), 3);
+        assert_eq!(scheduler.pick_worker(None).as_deref(), Some("gpu"));
+
+        // Jobs that never got an answer do count
+        run_on(&mut scheduler, &jobs[3], "gpu", Instant::now());
+        let lost = TtsResult::failure(&jobs[3].job_id, "connection reset");
+        scheduler.handle_result("gpu".to_string(), lost, Origin::Transport).await.unwrap();
+        assert_eq!(scheduler.worker("gpu").unwrap().consecutive_failures, 1);
+    }
+
+    #[test]
+    fn test_pick_speculative() {
+        let mut scheduler = test_scheduler(&[("cpu", 1, 1), ("gpu", 1, 1)]);
+        set_avg(&mut scheduler, "cpu", 10_000);
+        set_avg(&mut scheduler, "gpu", 1_000);
+        let job = TtsJob::new("sess", 0, 0, "Hello", TtsJobOptions::default());
+
+        // A job nearly done on the slow worker is left alone
+        let nearly_done = Instant::now() - Duration::from_millis(9_500);
+        run_on(&mut scheduler, &job, "cpu", nearly_done);
+        assert!(scheduler.pick_speculative().is_none());
+
+        // One that just started is copied to the idle fast worker, once
+        scheduler.in_flight[0].started = Instant::now();
+        let (copy, worker) = scheduler.pick_speculative().unwrap();
+        assert_eq!(copy.job_id, job.job_id);
+        assert_eq!(worker, "gpu");
+        run_on(&mut scheduler, &copy, "gpu", Instant::now());
+        assert!(scheduler.pick_speculative().is_none());
+        assert_eq!(scheduler.progress().speculative, 1);
+        assert_eq!(scheduler.progress().in_flight, 1);
+    }
+
+    #[tokio::test]
+    async fn test_first_copy_wins() {
+        let mut scheduler = test_scheduler(&[("cpu", 1, 1), ("gpu", 1, 1)]);
+        let temp_dir = tempfile::tempdir().unwrap();
+        scheduler.temp_dir = temp_dir.path().to_path_buf();
+
+        let job = TtsJob::new("sess", 0, 0, "Hello", TtsJobOptions::default());
+        run_on(&mut scheduler, &job, "cpu", Instant::now());
+        run_on(&mut scheduler, &job, "gpu", Instant::now());
+
+        // A failed copy doesn't count against the job while the other runs
+        let failed = TtsResult::failure(&job.job_id, "out of memory");
+        scheduler.handle_result("cpu".to_string(), failed, Origin::Worker).await.unwrap();
+        assert!(scheduler.failed.is_empty());
+        assert!(scheduler.retry_counts.is_empty());
+
+        run_on(&mut scheduler, &job, "cpu", Instant::now());
+        let done = TtsResult::success(&job.job_id, 10, 0, "").with_audio(b"gpu audio");
+        scheduler.handle_result("gpu".to_string(), done, Origin::Worker).await.unwrap();
+        assert_eq!(scheduler.completed.len(), 1);
+        assert!(scheduler.active_job_ids().is_empty());
+        // The slow copy still occupies its worker until it returns
+        assert_eq!(scheduler.jobs_on("cpu"), 1);
+
+        let late = TtsResult::success(&job.job_id, 10, 0, "").with_audio(b"cpu audio");
+        scheduler.handle_result("cpu".to_string(), late, Origin::Worker).await.unwrap();
+        assert_eq!(scheduler.completed.len(), 1);
+        assert_eq!(scheduler.jobs_on("cpu"), 0);
+        let audio = std::fs::read(temp_dir.path().join(format!("{}.wav", job.job_id))).unwrap();
+        assert_eq!(audio, b"gpu audio");
+    }
+
+    #[tokio::test]
+    async fn test_inline_audio_is_verified() {
+        use crate::coordinator::config::WorkersConfig;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let pool = WorkerPool::new(&WorkersConfig::default());
+        let mut scheduler = JobScheduler::new(pool, temp_dir.path().to_path_buf());
+
+        let jobs = create_jobs(
+            "sess",
+            &[(0, 0, "Hello".to_string()), (0, 1, "World".to_string())],
+            TtsJobOptions::default(),
+        );
+        for job in &jobs {
+            run_on(&mut scheduler, job, "w", Instant::now());
+        }
+
+        // Intact audio is written out
+        let good = TtsResult::success(&jobs[0].job_id, 10, 0, "").with_audio(b"audio");
+        scheduler.handle_result("w".to_string(), good, Origin::Worker).await.unwrap();
+        let written = std::fs::read(temp_dir.path().join(format!("{}.wav", jobs[0].job_id)));
+        assert_eq!(written.unwrap(), b"audio");
+        assert_eq!(scheduler.completed.len(), 1);
+        assert!(scheduler.completed[0].audio_data.is_none());
+
+        // A checksum mismatch fails the job so it is retried
+        let mut bad = TtsResult::success(&jobs[1].job_id, 10, 0, "").with_audio(b"audio");
+        bad.audio_sha256 = Some("0".repeat(64));
+        scheduler.handle_result("w".to_string(), bad, Origin::Worker).await.unwrap();
+        assert_eq!(scheduler.completed.len(), 1);
+        assert_eq!(scheduler.failed.len(), 1);
+        assert_eq!(scheduler.failed[0].job_id, jobs[1].job_id);
+    }
 }
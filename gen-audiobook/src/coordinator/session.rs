@@ -0,0 +1,304 @@
+//! Long-lived `gen-audio worker serve` sessions.
+//!
+//! One process per worker stays open for the whole run. Jobs are written to
+//! its stdin as JSON lines and results are read back from stdout, so the
+//! worker keeps Python and the model loaded between chunks. Results are
+//! matched to their jobs by ID.
+//!
+//! The model generates one chunk at a time, so a session runs one job at a
+//! time too: a job's timeout then covers only its own generation, not a wait
+//! behind others. A job that times out is still running on the worker, so the
+//! session is killed to drop it, and the worker falls back to one process
+//! per job.
+
+use crate::worker::protocol::{TtsJob, TtsResult, WorkerStatus};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::{oneshot, Mutex};
+use tokio::task::JoinHandle;
+
+/// Jobs waiting for a result, by job ID.
+type PendingJobs = Arc<StdMutex<HashMap<String, oneshot::Sender<TtsResult>>>>;
+
+/// A running `worker serve` process.
+pub struct WorkerSession {
+    /// Worker name, for messages.
+    name: String,
+    /// Job input stream.
+    stdin: Mutex<ChildStdin>,
+    /// Jobs waiting for a result.
+    pending: PendingJobs,
+    /// Set once the worker's output stream ends.
+    closed: Arc<AtomicBool>,
+    /// Task routing result lines to waiting jobs.
+    reader: JoinHandle<()>,
+    /// Held by the job running on the worker.
+    turn: Mutex<()>,
+    /// The process, killed when a job times out or the session is dropped.
+    child: StdMutex<Child>,
+}
+
+impl WorkerSession {
+    /// Start a session by spawning `command` and waiting for its status line.
+    pub async fn start(
+        name: &str,
+        mut command: Command,
+        startup_timeout: Duration,
+    ) -> Result<(Self, WorkerStatus)> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to start session on worker '{}'", name))?;
+
+        let stdin = child.stdin.take().context("Worker session has no stdin")?;
+        let stdout = child.stdout.take().context("Worker session has no stdout")?;
+        let mut lines = BufReader::new(stdout).lines();
+
+        // The first line is the worker status
+        let first = tokio::time::timeout(startup_timeout, lines.next_line())
+            .await
+            .with_context(|| format!("Worker '{}' did not start serving in time", name))??
+            .with_context(|| format!("Worker '{}' exited before serving", name))?;
+        let status: WorkerStatus = serde_json::from_str(&first)
+            .with_context(|| format!("Unexpected session output from worker '{}'", name))?;
+
+        let pending: PendingJobs = Arc::new(StdMutex::new(HashMap::new()));
+        let closed = Arc::new(AtomicBool::new(false));
+
+        let reader = {
+            let name = name.to_string();
+            let pending = Arc::clone(&pending);
+            let closed = Arc::clone(&closed);
+
+            tokio::spawn(async move {
+                while let Ok(Some(line)) = lines.next_line().await {
+                    match serde_json::from_str::<TtsResult>(&line) {
+                        Ok(result) => {
+                            let waiter = pending.lock().unwrap().remove(&result.job_id);
+                            if let Some(tx) = waiter {
+                                let _ = tx.send(result);
+                            }
+                        }
+                        Err(_) => {
+                            eprintln!("Warning: unexpected output from worker '{}': {}", name, line);
+                        }
+                    }
+                }
+
+                // Dropping the senders fails every job still waiting
+                closed.store(true, Ordering::SeqCst);
+                pending.lock().unwrap().clear();
+            })
+        };
+
+        let session = Self {
+            name: name.to_string(),
+            stdin: Mutex::new(stdin),
+            pending,
+            closed,
+            reader,
+            turn: Mutex::new(()),
+            child: StdMutex::new(child),
+        };
+
+        Ok((session, status))
+    }
+
+    /// Whether the worker's output stream has ended.
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Number of jobs waiting for a result.
+    #[allow(dead_code)]
+    pub fn in_flight(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    /// Send a job and wait for its result.
+    ///
+    /// Jobs submitted together wait for their turn, and `timeout` starts
+    /// once the job is sent. A job that runs past it gets a timeout result and
+    /// closes the session.
+    pub async fn submit(&self, job: &TtsJob, timeout: Duration) -> Result<TtsResult> {
+        let _turn = self.turn.lock().await;
+
+        let (tx, rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(job.job_id.clone(), tx);
+
+        // Checked after registering, so a close can't slip in between
+        if self.is_closed() {
+            self.pending.lock().unwrap().remove(&job.job_id);
+            anyhow::bail!("Session on worker '{}' is closed", self.name);
+        }
+
+        let mut line = serde_json::to_string(job).context("Failed to serialize job")?;
+        line.push('\n');
+
+        let written = {
+            let mut stdin = self.stdin.lock().await;
+            match stdin.write_all(line.as_bytes()).await {
+                Ok(()) => stdin.flush().await,
+                Err(e) => Err(e),
+            }
+        };
+        if let Err(e) = written {
+            self.pending.lock().unwrap().remove(&job.job_id);
+            return Err(e).with_context(|| format!("Failed to send job to worker '{}'", self.name));
+        }
+
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(result)) => Ok(result),
+            Ok(Err(_)) => anyhow::bail!(
+                "Session on worker '{}' closed before job {} finished",
+                self.name,
+                job.job_id
+            ),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&job.job_id);
+                eprintln!(
+                    "Job {} timed out on worker '{}'; closing its session, later jobs run one process each",
+                    job.job_id, self.name
+                );
+                self.closed.store(true, Ordering::SeqCst);
+                let _ = self.child.lock().unwrap().start_kill();
+                Ok(TtsResult::timeout(&job.job_id))
+            }
+        }
+    }
+}
+
+impl Drop for WorkerSession {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worker::protocol::{JobStatus, TtsJobOptions};
+
+    /// A stand-in for `worker serve`: prints a status line, then answers each
+    /// job line with a completed result.
+    const FAKE_SERVE: &str = r#"
+echo '{"ready":true,"device":"cpu","gen_audio_version":"test","chatterbox_installed":true,"jobs_in_progress":0,"available_disk_mb":1}'
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed 's/.*"job_id":"\([^"]*\)".*/\1/')
+  printf '{"version":1,"job_id":"%s","status":"completed","completed_at":"2024-01-01T00:00:00Z"}\n' "$id"
+done
+"#;
+
+    fn fake_worker(script: &str) -> Command {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(script);
+        cmd
+    }
+
+    #[tokio::test]
+    async fn test_session_routes_results_by_job_id() {
+        let (session, status) =
+            WorkerSession::start("fake", fake_worker(FAKE_SERVE), Duration::from_secs(5))
+                .await
+                .unwrap();
+        assert!(status.ready);
+
+        let jobs: Vec<TtsJob> = (0..3)
+            .map(|i| TtsJob::new("sess", 0, i, "Hello", TtsJobOptions::default()))
+            .collect();
+        let timeout = Duration::from_secs(5);
+        let (a, b, c) = tokio::join!(
+            session.submit(&jobs[0], timeout),
+            session.submit(&jobs[1], timeout),
+            session.submit(&jobs[2], timeout),
+        );
+
+        for (job, result) in jobs.iter().zip([a, b, c]) {
+            let result = result.unwrap();
+            assert_eq!(result.job_id, job.job_id);
+            assert_eq!(result.status, JobStatus::Completed);
+        }
+        assert_eq!(session.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_session_close_fails_pending_jobs() {
+        // Answers the handshake, then exits after reading one job
+        let script = r#"
+echo '{"ready":true,"device":"cpu","gen_audio_version":"test","chatterbox_installed":true,"jobs_in_progress":0,"available_disk_mb":1}'
+read -r line
+"#;
+        let (session, _) =
+            WorkerSession::start("fake", fake_worker(script), Duration::from_secs(5))
+                .await
+                .unwrap();
+
+        let job = TtsJob::new("sess", 0, 0, "Hello", TtsJobOptions::default());
+        let result = session.submit(&job, Duration::from_secs(5)).await;
+        assert!(result.is_err());
+        assert!(session.is_closed());
+    }
+
+    /// Like `FAKE_SERVE`, but each job takes 0.3s and jobs run one after another.
+    const SLOW_SERVE: &str = r#"
+echo '{"ready":true,"device":"cpu","gen_audio_version":"test","chatterbox_installed":true,"jobs_in_progress":0,"available_disk_mb":1}'
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed 's/.*"job_id":"\([^"]*\)".*/\1/')
+  sleep 0.3
+  printf '{"version":1,"job_id":"%s","status":"completed","completed_at":"2024-01-01T00:00:00Z"}\n' "$id"
+done
+"#;
+
+    #[tokio::test]
+    async fn test_session_timeout_starts_at_each_jobs_turn() {
+        let (session, _) =
+            WorkerSession::start("fake", fake_worker(SLOW_SERVE), Duration::from_secs(5))
+                .await
+                .unwrap();
+
+        // Together they take 0.6s, longer than either job's timeout
+        let jobs: Vec<TtsJob> = (0..2)
+            .map(|i| TtsJob::new("sess", 0, i, "Hello", TtsJobOptions::default()))
+            .collect();
+        let timeout = Duration::from_millis(500);
+        let (a, b) = tokio::join!(session.submit(&jobs[0], timeout), session.submit(&jobs[1], timeout));
+        assert_eq!(a.unwrap().status, JobStatus::Completed);
+        assert_eq!(b.unwrap().status, JobStatus::Completed);
+    }
+
+    #[tokio::test]
+    async fn test_session_timeout_closes_session() {
+        let (session, _) =
+            WorkerSession::start("fake", fake_worker(SLOW_SERVE), Duration::from_secs(5))
+                .await
+                .unwrap();
+
+        let job = TtsJob::new("sess", 0, 0, "Hello", TtsJobOptions::default());
+        let result = session.submit(&job, Duration::from_millis(100)).await.unwrap();
+        assert_eq!(result.status, JobStatus::Timeout);
+
+        // The abandoned job isn't left queued ahead of the next one
+        assert!(session.is_closed());
+        let next = TtsJob::new("sess", 0, 1, "Hello", TtsJobOptions::default());
+        assert!(session.submit(&next, Duration::from_secs(5)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_session_requires_status_line() {
+        let result =
+            WorkerSession::start("fake", fake_worker("echo oops"), Duration::from_secs(5)).await;
+        assert!(result.is_err());
+    }
+}
@@ -3,7 +3,9 @@
 //! Uses system SSH and SFTP commands for maximum compatibility.
 
 use super::config::WorkerConfig;
+use super::transport::Transport;
 use anyhow::{Context, Result};
+use async_trait::async_trait;
 use std::path::Path;
 use std::process::Stdio;
 use std::time::Duration;
@@ -145,67 +147,30 @@ impl SshConnection {
         }
         Ok(())
     }
+}
 
-    /// Execute a command on the remote host.
-    pub async fn exec(&self, command: &str) -> Result<String> {
-        let mut args = self.ssh_args();
-        args.push(self.config.ssh_target());
-        args.push(command.to_string());
-
-        let output = tokio::time::timeout(self.timeout, async {
-            Command::new("ssh")
-                .args(&args)
-                .output()
-                .await
-        })
-        .await
-        .context("SSH command timed out")?
-        .context("Failed to execute SSH command")?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            anyhow::bail!("SSH command failed: {}", stderr);
-        }
+/// Quote a path for an SFTP batch command.
+fn sftp_quote(path: &str) -> String {
+    format!("\"{}\"", path.replace('\\', "\\\\").replace('"', "\\\""))
+}
 
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+#[async_trait]
+impl Transport for SshConnection {
+    /// Build an SSH command that runs `command` on the remote host.
+    fn command(&self, command: &str) -> Command {
+        let mut cmd = Command::new("ssh");
+        cmd.args(self.ssh_args())
+            .arg(self.config.ssh_target())
+            .arg(command);
+        cmd
     }
 
-    /// Execute a command with stdin input and return stdout.
-    pub async fn exec_with_input(&self, command: &str, input: &[u8]) -> Result<Vec<u8>> {
-        let mut args = self.ssh_args();
-        args.push(self.config.ssh_target());
-        args.push(command.to_string());
-
-        let mut child = Command::new("ssh")
-            .args(&args)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .context("Failed to spawn SSH command")?;
-
-        // Write input to stdin
-        if let Some(mut stdin) = child.stdin.take() {
-            stdin.write_all(input).await
-                .context("Failed to write to SSH stdin")?;
-        }
-
-        // Wait for completion with timeout
-        let output = tokio::time::timeout(self.timeout, child.wait_with_output())
-            .await
-            .context("SSH command timed out")?
-            .context("Failed to get SSH output")?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            anyhow::bail!("SSH command failed: {}", stderr);
-        }
-
-        Ok(output.stdout)
+    fn timeout(&self) -> Duration {
+        self.timeout
     }
 
     /// Upload a file via SFTP.
-    pub async fn upload(&self, local: &Path, remote: &str) -> Result<()> {
+    async fn upload(&self, local: &Path, remote: &str) -> Result<()> {
         let mut sftp_args = vec![
             "-b".to_string(),
             "-".to_string(), // Read commands from stdin
@@ -234,8 +199,8 @@ impl SshConnection {
         // SFTP batch commands
         let batch_commands = format!(
             "put {} {}\nquit\n",
-            local.display(),
-            remote
+            sftp_quote(&local.to_string_lossy()),
+            sftp_quote(remote)
         );
 
         let mut child = Command::new("sftp")
@@ -268,7 +233,7 @@ impl SshConnection {
     }
 
     /// Download a file via SFTP.
-    pub async fn download(&self, remote: &str, local: &Path) -> Result<()> {
+    async fn download(&self, remote: &str, local: &Path) -> Result<()> {
         let mut sftp_args = vec![
             "-b".to_string(),
             "-".to_string(),
@@ -295,8 +260,8 @@ impl SshConnection {
 
         let batch_commands = format!(
             "get {} {}\nquit\n",
-            remote,
-            local.display()
+            sftp_quote(remote),
+            sftp_quote(&local.to_string_lossy())
         );
 
         let mut child = Command::new("sftp")
@@ -327,30 +292,6 @@ impl SshConnection {
 
         Ok(())
     }
-
-    /// Check if a remote file exists.
-    pub async fn file_exists(&self, remote: &str) -> Result<bool> {
-        let result = self.exec(&format!("test -f {} && echo yes || echo no", remote)).await?;
-        Ok(result.trim() == "yes")
-    }
-
-    /// Create a remote directory.
-    pub async fn mkdir(&self, remote: &str) -> Result<()> {
-        self.exec(&format!("mkdir -p {}", remote)).await?;
-        Ok(())
-    }
-
-    /// Remove a remote file.
-    pub async fn remove(&self, remote: &str) -> Result<()> {
-        self.exec(&format!("rm -f {}", remote)).await?;
-        Ok(())
-    }
-
-    /// Test connection to the worker.
-    pub async fn test_connection(&self) -> Result<()> {
-        self.exec("echo ok").await?;
-        Ok(())
-    }
 }
 
 impl Drop for SshConnection {
@@ -380,6 +321,15 @@ mod tests {
         assert!(args.contains(&"-i".to_string()));
     }
 
+    #[test]
+    fn test_sftp_quote() {
+        assert_eq!(
+            sftp_quote("/Users/a/Library/Application Support/x.wav"),
+            "\"/Users/a/Library/Application Support/x.wav\""
+        );
+        assert_eq!(sftp_quote("say \"hi\""), "\"say \\\"hi\\\"\"");
+    }
+
     #[test]
     fn test_ssh_target() {
         let config = WorkerConfig::new("test", "192.168.1.1", "ubuntu");
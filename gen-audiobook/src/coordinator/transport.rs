@@ -0,0 +1,196 @@
+//! Transports for reaching workers.
+//!
+//! A worker is anything that can run `gen-audio worker ...` commands: a host
+//! over SSH, a subprocess on this machine, or a container reached through a
+//! command prefix such as `docker exec -i`. The pool and scheduler talk to
+//! every worker through [`Transport`], so they behave the same for all three.
+
+use super::config::{TransportKind, WorkerConfig};
+use super::local::LocalTransport;
+use super::prefix::PrefixTransport;
+use super::ssh::SshConnection;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::Command;
+
+/// A way of running commands and moving files on a worker.
+#[async_trait]
+pub trait Transport: Send + Sync + std::fmt::Debug {
+    /// Build a command that runs the shell command `command` on the worker.
+    fn command(&self, command: &str) -> Command;
+
+    /// Timeout for commands run through `exec` and `exec_with_input`.
+    fn timeout(&self) -> Duration;
+
+    /// Copy a local file to the worker.
+    async fn upload(&self, local: &Path, remote: &str) -> Result<()>;
+
+    /// Copy a file from the worker.
+    async fn download(&self, remote: &str, local: &Path) -> Result<()>;
+
+    /// Execute a command on the worker and return stdout.
+    async fn exec(&self, command: &str) -> Result<String> {
+        let output = tokio::time::timeout(self.timeout(), self.command(command).output())
+            .await
+            .context("Worker command timed out")?
+            .context("Failed to execute worker command")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Worker command failed: {}", stderr);
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Execute a command with stdin input and return stdout.
+    async fn exec_with_input(&self, command: &str, input: &[u8]) -> Result<Vec<u8>> {
+        let mut child = self
+            .command(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context("Failed to spawn worker command")?;
+
+        // Write input to stdin, closing it afterwards
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(input)
+                .await
+                .context("Failed to write to worker stdin")?;
+        }
+
+        // Wait for completion with timeout
+        let output = tokio::time::timeout(self.timeout(), child.wait_with_output())
+            .await
+            .context("Worker command timed out")?
+            .context("Failed to get worker output")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Worker command failed: {}", stderr);
+        }
+
+        Ok(output.stdout)
+    }
+
+    /// Execute a command, passing each line of stdout to `on_line` as it
+    /// arrives. Stdin is closed, so the command can't wait on a prompt.
+    async fn exec_lines(&self, command: &str, on_line: &mut (dyn for<'l> FnMut(&'l str) + Send)) -> Result<()> {
+        let mut child = self
+            .command(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context("Failed to spawn worker command")?;
+
+        let mut stdout = BufReader::new(child.stdout.take().context("Worker stdout not captured")?).lines();
+        let mut stderr = child.stderr.take().context("Worker stderr not captured")?;
+        let stderr = tokio::spawn(async move {
+            let mut text = String::new();
+            let _ = stderr.read_to_string(&mut text).await;
+            text
+        });
+
+        let run = async {
+            while let Some(line) = stdout.next_line().await.context("Failed to read worker output")? {
+                on_line(&line);
+            }
+            child.wait().await.context("Failed to wait for worker command")
+        };
+        let status = tokio::time::timeout(self.timeout(), run)
+            .await
+            .context("Worker command timed out")??;
+
+        if !status.success() {
+            let stderr = stderr.await.unwrap_or_default();
+            anyhow::bail!("Worker command failed: {}", stderr);
+        }
+        Ok(())
+    }
+
+    /// Check if a file exists on the worker.
+    #[allow(dead_code)]
+    async fn file_exists(&self, remote: &str) -> Result<bool> {
+        let result = self
+            .exec(&format!("test -f {} && echo yes || echo no", quote_path(remote)))
+            .await?;
+        Ok(result.trim() == "yes")
+    }
+
+    /// Create a directory on the worker.
+    async fn mkdir(&self, remote: &str) -> Result<()> {
+        self.exec(&format!("mkdir -p {}", quote_path(remote))).await?;
+        Ok(())
+    }
+
+    /// Remove a file on the worker.
+    async fn remove(&self, remote: &str) -> Result<()> {
+        self.exec(&format!("rm -f {}", quote_path(remote))).await?;
+        Ok(())
+    }
+
+    /// SHA-256 of a file on the worker, or `None` if it doesn't exist.
+    async fn sha256(&self, remote: &str) -> Result<Option<String>> {
+        let path = quote_path(remote);
+        let output = self
+            .exec(&format!(
+                "test -f {path} || exit 0; sha256sum {path} 2>/dev/null || shasum -a 256 {path}"
+            ))
+            .await?;
+        Ok(output.split_whitespace().next().map(str::to_string))
+    }
+
+    /// Test that commands can be run on the worker.
+    async fn test_connection(&self) -> Result<()> {
+        self.exec("echo ok").await?;
+        Ok(())
+    }
+}
+
+/// Quote a worker path for the shell, leaving a leading `~/` to be expanded.
+///
+/// Workers may report data directories with spaces in them, such as
+/// `~/Library/Application Support` on macOS.
+pub fn quote_path(path: &str) -> String {
+    let quote = |s: &str| format!("'{}'", s.replace('\'', "'\\''"));
+    match path.strip_prefix("~/") {
+        Some(rest) => format!("~/{}", quote(rest)),
+        None => quote(path),
+    }
+}
+
+/// Create the transport configured for a worker.
+pub fn connect(config: &WorkerConfig, timeout_secs: u64) -> Box<dyn Transport> {
+    match config.transport {
+        TransportKind::Ssh => Box::new(SshConnection::new(config.clone(), timeout_secs)),
+        TransportKind::Local => Box::new(LocalTransport::new(timeout_secs)),
+        TransportKind::Command => Box::new(PrefixTransport::new(
+            config.command_prefix.as_deref().unwrap_or_default(),
+            timeout_secs,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_path() {
+        assert_eq!(quote_path("~/.gen-audio/bin/gen-audio"), "~/'.gen-audio/bin/gen-audio'");
+        assert_eq!(
+            quote_path("/Users/a/Library/Application Support"),
+            "'/Users/a/Library/Application Support'"
+        );
+        assert_eq!(quote_path("/tmp/it's"), "'/tmp/it'\\''s'");
+    }
+}
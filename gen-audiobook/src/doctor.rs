@@ -0,0 +1,865 @@
+//! `gen-audio doctor`: check that everything gen-audio needs works, with a
+//! specific fix for each problem found.
+//!
+//! Python is exercised in child processes (`doctor --probe` and
+//! `doctor --probe-device`): a bad PYTHONHOME aborts the interpreter, and a
+//! broken torch build can crash on import, either of which would take the
+//! whole report down with it.
+//!
+//! `--json` prints a [`Report`], which is also how `workers doctor` reads a
+//! worker's health.
+
+use crate::bootstrap::{self, external, ffmpeg, lock, models, versions, BootstrapStatus};
+use crate::config::GenAudioConfig;
+use crate::coordinator::config::WorkersConfig;
+use crate::tts::chatterbox::{self, RuntimeInfo};
+use crate::tts::{TtsBackend, TtsOptions};
+use anyhow::{Context, Result};
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Instant;
+
+/// Below this much free space in the data directory, a check fails.
+const MIN_FREE_MB: u64 = 1024;
+
+/// Below this much free space, a check warns: a long book's chunks take a
+/// few gigabytes before they are assembled.
+const LOW_FREE_MB: u64 = 5 * 1024;
+
+/// Sentence synthesized on each device.
+const TEST_SENTENCE: &str = "The quick brown fox jumps over the lazy dog.";
+
+/// Options for `gen-audio doctor`.
+#[derive(Args, Debug)]
+pub struct DoctorArgs {
+    /// Print the results as JSON, e.g. to check a worker's health
+    #[arg(long)]
+    pub json: bool,
+
+    /// Skip the test synthesis, which downloads the model on first use
+    #[arg(long)]
+    pub no_synthesis: bool,
+
+    /// Report what the embedded Python sees, as JSON (used by doctor itself)
+    #[arg(long, hide = true, conflicts_with = "probe_device")]
+    pub probe: bool,
+
+    /// Synthesize a test sentence on DEVICE (used by doctor itself)
+    #[arg(long, hide = true, value_name = "DEVICE")]
+    pub probe_device: Option<String>,
+}
+
+/// Outcome of one check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Warn,
+    Fail,
+    Skip,
+}
+
+/// One check and, if it didn't pass, how to fix it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Check {
+    /// Short name, e.g. "ffmpeg" or "synthesis (cuda)".
+    pub name: String,
+    pub status: CheckStatus,
+    /// What was found.
+    pub detail: String,
+    /// What to do about it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn new(name: &str, status: CheckStatus, detail: impl Into<String>, fix: Option<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            detail: detail.into(),
+            fix,
+        }
+    }
+
+    fn ok(name: &str, detail: impl Into<String>) -> Self {
+        Self::new(name, CheckStatus::Ok, detail, None)
+    }
+
+    fn skip(name: &str, detail: impl Into<String>) -> Self {
+        Self::new(name, CheckStatus::Skip, detail, None)
+    }
+
+    fn warn(name: &str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self::new(name, CheckStatus::Warn, detail, Some(fix.into()))
+    }
+
+    fn fail(name: &str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self::new(name, CheckStatus::Fail, detail, Some(fix.into()))
+    }
+}
+
+impl std::fmt::Display for Check {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self.status {
+            CheckStatus::Ok => "[ OK ]",
+            CheckStatus::Warn => "[WARN]",
+            CheckStatus::Fail => "[FAIL]",
+            CheckStatus::Skip => "[SKIP]",
+        };
+        write!(f, "{} {}: {}", label, self.name, self.detail)?;
+        if let Some(fix) = &self.fix {
+            write!(f, "\n       fix: {}", fix)?;
+        }
+        Ok(())
+    }
+}
+
+/// Results of all checks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    /// Whether no check failed (warnings don't count).
+    pub ok: bool,
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    pub fn new(checks: Vec<Check>) -> Self {
+        Self {
+            ok: !checks.iter().any(|c| c.status == CheckStatus::Fail),
+            checks,
+        }
+    }
+
+    /// Number of failed checks.
+    pub fn failures(&self) -> usize {
+        self.checks.iter().filter(|c| c.status == CheckStatus::Fail).count()
+    }
+
+    /// Print every check, then a summary line.
+    pub fn print(&self) {
+        for check in &self.checks {
+            println!("{}", check);
+        }
+        print_summary(&self.checks);
+    }
+}
+
+fn print_summary(checks: &[Check]) {
+    let count = |status| checks.iter().filter(|c| c.status == status).count();
+    println!();
+    println!(
+        "{} passed, {} warning(s), {} failed",
+        count(CheckStatus::Ok),
+        count(CheckStatus::Warn),
+        count(CheckStatus::Fail)
+    );
+}
+
+/// Handle `gen-audio doctor`.
+pub async fn run(args: &DoctorArgs) -> Result<()> {
+    if args.probe {
+        let info = chatterbox::runtime_info()?;
+        println!("{}", serde_json::to_string(&info)?);
+        return Ok(());
+    }
+    if let Some(device) = &args.probe_device {
+        let result = probe_synthesis(device).await?;
+        println!("{}", serde_json::to_string(&result)?);
+        return Ok(());
+    }
+
+    let report = if args.json {
+        diagnose(!args.no_synthesis, &mut |_| {}).await
+    } else {
+        let report = diagnose(!args.no_synthesis, &mut |check| println!("{}", check)).await;
+        print_summary(&report.checks);
+        report
+    };
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    }
+
+    if !report.ok {
+        anyhow::bail!("{} check(s) failed", report.failures());
+    }
+    Ok(())
+}
+
+/// Run every check, passing each to `on_check` as it completes.
+pub async fn diagnose(synthesis: bool, on_check: &mut dyn FnMut(&Check)) -> Report {
+    let mut checks = Vec::new();
+    let mut record = |check: Check| {
+        on_check(&check);
+        checks.push(check);
+    };
+
+    record(check_config());
+    record(check_workers_config());
+
+    let bootstrap = check_bootstrap();
+    let ready = bootstrap.status == CheckStatus::Ok;
+    record(bootstrap);
+
+    if ready {
+        match check_python().await {
+            Ok((check, info)) => {
+                record(check);
+                for check in check_devices(&info) {
+                    record(check);
+                }
+                if synthesis {
+                    for device in info.devices() {
+                        record(check_synthesis(device).await);
+                    }
+                } else {
+                    record(Check::skip("synthesis", "skipped (--no-synthesis)"));
+                }
+            }
+            Err(check) => record(check),
+        }
+    } else {
+        record(Check::skip("python", "skipped until setup is complete"));
+    }
+
+    record(check_ffmpeg());
+    record(check_ffprobe());
+    record(check_disk_space());
+    record(check_hf_cache());
+
+    Report::new(checks)
+}
+
+/// Check that gen-audio.toml parses and its values are in range.
+fn check_config() -> Check {
+    const NAME: &str = "config";
+    let path = match GenAudioConfig::config_path() {
+        Ok(path) => path,
+        Err(e) => return Check::fail(NAME, format!("{:#}", e), "Set HOME to your home directory"),
+    };
+
+    match GenAudioConfig::load() {
+        Ok(config) => {
+            let problems = config_problems(&config);
+            if problems.is_empty() {
+                let source = if path.exists() { "" } else { " (defaults; the file doesn't exist)" };
+                Check::ok(NAME, format!("{}{}", path.display(), source))
+            } else {
+                let (details, fixes): (Vec<_>, Vec<_>) = problems.into_iter().unzip();
+                Check::warn(NAME, details.join("; "), fixes.join("; "))
+            }
+        }
+        Err(e) => Check::fail(
+            NAME,
+            format!("{}: {:#}", path.display(), e),
+            format!("Fix the syntax in {}, or move it aside to use the defaults", path.display()),
+        ),
+    }
+}
+
+/// Out-of-range or dangling settings, each with the command that fixes it.
+fn config_problems(config: &GenAudioConfig) -> Vec<(String, String)> {
+    let mut problems = Vec::new();
+    let mut range = |name: &str, value: f32, min: f32, max: f32| {
+        if !(min..=max).contains(&value) {
+            problems.push((
+                format!("{} {} is outside {}-{}", name, value, min, max),
+                format!("gen-audio config set-{} <value>", name),
+            ));
+        }
+    };
+    range("exaggeration", config.exaggeration, 0.25, 2.0);
+    range("cfg", config.cfg, 0.0, 1.0);
+    range("temperature", config.temperature, 0.05, 5.0);
+
+    if let Some(voice) = config.voice_ref.as_ref().filter(|v| crate::voices::resolve(v).is_err()) {
+        problems.push((
+            format!("voice_ref {} is neither a file nor a library voice", voice.display()),
+            "gen-audio config set-voice <path|name>".to_string(),
+        ));
+    }
+    if let Some(device) = config.device.as_deref().filter(|d| !["mps", "cuda", "cpu"].contains(d)) {
+        problems.push((
+            format!("device \"{}\" isn't one of mps, cuda or cpu", device),
+            "Fix or remove `device` in the config file".to_string(),
+        ));
+    }
+    if config.chunk_size == 0 {
+        problems.push((
+            "chunk_size is 0".to_string(),
+            "Remove `chunk_size` from the config file to use the default".to_string(),
+        ));
+    }
+    if config.bitrate_kbps == 0 {
+        problems.push((
+            "bitrate_kbps is 0".to_string(),
+            "Remove `bitrate_kbps` from the config file to use the default".to_string(),
+        ));
+    }
+
+    for (name, profile) in &config.profiles {
+        let fix = format!("gen-audio config profile add {} --force ...", name);
+        for problem in profile.problems() {
+            problems.push((format!("profile {}: {}", name, problem), fix.clone()));
+        }
+        if let Some(voice) = profile.voice_ref.as_ref().filter(|v| crate::voices::resolve(v).is_err()) {
+            problems.push((
+                format!(
+                    "profile {}: voice_ref {} is neither a file nor a library voice",
+                    name,
+                    voice.display()
+                ),
+                fix.clone(),
+            ));
+        }
+    }
+    problems
+}
+
+/// Check that gen-audio-workers.toml parses and every worker is valid.
+fn check_workers_config() -> Check {
+    const NAME: &str = "workers config";
+    let path = WorkersConfig::config_path();
+    match WorkersConfig::load() {
+        Ok(config) if config.workers.is_empty() => Check::ok(NAME, "no workers configured"),
+        Ok(config) => Check::ok(
+            NAME,
+            format!("{} worker(s) in {}", config.workers.len(), path.display()),
+        ),
+        Err(e) => Check::fail(
+            NAME,
+            format!("{:#}", e),
+            format!(
+                "Fix {}, or remove the broken worker and add it again with 'gen-audio workers add'",
+                path.display()
+            ),
+        ),
+    }
+}
+
+/// Check that Python, FFmpeg and the packages are installed.
+fn check_bootstrap() -> Check {
+    const NAME: &str = "setup";
+    match bootstrap::check_status() {
+        Ok(BootstrapStatus::Ready) => Check::ok(NAME, "Python, FFmpeg and packages installed"),
+        Ok(BootstrapStatus::External) => Check::ok(NAME, "ready, using external components"),
+        Ok(BootstrapStatus::NeedsFullBootstrap) => Check::fail(
+            NAME,
+            "Python or FFmpeg isn't installed",
+            "Run 'gen-audio setup'",
+        ),
+        Ok(BootstrapStatus::NeedsPackages) => Check::fail(
+            NAME,
+            "the Python packages aren't installed",
+            "Run 'gen-audio setup'",
+        ),
+        Ok(BootstrapStatus::PlatformChanged) => Check::fail(
+            NAME,
+            "dependencies were installed for a different platform",
+            "Run 'gen-audio setup --clean'",
+        ),
+        Err(e) => Check::fail(
+            NAME,
+            format!("{:#}", e),
+            "Fix the external component, or clear it with 'gen-audio config set-external <component>'",
+        ),
+    }
+}
+
+/// Import torch and chatterbox in a child process.
+async fn check_python() -> std::result::Result<(Check, RuntimeInfo), Check> {
+    const NAME: &str = "python";
+    let python_dir = versions::get_python_dir().unwrap_or_default();
+
+    let output = run_probe(&["doctor", "--probe"])
+        .await
+        .map_err(|e| Check::fail(NAME, format!("{:#}", e), "Check that the gen-audio binary can be run"))?;
+
+    match output {
+        Ok(info) => {
+            let info: RuntimeInfo = info;
+            let detail = format!(
+                "Python {}, torch {}, chatterbox-tts {}",
+                info.python, info.torch, info.chatterbox
+            );
+            Ok((Check::ok(NAME, detail), info))
+        }
+        Err(stderr) => Err(Check::fail(
+            NAME,
+            error_summary(&stderr),
+            python_failure_fix(&stderr, &python_dir),
+        )),
+    }
+}
+
+/// Check that torch can use this machine's GPU.
+fn check_devices(info: &RuntimeInfo) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    if cfg!(target_os = "macos") {
+        const NAME: &str = "mps";
+        checks.push(if info.mps_available {
+            Check::ok(NAME, "available")
+        } else if cfg!(target_arch = "aarch64") {
+            let fix = if info.mps_built {
+                "MPS needs macOS 12.3 or later; update macOS to use the GPU"
+            } else {
+                "torch was built without MPS; reinstall it with 'gen-audio setup --clean'"
+            };
+            Check::warn(NAME, "unavailable, so synthesis falls back to the much slower CPU", fix)
+        } else {
+            Check::skip(NAME, "not available on Intel Macs")
+        });
+    } else {
+        const NAME: &str = "cuda";
+        checks.push(match (&info.cuda_device, &info.torch_cuda) {
+            (Some(device), cuda) => Check::ok(
+                NAME,
+                format!("{} (CUDA {})", device, cuda.as_deref().unwrap_or("unknown")),
+            ),
+            (None, None) if lock::has_nvidia_driver() => Check::warn(
+                NAME,
+                format!("an NVIDIA driver is loaded, but torch {} is a CPU-only build", info.torch),
+                "Install the CUDA build with 'GEN_AUDIO_TORCH_VARIANT=cuda gen-audio setup --clean'",
+            ),
+            (None, Some(cuda)) if lock::has_nvidia_driver() => Check::warn(
+                NAME,
+                format!("torch {} was built for CUDA {} but can't use the GPU", info.torch, cuda),
+                format!(
+                    "Check that 'nvidia-smi' works and the driver supports CUDA {}, or install the CPU build with 'GEN_AUDIO_TORCH_VARIANT=cpu gen-audio setup --clean'",
+                    cuda
+                ),
+            ),
+            _ => Check::skip(NAME, "no NVIDIA GPU found; synthesis will use the CPU"),
+        });
+    }
+
+    checks
+}
+
+/// Result of `doctor --probe-device`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SynthesisProbe {
+    /// Length of the synthesized audio.
+    duration_ms: u64,
+    /// Time taken, including loading the model.
+    elapsed_ms: u64,
+    /// Quality problems with the audio.
+    issues: Vec<String>,
+}
+
+/// Synthesize the test sentence on `device` in a child process.
+async fn check_synthesis(device: &str) -> Check {
+    let name = format!("synthesis ({})", device);
+    let output = match run_probe::<SynthesisProbe>(&["doctor", "--probe-device", device]).await {
+        Ok(output) => output,
+        Err(e) => return Check::fail(&name, format!("{:#}", e), "Check that the gen-audio binary can be run"),
+    };
+
+    match output {
+        Ok(probe) if probe.issues.is_empty() => Check::ok(
+            &name,
+            format!(
+                "{:.1}s of audio in {:.1}s",
+                probe.duration_ms as f64 / 1000.0,
+                probe.elapsed_ms as f64 / 1000.0
+            ),
+        ),
+        Ok(probe) => Check::warn(
+            &name,
+            format!("the audio failed quality checks: {}", probe.issues.join("; ")),
+            if device == "cpu" {
+                "Run 'gen-audio setup --upgrade' to reinstall the Python packages".to_string()
+            } else {
+                format!("The {} device may be producing bad audio; compare with '--device cpu'", device)
+            },
+        ),
+        Err(stderr) => Check::fail(&name, error_summary(&stderr), synthesis_failure_fix(&stderr, device)),
+    }
+}
+
+/// Synthesize the test sentence in this process (`doctor --probe-device`).
+async fn probe_synthesis(device: &str) -> Result<SynthesisProbe> {
+    let started = Instant::now();
+    let backend = chatterbox::ChatterboxBackend::new(Some(device), None)?;
+    let output = tempfile::Builder::new()
+        .prefix("gen-audio-doctor")
+        .suffix(".wav")
+        .tempfile()
+        .context("Failed to create a temporary file")?;
+
+    let options = TtsOptions::new();
+    let metrics = backend.synthesize(TEST_SENTENCE, output.path(), &options).await?;
+
+    Ok(SynthesisProbe {
+        duration_ms: metrics.duration_ms,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+        issues: metrics.issues(&options.quality),
+    })
+}
+
+/// Run this binary with `args` and parse the JSON on the last line of its
+/// output. The inner error is the child's stderr, if it failed.
+async fn run_probe<T: serde::de::DeserializeOwned>(args: &[&str]) -> Result<std::result::Result<T, String>> {
+    let exe = std::env::current_exe().context("Failed to locate the gen-audio binary")?;
+    let output = tokio::process::Command::new(&exe)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .await
+        .with_context(|| format!("Failed to run {}", exe.display()))?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    if !output.status.success() {
+        let stderr = if stderr.trim().is_empty() {
+            format!("exited with {}", output.status)
+        } else {
+            stderr
+        };
+        return Ok(Err(stderr));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let last = stdout.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or_default();
+    Ok(serde_json::from_str(last).map_err(|_| format!("unexpected output: {}", last)))
+}
+
+/// The most useful line of a failed probe's stderr.
+///
+/// A fatal interpreter error is reported as such; otherwise the gen-audio
+/// error and its causes are joined, as `{:#}` would.
+fn error_summary(stderr: &str) -> String {
+    let lines: Vec<&str> = stderr.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+
+    if let Some(fatal) = lines.iter().find(|l| l.starts_with("Fatal Python error:")) {
+        let cause = lines
+            .iter()
+            .find(|l| l.starts_with("ModuleNotFoundError") || l.starts_with("ImportError"));
+        return match cause {
+            Some(cause) => format!("{} ({})", fatal, cause),
+            None => fatal.to_string(),
+        };
+    }
+
+    if let Some(start) = lines.iter().position(|l| l.starts_with("Error: ")) {
+        return lines[start..]
+            .iter()
+            .filter(|l| **l != "Caused by:" && !l.starts_with("Stack backtrace:"))
+            .map(|l| {
+                let l = l.strip_prefix("Error: ").unwrap_or(l);
+                // Numbered causes: "0: message"
+                l.split_once(": ")
+                    .filter(|(n, _)| n.chars().all(|c| c.is_ascii_digit()))
+                    .map_or(l, |(_, rest)| rest)
+            })
+            .collect::<Vec<_>>()
+            .join(": ");
+    }
+
+    lines.last().map_or_else(String::new, |l| l.to_string())
+}
+
+/// How to fix the embedded Python failing to start or import packages.
+fn python_failure_fix(stderr: &str, python_dir: &Path) -> String {
+    if stderr.contains("No module named 'encodings'") || stderr.contains("init_fs_encoding") {
+        return format!(
+            "The embedded Python can't find its standard library. Point it at a Python 3.11 install with 'gen-audio config set-external python-home <dir>' (gen-audio's own is {}), or set PYTHONHOME",
+            python_dir.display()
+        );
+    }
+    if stderr.contains("libcudart") || stderr.contains("libcuda.so") || stderr.contains("CUDA driver version is insufficient") {
+        return "torch can't load CUDA. Update the NVIDIA driver, or install the CPU build with 'GEN_AUDIO_TORCH_VARIANT=cpu gen-audio setup --clean'".to_string();
+    }
+    if stderr.contains("No module named") || stderr.contains("Failed to import") {
+        return "Reinstall the Python packages with 'gen-audio setup --upgrade'".to_string();
+    }
+    "Reinstall the Python packages with 'gen-audio setup --upgrade'; if that doesn't help, start over with 'gen-audio setup --clean'".to_string()
+}
+
+/// How to fix a test synthesis failing on `device`.
+fn synthesis_failure_fix(stderr: &str, device: &str) -> String {
+    let hub = versions::get_hf_hub_dir().unwrap_or_default();
+    if stderr.contains("out of memory") || stderr.contains("OutOfMemoryError") {
+        return format!("The {} device ran out of memory. Close other GPU programs, or use '--device cpu'", device);
+    }
+    if stderr.contains("Permission denied") || stderr.contains("PermissionError") {
+        return format!(
+            "The model cache isn't writable. Fix the permissions on {}, or set HF_HUB_CACHE to a writable directory",
+            hub.display()
+        );
+    }
+    if ["ConnectionError", "Max retries exceeded", "LocalEntryNotFoundError", "offline mode"]
+        .iter()
+        .any(|s| stderr.contains(s))
+    {
+        return "The model weights couldn't be downloaded. Check the network, or copy them from another machine with 'gen-audio setup export-bundle --include-models'".to_string();
+    }
+    if device == "mps" && stderr.contains("not currently implemented for the MPS device") {
+        return "An operation isn't supported on MPS. Set PYTORCH_ENABLE_MPS_FALLBACK=1 before running gen-audio, or use '--device cpu'".to_string();
+    }
+    format!("Run 'gen-audio say \"test\" --device {}' to see the full error", device)
+}
+
+/// Check that FFmpeg runs and has the AAC encoder M4B output needs.
+fn check_ffmpeg() -> Check {
+    const NAME: &str = "ffmpeg";
+    let is_external = external::external().ffmpeg.is_some();
+    let reinstall = if is_external {
+        "Use an FFmpeg build with AAC support, or clear the setting with 'gen-audio config set-external ffmpeg' to use gen-audio's own"
+    } else {
+        "Reinstall FFmpeg with 'gen-audio setup --clean'"
+    };
+
+    let path = match ffmpeg::get_ffmpeg_executable() {
+        Ok(path) => path,
+        Err(e) => return Check::fail(NAME, format!("{:#}", e), "Run 'gen-audio setup'"),
+    };
+    let output = match std::process::Command::new(&path).args(["-hide_banner", "-encoders"]).output() {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            return Check::fail(
+                NAME,
+                format!("{} exited with {}", path.display(), output.status),
+                reinstall,
+            )
+        }
+        Err(e) => {
+            let fix = if is_external { reinstall } else { "Run 'gen-audio setup'" };
+            return Check::fail(NAME, format!("can't run {}: {}", path.display(), e), fix);
+        }
+    };
+
+    if has_encoder(&String::from_utf8_lossy(&output.stdout), "aac") {
+        Check::ok(NAME, format!("{} (AAC encoder available)", path.display()))
+    } else {
+        Check::fail(
+            NAME,
+            format!("{} has no AAC encoder, which M4B output needs", path.display()),
+            reinstall,
+        )
+    }
+}
+
+/// Check that FFprobe runs.
+fn check_ffprobe() -> Check {
+    const NAME: &str = "ffprobe";
+    let is_external = external::external().ffprobe().is_some();
+    let fix = if is_external {
+        "Point gen-audio at a working ffprobe with 'gen-audio config set-external ffprobe <path>'"
+    } else {
+        "Reinstall FFmpeg with 'gen-audio setup --clean'"
+    };
+    match ffmpeg::get_ffprobe_executable() {
+        Ok(path) => match std::process::Command::new(&path).arg("-version").output() {
+            Ok(output) if output.status.success() => Check::ok(NAME, path.display().to_string()),
+            Ok(output) => Check::fail(NAME, format!("{} exited with {}", path.display(), output.status), fix),
+            Err(e) => {
+                let fix = if is_external { fix } else { "Run 'gen-audio setup'" };
+                Check::fail(NAME, format!("can't run {}: {}", path.display(), e), fix)
+            }
+        },
+        Err(e) => Check::fail(NAME, format!("{:#}", e), fix),
+    }
+}
+
+/// Whether `ffmpeg -encoders` output lists an encoder called `name`.
+fn has_encoder(encoders: &str, name: &str) -> bool {
+    encoders
+        .lines()
+        .skip_while(|l| !l.trim_start().starts_with("---"))
+        .skip(1)
+        .any(|l| l.split_whitespace().nth(1) == Some(name))
+}
+
+/// Check there is room for a book's chunks in the data directory.
+fn check_disk_space() -> Check {
+    const NAME: &str = "disk space";
+    let dir = match versions::get_data_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            return Check::fail(
+                NAME,
+                format!("{:#}", e),
+                "Make sure your home directory exists and is writable",
+            )
+        }
+    };
+
+    match available_space_mb(&dir) {
+        Ok(mb) if mb < MIN_FREE_MB => Check::fail(
+            NAME,
+            format!("{} MB free in {}", mb, dir.display()),
+            "Free up space; synthesizing a book needs at least a few gigabytes",
+        ),
+        Ok(mb) if mb < LOW_FREE_MB => Check::warn(
+            NAME,
+            format!("{} MB free in {}", mb, dir.display()),
+            "Free up space; long books can need several gigabytes before assembly",
+        ),
+        Ok(mb) => Check::ok(NAME, format!("{:.1} GB free in {}", mb as f64 / 1024.0, dir.display())),
+        Err(e) => Check::warn(NAME, format!("{:#}", e), "Check free space with 'df -h'"),
+    }
+}
+
+/// Free space, in megabytes, on the filesystem holding `path`.
+pub fn available_space_mb(path: &Path) -> Result<u64> {
+    let output = std::process::Command::new("df")
+        .arg("-Pk")
+        .arg(path)
+        .output()
+        .context("Failed to run df")?;
+    if !output.status.success() {
+        anyhow::bail!("df failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    parse_df_available_kb(&String::from_utf8_lossy(&output.stdout))
+        .map(|kb| kb / 1024)
+        .ok_or_else(|| anyhow::anyhow!("Unrecognized df output"))
+}
+
+/// Available kilobytes from `df -Pk` output.
+fn parse_df_available_kb(output: &str) -> Option<u64> {
+    output.lines().nth(1)?.split_whitespace().nth(3)?.parse().ok()
+}
+
+/// Check the Hugging Face cache is writable, since the model is downloaded there.
+fn check_hf_cache() -> Check {
+    const NAME: &str = "model cache";
+    let Some(dir) = versions::get_hf_hub_dir() else {
+        return Check::fail(
+            NAME,
+            "no home directory to put the Hugging Face cache in",
+            "Set HF_HUB_CACHE to a writable directory",
+        );
+    };
+    let fix = || {
+        format!(
+            "Make {} writable (e.g. 'sudo chown -R $USER {}'), or set HF_HUB_CACHE to a writable directory",
+            dir.display(),
+            dir.display()
+        )
+    };
+
+    if let Err(e) = std::fs::create_dir_all(&dir).and_then(|_| tempfile::tempfile_in(&dir)) {
+        return Check::fail(NAME, format!("{} isn't writable: {}", dir.display(), e), fix());
+    }
+
+    if let Some(model) = models::installed().filter(|m| !m.is_complete()) {
+        return Check::fail(
+            NAME,
+            format!("files of the pinned weights ({}) are missing", model.short_commit()),
+            "Download them again with 'gen-audio models fetch'",
+        );
+    }
+    Check::ok(NAME, format!("{}; weights {}", dir.display(), models::describe_installed()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_json() {
+        let report = Report::new(vec![
+            Check::ok("config", "defaults"),
+            Check::warn("cuda", "CPU-only torch", "reinstall"),
+        ]);
+        assert!(report.ok);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["ok"], true);
+        assert_eq!(json["checks"][0]["status"], "ok");
+        assert!(json["checks"][0].get("fix").is_none());
+        assert_eq!(json["checks"][1]["fix"], "reinstall");
+
+        let failed = Report::new(vec![Check::fail("ffmpeg", "no AAC", "reinstall")]);
+        assert!(!failed.ok);
+        assert_eq!(failed.failures(), 1);
+        let parsed: Report = serde_json::from_str(&serde_json::to_string(&failed).unwrap()).unwrap();
+        assert_eq!(parsed, failed);
+    }
+
+    #[test]
+    fn test_has_encoder() {
+        let output = "Encoders:\n \
+            V..... = Video\n \
+            ------\n \
+            V....D libx264              libx264 H.264\n \
+            A....D aac                  AAC (Advanced Audio Coding)\n \
+            A....D libmp3lame           libmp3lame MP3\n";
+        assert!(has_encoder(output, "aac"));
+        assert!(has_encoder(output, "libmp3lame"));
+        assert!(!has_encoder(output, "libfdk_aac"));
+        // Legend lines before the separator aren't encoders
+        assert!(!has_encoder(output, "="));
+    }
+
+    #[test]
+    fn test_parse_df_available_kb() {
+        let output = "Filesystem     1024-blocks      Used Available Capacity Mounted on\n\
+                      /dev/nvme0n1p2   490617784 301234567 164383217      65% /\n";
+        assert_eq!(parse_df_available_kb(output), Some(164383217));
+        assert_eq!(parse_df_available_kb("Filesystem\n"), None);
+        assert!(available_space_mb(Path::new("/")).is_ok());
+    }
+
+    #[test]
+    fn test_error_summary() {
+        let fatal = "Python path configuration:\n  PYTHONHOME = '/install'\n\
+                     Fatal Python error: init_fs_encoding: failed to get the Python codec of the filesystem encoding\n\
+                     Python runtime state: core initialized\n\
+                     ModuleNotFoundError: No module named 'encodings'\n\n\
+                     Current thread 0x0001 (most recent call first):\n  <no Python frame>\n";
+        assert_eq!(
+            error_summary(fatal),
+            "Fatal Python error: init_fs_encoding: failed to get the Python codec of the filesystem encoding (ModuleNotFoundError: No module named 'encodings')"
+        );
+        assert!(python_failure_fix(fatal, Path::new("/data/python")).contains("set-external python-home"));
+
+        let anyhow = "Error: Failed to import torch\n\nCaused by:\n    ModuleNotFoundError: No module named 'torch'\n";
+        assert_eq!(
+            error_summary(anyhow),
+            "Failed to import torch: ModuleNotFoundError: No module named 'torch'"
+        );
+        assert!(python_failure_fix(anyhow, Path::new("/")).contains("setup --upgrade"));
+
+        assert_eq!(error_summary("warning\nsegfault\n"), "segfault");
+    }
+
+    #[test]
+    fn test_synthesis_failure_fix() {
+        assert!(synthesis_failure_fix("torch.OutOfMemoryError: CUDA out of memory", "cuda").contains("--device cpu"));
+        assert!(synthesis_failure_fix("PermissionError: [Errno 13] Permission denied: '/x'", "cpu").contains("HF_HUB_CACHE"));
+        assert!(synthesis_failure_fix("requests.exceptions.ConnectionError", "cpu").contains("export-bundle"));
+        assert!(synthesis_failure_fix("something else", "mps").contains("--device mps"));
+    }
+
+    #[test]
+    fn test_config_problems() {
+        let mut config = GenAudioConfig::default();
+        assert!(config_problems(&config).is_empty());
+
+        config.exaggeration = 3.0;
+        config.device = Some("gpu".to_string());
+        let problems = config_problems(&config);
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0].0, "exaggeration 3 is outside 0.25-2");
+        assert_eq!(problems[0].1, "gen-audio config set-exaggeration <value>");
+    }
+
+    #[test]
+    fn test_config_problems_in_profiles() {
+        let mut config = GenAudioConfig::default();
+        config.profiles.insert(
+            "loud".to_string(),
+            crate::config::Profile {
+                temperature: Some(9.0),
+                ..Default::default()
+            },
+        );
+        let problems = config_problems(&config);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].0, "profile loud: temperature 9 is outside 0.05-5");
+        assert_eq!(problems[0].1, "gen-audio config profile add loud --force ...");
+    }
+}
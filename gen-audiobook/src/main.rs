@@ -4,20 +4,27 @@ mod audio;
 mod bootstrap;
 mod config;
 mod coordinator;
+mod doctor;
 mod epub;
+mod plan;
+mod preview;
 mod session;
 mod setup;
 mod text;
 mod tts;
+mod voices;
 mod worker;
 
 use anyhow::{Context, Result};
+use audio::GapFill;
+use bootstrap::external::ExternalComponent;
+use bootstrap::progress::ProgressMode;
 use clap::{Parser, Subcommand};
 use std::os::unix::process::CommandExt;
 use config::GenAudioConfig;
 use indicatif::{ProgressBar, ProgressStyle};
-use session::Session;
-use std::path::PathBuf;
+use session::{ChunkStatus, Session};
+use std::path::{Path, PathBuf};
 use text::TextChunk;
 use tts::TtsOptions;
 
@@ -33,7 +40,7 @@ struct Args {
     #[arg(short, long)]
     output: Option<PathBuf>,
 
-    /// Path to voice reference audio for voice cloning
+    /// Voice reference audio for voice cloning, or the name of a voice in the library
     #[arg(long)]
     voice: Option<PathBuf>,
 
@@ -45,21 +52,25 @@ struct Args {
     #[arg(long)]
     chapters: Option<String>,
 
+    /// Named set of settings from the config; flags given here override it
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
     /// Device to use (mps, cuda, cpu). Auto-detects if not specified.
     #[arg(long)]
     device: Option<String>,
 
-    /// Expressiveness/exaggeration (0.25-2.0, default 0.5)
-    #[arg(long, default_value = "0.5")]
-    exaggeration: f32,
+    /// Expressiveness/exaggeration (0.25-2.0; default: from profile or config, 0.5)
+    #[arg(long)]
+    exaggeration: Option<f32>,
 
-    /// Pacing/CFG weight (0.0-1.0, default 0.5)
-    #[arg(long, default_value = "0.5")]
-    cfg: f32,
+    /// Pacing/CFG weight (0.0-1.0; default: from profile or config, 0.5)
+    #[arg(long)]
+    cfg: Option<f32>,
 
-    /// Temperature for randomness (0.05-5.0, default 0.8)
-    #[arg(long, default_value = "0.8")]
-    temperature: f32,
+    /// Temperature for randomness (0.05-5.0; default: from profile or config, 0.8)
+    #[arg(long)]
+    temperature: Option<f32>,
 
     /// Enable debug output
     #[arg(short, long, default_value_t = false)]
@@ -73,6 +84,38 @@ struct Args {
     #[arg(long)]
     workers: Option<String>,
 
+    /// Also synthesize on this machine alongside the workers
+    #[arg(long, requires = "distributed")]
+    include_local: bool,
+
+    /// Assemble even if some chunks have no audio, filling them with silence or a tone
+    #[arg(long)]
+    allow_gaps: bool,
+
+    /// Book seed for reproducible synthesis (random if not specified)
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Show the chunk plan with time and size estimates without synthesizing
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Write the dry-run chunk plan to this JSON file
+    #[arg(long, requires = "dry_run")]
+    plan_json: Option<PathBuf>,
+
+    /// Download dependencies without asking (or set GEN_AUDIO_ASSUME_YES=1)
+    #[arg(long, global = true)]
+    yes: bool,
+
+    /// Fail instead of asking when setup needs confirmation
+    #[arg(long, global = true)]
+    non_interactive: bool,
+
+    /// How to report setup progress
+    #[arg(long, global = true, value_enum, default_value_t = ProgressMode::Human)]
+    progress: ProgressMode,
+
     /// Subcommands
     #[command(subcommand)]
     command: Option<Commands>,
@@ -81,14 +124,26 @@ struct Args {
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Set up Python environment for Chatterbox TTS
+    #[command(args_conflicts_with_subcommands = true)]
     Setup {
-        /// Upgrade existing packages
+        /// Reinstall Python packages from the lockfile
         #[arg(long)]
         upgrade: bool,
 
+        /// With --upgrade, resolve the newest package versions and save them as this machine's lockfile
+        #[arg(long, requires = "upgrade")]
+        lock: bool,
+
         /// Remove bootstrap cache and re-download everything
         #[arg(long)]
         clean: bool,
+
+        /// Install from an exported bundle (directory or tarball) instead of downloading
+        #[arg(long, value_name = "DIR|TARBALL", conflicts_with_all = ["upgrade", "clean"])]
+        from: Option<PathBuf>,
+
+        #[command(subcommand)]
+        action: Option<SetupAction>,
     },
     /// Remove all gen-audio dependencies from the system
     Uninstall {
@@ -103,6 +158,11 @@ enum Commands {
     },
     /// Show environment info for debugging
     Info,
+    /// Check that everything gen-audio needs works, and suggest fixes
+    Doctor {
+        #[command(flatten)]
+        args: doctor::DoctorArgs,
+    },
     /// Worker mode for distributed processing (runs on remote GPU machines)
     Worker {
         #[command(subcommand)]
@@ -113,6 +173,138 @@ enum Commands {
         #[command(subcommand)]
         action: coordinator::WorkersCommand,
     },
+    /// Synthesize one chunk of a book to preview TTS settings
+    Preview {
+        /// Path to the EPUB file
+        epub_file: PathBuf,
+        /// Chapter number, from 1
+        #[arg(long, default_value = "1")]
+        chapter: usize,
+        /// Chunk number within the chapter, from 1
+        #[arg(long, default_value = "1")]
+        chunk: usize,
+        #[command(flatten)]
+        options: preview::PreviewArgs,
+    },
+    /// Synthesize a line of text to preview TTS settings
+    Say {
+        /// Text to speak
+        text: String,
+        #[command(flatten)]
+        options: preview::PreviewArgs,
+    },
+    /// Inspect saved sessions and regenerate individual chunks
+    Sessions {
+        #[command(subcommand)]
+        action: SessionsAction,
+    },
+    /// Download, pin and check the Chatterbox model weights
+    Models {
+        #[command(subcommand)]
+        action: ModelsAction,
+    },
+    /// Manage the library of named voices
+    Voices {
+        #[command(subcommand)]
+        action: VoicesAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SetupAction {
+    /// Package Python, FFmpeg and the Python packages into one archive for
+    /// machines without internet access
+    ExportBundle {
+        /// Output file (default: gen-audio-bundle-<version>-<platform>.tar)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Also include the Chatterbox weights from the Hugging Face cache
+        #[arg(long)]
+        include_models: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SessionsAction {
+    /// List saved sessions
+    List,
+    /// Regenerate a single chunk of a session
+    Regen {
+        /// Chunk as CHAPTER:CHUNK, numbered from 1 as in progress output (e.g., "3:12")
+        chunk: String,
+        /// Seed to use (default: the seed recorded for the chunk)
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Session ID (default: the most recently updated session)
+        #[arg(long)]
+        session: Option<String>,
+        /// Device to use (mps, cuda, cpu). Auto-detects if not specified.
+        #[arg(long)]
+        device: Option<String>,
+        /// Write the audio to this file instead of replacing the chunk in the session
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ModelsAction {
+    /// Show the pinned weights and every cached snapshot
+    List,
+    /// Download the weights now and pin them, instead of on first use
+    Fetch {
+        /// Branch, tag or commit to pin (default: the current pin, or main)
+        #[arg(long)]
+        revision: Option<String>,
+    },
+    /// Check the pinned weights against the hashes recorded when they were fetched
+    Verify,
+    /// Remove the pinned weights from the Hugging Face cache
+    Rm {
+        /// Remove every cached Chatterbox snapshot, not just the pinned one
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum VoicesAction {
+    /// List the voices in the library
+    List,
+    /// Prepare a clip (mono 24 kHz WAV, trimmed and normalized) and add it to the library
+    Add {
+        /// Name to use the voice by, e.g. `--voice narrator`
+        name: String,
+        /// Clip of the voice speaking, in any format FFmpeg reads
+        file: PathBuf,
+        /// Use the clip from this many seconds in
+        #[arg(long)]
+        start: Option<f32>,
+        /// Use this many seconds of the clip
+        #[arg(long)]
+        length: Option<f32>,
+        /// Replace a voice with the same name
+        #[arg(long)]
+        force: bool,
+        /// Synthesize a sample sentence in the new voice to check the clone
+        #[arg(long)]
+        preview: bool,
+        /// Play the sample (or, without --preview, the prepared clip) with the `player` command from the config
+        #[arg(long)]
+        play: bool,
+    },
+    /// Remove a voice from the library
+    Rm {
+        /// Voice name
+        name: String,
+    },
+    /// Copy a voice's prepared clip out of the library
+    Export {
+        /// Voice name
+        name: String,
+        /// Output file or directory
+        dest: PathBuf,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -121,7 +313,7 @@ enum ConfigAction {
     Show,
     /// Set default voice reference
     SetVoice {
-        /// Path to voice reference audio
+        /// Path to voice reference audio, or the name of a voice in the library
         path: PathBuf,
     },
     /// Set default exaggeration
@@ -139,6 +331,80 @@ enum ConfigAction {
         /// Value (0.05-5.0)
         value: f32,
     },
+    /// Set the command used to play previews
+    SetPlayer {
+        /// Command, e.g. "afplay" or "ffplay -nodisp -autoexit"
+        command: String,
+    },
+    /// Set the mirror to install dependencies from (an exported bundle served over HTTP)
+    SetMirror {
+        /// Base URL; omit to download from the upstream sites again
+        url: Option<String>,
+    },
+    /// Use an existing Python environment or FFmpeg instead of bootstrapping it
+    SetExternal {
+        /// Which component to set
+        #[arg(value_enum)]
+        component: ExternalComponent,
+        /// Path (or, for ffmpeg and ffprobe, a command on PATH); omit to let gen-audio install its own
+        path: Option<PathBuf>,
+    },
+    /// Manage named sets of settings, selected with `--profile`
+    Profile {
+        #[command(subcommand)]
+        action: ProfileAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ProfileAction {
+    /// List the profiles
+    List,
+    /// Show a profile's settings, with the ones it leaves to the config
+    Show {
+        /// Profile name
+        name: String,
+    },
+    /// Add a profile; settings left out fall back to the top-level config
+    Add {
+        /// Name to use the profile by, e.g. `--profile thriller`
+        name: String,
+        /// Voice reference audio, or the name of a voice in the library
+        #[arg(long)]
+        voice: Option<PathBuf>,
+        /// Device to use (mps, cuda, cpu)
+        #[arg(long)]
+        device: Option<String>,
+        /// Expressiveness/exaggeration (0.25-2.0)
+        #[arg(long)]
+        exaggeration: Option<f32>,
+        /// Pacing/CFG weight (0.0-1.0)
+        #[arg(long)]
+        cfg: Option<f32>,
+        /// Temperature for randomness (0.05-5.0)
+        #[arg(long)]
+        temperature: Option<f32>,
+        /// Target characters per synthesized chunk
+        #[arg(long)]
+        chunk_size: Option<usize>,
+        /// AAC bitrate of the audiobook, in kbps
+        #[arg(long)]
+        bitrate: Option<u32>,
+        /// What to put in the gap left by a failed chunk
+        #[arg(long, value_enum)]
+        gap_fill: Option<GapFill>,
+        /// Length of that gap, in milliseconds
+        #[arg(long)]
+        gap_duration_ms: Option<u64>,
+        /// Replace a profile with the same name
+        #[arg(long)]
+        force: bool,
+    },
+    /// Remove a profile
+    Rm {
+        /// Profile name
+        name: String,
+    },
 }
 
 /// Ensure PYTHONHOME is set before Python initializes.
@@ -153,7 +419,8 @@ fn ensure_python_home() {
         return; // Already set
     }
 
-    if let Some(python_home) = find_python_home() {
+    let configured = bootstrap::external::external().python_home.clone();
+    if let Some(python_home) = configured.or_else(find_python_home) {
         let exe = std::env::current_exe().expect("Failed to get current exe");
         let args: Vec<_> = std::env::args().collect();
 
@@ -194,11 +461,21 @@ async fn main() -> Result<()> {
     ensure_python_home();
 
     let args = Args::parse();
+    bootstrap::set_consent(bootstrap::Consent::from_flags(args.yes, args.non_interactive));
+    bootstrap::progress::set_mode(args.progress);
 
     // Handle subcommands that don't need bootstrap
     match &args.command {
-        Some(Commands::Setup { upgrade, clean }) => {
-            return handle_setup_command(*upgrade, *clean).await;
+        Some(Commands::Setup {
+            upgrade,
+            lock,
+            clean,
+            from,
+            action,
+        }) => {
+            let result = handle_setup_command(*upgrade, *lock, *clean, from.as_deref(), action.as_ref()).await;
+            bootstrap::progress::finish(&result);
+            return result;
         }
         Some(Commands::Uninstall { include_models }) => {
             return handle_uninstall_command(*include_models);
@@ -209,21 +486,48 @@ async fn main() -> Result<()> {
         Some(Commands::Info) => {
             return show_info();
         }
+        Some(Commands::Doctor { args }) => {
+            return doctor::run(args).await;
+        }
         Some(Commands::Worker { action }) => {
             return worker::handle_worker_command(action).await;
         }
         Some(Commands::Workers { action }) => {
             return coordinator::handle_workers_command(action).await;
         }
+        Some(Commands::Sessions { action }) => {
+            return handle_sessions_command(action).await;
+        }
+        Some(Commands::Models { action }) => {
+            let result = handle_models_command(action).await;
+            bootstrap::progress::finish(&result);
+            return result;
+        }
+        Some(Commands::Voices { action }) => {
+            return handle_voices_command(action).await;
+        }
+        Some(Commands::Preview {
+            epub_file,
+            chapter,
+            chunk,
+            options,
+        }) => {
+            return handle_preview_command(epub_file, *chapter, *chunk, args.profile.as_deref(), options)
+                .await;
+        }
+        Some(Commands::Say { text, options }) => {
+            let config = GenAudioConfig::load()
+                .context("Failed to load configuration")?
+                .with_profile(args.profile.as_deref())?;
+            bootstrap_python().await?;
+            return preview::run(text, options, &config).await;
+        }
         None => {}
     }
 
-    // Auto-bootstrap if needed
-    let paths = bootstrap::ensure_bootstrapped().await?;
-
-    // Set Python path for PyO3
-    unsafe {
-        std::env::set_var("PYO3_PYTHON", &paths.python);
+    // Auto-bootstrap if needed (a dry run never loads the model)
+    if !args.dry_run {
+        bootstrap_python().await?;
     }
 
     // Require EPUB file for conversion
@@ -236,8 +540,11 @@ async fn main() -> Result<()> {
         anyhow::bail!("EPUB file not found: {}", epub_path.display());
     }
 
-    // Load configuration
-    let config = GenAudioConfig::load().context("Failed to load configuration")?;
+    // Load configuration; command-line flags override the profile, which overrides the rest
+    let config = GenAudioConfig::load()
+        .context("Failed to load configuration")?
+        .with_profile(args.profile.as_deref())?;
+    let device = args.device.as_deref().or(config.device.as_deref());
 
     // Determine output path (M4B for audiobook with chapters)
     let output_path = args.output.clone().unwrap_or_else(|| {
@@ -246,11 +553,17 @@ async fn main() -> Result<()> {
     });
 
     // Build TTS options from args and config
-    let voice_ref = args.voice.clone().or(config.voice_ref);
+    let voice_ref = args
+        .voice
+        .as_ref()
+        .or(config.voice_ref.as_ref())
+        .map(|voice| voices::resolve(voice))
+        .transpose()?;
     let tts_options = TtsOptions::new()
-        .with_exaggeration(args.exaggeration)
-        .with_cfg(args.cfg)
-        .with_temperature(args.temperature);
+        .with_exaggeration(args.exaggeration.unwrap_or(config.exaggeration))
+        .with_cfg(args.cfg.unwrap_or(config.cfg))
+        .with_temperature(args.temperature.unwrap_or(config.temperature))
+        .with_quality(config.quality.clone());
     let tts_options = if let Some(ref voice) = voice_ref {
         tts_options.with_voice_ref(voice.clone())
     } else {
@@ -261,10 +574,12 @@ async fn main() -> Result<()> {
         eprintln!("EPUB: {}", epub_path.display());
         eprintln!("Output: {}", output_path.display());
         eprintln!("Voice ref: {:?}", voice_ref);
-        eprintln!("Device: {:?}", args.device);
-        eprintln!("Exaggeration: {}", args.exaggeration);
-        eprintln!("CFG: {}", args.cfg);
-        eprintln!("Temperature: {}", args.temperature);
+        eprintln!("Profile: {:?}", args.profile);
+        eprintln!("Device: {:?}", device);
+        eprintln!("Exaggeration: {}", tts_options.exaggeration);
+        eprintln!("CFG: {}", tts_options.cfg);
+        eprintln!("Temperature: {}", tts_options.temperature);
+        eprintln!("Chunk size: {}", config.chunk_size);
     }
 
     // Parse EPUB
@@ -296,6 +611,45 @@ async fn main() -> Result<()> {
         None
     };
 
+    if args.dry_run {
+        return print_dry_run(
+            &args,
+            &config,
+            &book,
+            session.as_ref(),
+            start_chapter,
+            end_chapter,
+        );
+    }
+
+    // A resumed session keeps the voice and settings its chunks were made with
+    let stored_options = session.as_ref().and_then(|s| s.tts_options.clone());
+    let (tts_options, voice_ref) = match stored_options {
+        Some(stored) => {
+            let changed = stored.voice_ref != tts_options.voice_ref
+                || stored.exaggeration != tts_options.exaggeration
+                || stored.cfg != tts_options.cfg
+                || stored.temperature != tts_options.temperature;
+            if changed {
+                eprintln!(
+                    "Keeping the session's voice and settings (exaggeration {}, cfg {}, temperature {}); \
+                     use --no-resume to start over with the new ones.",
+                    stored.exaggeration, stored.cfg, stored.temperature
+                );
+            }
+            let voice_ref = stored.voice_ref.clone();
+            (stored.with_quality(config.quality.clone()), voice_ref)
+        }
+        None => (tts_options, voice_ref),
+    };
+
+    // Clone from a prepared copy of the voice reference, kept for this run
+    let voice_dir = tempfile::TempDir::new()?;
+    let prepared_voice = voice_ref
+        .as_ref()
+        .map(|voice| voices::prepare_reference(voice, voice_dir.path()))
+        .transpose()?;
+
     // If resuming, show progress
     if let Some(ref s) = session {
         let (completed, total, pct) = session::get_progress(s);
@@ -303,6 +657,18 @@ async fn main() -> Result<()> {
             "Resuming session: {}/{} chunks ({:.1}% complete)",
             completed, total, pct
         );
+        if args.seed.is_some_and(|seed| seed != s.seed) {
+            eprintln!(
+                "Keeping the session's seed {}; use --no-resume to start over with a new one.",
+                s.seed
+            );
+        }
+        if config.chunk_size != s.chunk_size {
+            eprintln!(
+                "Keeping the session's chunk size {}; use --no-resume to start over with {}.",
+                s.chunk_size, config.chunk_size
+            );
+        }
     }
 
     // Create new session if needed
@@ -310,7 +676,7 @@ async fn main() -> Result<()> {
     if session.is_none() {
         // Process chapters into chunks
         eprintln!("Processing text into chunks...");
-        chunks = process_book_chapters(&book, start_chapter, end_chapter);
+        chunks = process_book_chapters(&book, start_chapter, end_chapter, config.chunk_size);
         eprintln!("Total chunks: {}", chunks.len());
 
         // Create session
@@ -319,17 +685,30 @@ async fn main() -> Result<()> {
             &book.title,
             book.author.as_deref().unwrap_or("Unknown"),
             &chunks,
+            args.seed.unwrap_or_else(tts::random_seed),
+            &tts_options,
+            config.chunk_size,
         )?);
     } else {
         // For resume, we need to reconstruct chunks from book
-        chunks = process_book_chapters(&book, start_chapter, end_chapter);
+        let chunk_size = session.as_ref().map_or(config.chunk_size, |s| s.chunk_size);
+        chunks = process_book_chapters(&book, start_chapter, end_chapter, chunk_size);
     }
 
     let mut session = session.unwrap();
+    if args.debug {
+        eprintln!("Seed: {}", session.seed);
+    }
 
     // Get temp directory for audio chunks
     let temp_dir = session::get_temp_dir(&session.session_id)?;
 
+    // The session keeps the original voice reference; synthesis uses the prepared one
+    let tts_options = match &prepared_voice {
+        Some(voice) => tts_options.with_voice_ref(voice.clone()),
+        None => tts_options,
+    };
+
     // Check if we should use distributed processing
     let use_distributed = args.distributed || args.workers.is_some();
 
@@ -339,7 +718,9 @@ async fn main() -> Result<()> {
             &mut session,
             &chunks,
             &args,
-            voice_ref.as_ref(),
+            device,
+            &tts_options,
+            prepared_voice.as_ref(),
             &temp_dir,
         )
         .await?;
@@ -349,8 +730,8 @@ async fn main() -> Result<()> {
             &mut session,
             &chunks,
             &tts_options,
-            args.device.as_deref(),
-            voice_ref,
+            device,
+            prepared_voice,
             &temp_dir,
         )
         .await?;
@@ -366,15 +747,39 @@ async fn main() -> Result<()> {
         None
     };
 
+    // Refuse to assemble over chunks that failed all retries, unless allowed
+    let missing = session::get_missing_chunks(&session);
+    let gap_count = missing.len();
+    if gap_count > 0 {
+        print_gap_report(&missing, &chunks);
+        if !args.allow_gaps {
+            anyhow::bail!(
+                "{} chunk(s) have no audio. Re-run the same command to retry them, \
+                 or pass --allow-gaps to fill them with {}.",
+                gap_count,
+                config.gap_fill
+            );
+        }
+    }
+
+    let gap_audio = if gap_count > 0 {
+        let gap_file = temp_dir.join("gap.wav");
+        create_gap_audio(&session, &gap_file, config.gap_fill, config.gap_duration_ms)?;
+        Some(gap_file)
+    } else {
+        None
+    };
+
     // Assemble M4B with chapter markers
     eprintln!("\nAssembling audiobook...");
     assemble_audiobook(
         &session,
         &book,
         &output_path,
-        start_chapter,
-        end_chapter,
+        start_chapter..end_chapter,
         cover_path.as_deref(),
+        gap_audio.as_deref(),
+        config.bitrate_kbps,
     )?;
 
     // Get output file size
@@ -383,8 +788,70 @@ async fn main() -> Result<()> {
 
     eprintln!("Output: {} ({:.1} MB)", output_path.display(), size_mb);
 
-    // Cleanup session on success
-    session::cleanup_session(&session)?;
+    // Cleanup session on success, but keep it if there are gaps left to retry
+    if gap_count == 0 {
+        session::cleanup_session(&session)?;
+    } else {
+        eprintln!(
+            "Session kept with {} gap(s) filled with {}. Re-run the same command to retry them.",
+            gap_count, config.gap_fill
+        );
+    }
+
+    Ok(())
+}
+
+/// Bootstrap dependencies if needed and point PyO3 at the managed Python.
+async fn bootstrap_python() -> Result<()> {
+    let paths = bootstrap::ensure_bootstrapped().await?;
+
+    // Set Python path for PyO3
+    unsafe {
+        std::env::set_var("PYO3_PYTHON", &paths.python);
+    }
+
+    Ok(())
+}
+
+/// Print the chunk plan and estimates for a dry run.
+fn print_dry_run(
+    args: &Args,
+    config: &GenAudioConfig,
+    book: &epub::Book,
+    session: Option<&Session>,
+    start_chapter: usize,
+    end_chapter: usize,
+) -> Result<()> {
+    let chunk_size = session.map_or(config.chunk_size, |s| s.chunk_size);
+    let chunks = process_book_chapters(book, start_chapter, end_chapter, chunk_size);
+    let completed = session
+        .map(|s| {
+            s.chunks
+                .iter()
+                .filter(|c| c.completed)
+                .map(|c| (c.chapter_id, c.chunk_id))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let history = plan::ThroughputHistory::load()?;
+    let device = args.device.as_deref().or(config.device.as_deref());
+    let plan = plan::build_plan(
+        book,
+        &chunks,
+        &completed,
+        &config.quality,
+        history.for_device(device),
+        config.bitrate_kbps,
+    );
+
+    println!();
+    plan::print_plan(&plan);
+
+    if let Some(ref path) = args.plan_json {
+        plan::write_plan_json(&plan, path)?;
+        println!("\nPlan written to {}", path.display());
+    }
 
     Ok(())
 }
@@ -403,6 +870,9 @@ async fn process_local(
     let backend = tts::create_backend(device, voice_ref)?;
     eprintln!("Using device: {}", backend.device());
 
+    // Throughput is recorded so dry runs can estimate synthesis time
+    let mut throughput = plan::ThroughputHistory::load().unwrap_or_default();
+
     // Create progress bar
     let (completed, total, _) = session::get_progress(session);
     let pb = ProgressBar::new(total as u64);
@@ -414,8 +884,8 @@ async fn process_local(
     );
     pb.set_position(completed as u64);
 
-    // Generate audio for each chunk
-    while let Some((chapter_id, chunk_id)) = session::get_next_chunk(session) {
+    // Generate audio for each chunk (failed chunks stay pending for the next run)
+    for (chapter_id, chunk_id) in session::get_pending_chunks(session) {
         // Find the chunk text
         let chunk_text = chunks
             .iter()
@@ -430,6 +900,8 @@ async fn process_local(
                 chapter_id,
                 chunk_id,
                 &temp_dir.join("empty.wav"),
+                None,
+                None,
             )?;
             pb.inc(1);
             continue;
@@ -439,12 +911,33 @@ async fn process_local(
 
         pb.set_message(format!("Chapter {} chunk {}", chapter_id + 1, chunk_id + 1));
 
+        let options = tts_options
+            .clone()
+            .with_seed(session.chunk_seed(chapter_id, chunk_id));
+
+        let started = std::time::Instant::now();
         match backend
-            .synthesize_with_retry(chunk_text, &audio_path, tts_options, 3)
+            .synthesize_with_retry(chunk_text, &audio_path, &options, 3)
             .await
         {
-            Ok(()) => {
-                session::mark_chunk_complete(session, chapter_id, chunk_id, &audio_path)?;
+            Ok(report) => {
+                throughput.record(
+                    backend.device(),
+                    chunk_text.chars().count(),
+                    started.elapsed().as_millis() as u64,
+                );
+                if let Err(e) = throughput.save() {
+                    eprintln!("\nWarning: Failed to save throughput history: {}", e);
+                }
+
+                session::mark_chunk_complete(
+                    session,
+                    chapter_id,
+                    chunk_id,
+                    &audio_path,
+                    Some(report.quality),
+                    Some(report.seed),
+                )?;
             }
             Err(e) => {
                 session::mark_chunk_error(session, chapter_id, chunk_id, &e.to_string())?;
@@ -464,20 +957,87 @@ async fn process_distributed(
     session: &mut Session,
     chunks: &[TextChunk],
     args: &Args,
+    device: Option<&str>,
+    tts_options: &TtsOptions,
     voice_ref: Option<&PathBuf>,
     temp_dir: &PathBuf,
 ) -> Result<()> {
     use coordinator::{
-        create_jobs, JobScheduler, WorkerPool, WorkersConfig,
+        adopt_finished_audio, create_jobs, JobScheduler, Worker, WorkerPool, WorkersConfig,
+        LOCAL_WORKER_NAME,
     };
     use worker::protocol::TtsJobOptions;
 
     eprintln!("Initializing distributed processing...");
 
+    // Get pending chunks
+    let pending_chunks: Vec<(usize, usize, String)> = chunks
+        .iter()
+        .filter(|c| {
+            !session
+                .chunks
+                .iter()
+                .any(|s| s.chapter_id == c.chapter_id && s.chunk_id == c.chunk_id && s.completed)
+        })
+        .filter(|c| !c.text.is_empty())
+        .map(|c| (c.chapter_id, c.chunk_id, c.text.clone()))
+        .collect();
+
+    if pending_chunks.is_empty() {
+        eprintln!("All chunks already processed!");
+        return Ok(());
+    }
+
+    // Create TTS job options; library voices go by their full hash
+    let voice_hash = voice_ref
+        .map(|p| {
+            if voices::is_library_voice(p) {
+                coordinator::compute_file_sha256(p)
+            } else {
+                coordinator::compute_file_hash(p)
+            }
+        })
+        .transpose()?;
+
+    let job_options = TtsJobOptions {
+        exaggeration: tts_options.exaggeration,
+        cfg: tts_options.cfg,
+        temperature: tts_options.temperature,
+        voice_ref_hash: voice_hash.clone(),
+        quality: tts_options.quality.clone(),
+        seed: Some(session.seed),
+    };
+
+    // Create jobs
+    let jobs = create_jobs(&session.session_id, &pending_chunks, job_options);
+
+    // Record audio an interrupted run finished but never saved to the session
+    let (jobs, adopted) = adopt_finished_audio(temp_dir, jobs);
+    for job in &adopted {
+        let audio_path = temp_dir.join(format!("{}.wav", job.job_id));
+        let seed = session.chunk_seed(job.chapter_id, job.chunk_id);
+        session::mark_chunk_complete(
+            session,
+            job.chapter_id,
+            job.chunk_id,
+            &audio_path,
+            None,
+            Some(seed),
+        )?;
+    }
+    if !adopted.is_empty() {
+        eprintln!("Recovered {} chunk(s) from an interrupted run", adopted.len());
+    }
+
+    if jobs.is_empty() {
+        eprintln!("All chunks already processed!");
+        return Ok(());
+    }
+
     // Load worker configuration
     let workers_config = WorkersConfig::load()?;
 
-    if workers_config.workers.is_empty() {
+    if workers_config.workers.is_empty() && !args.include_local {
         anyhow::bail!(
             "No workers configured. Add workers with: gen-audio workers add <name> <host> -u <user>"
         );
@@ -491,11 +1051,13 @@ async fn process_distributed(
         WorkerPool::new(&workers_config)
     };
 
-    if pool.is_empty() {
+    if pool.is_empty() && !args.include_local {
         anyhow::bail!("No matching workers found");
     }
 
-    eprintln!("Connecting to {} worker(s)...", pool.len());
+    if !pool.is_empty() {
+        eprintln!("Connecting to {} worker(s)...", pool.len());
+    }
 
     // Connect to all workers
     let connection_results = pool.connect_all().await;
@@ -514,6 +1076,19 @@ async fn process_distributed(
         }
     }
 
+    // This machine's own device takes jobs from the same queue
+    if args.include_local {
+        let backend = tts::create_backend(device, voice_ref.cloned())?;
+        eprintln!("  {} ({}): ready", LOCAL_WORKER_NAME, backend.device());
+        let worker = Worker::in_process(
+            backend,
+            voice_ref.cloned(),
+            temp_dir.join(LOCAL_WORKER_NAME),
+            pool.defaults(),
+        )?;
+        pool.add_worker(worker)?;
+    }
+
     let ready_count = pool.ready_workers().len();
     if ready_count == 0 {
         anyhow::bail!("No workers are ready");
@@ -521,53 +1096,30 @@ async fn process_distributed(
 
     eprintln!("{} worker(s) ready", ready_count);
 
-    // Upload voice reference if provided
-    if let Some(voice_path) = voice_ref {
-        let hash = coordinator::compute_file_hash(&voice_path.to_path_buf())?;
-        eprintln!("Uploading voice reference ({})...", &hash[..8]);
-        pool.ensure_voice_ref(voice_path, &hash).await?;
+    // Keep the model loaded on each worker for the whole run
+    for (name, result) in pool.start_sessions().await {
+        if let Err(e) = result {
+            eprintln!(
+                "  {}: no persistent session ({}), using one SSH process per job",
+                name, e
+            );
+        }
     }
 
-    // Get pending chunks
-    let pending_chunks: Vec<(usize, usize, String)> = chunks
-        .iter()
-        .filter(|c| {
-            !session
-                .chunks
-                .iter()
-                .any(|s| s.chapter_id == c.chapter_id && s.chunk_id == c.chunk_id && s.completed)
-        })
-        .filter(|c| !c.text.is_empty())
-        .map(|c| (c.chapter_id, c.chunk_id, c.text.clone()))
-        .collect();
-
-    if pending_chunks.is_empty() {
-        eprintln!("All chunks already processed!");
-        return Ok(());
+    // Upload voice reference if provided
+    if let (Some(voice_path), Some(hash)) = (voice_ref, &voice_hash) {
+        eprintln!("Uploading voice reference ({})...", &hash[..8]);
+        pool.ensure_voice_ref(voice_path, hash).await?;
     }
 
-    eprintln!("Processing {} chunks...", pending_chunks.len());
-
-    // Create TTS job options
-    let voice_hash = voice_ref.map(|p| coordinator::compute_file_hash(&p.to_path_buf()))
-        .transpose()?;
-
-    let job_options = TtsJobOptions {
-        exaggeration: args.exaggeration,
-        cfg: args.cfg,
-        temperature: args.temperature,
-        voice_ref_hash: voice_hash,
-    };
-
-    // Create jobs
-    let jobs = create_jobs(&session.session_id, &pending_chunks, job_options);
+    eprintln!("Processing {} chunks...", jobs.len());
 
     // Create scheduler
+    let total = jobs.len();
     let mut scheduler = JobScheduler::new(pool, temp_dir.clone());
     scheduler.enqueue(jobs);
 
     // Create progress bar
-    let total = pending_chunks.len();
     let pb = ProgressBar::new(total as u64);
     pb.set_style(
         ProgressStyle::default_bar()
@@ -576,43 +1128,33 @@ async fn process_distributed(
             .progress_chars("#>-"),
     );
 
-    // Run scheduler
+    // Run scheduler, saving each result to the session as it arrives so an
+    // interrupted run keeps everything finished so far
     let results = scheduler
-        .run_to_completion(|progress| {
-            pb.set_position(progress.completed as u64);
-            if !progress.workers.is_empty() {
-                let worker_info: Vec<String> = progress
-                    .workers
-                    .iter()
-                    .map(|w| format!("{}:{}", w.name, w.completed))
-                    .collect();
-                pb.set_message(worker_info.join(" "));
-            }
-        })
+        .run_to_completion(
+            |progress| {
+                pb.set_position(progress.completed as u64);
+                if !progress.workers.is_empty() {
+                    let worker_info: Vec<String> = progress
+                        .workers
+                        .iter()
+                        .map(|w| {
+                            if w.healthy {
+                                format!("{}:{}", w.name, w.completed)
+                            } else {
+                                format!("{}:{} (unhealthy)", w.name, w.completed)
+                            }
+                        })
+                        .collect();
+                    pb.set_message(worker_info.join(" "));
+                }
+            },
+            |result| record_distributed_result(session, temp_dir, result),
+        )
         .await?;
 
     pb.finish_with_message("Distributed processing complete!");
 
-    // Update session with results
-    for result in &results {
-        // Parse chapter and chunk from job_id
-        if let (Some(chapter_id), Some(chunk_id)) = (
-            parse_chapter_from_job_id(&result.job_id),
-            parse_chunk_from_job_id(&result.job_id),
-        ) {
-            match result.status {
-                worker::protocol::JobStatus::Completed => {
-                    let audio_path = temp_dir.join(format!("{}.wav", result.job_id));
-                    session::mark_chunk_complete(session, chapter_id, chunk_id, &audio_path)?;
-                }
-                _ => {
-                    let error = result.error.as_deref().unwrap_or("Unknown error");
-                    session::mark_chunk_error(session, chapter_id, chunk_id, error)?;
-                }
-            }
-        }
-    }
-
     // Report summary
     let successful = results
         .iter()
@@ -625,26 +1167,38 @@ async fn process_distributed(
     Ok(())
 }
 
-/// Parse chapter number from job ID.
-fn parse_chapter_from_job_id(job_id: &str) -> Option<usize> {
-    let parts: Vec<&str> = job_id.split('_').collect();
-    for part in parts {
-        if part.starts_with("ch") {
-            return part[2..].parse().ok();
-        }
-    }
-    None
-}
+/// Save a distributed job's final result to the session.
+fn record_distributed_result(
+    session: &mut Session,
+    temp_dir: &Path,
+    result: &worker::protocol::TtsResult,
+) -> Result<()> {
+    use coordinator::scheduler::{parse_chapter_from_job_id, parse_chunk_from_job_id};
+
+    let (Some(chapter_id), Some(chunk_id)) = (
+        parse_chapter_from_job_id(&result.job_id),
+        parse_chunk_from_job_id(&result.job_id),
+    ) else {
+        return Ok(());
+    };
 
-/// Parse chunk number from job ID.
-fn parse_chunk_from_job_id(job_id: &str) -> Option<usize> {
-    let parts: Vec<&str> = job_id.split('_').collect();
-    for part in parts {
-        if part.starts_with("ck") {
-            return part[2..].parse().ok();
+    match result.status {
+        worker::protocol::JobStatus::Completed => {
+            let audio_path = temp_dir.join(format!("{}.wav", result.job_id));
+            session::mark_chunk_complete(
+                session,
+                chapter_id,
+                chunk_id,
+                &audio_path,
+                result.quality.clone(),
+                result.seed,
+            )
+        }
+        _ => {
+            let error = result.error.as_deref().unwrap_or("Unknown error");
+            session::mark_chunk_error(session, chapter_id, chunk_id, error)
         }
     }
-    None
 }
 
 /// Parse chapter range string like "0-10" or "5".
@@ -673,6 +1227,7 @@ fn process_book_chapters(
     book: &epub::Book,
     start_chapter: usize,
     end_chapter: usize,
+    chunk_size: usize,
 ) -> Vec<TextChunk> {
     let mut all_chunks = Vec::new();
 
@@ -686,21 +1241,95 @@ fn process_book_chapters(
             chapter.content.clone()
         };
 
-        let chunks = text::process_chapter(chapter_id, &text, text::chunker::DEFAULT_TARGET_SIZE);
+        let chunks = text::process_chapter(chapter_id, &text, chunk_size);
         all_chunks.extend(chunks);
     }
 
     all_chunks
 }
 
+/// Get the text of one chunk, chunking only its chapter.
+fn find_chunk_text(
+    book: &epub::Book,
+    chapter_id: usize,
+    chunk_id: usize,
+    chunk_size: usize,
+) -> Result<String> {
+    if chapter_id >= book.chapters.len() {
+        anyhow::bail!(
+            "Book has {} chapters, no chapter {}",
+            book.chapters.len(),
+            chapter_id + 1
+        );
+    }
+
+    let chunks = process_book_chapters(book, chapter_id, chapter_id + 1, chunk_size);
+    let count = chunks.len();
+    chunks
+        .into_iter()
+        .find(|c| c.chunk_id == chunk_id)
+        .map(|c| c.text)
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Chapter {} has {} chunks, no chunk {}",
+                chapter_id + 1,
+                count,
+                chunk_id + 1
+            )
+        })
+}
+
+/// Print the chunks that have no audio, with their text, so they can be reviewed.
+fn print_gap_report(missing: &[&ChunkStatus], chunks: &[TextChunk]) {
+    eprintln!("\n{} chunk(s) have no audio:", missing.len());
+    for status in missing {
+        let text = chunks
+            .iter()
+            .find(|c| c.chapter_id == status.chapter_id && c.chunk_id == status.chunk_id)
+            .map(|c| c.text.as_str())
+            .unwrap_or("(text unavailable)");
+
+        eprintln!(
+            "\n  Chapter {}, chunk {}:",
+            status.chapter_id + 1,
+            status.chunk_id + 1
+        );
+        eprintln!("    \"{}\"", text);
+        if let Some(ref error) = status.error {
+            eprintln!("    Error: {}", error);
+        }
+    }
+    eprintln!();
+}
+
+/// Create the audio inserted for missing chunks, matching the generated chunks' format.
+fn create_gap_audio(
+    session: &Session,
+    gap_file: &Path,
+    fill: GapFill,
+    duration_ms: u64,
+) -> Result<()> {
+    let reference = session
+        .chunks
+        .iter()
+        .filter(|c| c.completed)
+        .filter_map(|c| c.audio_path.as_ref())
+        .find(|p| p.exists())
+        .ok_or_else(|| anyhow::anyhow!("No audio files generated"))?;
+
+    let (sample_rate, channels) = audio::assembler::get_audio_format(reference)?;
+    audio::assembler::create_gap_audio(gap_file, fill, duration_ms, sample_rate, channels)
+}
+
 /// Assemble the final M4B audiobook.
 fn assemble_audiobook(
     session: &Session,
     book: &epub::Book,
     output_path: &PathBuf,
-    start_chapter: usize,
-    end_chapter: usize,
+    chapters: std::ops::Range<usize>,
     cover_image: Option<&std::path::Path>,
+    gap_audio: Option<&Path>,
+    bitrate_kbps: u32,
 ) -> Result<()> {
     // Collect all completed audio files
     let mut all_audio_files: Vec<PathBuf> = Vec::new();
@@ -708,7 +1337,7 @@ fn assemble_audiobook(
 
     let mut current_chunk_index = 0;
 
-    for chapter_id in start_chapter..end_chapter {
+    for chapter_id in chapters {
         // Record chapter boundary
         let chapter_title = book.chapters[chapter_id]
             .title
@@ -717,7 +1346,7 @@ fn assemble_audiobook(
         chapter_boundaries.push((chapter_title, current_chunk_index));
 
         // Get audio files for this chapter
-        let chapter_files = session::get_chapter_audio_files(session, chapter_id);
+        let chapter_files = session::get_chapter_audio_files(session, chapter_id, gap_audio);
         current_chunk_index += chapter_files.len();
         all_audio_files.extend(chapter_files);
     }
@@ -737,6 +1366,7 @@ fn assemble_audiobook(
         &book.title,
         book.author.as_deref().unwrap_or("Unknown"),
         cover_image,
+        bitrate_kbps,
     )?;
 
     Ok(())
@@ -759,6 +1389,99 @@ fn detect_cover_filename(data: &[u8]) -> &'static str {
     }
 }
 
+async fn handle_voices_command(action: &VoicesAction) -> Result<()> {
+    /// Sentence synthesized by `voices add --preview`.
+    const PREVIEW_TEXT: &str =
+        "The old lighthouse keeper climbed the stairs one last time, counting each step aloud.";
+
+    match action {
+        VoicesAction::List => {
+            let library = voices::list()?;
+            if library.is_empty() {
+                println!("No voices yet. Add one with 'gen-audio voices add <name> <file>'.");
+                return Ok(());
+            }
+
+            let default = GenAudioConfig::load()?.voice_ref;
+            let width = library.iter().map(|v| v.name.len()).max().unwrap_or(0);
+            for voice in &library {
+                let is_default = default.as_deref() == Some(Path::new(&voice.name));
+                println!(
+                    "  {:width$}  {:>5.1}s  added {}  from {}{}",
+                    voice.name,
+                    voice.duration_ms as f32 / 1000.0,
+                    voice.added_at.format("%Y-%m-%d"),
+                    voice.source.display(),
+                    if is_default { "  (default)" } else { "" },
+                );
+            }
+        }
+        VoicesAction::Add {
+            name,
+            file,
+            start,
+            length,
+            force,
+            preview,
+            play,
+        } => {
+            let config = GenAudioConfig::load()?;
+            // Fail before the work if playing can't happen
+            let player = if *play { Some(preview::configured_player(&config)?) } else { None };
+
+            // Preparing the clip needs FFmpeg; install ours if there's none
+            if !audio::assembler::is_ffmpeg_available() {
+                bootstrap_python().await?;
+            }
+
+            let segment = audio::voice::Segment {
+                start: *start,
+                length: *length,
+            };
+            let (voice, prepared) = voices::add(name, file, segment, *force)?;
+            voices::report(file, &prepared);
+            println!(
+                "Added voice '{}' ({:.1}s, {})",
+                voice.name,
+                voice.duration_ms as f32 / 1000.0,
+                voice.path.display()
+            );
+
+            if *preview {
+                bootstrap_python().await?;
+                let options = preview::PreviewArgs {
+                    output: None,
+                    play: *play,
+                    sweep: Vec::new(),
+                    voice: Some(voice.path.clone()),
+                    device: None,
+                    exaggeration: None,
+                    cfg: None,
+                    temperature: None,
+                    seed: None,
+                };
+                preview::run(PREVIEW_TEXT, &options, &config).await?;
+            } else if let Some(player) = player {
+                preview::play(player, &voice.path)?;
+            }
+            println!("Use it with: gen-audio book.epub --voice {}", voice.name);
+        }
+        VoicesAction::Rm { name } => {
+            let voice = voices::remove(name)?;
+            println!("Removed voice '{}'", voice.name);
+            let config = GenAudioConfig::load()?;
+            if config.voice_ref.as_deref() == Some(Path::new(name)) {
+                println!("It was the default voice; set another with 'gen-audio config set-voice'.");
+            }
+        }
+        VoicesAction::Export { name, dest } => {
+            let path = voices::export(name, dest)?;
+            println!("Exported '{}' to {}", name, path.display());
+        }
+    }
+    Ok(())
+}
+
 fn handle_config_command(action: &ConfigAction) -> Result<()> {
     match action {
         ConfigAction::Show => {
@@ -778,8 +1501,36 @@ fn handle_config_command(action: &ConfigAction) -> Result<()> {
             } else {
                 println!("device = (auto-detect)");
             }
+            if let Some(player) = &config.player {
+                println!("player = \"{}\"", player);
+            } else {
+                println!("player = (none)");
+            }
+            println!("gap_fill = \"{}\"", config.gap_fill);
+            println!("gap_duration_ms = {}", config.gap_duration_ms);
+            println!("chunk_size = {}", config.chunk_size);
+            println!("bitrate_kbps = {}", config.bitrate_kbps);
+            if let Some(mirror) = &config.mirror_url {
+                println!("mirror_url = \"{}\"", mirror);
+            } else {
+                println!("mirror_url = (none)");
+            }
+            println!();
+            println!("[quality]");
+            print!("{}", toml::to_string_pretty(&config.quality)?);
+            if !config.external.is_empty() {
+                println!();
+                println!("[external]");
+                print!("{}", toml::to_string_pretty(&config.external)?);
+            }
+            for (name, profile) in &config.profiles {
+                println!();
+                println!("[profiles.{}]", name);
+                print!("{}", toml::to_string_pretty(profile)?);
+            }
         }
         ConfigAction::SetVoice { path } => {
+            voices::resolve(path)?;
             let mut config = GenAudioConfig::load()?;
             config.voice_ref = Some(path.clone());
             config.save()?;
@@ -803,17 +1554,464 @@ fn handle_config_command(action: &ConfigAction) -> Result<()> {
             config.save()?;
             println!("Default temperature set to: {}", config.temperature);
         }
+        ConfigAction::SetPlayer { command } => {
+            let mut config = GenAudioConfig::load()?;
+            config.player = Some(command.clone());
+            config.save()?;
+            println!("Preview player set to: {}", command);
+        }
+        ConfigAction::SetMirror { url } => {
+            let mut config = GenAudioConfig::load()?;
+            config.mirror_url = url.clone();
+            config.save()?;
+            match url {
+                Some(url) => println!("Mirror set to: {}", url),
+                None => println!("Mirror cleared; downloading from the upstream sites."),
+            }
+        }
+        ConfigAction::SetExternal { component, path } => {
+            let mut config = GenAudioConfig::load()?;
+            config.external.set(*component, path.clone());
+            if path.is_some() {
+                // FFprobe defaults to the one next to FFmpeg, so check both
+                let checked: &[_] = match component {
+                    ExternalComponent::Ffmpeg => &[ExternalComponent::Ffmpeg, ExternalComponent::Ffprobe],
+                    other => std::slice::from_ref(other),
+                };
+                config.external.validate_components(checked)?;
+            }
+            config.save()?;
+            match path {
+                Some(path) => println!("{} set to: {}", component.key(), path.display()),
+                None => println!("{} cleared; gen-audio will install its own.", component.key()),
+            }
+            if std::env::var(component.env_var()).is_ok_and(|v| !v.is_empty()) {
+                println!("Note: {} is set and takes precedence.", component.env_var());
+            }
+        }
+        ConfigAction::Profile { action } => handle_profile_command(action)?,
     }
     Ok(())
 }
 
+fn handle_profile_command(action: &ProfileAction) -> Result<()> {
+    match action {
+        ProfileAction::List => {
+            let config = GenAudioConfig::load()?;
+            if config.profiles.is_empty() {
+                println!("No profiles yet. Add one with 'gen-audio config profile add <name> ...'.");
+                return Ok(());
+            }
+            let width = config.profiles.keys().map(String::len).max().unwrap_or(0);
+            for (name, profile) in &config.profiles {
+                // One line per profile: its settings as inline TOML
+                let settings = toml::to_string(profile)?
+                    .lines()
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                println!("  {:width$}  {}", name, settings);
+            }
+        }
+        ProfileAction::Show { name } => {
+            let config = GenAudioConfig::load()?;
+            let Some(profile) = config.profiles.get(name) else {
+                anyhow::bail!("No profile named '{}' (see 'gen-audio config profile list')", name);
+            };
+            print!("{}", toml::to_string_pretty(profile)?);
+
+            let resolved = config.clone().with_profile(Some(name))?;
+            let inherited = config::Profile {
+                voice_ref: resolved.voice_ref.filter(|_| profile.voice_ref.is_none()),
+                device: resolved.device.filter(|_| profile.device.is_none()),
+                exaggeration: Some(resolved.exaggeration).filter(|_| profile.exaggeration.is_none()),
+                cfg: Some(resolved.cfg).filter(|_| profile.cfg.is_none()),
+                temperature: Some(resolved.temperature).filter(|_| profile.temperature.is_none()),
+                chunk_size: Some(resolved.chunk_size).filter(|_| profile.chunk_size.is_none()),
+                bitrate_kbps: Some(resolved.bitrate_kbps).filter(|_| profile.bitrate_kbps.is_none()),
+                gap_fill: Some(resolved.gap_fill).filter(|_| profile.gap_fill.is_none()),
+                gap_duration_ms: Some(resolved.gap_duration_ms)
+                    .filter(|_| profile.gap_duration_ms.is_none()),
+                quality: Some(resolved.quality).filter(|_| profile.quality.is_none()),
+            };
+            if inherited != config::Profile::default() {
+                println!();
+                println!("# From the top-level config:");
+                for line in toml::to_string_pretty(&inherited)?.lines() {
+                    println!("# {}", line);
+                }
+            }
+        }
+        ProfileAction::Add {
+            name,
+            voice,
+            device,
+            exaggeration,
+            cfg,
+            temperature,
+            chunk_size,
+            bitrate,
+            gap_fill,
+            gap_duration_ms,
+            force,
+        } => {
+            config::Profile::validate_name(name)?;
+            if let Some(voice) = voice {
+                voices::resolve(voice)?;
+            }
+            let profile = config::Profile {
+                voice_ref: voice.clone(),
+                device: device.clone(),
+                exaggeration: *exaggeration,
+                cfg: *cfg,
+                temperature: *temperature,
+                chunk_size: *chunk_size,
+                bitrate_kbps: *bitrate,
+                gap_fill: *gap_fill,
+                gap_duration_ms: *gap_duration_ms,
+                quality: None,
+            };
+            let problems = profile.problems();
+            if !problems.is_empty() {
+                anyhow::bail!("Invalid profile '{}': {}", name, problems.join("; "));
+            }
+
+            let mut config = GenAudioConfig::load()?;
+            if config.profiles.contains_key(name) && !*force {
+                anyhow::bail!("Profile '{}' already exists (use --force to replace it)", name);
+            }
+            config.profiles.insert(name.clone(), profile);
+            config.save()?;
+            println!("Profile '{}' saved; use it with --profile {}", name, name);
+        }
+        ProfileAction::Rm { name } => {
+            let mut config = GenAudioConfig::load()?;
+            if config.profiles.remove(name).is_none() {
+                anyhow::bail!("No profile named '{}' (see 'gen-audio config profile list')", name);
+            }
+            config.save()?;
+            println!("Removed profile '{}'", name);
+        }
+    }
+    Ok(())
+}
+
+async fn handle_models_command(action: &ModelsAction) -> Result<()> {
+    use bootstrap::download::format_bytes;
+    use bootstrap::models;
+
+    match action {
+        ModelsAction::List => {
+            match models::installed() {
+                Some(model) => {
+                    println!(
+                        "Pinned: {} {} -> {} (fetched {})",
+                        model.repo,
+                        model.revision,
+                        model.commit,
+                        model.fetched_at.format("%Y-%m-%d %H:%M")
+                    );
+                    println!("  {}", model.path.display());
+                }
+                None => println!("No weights pinned. Download them with 'gen-audio models fetch'."),
+            }
+
+            let snapshots = models::snapshots();
+            if !snapshots.is_empty() {
+                let pinned = models::installed().map(|m| m.commit);
+                println!();
+                println!("Cached snapshots of {}:", models::MODEL_REPO);
+                for snapshot in &snapshots {
+                    let mut notes = Vec::new();
+                    if pinned.as_deref() == Some(snapshot.commit.as_str()) {
+                        notes.push("pinned");
+                    }
+                    if !snapshot.complete {
+                        notes.push("incomplete");
+                    }
+                    println!(
+                        "  {}  {}{}",
+                        snapshot.commit,
+                        format_bytes(snapshot.size),
+                        if notes.is_empty() { String::new() } else { format!(" ({})", notes.join(", ")) }
+                    );
+                }
+            }
+        }
+        ModelsAction::Fetch { revision } => {
+            let model = models::fetch(revision.as_deref()).await?;
+            bootstrap::progress::message(format!(
+                "Pinned {} {} -> {}",
+                model.repo, model.revision, model.commit
+            ));
+        }
+        ModelsAction::Verify => {
+            let model = models::installed()
+                .ok_or_else(|| anyhow::anyhow!("No weights pinned. Download them with 'gen-audio models fetch'."))?;
+            let problems = models::verify(&model)?;
+            if !problems.is_empty() {
+                for problem in &problems {
+                    eprintln!("  {}", problem);
+                }
+                anyhow::bail!(
+                    "{} model file(s) failed verification; run 'gen-audio models fetch' to download them again",
+                    problems.len()
+                );
+            }
+            bootstrap::progress::message(format!(
+                "All {} files match {} at {}",
+                model.files.len(),
+                model.repo,
+                model.short_commit()
+            ));
+        }
+        ModelsAction::Rm { all } => {
+            let freed = models::remove(*all)?;
+            println!("Removed the model weights ({} freed)", format_bytes(freed));
+        }
+    }
+    Ok(())
+}
+
+async fn handle_sessions_command(action: &SessionsAction) -> Result<()> {
+    match action {
+        SessionsAction::List => {
+            let sessions = session::list_sessions()?;
+            if sessions.is_empty() {
+                println!("No saved sessions.");
+                return Ok(());
+            }
+
+            println!("Saved sessions:");
+            println!();
+            for s in &sessions {
+                let (completed, total, pct) = session::get_progress(s);
+                println!("  {}", s.session_id);
+                println!("    Book: \"{}\" by {}", s.title, s.author);
+                println!(
+                    "    Progress: {}/{} chunks ({:.1}%)",
+                    completed, total, pct
+                );
+                println!("    Seed: {}", s.seed);
+                println!("    Updated: {}", s.updated_at.format("%Y-%m-%d %H:%M:%S"));
+                println!();
+            }
+        }
+        SessionsAction::Regen {
+            chunk,
+            seed,
+            session,
+            device,
+            output,
+        } => {
+            regenerate_chunk(
+                chunk,
+                *seed,
+                session.as_deref(),
+                device.as_deref(),
+                output.as_deref(),
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Parse a chunk reference like "3:12" (1-based) into 0-based (chapter_id, chunk_id).
+fn parse_chunk_ref(chunk: &str) -> Result<(usize, usize)> {
+    let (chapter, chunk) = chunk
+        .split_once(':')
+        .ok_or_else(|| anyhow::anyhow!("Invalid chunk '{}'. Use CHAPTER:CHUNK (e.g., '3:12')", chunk))?;
+    let chapter: usize = chapter.trim().parse().context("Invalid chapter number")?;
+    let chunk: usize = chunk.trim().parse().context("Invalid chunk number")?;
+    if chapter == 0 || chunk == 0 {
+        anyhow::bail!("Chapters and chunks are numbered from 1");
+    }
+    Ok((chapter - 1, chunk - 1))
+}
+
+/// Regenerate one chunk of a saved session with a known seed.
+async fn regenerate_chunk(
+    chunk: &str,
+    seed: Option<u64>,
+    session_id: Option<&str>,
+    device: Option<&str>,
+    output: Option<&Path>,
+) -> Result<()> {
+    let (chapter_id, chunk_id) = parse_chunk_ref(chunk)?;
+
+    let mut session = match session_id {
+        Some(id) => session::load_session(id)?,
+        None => session::list_sessions()?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("No saved sessions"))?,
+    };
+
+    let status = session
+        .chunks
+        .iter()
+        .find(|c| c.chapter_id == chapter_id && c.chunk_id == chunk_id)
+        .cloned()
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Session {} has no chapter {} chunk {}",
+                session.session_id,
+                chapter_id + 1,
+                chunk_id + 1
+            )
+        })?;
+
+    // Re-chunk the chapter to recover the text
+    let book = epub::parse_epub(&session.book_path)
+        .with_context(|| format!("Failed to parse EPUB: {}", session.book_path.display()))?;
+    let text = find_chunk_text(&book, chapter_id, chunk_id, session.chunk_size)?;
+
+    // Reuse the session's options so only the seed can change the result
+    let tts_options = match session.tts_options.clone() {
+        Some(options) => options,
+        None => {
+            eprintln!("Session has no recorded TTS options; using current configuration.");
+            let config = GenAudioConfig::load().context("Failed to load configuration")?;
+            let options = TtsOptions::new()
+                .with_exaggeration(config.exaggeration)
+                .with_cfg(config.cfg)
+                .with_temperature(config.temperature)
+                .with_quality(config.quality);
+            match config.voice_ref {
+                Some(voice) => options.with_voice_ref(voices::resolve(&voice)?),
+                None => options,
+            }
+        }
+    };
+    let seed = seed
+        .or(status.seed)
+        .unwrap_or_else(|| session.chunk_seed(chapter_id, chunk_id));
+    let tts_options = tts_options.with_seed(seed);
+
+    let audio_path = match output {
+        Some(path) => path.to_path_buf(),
+        None => match status.audio_path.clone().filter(|_| status.completed) {
+            Some(path) => path,
+            None => session::get_temp_dir(&session.session_id)?
+                .join(format!("ch{:03}_chunk{:04}.wav", chapter_id, chunk_id)),
+        },
+    };
+
+    bootstrap_python().await?;
+
+    // Synthesize from a prepared copy of the voice reference
+    let voice_dir = tempfile::TempDir::new()?;
+    let tts_options = match tts_options.voice_ref.clone() {
+        Some(voice) => {
+            let voice = voices::prepare_reference(&voice, voice_dir.path())?;
+            tts_options.with_voice_ref(voice)
+        }
+        None => tts_options,
+    };
+
+    eprintln!(
+        "Regenerating chapter {} chunk {} with seed {}:",
+        chapter_id + 1,
+        chunk_id + 1,
+        seed
+    );
+    eprintln!("  \"{}\"", text);
+
+    let backend = tts::create_backend(device, tts_options.voice_ref.clone())?;
+    let quality = backend.synthesize(&text, &audio_path, &tts_options).await?;
+
+    for issue in quality.issues(&tts_options.quality) {
+        eprintln!("Warning: {}", issue);
+    }
+
+    if output.is_none() {
+        session::mark_chunk_complete(
+            &mut session,
+            chapter_id,
+            chunk_id,
+            &audio_path,
+            Some(quality),
+            Some(seed),
+        )?;
+        eprintln!(
+            "Updated session {}. Re-run the conversion to reassemble.",
+            session.session_id
+        );
+    }
+
+    eprintln!("Output: {}", audio_path.display());
+    Ok(())
+}
+
+async fn handle_preview_command(
+    epub_path: &Path,
+    chapter: usize,
+    chunk: usize,
+    profile: Option<&str>,
+    options: &preview::PreviewArgs,
+) -> Result<()> {
+    if chapter == 0 || chunk == 0 {
+        anyhow::bail!("Chapters and chunks are numbered from 1");
+    }
+
+    let config = GenAudioConfig::load()
+        .context("Failed to load configuration")?
+        .with_profile(profile)?;
+    let book = epub::parse_epub(epub_path).context("Failed to parse EPUB")?;
+    let text = find_chunk_text(&book, chapter - 1, chunk - 1, config.chunk_size)?;
+
+    eprintln!("Chapter {} chunk {}:", chapter, chunk);
+    eprintln!("  \"{}\"", text);
+
+    bootstrap_python().await?;
+    preview::run(&text, options, &config).await
+}
+
 fn show_info() -> Result<()> {
     println!("gen-audio environment info:\n");
     println!("{}", bootstrap::get_info()?);
     Ok(())
 }
 
-async fn handle_setup_command(upgrade: bool, clean: bool) -> Result<()> {
+async fn handle_setup_command(
+    upgrade: bool,
+    lock: bool,
+    clean: bool,
+    from: Option<&Path>,
+    action: Option<&SetupAction>,
+) -> Result<()> {
+    if let Some(SetupAction::ExportBundle {
+        output,
+        include_models,
+    }) = action
+    {
+        let output = match output {
+            Some(output) => output.clone(),
+            None => PathBuf::from(bootstrap::bundle::default_bundle_name(
+                &bootstrap::platform::Platform::detect()?,
+            )),
+        };
+        let bundle = bootstrap::bundle::export_bundle(&output, *include_models).await?;
+        let size = std::fs::metadata(&output).map(|m| m.len()).unwrap_or(0);
+        eprintln!(
+            "Wrote {} ({}, {} wheels{})",
+            output.display(),
+            bootstrap::download::format_bytes(size),
+            bundle.wheels.len(),
+            if bundle.models.is_some() { ", Chatterbox weights" } else { "" }
+        );
+        eprintln!("Install it with 'gen-audio setup --from {}',", output.display());
+        eprintln!("or serve it unpacked over HTTP and run 'gen-audio config set-mirror <url>'.");
+        return Ok(());
+    }
+
+    if let Some(path) = from {
+        let source = bootstrap::bundle::Source::open_bundle(path)?;
+        bootstrap::install_from(&source).await?;
+        eprintln!("\nSetup complete.");
+        return Ok(());
+    }
+
     if clean {
         eprintln!("Removing bootstrap cache...");
         let stats = bootstrap::clean_all(false)?;
@@ -831,11 +2029,16 @@ async fn handle_setup_command(upgrade: bool, clean: bool) -> Result<()> {
     let _paths = bootstrap::ensure_bootstrapped().await?;
 
     if upgrade {
-        eprintln!("\nUpgrading packages...");
-        bootstrap::python::install_packages(|msg| {
-            eprintln!("  {}", msg);
-        })?;
-        eprintln!("Packages upgraded successfully.");
+        let source = bootstrap::bundle::Source::configured().await?;
+        if lock {
+            bootstrap::progress::message("Updating the package lock...");
+            let path = bootstrap::relock_packages(&source).await?;
+            bootstrap::progress::message(format!("Packages upgraded and locked in {}.", path.display()));
+        } else {
+            bootstrap::progress::message("Reinstalling packages from the lockfile...");
+            bootstrap::install_packages(&source).await?;
+            bootstrap::progress::message("Packages upgraded successfully.");
+        }
     }
 
     Ok(())
@@ -873,3 +2076,15 @@ fn handle_uninstall_command(include_models: bool) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        // Catches clashing flags, including global ones reaching subcommands
+        Args::command().debug_assert();
+    }
+}
@@ -0,0 +1,478 @@
+//! Dry-run planning: chunk a book and estimate time and storage up front.
+//!
+//! Nothing here loads a model. Audio duration comes from the expected speaking
+//! rate used by the quality checks, and synthesis time from a per-device
+//! throughput history recorded during earlier runs.
+
+use crate::bootstrap::versions::get_data_dir;
+use crate::epub::Book;
+use crate::text::TextChunk;
+use crate::tts::QualityThresholds;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Throughput history file name in the data directory.
+const THROUGHPUT_FILE: &str = "throughput.json";
+
+/// Once a device has this much recorded synthesis time, older measurements
+/// are halved so the estimate follows recent performance.
+const THROUGHPUT_WINDOW_MS: u64 = 6 * 60 * 60 * 1000;
+
+/// Title keywords that mark front matter.
+const FRONT_MATTER_TITLES: &[&str] = &[
+    "copyright",
+    "contents",
+    "dedication",
+    "title page",
+    "half title",
+    "epigraph",
+    "praise for",
+];
+
+/// Title keywords that mark back matter.
+const BACK_MATTER_TITLES: &[&str] = &[
+    "acknowledgment",
+    "acknowledgement",
+    "about the author",
+    "also by",
+    "index",
+    "bibliography",
+    "glossary",
+];
+
+/// Rough role of a chapter in the book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChapterKind {
+    /// Title page, copyright, contents and similar.
+    FrontMatter,
+    /// The body of the book.
+    Content,
+    /// Acknowledgments, about the author, index and similar.
+    BackMatter,
+}
+
+impl std::fmt::Display for ChapterKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // pad() so table column widths apply
+        f.pad(match self {
+            ChapterKind::FrontMatter => "front",
+            ChapterKind::Content => "content",
+            ChapterKind::BackMatter => "back",
+        })
+    }
+}
+
+/// Classify a chapter from its title.
+pub fn classify_chapter(title: Option<&str>) -> ChapterKind {
+    let Some(title) = title else {
+        return ChapterKind::Content;
+    };
+    let title = title.to_lowercase();
+
+    if FRONT_MATTER_TITLES.iter().any(|k| title.contains(k)) {
+        ChapterKind::FrontMatter
+    } else if BACK_MATTER_TITLES.iter().any(|k| title.contains(k)) {
+        ChapterKind::BackMatter
+    } else {
+        ChapterKind::Content
+    }
+}
+
+/// Planned work for one chapter.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChapterPlan {
+    /// Chapter index (0-based, as used by `--chapters`).
+    pub chapter_id: usize,
+    pub title: Option<String>,
+    pub kind: ChapterKind,
+    pub chunks: usize,
+    pub chars: usize,
+    /// Chunks already generated in a resumable session.
+    pub completed_chunks: usize,
+    pub estimated_duration_ms: u64,
+}
+
+/// Planned work for one chunk.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChunkPlan {
+    pub chapter_id: usize,
+    pub chunk_id: usize,
+    pub chars: usize,
+    pub completed: bool,
+    pub text: String,
+}
+
+/// Estimated synthesis time for the remaining chunks.
+#[derive(Debug, Clone, Serialize)]
+pub struct SynthesisEstimate {
+    /// Device the history was recorded on.
+    pub device: String,
+    /// Characters synthesized per second on that device.
+    pub chars_per_second: f64,
+    /// Number of chunks the throughput was measured over.
+    pub measured_chunks: u64,
+    pub estimated_ms: u64,
+}
+
+/// Complete plan for converting a book.
+#[derive(Debug, Clone, Serialize)]
+pub struct BookPlan {
+    pub title: String,
+    pub author: Option<String>,
+    pub chapters: Vec<ChapterPlan>,
+    pub chunks: Vec<ChunkPlan>,
+    pub total_chars: usize,
+    /// Characters in chunks that still need to be synthesized.
+    pub remaining_chars: usize,
+    pub estimated_duration_ms: u64,
+    pub estimated_size_bytes: u64,
+    /// AAC bitrate the output size is estimated for (kbps).
+    pub bitrate_kbps: u32,
+    /// Missing when there is no throughput history for the device yet.
+    pub synthesis: Option<SynthesisEstimate>,
+}
+
+/// Build a plan for the given chunks.
+///
+/// `completed` holds the (chapter_id, chunk_id) of chunks that a resumable
+/// session has already generated; they count toward the audio and output
+/// size but not toward synthesis time.
+pub fn build_plan(
+    book: &Book,
+    chunks: &[TextChunk],
+    completed: &HashSet<(usize, usize)>,
+    thresholds: &QualityThresholds,
+    throughput: Option<(&str, &ThroughputRecord)>,
+    bitrate_kbps: u32,
+) -> BookPlan {
+    let chunk_plans: Vec<ChunkPlan> = chunks
+        .iter()
+        .map(|c| ChunkPlan {
+            chapter_id: c.chapter_id,
+            chunk_id: c.chunk_id,
+            chars: c.text.chars().count(),
+            completed: completed.contains(&(c.chapter_id, c.chunk_id)),
+            text: c.text.clone(),
+        })
+        .collect();
+
+    let mut chapter_ids: Vec<usize> = chunks.iter().map(|c| c.chapter_id).collect();
+    chapter_ids.dedup();
+
+    let chapters: Vec<ChapterPlan> = chapter_ids
+        .into_iter()
+        .map(|chapter_id| {
+            let chapter_chunks: Vec<&ChunkPlan> = chunk_plans
+                .iter()
+                .filter(|c| c.chapter_id == chapter_id)
+                .collect();
+            let title = book.chapters.get(chapter_id).and_then(|c| c.title.clone());
+
+            ChapterPlan {
+                chapter_id,
+                kind: classify_chapter(title.as_deref()),
+                title,
+                chunks: chapter_chunks.len(),
+                chars: chapter_chunks.iter().map(|c| c.chars).sum(),
+                completed_chunks: chapter_chunks.iter().filter(|c| c.completed).count(),
+                estimated_duration_ms: chapter_chunks
+                    .iter()
+                    .map(|c| thresholds.expected_duration_ms(&c.text))
+                    .sum(),
+            }
+        })
+        .collect();
+
+    let total_chars = chunk_plans.iter().map(|c| c.chars).sum();
+    let remaining_chars: usize = chunk_plans
+        .iter()
+        .filter(|c| !c.completed)
+        .map(|c| c.chars)
+        .sum();
+    let estimated_duration_ms: u64 = chapters.iter().map(|c| c.estimated_duration_ms).sum();
+
+    let synthesis = throughput.and_then(|(device, record)| {
+        let chars_per_second = record.chars_per_second()?;
+        Some(SynthesisEstimate {
+            device: device.to_string(),
+            chars_per_second,
+            measured_chunks: record.chunks,
+            estimated_ms: (remaining_chars as f64 / chars_per_second * 1000.0) as u64,
+        })
+    });
+
+    BookPlan {
+        title: book.title.clone(),
+        author: book.author.clone(),
+        chapters,
+        chunks: chunk_plans,
+        total_chars,
+        remaining_chars,
+        estimated_duration_ms,
+        estimated_size_bytes: estimate_output_size(estimated_duration_ms, bitrate_kbps),
+        bitrate_kbps,
+        synthesis,
+    }
+}
+
+/// Estimated M4B size for the given audio duration.
+fn estimate_output_size(duration_ms: u64, bitrate_kbps: u32) -> u64 {
+    duration_ms * bitrate_kbps as u64 / 8
+}
+
+/// Format milliseconds as H:MM:SS.
+fn format_duration(ms: u64) -> String {
+    let secs = ms / 1000;
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Print the plan as a per-chapter table followed by totals.
+pub fn print_plan(plan: &BookPlan) {
+    println!(
+        "\"{}\" by {}",
+        plan.title,
+        plan.author.as_deref().unwrap_or("Unknown")
+    );
+    println!();
+    println!(
+        "{:>4}  {:<7}  {:>6}  {:>9}  {:>8}  Title",
+        "#", "Kind", "Chunks", "Chars", "Audio"
+    );
+
+    for chapter in &plan.chapters {
+        let chunks = if chapter.completed_chunks > 0 {
+            format!("{}/{}", chapter.completed_chunks, chapter.chunks)
+        } else {
+            chapter.chunks.to_string()
+        };
+        println!(
+            "{:>4}  {:<7}  {:>6}  {:>9}  {:>8}  {}",
+            chapter.chapter_id,
+            chapter.kind,
+            chunks,
+            chapter.chars,
+            format_duration(chapter.estimated_duration_ms),
+            chapter.title.as_deref().unwrap_or("(untitled)")
+        );
+    }
+
+    println!();
+    println!(
+        "Total: {} chapters, {} chunks, {} characters",
+        plan.chapters.len(),
+        plan.chunks.len(),
+        plan.total_chars
+    );
+    let completed = plan.chunks.iter().filter(|c| c.completed).count();
+    if completed > 0 {
+        println!(
+            "Already generated: {} chunks ({} characters remaining)",
+            completed, plan.remaining_chars
+        );
+    }
+    println!(
+        "Estimated audio: {}",
+        format_duration(plan.estimated_duration_ms)
+    );
+    println!(
+        "Estimated output: {:.0} MB (M4B, AAC {} kbps)",
+        plan.estimated_size_bytes as f64 / (1024.0 * 1024.0),
+        plan.bitrate_kbps
+    );
+
+    match &plan.synthesis {
+        Some(estimate) => println!(
+            "Estimated synthesis time: {} on {} ({:.1} chars/s over {} recorded chunks)",
+            format_duration(estimate.estimated_ms),
+            estimate.device,
+            estimate.chars_per_second,
+            estimate.measured_chunks
+        ),
+        None => println!(
+            "Estimated synthesis time: unknown (no throughput history for this device yet; \
+             it is recorded during conversions)"
+        ),
+    }
+}
+
+/// Write the plan as JSON for review.
+pub fn write_plan_json(plan: &BookPlan, path: &Path) -> Result<()> {
+    let json = serde_json::to_string_pretty(plan)?;
+    fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Measured synthesis throughput for one device.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThroughputRecord {
+    /// Chunks measured.
+    pub chunks: u64,
+    /// Characters synthesized.
+    pub chars: u64,
+    /// Wall-clock synthesis time, including regenerations.
+    pub synth_ms: u64,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl ThroughputRecord {
+    /// Characters synthesized per second, if anything has been measured.
+    pub fn chars_per_second(&self) -> Option<f64> {
+        if self.synth_ms == 0 || self.chars == 0 {
+            return None;
+        }
+        Some(self.chars as f64 / (self.synth_ms as f64 / 1000.0))
+    }
+
+    /// Add one measured chunk.
+    fn record(&mut self, chars: usize, synth_ms: u64) {
+        if self.synth_ms >= THROUGHPUT_WINDOW_MS {
+            self.chunks /= 2;
+            self.chars /= 2;
+            self.synth_ms /= 2;
+        }
+        self.chunks += 1;
+        self.chars += chars as u64;
+        self.synth_ms += synth_ms;
+        self.updated_at = Some(Utc::now());
+    }
+}
+
+/// Synthesis throughput recorded per device across runs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThroughputHistory {
+    pub devices: HashMap<String, ThroughputRecord>,
+}
+
+impl ThroughputHistory {
+    /// Path of the history file.
+    pub fn path() -> Result<PathBuf> {
+        Ok(get_data_dir()?.join(THROUGHPUT_FILE))
+    }
+
+    /// Load the history, or an empty one if none has been recorded.
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Save the history.
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path()?;
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Add one measured chunk for a device.
+    pub fn record(&mut self, device: &str, chars: usize, synth_ms: u64) {
+        self.devices
+            .entry(device.to_string())
+            .or_default()
+            .record(chars, synth_ms);
+    }
+
+    /// Record for `device`, or the most recently used device if not given.
+    pub fn for_device(&self, device: Option<&str>) -> Option<(&str, &ThroughputRecord)> {
+        match device {
+            Some(device) => self
+                .devices
+                .get_key_value(device)
+                .map(|(name, record)| (name.as_str(), record)),
+            None => self
+                .devices
+                .iter()
+                .max_by_key(|(_, record)| record.updated_at)
+                .map(|(name, record)| (name.as_str(), record)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epub::Chapter;
+
+    fn book() -> Book {
+        let chapter = |title: &str| Chapter {
+            title: Some(title.to_string()),
+            content: String::new(),
+        };
+        Book {
+            title: "Test".to_string(),
+            author: None,
+            chapters: vec![chapter("Copyright"), chapter("Chapter One")],
+            cover_image: None,
+        }
+    }
+
+    #[test]
+    fn test_classify_chapter() {
+        assert_eq!(classify_chapter(Some("Table of Contents")), ChapterKind::FrontMatter);
+        assert_eq!(classify_chapter(Some("About the Author")), ChapterKind::BackMatter);
+        assert_eq!(classify_chapter(Some("Chapter 1: The Storm")), ChapterKind::Content);
+        assert_eq!(classify_chapter(None), ChapterKind::Content);
+    }
+
+    #[test]
+    fn test_build_plan() {
+        let chunks = vec![
+            TextChunk::new(0, 0, "a".repeat(150)),
+            TextChunk::new(1, 0, "b".repeat(300)),
+            TextChunk::new(1, 1, "c".repeat(300)),
+        ];
+        let completed: HashSet<_> = [(1, 0)].into_iter().collect();
+        let mut record = ThroughputRecord::default();
+        record.record(600, 60_000); // 10 chars/s
+
+        let plan = build_plan(
+            &book(),
+            &chunks,
+            &completed,
+            &QualityThresholds::default(),
+            Some(("cuda", &record)),
+            128,
+        );
+
+        assert_eq!(plan.chapters.len(), 2);
+        assert_eq!(plan.chapters[0].kind, ChapterKind::FrontMatter);
+        assert_eq!(plan.chapters[1].chunks, 2);
+        assert_eq!(plan.chapters[1].completed_chunks, 1);
+        // 750 chars at 15 chars/s
+        assert_eq!(plan.estimated_duration_ms, 50_000);
+        assert_eq!(plan.estimated_size_bytes, 50_000 * 16);
+        assert_eq!(plan.remaining_chars, 450);
+        assert_eq!(plan.synthesis.unwrap().estimated_ms, 45_000);
+    }
+
+    #[test]
+    fn test_throughput_history() {
+        let mut history = ThroughputHistory::default();
+        assert!(history.for_device(None).is_none());
+
+        history.record("cpu", 100, 20_000);
+        history.record("cuda", 100, 1_000);
+        history.devices.get_mut("cpu").unwrap().updated_at =
+            Some(Utc::now() - chrono::Duration::hours(1));
+        let (device, record) = history.for_device(None).unwrap();
+        assert_eq!(device, "cuda");
+        assert_eq!(record.chars_per_second(), Some(100.0));
+
+        let (_, cpu) = history.for_device(Some("cpu")).unwrap();
+        assert_eq!(cpu.chars_per_second(), Some(5.0));
+        assert!(history.for_device(Some("mps")).is_none());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0), "0:00:00");
+        assert_eq!(format_duration(3_723_000), "1:02:03");
+    }
+}
@@ -0,0 +1,396 @@
+//! Quick previews of TTS settings on a single chunk or sentence.
+//!
+//! Synthesizes just the given text, so voices and parameters can be tuned
+//! without converting a whole chapter. A parameter sweep renders every
+//! combination into a directory, with a manifest for A/B listening.
+
+use crate::config::GenAudioConfig;
+use crate::tts::{self, QualityMetrics, TtsBackend, TtsOptions};
+use crate::voices;
+use anyhow::{Context, Result};
+use clap::Args;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Default output file for a single preview.
+const DEFAULT_OUTPUT_FILE: &str = "preview.wav";
+
+/// Default output directory for a sweep.
+const DEFAULT_SWEEP_DIR: &str = "preview";
+
+/// Options shared by the `preview` and `say` commands.
+#[derive(Args, Debug)]
+pub struct PreviewArgs {
+    /// Output WAV file, or directory when sweeping (default: preview.wav or preview/)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Play the result with the `player` command from the config
+    #[arg(long)]
+    pub play: bool,
+
+    /// Sweep a parameter, e.g. "temperature=0.5,0.8,1.1". Repeat to sweep a grid.
+    #[arg(long, value_parser = parse_sweep)]
+    pub sweep: Vec<SweepAxis>,
+
+    /// Voice reference audio, or the name of a voice in the library (default: from config)
+    #[arg(long)]
+    pub voice: Option<PathBuf>,
+
+    /// Device to use (mps, cuda, cpu). Auto-detects if not specified.
+    #[arg(long)]
+    pub device: Option<String>,
+
+    /// Expressiveness/exaggeration (default: from config)
+    #[arg(long)]
+    pub exaggeration: Option<f32>,
+
+    /// Pacing/CFG weight (default: from config)
+    #[arg(long)]
+    pub cfg: Option<f32>,
+
+    /// Temperature (default: from config)
+    #[arg(long)]
+    pub temperature: Option<f32>,
+
+    /// Seed (default: random, shared by every file in a sweep)
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+/// A single TTS parameter value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Setting {
+    Exaggeration(f32),
+    Cfg(f32),
+    Temperature(f32),
+    Seed(u64),
+}
+
+impl Setting {
+    /// Apply this setting to a set of options.
+    fn apply(&self, options: TtsOptions) -> TtsOptions {
+        match *self {
+            Setting::Exaggeration(v) => options.with_exaggeration(v),
+            Setting::Cfg(v) => options.with_cfg(v),
+            Setting::Temperature(v) => options.with_temperature(v),
+            Setting::Seed(v) => options.with_seed(v),
+        }
+    }
+
+    /// Short label used in file names.
+    fn label(&self) -> String {
+        match self {
+            Setting::Exaggeration(v) => format!("exaggeration-{}", v),
+            Setting::Cfg(v) => format!("cfg-{}", v),
+            Setting::Temperature(v) => format!("temperature-{}", v),
+            Setting::Seed(v) => format!("seed-{}", v),
+        }
+    }
+}
+
+/// All values to try for one parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepAxis(Vec<Setting>);
+
+/// Parse a sweep spec like "temperature=0.5,0.8,1.1".
+fn parse_sweep(spec: &str) -> Result<SweepAxis, String> {
+    let (name, values) = spec
+        .split_once('=')
+        .ok_or_else(|| format!("expected PARAM=V1,V2,... but got '{}'", spec))?;
+
+    let parse_f32 = |v: &str| {
+        v.parse::<f32>()
+            .map_err(|_| format!("invalid value '{}' for {}", v, name))
+    };
+
+    let settings = values
+        .split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(|v| match name.trim() {
+            "exaggeration" => parse_f32(v).map(Setting::Exaggeration),
+            "cfg" => parse_f32(v).map(Setting::Cfg),
+            "temperature" => parse_f32(v).map(Setting::Temperature),
+            "seed" => v
+                .parse()
+                .map(Setting::Seed)
+                .map_err(|_| format!("invalid seed '{}'", v)),
+            other => Err(format!(
+                "unknown parameter '{}' (use exaggeration, cfg, temperature or seed)",
+                other
+            )),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if settings.is_empty() {
+        return Err(format!("no values given for {}", name));
+    }
+
+    Ok(SweepAxis(settings))
+}
+
+/// Expand sweep axes into every combination of their values.
+fn expand_grid(axes: &[SweepAxis]) -> Vec<Vec<Setting>> {
+    axes.iter().fold(vec![Vec::new()], |combos, axis| {
+        combos
+            .iter()
+            .flat_map(|combo| {
+                axis.0.iter().map(move |setting| {
+                    let mut combo = combo.clone();
+                    combo.push(setting.clone());
+                    combo
+                })
+            })
+            .collect()
+    })
+}
+
+/// One rendered file in a sweep.
+#[derive(Debug, Serialize)]
+struct ManifestEntry {
+    file: String,
+    exaggeration: f32,
+    cfg: f32,
+    temperature: f32,
+    seed: Option<u64>,
+    quality: QualityMetrics,
+    issues: Vec<String>,
+}
+
+/// Description of a sweep, written next to its files.
+#[derive(Debug, Serialize)]
+struct Manifest {
+    text: String,
+    voice_ref: Option<PathBuf>,
+    entries: Vec<ManifestEntry>,
+}
+
+/// Build TTS options from the config, overridden by command-line values.
+fn build_options(args: &PreviewArgs, config: &GenAudioConfig) -> Result<TtsOptions> {
+    let options = TtsOptions::new()
+        .with_exaggeration(args.exaggeration.unwrap_or(config.exaggeration))
+        .with_cfg(args.cfg.unwrap_or(config.cfg))
+        .with_temperature(args.temperature.unwrap_or(config.temperature))
+        .with_quality(config.quality.clone())
+        .with_seed(args.seed.unwrap_or_else(tts::random_seed));
+
+    match args.voice.as_ref().or(config.voice_ref.as_ref()) {
+        Some(voice) => Ok(options.with_voice_ref(voices::resolve(voice)?)),
+        None => Ok(options),
+    }
+}
+
+/// Synthesize `text` with the previewed settings.
+pub async fn run(text: &str, args: &PreviewArgs, config: &GenAudioConfig) -> Result<()> {
+    let player = if args.play {
+        Some(configured_player(config)?)
+    } else {
+        None
+    };
+
+    let options = build_options(args, config)?;
+
+    // Synthesize from a prepared copy of the voice reference
+    let voice_dir = tempfile::TempDir::new()?;
+    let voice_ref = options.voice_ref.clone();
+    let options = match &voice_ref {
+        Some(voice) => options.with_voice_ref(voices::prepare_reference(voice, voice_dir.path())?),
+        None => options,
+    };
+    let device = args.device.as_deref().or(config.device.as_deref());
+
+    eprintln!("Initializing Chatterbox TTS...");
+    let backend = tts::create_backend(device, options.voice_ref.clone())?;
+    eprintln!("Using device: {}", backend.device());
+
+    let files = if args.sweep.is_empty() {
+        let output = args
+            .output
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_OUTPUT_FILE));
+        synthesize(backend.as_ref(), text, &output, &options).await?;
+        vec![output]
+    } else {
+        let dir = args
+            .output
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_SWEEP_DIR));
+        run_sweep(backend.as_ref(), text, &dir, &options, voice_ref, &args.sweep).await?
+    };
+
+    if let Some(player) = player {
+        for file in &files {
+            eprintln!("Playing {}", file.display());
+            play(player, file)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Render every combination of the sweep axes into `dir` with a manifest.
+async fn run_sweep(
+    backend: &dyn TtsBackend,
+    text: &str,
+    dir: &Path,
+    base: &TtsOptions,
+    voice_ref: Option<PathBuf>,
+    axes: &[SweepAxis],
+) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let grid = expand_grid(axes);
+    let mut files = Vec::new();
+    let mut entries = Vec::new();
+
+    for (i, settings) in grid.iter().enumerate() {
+        let options = settings
+            .iter()
+            .fold(base.clone(), |options, setting| setting.apply(options));
+        let labels: Vec<String> = settings.iter().map(Setting::label).collect();
+        let file = format!("{:02}_{}.wav", i + 1, labels.join("_"));
+        let path = dir.join(&file);
+
+        eprintln!("[{}/{}] {}", i + 1, grid.len(), labels.join(", "));
+        let quality = synthesize(backend, text, &path, &options).await?;
+
+        entries.push(ManifestEntry {
+            file,
+            exaggeration: options.exaggeration,
+            cfg: options.cfg,
+            temperature: options.temperature,
+            seed: options.seed,
+            issues: quality.issues(&options.quality),
+            quality,
+        });
+        files.push(path);
+    }
+
+    let manifest = Manifest {
+        text: text.to_string(),
+        voice_ref,
+        entries,
+    };
+    let manifest_path = dir.join("manifest.json");
+    fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)
+        .with_context(|| format!("Failed to write {}", manifest_path.display()))?;
+
+    eprintln!("Wrote {} files and {}", files.len(), manifest_path.display());
+    Ok(files)
+}
+
+/// Synthesize one file and report its duration and any quality issues.
+async fn synthesize(
+    backend: &dyn TtsBackend,
+    text: &str,
+    output: &Path,
+    options: &TtsOptions,
+) -> Result<QualityMetrics> {
+    let quality = backend.synthesize(text, output, options).await?;
+
+    eprintln!(
+        "  {} ({:.1}s, seed {})",
+        output.display(),
+        quality.duration_ms as f32 / 1000.0,
+        options.seed.unwrap_or_default()
+    );
+    for issue in quality.issues(&options.quality) {
+        eprintln!("  Warning: {}", issue);
+    }
+
+    Ok(quality)
+}
+
+/// The `player` command from the config.
+pub fn configured_player(config: &GenAudioConfig) -> Result<&str> {
+    config.player.as_deref().ok_or_else(|| {
+        anyhow::anyhow!(
+            "No player configured. Set one with: gen-audio config set-player \"ffplay -nodisp -autoexit\""
+        )
+    })
+}
+
+/// Play an audio file with the configured player command.
+pub fn play(player: &str, path: &Path) -> Result<()> {
+    let mut parts = player.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Player command is empty"))?;
+
+    let status = Command::new(program)
+        .args(parts)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to run player '{}'", program))?;
+
+    if !status.success() {
+        anyhow::bail!("Player '{}' exited with {}", program, status);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sweep() {
+        let axis = parse_sweep("temperature=0.5, 0.8").unwrap();
+        assert_eq!(
+            axis,
+            SweepAxis(vec![Setting::Temperature(0.5), Setting::Temperature(0.8)])
+        );
+
+        let axis = parse_sweep("seed=1,2").unwrap();
+        assert_eq!(axis, SweepAxis(vec![Setting::Seed(1), Setting::Seed(2)]));
+
+        assert!(parse_sweep("temperature").is_err());
+        assert!(parse_sweep("pitch=1,2").is_err());
+        assert!(parse_sweep("cfg=abc").is_err());
+        assert!(parse_sweep("cfg=").is_err());
+    }
+
+    #[test]
+    fn test_expand_grid() {
+        let axes = vec![
+            parse_sweep("exaggeration=0.3,0.7").unwrap(),
+            parse_sweep("cfg=0.2,0.5,0.8").unwrap(),
+        ];
+        let grid = expand_grid(&axes);
+
+        assert_eq!(grid.len(), 6);
+        assert_eq!(grid[0], vec![Setting::Exaggeration(0.3), Setting::Cfg(0.2)]);
+        assert_eq!(grid[5], vec![Setting::Exaggeration(0.7), Setting::Cfg(0.8)]);
+        assert_eq!(expand_grid(&[]), vec![Vec::<Setting>::new()]);
+    }
+
+    #[test]
+    fn test_settings_override_config() {
+        let config = GenAudioConfig::default();
+        let args = PreviewArgs {
+            output: None,
+            play: false,
+            sweep: Vec::new(),
+            voice: None,
+            device: None,
+            exaggeration: Some(0.9),
+            cfg: None,
+            temperature: None,
+            seed: Some(5),
+        };
+
+        let options = build_options(&args, &config).unwrap();
+        assert_eq!(options.exaggeration, 0.9);
+        assert_eq!(options.cfg, config.cfg);
+        assert_eq!(options.seed, Some(5));
+
+        let options = Setting::Temperature(1.2).apply(options);
+        assert_eq!(options.temperature, 1.2);
+        assert_eq!(Setting::Cfg(0.25).label(), "cfg-0.25");
+    }
+}
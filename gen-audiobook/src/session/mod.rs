@@ -5,6 +5,7 @@ mod types;
 
 pub use persistence::{
     cleanup_session, create_session, find_session_for_book, get_chapter_audio_files,
-    get_next_chunk, get_progress, get_temp_dir, mark_chunk_complete, mark_chunk_error,
+    get_missing_chunks, get_pending_chunks, get_progress, get_temp_dir, list_sessions,
+    load_session, mark_chunk_complete, mark_chunk_error,
 };
-pub use types::Session;
+pub use types::{ChunkStatus, Session};
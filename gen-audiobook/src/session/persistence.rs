@@ -2,6 +2,7 @@
 
 use super::types::{ChunkStatus, Session};
 use crate::text::TextChunk;
+use crate::tts::{QualityMetrics, TtsOptions};
 use anyhow::{Context, Result};
 use chrono::Utc;
 use sha2::{Digest, Sha256};
@@ -55,11 +56,18 @@ pub fn compute_book_hash(book_path: &Path) -> Result<String> {
 }
 
 /// Create a new generation session.
+///
+/// `seed` is the book seed that every chunk's seed is derived from, and
+/// `tts_options` and `chunk_size` are recorded so individual chunks can be
+/// found and regenerated later.
 pub fn create_session(
     book_path: &Path,
     title: &str,
     author: &str,
     chunks: &[TextChunk],
+    seed: u64,
+    tts_options: &TtsOptions,
+    chunk_size: usize,
 ) -> Result<Session> {
     let book_hash = compute_book_hash(book_path)?;
     let timestamp = Utc::now().format("%Y%m%d_%H%M%S");
@@ -71,7 +79,7 @@ pub fn create_session(
         .map(|c| ChunkStatus::new(c.chapter_id, c.chunk_id))
         .collect();
 
-    let session = Session::new(
+    let mut session = Session::new(
         session_id,
         book_path.to_path_buf(),
         book_hash,
@@ -79,6 +87,9 @@ pub fn create_session(
         author.to_string(),
         chunk_statuses,
     );
+    session.seed = seed;
+    session.tts_options = Some(tts_options.clone());
+    session.chunk_size = chunk_size;
 
     // Save immediately
     save_session(&session)?;
@@ -102,50 +113,90 @@ pub fn save_session(session: &Session) -> Result<()> {
     Ok(())
 }
 
-/// Find the most recent incomplete session for a book.
-pub fn find_session_for_book(book_path: &Path) -> Result<Option<Session>> {
-    let book_hash = compute_book_hash(book_path)?;
-    let sessions_dir = get_sessions_dir()?;
+/// Read a session file, upgrading one saved by an older version.
+///
+/// A session without a seed gets one derived from its ID, so every load
+/// agrees on it until the next save records it.
+fn read_session(path: &Path) -> Result<Session> {
+    let file = File::open(path)?;
+    let mut value: serde_json::Value = serde_json::from_reader(BufReader::new(file))?;
+
+    let legacy_seed = value
+        .as_object()
+        .filter(|fields| !fields.contains_key("seed"))
+        .and_then(|fields| fields.get("session_id")?.as_str())
+        .map(seed_from_session_id);
+    if let Some(seed) = legacy_seed {
+        value["seed"] = seed.into();
+    }
+
+    Ok(serde_json::from_value(value)?)
+}
 
-    // Find all sessions for this book
-    let mut matching_sessions: Vec<Session> = Vec::new();
+/// Book seed for a session saved before seeds were recorded.
+fn seed_from_session_id(session_id: &str) -> u64 {
+    let digest = Sha256::digest(session_id.as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    u64::from_le_bytes(bytes)
+}
+
+/// Load all saved sessions, most recently updated first.
+pub fn list_sessions() -> Result<Vec<Session>> {
+    let sessions_dir = get_sessions_dir()?;
+    let mut sessions: Vec<Session> = Vec::new();
 
     for entry in fs::read_dir(&sessions_dir)? {
-        let entry = entry?;
-        let path = entry.path();
+        let path = entry?.path();
 
         if path.extension().map(|e| e == "json").unwrap_or(false) {
-            if let Ok(file) = File::open(&path) {
-                let reader = BufReader::new(file);
-                if let Ok(session) = serde_json::from_reader::<_, Session>(reader) {
-                    if session.book_hash == book_hash && !session.completed {
-                        matching_sessions.push(session);
-                    }
-                }
+            if let Ok(session) = read_session(&path) {
+                sessions.push(session);
             }
         }
     }
 
-    if matching_sessions.is_empty() {
-        return Ok(None);
+    sessions.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    Ok(sessions)
+}
+
+/// Load a session by its ID.
+pub fn load_session(session_id: &str) -> Result<Session> {
+    let sessions_dir = get_sessions_dir()?;
+    let session_file = sessions_dir.join(format!("{}.json", session_id));
+    if !session_file.exists() {
+        anyhow::bail!("Session '{}' not found", session_id);
     }
+    read_session(&session_file)
+        .with_context(|| format!("Failed to parse session '{}'", session_id))
+}
 
-    // Return the most recent one
-    matching_sessions.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
-    Ok(matching_sessions.into_iter().next())
+/// Find the most recent incomplete session for a book.
+pub fn find_session_for_book(book_path: &Path) -> Result<Option<Session>> {
+    let book_hash = compute_book_hash(book_path)?;
+
+    // Sessions are sorted most recent first
+    Ok(list_sessions()?
+        .into_iter()
+        .find(|s| s.book_hash == book_hash && !s.completed))
 }
 
-/// Mark a chunk as completed with its audio file path.
+/// Mark a chunk as completed with its audio file path, quality measurements,
+/// and the seed that produced it.
 pub fn mark_chunk_complete(
     session: &mut Session,
     chapter_id: usize,
     chunk_id: usize,
     audio_path: &Path,
+    quality: Option<QualityMetrics>,
+    seed: Option<u64>,
 ) -> Result<()> {
     // Find and update the chunk
     for chunk in &mut session.chunks {
         if chunk.chapter_id == chapter_id && chunk.chunk_id == chunk_id {
             chunk.mark_completed(audio_path.to_path_buf());
+            chunk.quality = quality;
+            chunk.seed = seed;
             break;
         }
     }
@@ -180,8 +231,9 @@ pub fn mark_chunk_error(
     Ok(())
 }
 
-/// Get the next incomplete chunk's (chapter_id, chunk_id).
-pub fn get_next_chunk(session: &Session) -> Option<(usize, usize)> {
+/// Get the next incomplete chunk's (chapter_id, chunk_id), for the session's
+/// current position.
+fn get_next_chunk(session: &Session) -> Option<(usize, usize)> {
     session
         .chunks
         .iter()
@@ -189,6 +241,28 @@ pub fn get_next_chunk(session: &Session) -> Option<(usize, usize)> {
         .map(|c| (c.chapter_id, c.chunk_id))
 }
 
+/// Get all incomplete chunks' (chapter_id, chunk_id) in session order.
+///
+/// This is a snapshot, so callers can iterate it once without revisiting
+/// chunks that fail.
+pub fn get_pending_chunks(session: &Session) -> Vec<(usize, usize)> {
+    session
+        .chunks
+        .iter()
+        .filter(|c| !c.completed)
+        .map(|c| (c.chapter_id, c.chunk_id))
+        .collect()
+}
+
+/// Get chunks that have no usable audio (failed or never processed).
+pub fn get_missing_chunks(session: &Session) -> Vec<&ChunkStatus> {
+    session
+        .chunks
+        .iter()
+        .filter(|c| !c.completed || c.audio_path.is_none())
+        .collect()
+}
+
 /// Get progress as (completed, total, percentage).
 pub fn get_progress(session: &Session) -> (usize, usize, f64) {
     let completed = session.completed_count();
@@ -202,17 +276,26 @@ pub fn get_progress(session: &Session) -> (usize, usize, f64) {
 }
 
 /// Get all audio files for a chapter in order.
-pub fn get_chapter_audio_files(session: &Session, chapter_id: usize) -> Vec<PathBuf> {
+///
+/// Chunks without audio are skipped, or replaced by `gap_audio` if given.
+pub fn get_chapter_audio_files(
+    session: &Session,
+    chapter_id: usize,
+    gap_audio: Option<&Path>,
+) -> Vec<PathBuf> {
     let mut chapter_chunks: Vec<_> = session
         .chunks
         .iter()
-        .filter(|c| c.chapter_id == chapter_id && c.completed && c.audio_path.is_some())
+        .filter(|c| c.chapter_id == chapter_id)
         .collect();
 
     chapter_chunks.sort_by_key(|c| c.chunk_id);
     chapter_chunks
         .into_iter()
-        .filter_map(|c| c.audio_path.clone())
+        .filter_map(|c| match (&c.audio_path, c.completed) {
+            (Some(path), true) => Some(path.clone()),
+            _ => gap_audio.map(Path::to_path_buf),
+        })
         .collect()
 }
 
@@ -292,6 +375,39 @@ mod tests {
         assert_eq!(get_next_chunk(&session), None);
     }
 
+    #[test]
+    fn test_legacy_session_gets_a_stable_seed() {
+        let dir = TempDir::new().unwrap();
+        let session = Session::new(
+            "abc_20240101_000000".to_string(),
+            PathBuf::from("/tmp/test.epub"),
+            "abc".to_string(),
+            "Test".to_string(),
+            "Author".to_string(),
+            vec![ChunkStatus::new(0, 0)],
+        );
+
+        // Saved before sessions had seeds
+        let mut legacy = serde_json::to_value(&session).unwrap();
+        legacy.as_object_mut().unwrap().remove("seed");
+        let path = dir.path().join("abc_20240101_000000.json");
+        fs::write(&path, legacy.to_string()).unwrap();
+
+        let first = read_session(&path).unwrap();
+        let second = read_session(&path).unwrap();
+        assert_eq!(first.seed, seed_from_session_id("abc_20240101_000000"));
+        assert_eq!(second.seed, first.seed);
+
+        // Reading leaves the file alone
+        assert_eq!(fs::read_to_string(&path).unwrap(), legacy.to_string());
+
+        // A recorded seed is kept
+        let mut seeded = serde_json::to_value(&session).unwrap();
+        seeded["seed"] = 42.into();
+        fs::write(&path, seeded.to_string()).unwrap();
+        assert_eq!(read_session(&path).unwrap().seed, 42);
+    }
+
     #[test]
     fn test_get_progress() {
         let mut chunks = vec![
@@ -339,12 +455,50 @@ mod tests {
             chunks,
         );
 
-        let chapter_0_files = get_chapter_audio_files(&session, 0);
+        let chapter_0_files = get_chapter_audio_files(&session, 0, None);
         assert_eq!(chapter_0_files.len(), 2);
         assert_eq!(chapter_0_files[0], PathBuf::from("/tmp/ch0_0.wav"));
         assert_eq!(chapter_0_files[1], PathBuf::from("/tmp/ch0_2.wav"));
 
-        let chapter_1_files = get_chapter_audio_files(&session, 1);
+        let chapter_1_files = get_chapter_audio_files(&session, 1, None);
         assert_eq!(chapter_1_files.len(), 1);
+
+        // With a gap file, the missing chunk keeps its place
+        let gap = PathBuf::from("/tmp/gap.wav");
+        let with_gaps = get_chapter_audio_files(&session, 0, Some(&gap));
+        assert_eq!(
+            with_gaps,
+            vec![
+                PathBuf::from("/tmp/ch0_0.wav"),
+                gap.clone(),
+                PathBuf::from("/tmp/ch0_2.wav"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pending_and_missing_chunks() {
+        let mut chunks = vec![
+            ChunkStatus::new(0, 0),
+            ChunkStatus::new(0, 1),
+            ChunkStatus::new(1, 0),
+        ];
+        chunks[0].mark_completed(PathBuf::from("/tmp/0.wav"));
+        chunks[1].mark_failed("TTS failed".to_string());
+
+        let session = Session::new(
+            "test".to_string(),
+            PathBuf::from("/tmp/test.epub"),
+            "abc".to_string(),
+            "Test".to_string(),
+            "Author".to_string(),
+            chunks,
+        );
+
+        assert_eq!(get_pending_chunks(&session), vec![(0, 1), (1, 0)]);
+
+        let missing = get_missing_chunks(&session);
+        assert_eq!(missing.len(), 2);
+        assert_eq!(missing[0].error.as_deref(), Some("TTS failed"));
     }
 }
@@ -1,5 +1,7 @@
 //! Session data types for audiobook generation.
 
+use crate::text::chunker::DEFAULT_TARGET_SIZE;
+use crate::tts::{self, QualityMetrics, TtsOptions};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
@@ -17,6 +19,12 @@ pub struct ChunkStatus {
     pub completed: bool,
     /// Error message if processing failed
     pub error: Option<String>,
+    /// Quality measurements of the generated audio
+    #[serde(default)]
+    pub quality: Option<QualityMetrics>,
+    /// Seed that produced the generated audio
+    #[serde(default)]
+    pub seed: Option<u64>,
 }
 
 impl ChunkStatus {
@@ -28,6 +36,8 @@ impl ChunkStatus {
             audio_path: None,
             completed: false,
             error: None,
+            quality: None,
+            seed: None,
         }
     }
 
@@ -74,6 +84,19 @@ pub struct Session {
     pub updated_at: DateTime<Utc>,
     /// Whether all chunks have been processed
     pub completed: bool,
+    /// Book seed that per-chunk seeds are derived from. Sessions saved
+    /// before seeds were recorded get one when they are loaded.
+    pub seed: u64,
+    /// TTS options the session was started with
+    #[serde(default)]
+    pub tts_options: Option<TtsOptions>,
+    /// Target chunk size the book was split with, so resuming splits it the same way
+    #[serde(default = "default_chunk_size")]
+    pub chunk_size: usize,
+}
+
+fn default_chunk_size() -> usize {
+    DEFAULT_TARGET_SIZE
 }
 
 impl Session {
@@ -109,6 +132,9 @@ impl Session {
             created_at: now,
             updated_at: now,
             completed: false,
+            seed: tts::random_seed(),
+            tts_options: None,
+            chunk_size: DEFAULT_TARGET_SIZE,
         }
     }
 
@@ -116,6 +142,11 @@ impl Session {
     pub fn completed_count(&self) -> usize {
         self.chunks.iter().filter(|c| c.completed).count()
     }
+
+    /// Seed for a chunk derived from the book seed.
+    pub fn chunk_seed(&self, chapter_id: usize, chunk_id: usize) -> u64 {
+        tts::derive_chunk_seed(self.seed, chapter_id, chunk_id)
+    }
 }
 
 #[cfg(test)]
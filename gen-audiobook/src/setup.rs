@@ -31,7 +31,7 @@ pub fn get_env_info() -> Result<String> {
 /// Check if setup is needed.
 #[allow(dead_code)]
 pub fn check_setup_needed() -> Result<bool> {
-    Ok(bootstrap::check_status()? != bootstrap::BootstrapStatus::Ready)
+    Ok(!bootstrap::check_status()?.is_ready())
 }
 
 #[cfg(test)]
@@ -3,18 +3,31 @@
 //! This backend uses Chatterbox TTS from Resemble AI for high-quality voice synthesis.
 //! It supports voice cloning from reference audio, expressiveness control, and GPU acceleration.
 
-use super::{TtsBackend, TtsOptions};
+use super::quality::{self, QualityMetrics};
+use super::{SynthesisReport, TtsBackend, TtsOptions};
 use crate::setup;
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use pyo3::prelude::*;
 use pyo3::types::PyDict;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::sync::Once;
+use std::sync::{Mutex, Once, OnceLock};
 
 /// Initialize Python runtime once.
 static PYTHON_INIT: Once = Once::new();
 
+/// Loaded models by device, kept for the life of the process.
+static MODELS: OnceLock<Mutex<HashMap<String, Py<PyAny>>>> = OnceLock::new();
+
+/// Held for each generation, from seeding to reading the audio back.
+///
+/// Sampling draws from torch's process-wide RNG, and `generate` releases
+/// the GIL, so two chunks generated at once would interleave their draws
+/// and neither would be reproducible from its seed.
+static GENERATION: Mutex<()> = Mutex::new(());
+
 /// Chatterbox TTS backend using PyO3.
 pub struct ChatterboxBackend {
     /// Device to use (mps, cuda, cpu)
@@ -45,33 +58,7 @@ impl ChatterboxBackend {
             );
         }
 
-        // Initialize Python once
-        // Note: PYTHONHOME is set by ensure_python_home() in main.rs via re-exec
-        PYTHON_INIT.call_once(|| {
-            // Get venv path for later use
-            let venv_site_packages = setup::get_python_path().ok().and_then(|python_path| {
-                // venv Python: .../venv/bin/python -> site-packages: .../venv/lib/python3.11/site-packages
-                let venv_dir = python_path.parent()?.parent()?;
-                let site_packages = venv_dir.join("lib").join("python3.11").join("site-packages");
-                if site_packages.exists() {
-                    Some(site_packages)
-                } else {
-                    None
-                }
-            });
-
-            pyo3::prepare_freethreaded_python();
-
-            // Add venv site-packages to sys.path after Python initializes
-            if let Some(site_packages) = venv_site_packages {
-                let _ = Python::with_gil(|py| -> PyResult<()> {
-                    let sys = py.import("sys")?;
-                    let path = sys.getattr("path")?;
-                    path.call_method1("insert", (0, site_packages.to_string_lossy().as_ref()))?;
-                    Ok(())
-                });
-            }
-        });
+        init_python();
 
         // Auto-detect device if not specified
         let device = match device {
@@ -110,27 +97,68 @@ impl ChatterboxBackend {
         })
     }
 
+    /// Load the model for this backend's device so the first chunk doesn't wait for it.
+    pub fn preload(&self) -> Result<()> {
+        Python::with_gil(|py| {
+            self.load_model(py)?;
+            Ok(())
+        })
+    }
+
+    /// Get the cached model for this device, loading it on first use.
+    fn load_model<'py>(&self, py: Python<'py>) -> Result<Bound<'py, PyAny>> {
+        let models = MODELS.get_or_init(Default::default);
+
+        // The lock isn't held while loading: from_pretrained may release the
+        // GIL, and another thread could then block on the lock while holding it
+        if let Some(model) = models.lock().unwrap().get(&self.device) {
+            return Ok(model.clone_ref(py).into_bound(py));
+        }
+
+        // Enable MPS fallback
+        let os = py.import("os")?;
+        let environ = os.getattr("environ")?;
+        environ.set_item("PYTORCH_ENABLE_MPS_FALLBACK", "1")?;
+
+        // Import chatterbox
+        let chatterbox_tts = py.import("chatterbox.tts")?;
+        let chatterbox_class = chatterbox_tts.getattr("ChatterboxTTS")?;
+
+        // Load the pinned weights if `gen-audio models fetch` recorded them,
+        // otherwise let from_pretrained download the latest on first use
+        let pinned = crate::bootstrap::models::installed().filter(|m| m.is_complete());
+        let model = match pinned {
+            Some(pinned) => chatterbox_class.call_method1(
+                "from_local",
+                (pinned.path.to_string_lossy().as_ref(), &self.device),
+            )?,
+            None => {
+                let kwargs = PyDict::new(py);
+                kwargs.set_item("device", &self.device)?;
+                chatterbox_class.call_method("from_pretrained", (), Some(&kwargs))?
+            }
+        };
+
+        models
+            .lock()
+            .unwrap()
+            .insert(self.device.clone(), model.clone().unbind());
+        Ok(model)
+    }
+
     /// Generate audio using Chatterbox.
     fn generate_audio_sync(
         &self,
         text: &str,
         output_path: &Path,
         options: &TtsOptions,
-    ) -> Result<()> {
-        Python::with_gil(|py| {
-            // Enable MPS fallback
-            let os = py.import("os")?;
-            let environ = os.getattr("environ")?;
-            environ.set_item("PYTORCH_ENABLE_MPS_FALLBACK", "1")?;
-
-            // Import chatterbox
-            let chatterbox_tts = py.import("chatterbox.tts")?;
-            let chatterbox_class = chatterbox_tts.getattr("ChatterboxTTS")?;
+    ) -> Result<QualityMetrics> {
+        // Taken before the GIL: a thread waiting here while holding the GIL
+        // would block the generation it is waiting on
+        let _generating = GENERATION.lock().unwrap();
 
-            // Load model
-            let kwargs = PyDict::new(py);
-            kwargs.set_item("device", &self.device)?;
-            let model = chatterbox_class.call_method("from_pretrained", (), Some(&kwargs))?;
+        Python::with_gil(|py| {
+            let model = self.load_model(py)?;
 
             // Prepare generation kwargs
             let gen_kwargs = PyDict::new(py);
@@ -150,6 +178,11 @@ impl ChatterboxBackend {
             gen_kwargs.set_item("cfg_weight", options.cfg)?;
             gen_kwargs.set_item("temperature", options.temperature)?;
 
+            // Seed every RNG sampling may draw from, so the output is reproducible
+            if let Some(seed) = options.seed {
+                seed_rngs(py, seed)?;
+            }
+
             // Generate audio
             let wav = model.call_method("generate", (), Some(&gen_kwargs))?;
 
@@ -163,6 +196,15 @@ impl ChatterboxBackend {
             let wav_cpu = wav.call_method0("cpu")?;
             let wav_np = wav_cpu.call_method0("numpy")?;
 
+            // Measure the raw samples before they are quantized on write,
+            // so NaNs and clipping are still visible
+            let samples: Vec<f32> = wav_np
+                .call_method0("flatten")?
+                .call_method1("astype", ("float32",))?
+                .call_method0("tolist")?
+                .extract()?;
+            let metrics = quality::analyze(&samples, sample_rate, text, &options.quality);
+
             // Handle dimensions - soundfile expects (samples, channels)
             let ndim: i32 = wav_np.getattr("ndim")?.extract()?;
             let wav_np = if ndim == 2 {
@@ -187,7 +229,7 @@ impl ChatterboxBackend {
             // Cleanup memory
             self.cleanup_memory(py)?;
 
-            Ok(())
+            Ok(metrics)
         })
     }
 
@@ -210,6 +252,131 @@ impl ChatterboxBackend {
     }
 }
 
+/// Initialize the embedded Python once, with the venv's packages importable.
+fn init_python() {
+    // Note: PYTHONHOME is set by ensure_python_home() in main.rs via re-exec
+    PYTHON_INIT.call_once(|| {
+        // Get venv path for later use
+        let venv_site_packages = setup::get_python_path().ok().and_then(|python_path| {
+            // venv Python: .../venv/bin/python -> site-packages: .../venv/lib/python3.11/site-packages
+            let venv_dir = python_path.parent()?.parent()?;
+            let site_packages = venv_dir.join("lib").join("python3.11").join("site-packages");
+            if site_packages.exists() {
+                Some(site_packages)
+            } else {
+                None
+            }
+        });
+
+        pyo3::prepare_freethreaded_python();
+
+        let _ = Python::with_gil(|py| -> PyResult<()> {
+            let sys = py.import("sys")?;
+
+            // Add venv site-packages to sys.path after Python initializes
+            if let Some(site_packages) = venv_site_packages {
+                let path = sys.getattr("path")?;
+                path.call_method1("insert", (0, site_packages.to_string_lossy().as_ref()))?;
+            }
+
+            // Keep Python's prints off stdout, which carries worker results
+            sys.setattr("stdout", sys.getattr("stderr")?)?;
+            Ok(())
+        });
+    });
+}
+
+/// What the embedded Python sees: versions and usable devices.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuntimeInfo {
+    /// Python version, e.g. "3.11.9".
+    pub python: String,
+    /// torch version, e.g. "2.6.0+cu124".
+    pub torch: String,
+    /// CUDA version torch was built for; None for CPU-only builds.
+    pub torch_cuda: Option<String>,
+    /// Whether torch can use a CUDA GPU.
+    pub cuda_available: bool,
+    /// Name of the first CUDA GPU.
+    pub cuda_device: Option<String>,
+    /// Whether torch was built with MPS support.
+    pub mps_built: bool,
+    /// Whether torch can use MPS (Apple Silicon).
+    pub mps_available: bool,
+    /// chatterbox-tts version.
+    pub chatterbox: String,
+}
+
+impl RuntimeInfo {
+    /// Devices synthesis can run on, fastest first.
+    pub fn devices(&self) -> Vec<&'static str> {
+        let mut devices = Vec::new();
+        if self.cuda_available {
+            devices.push("cuda");
+        }
+        if self.mps_available {
+            devices.push("mps");
+        }
+        devices.push("cpu");
+        devices
+    }
+}
+
+/// Import torch and chatterbox in the embedded Python and report what they see.
+pub fn runtime_info() -> Result<RuntimeInfo> {
+    init_python();
+
+    Python::with_gil(|py| {
+        let version: String = py.import("sys")?.getattr("version")?.extract()?;
+        let python = version.split_whitespace().next().unwrap_or_default().to_string();
+
+        let torch = py.import("torch").context("Failed to import torch")?;
+        let torch_version: String = torch.getattr("__version__")?.str()?.extract()?;
+        let torch_cuda: Option<String> = torch.getattr("version")?.getattr("cuda")?.extract()?;
+
+        let cuda = torch.getattr("cuda")?;
+        let cuda_available: bool = cuda.call_method0("is_available")?.extract()?;
+        let cuda_device = if cuda_available {
+            Some(cuda.call_method1("get_device_name", (0,))?.extract()?)
+        } else {
+            None
+        };
+
+        let mps = torch.getattr("backends")?.getattr("mps")?;
+        let mps_built: bool = mps.call_method0("is_built")?.extract()?;
+        let mps_available: bool = mps.call_method0("is_available")?.extract()?;
+
+        py.import("chatterbox.tts").context("Failed to import chatterbox")?;
+        let chatterbox: String = py
+            .import("importlib.metadata")?
+            .call_method1("version", ("chatterbox-tts",))?
+            .extract()?;
+
+        Ok(RuntimeInfo {
+            python,
+            torch: torch_version,
+            torch_cuda,
+            cuda_available,
+            cuda_device,
+            mps_built,
+            mps_available,
+            chatterbox,
+        })
+    })
+}
+
+/// Seed torch (all devices), numpy and Python's `random`.
+fn seed_rngs(py: Python<'_>, seed: u64) -> Result<()> {
+    py.import("torch")?.call_method1("manual_seed", (seed,))?;
+    py.import("random")?.call_method1("seed", (seed,))?;
+
+    // numpy only accepts 32-bit seeds
+    let numpy_random = py.import("numpy")?.getattr("random")?;
+    numpy_random.call_method1("seed", (seed & 0xFFFF_FFFF,))?;
+
+    Ok(())
+}
+
 #[async_trait]
 impl TtsBackend for ChatterboxBackend {
     async fn synthesize(
@@ -217,7 +384,7 @@ impl TtsBackend for ChatterboxBackend {
         text: &str,
         output_path: &Path,
         options: &TtsOptions,
-    ) -> Result<()> {
+    ) -> Result<QualityMetrics> {
         // Clone data for the blocking task
         let text = text.to_string();
         let output_path = output_path.to_path_buf();
@@ -227,7 +394,7 @@ impl TtsBackend for ChatterboxBackend {
         let sample_rate = self.sample_rate;
 
         // Run in a blocking task to not block the tokio runtime
-        tokio::task::spawn_blocking(move || {
+        let metrics = tokio::task::spawn_blocking(move || {
             let backend = ChatterboxBackend {
                 device,
                 voice_ref,
@@ -238,7 +405,7 @@ impl TtsBackend for ChatterboxBackend {
         .await
         .context("Task join error")??;
 
-        Ok(())
+        Ok(metrics)
     }
 
     async fn synthesize_with_retry(
@@ -247,18 +414,43 @@ impl TtsBackend for ChatterboxBackend {
         output_path: &Path,
         options: &TtsOptions,
         max_retries: u32,
-    ) -> Result<()> {
+    ) -> Result<SynthesisReport> {
         let mut last_error = None;
-
-        for attempt in 0..max_retries {
-            match self.synthesize(text, output_path, options).await {
-                Ok(()) => return Ok(()),
+        let mut attempt = 0;
+        let mut regenerations = 0;
+        let mut seed = options.seed.unwrap_or_else(super::random_seed);
+
+        while attempt < max_retries {
+            let seeded = options.clone().with_seed(seed);
+            match self.synthesize(text, output_path, &seeded).await {
+                Ok(quality) => {
+                    let issues = quality.issues(&options.quality);
+                    if issues.is_empty() {
+                        return Ok(SynthesisReport { quality, seed });
+                    }
+
+                    if regenerations >= options.quality.max_regenerations {
+                        anyhow::bail!(
+                            "Quality check failed after {} regeneration(s): {}",
+                            regenerations,
+                            issues.join("; ")
+                        );
+                    }
+
+                    regenerations += 1;
+                    seed = super::next_seed(seed);
+                    eprintln!(
+                        "Quality check failed ({}), regenerating ({}/{})",
+                        issues.join("; "),
+                        regenerations,
+                        options.quality.max_regenerations
+                    );
+                }
                 Err(e) => {
+                    attempt += 1;
                     eprintln!(
                         "Generation failed (attempt {}/{}): {}",
-                        attempt + 1,
-                        max_retries,
-                        e
+                        attempt, max_retries, e
                     );
                     last_error = Some(e);
                 }
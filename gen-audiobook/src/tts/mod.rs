@@ -1,13 +1,20 @@
 //! TTS backend trait and types.
 
 pub mod chatterbox;
+pub mod quality;
 
 use anyhow::Result;
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
 use std::path::{Path, PathBuf};
 
+pub use quality::{QualityMetrics, QualityThresholds};
+
 /// Options for TTS synthesis with Chatterbox.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TtsOptions {
     /// Path to voice reference audio for cloning
     pub voice_ref: Option<PathBuf>,
@@ -20,6 +27,12 @@ pub struct TtsOptions {
     /// Temperature for randomness (0.05-5.0, default 0.8)
     /// Lower values = more consistent/predictable
     pub temperature: f32,
+    /// Thresholds used to accept or regenerate synthesized audio
+    #[serde(default)]
+    pub quality: QualityThresholds,
+    /// Seed for the random number generators (random if not set)
+    #[serde(default)]
+    pub seed: Option<u64>,
 }
 
 impl Default for TtsOptions {
@@ -29,6 +42,8 @@ impl Default for TtsOptions {
             exaggeration: 0.5,
             cfg: 0.5,
             temperature: 0.8,
+            quality: QualityThresholds::default(),
+            seed: None,
         }
     }
 }
@@ -62,27 +77,82 @@ impl TtsOptions {
         self.temperature = temperature.clamp(0.05, 5.0);
         self
     }
+
+    /// Set the quality check thresholds.
+    pub fn with_quality(mut self, quality: QualityThresholds) -> Self {
+        self.quality = quality;
+        self
+    }
+
+    /// Set the random seed.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+/// Pick a random seed for a new book or an unseeded synthesis.
+pub fn random_seed() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// Derive the seed for one chunk from the book seed and the chunk's position.
+///
+/// The result depends only on its inputs, so a chunk gets the same seed no
+/// matter which machine generates it or in what order.
+pub fn derive_chunk_seed(book_seed: u64, chapter_id: usize, chunk_id: usize) -> u64 {
+    let mut hasher = Sha256::new();
+    hasher.update(book_seed.to_le_bytes());
+    hasher.update((chapter_id as u64).to_le_bytes());
+    hasher.update((chunk_id as u64).to_le_bytes());
+    let digest = hasher.finalize();
+
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    u64::from_le_bytes(bytes)
+}
+
+/// Seed used to regenerate a chunk after `seed` produced bad audio.
+pub fn next_seed(seed: u64) -> u64 {
+    // splitmix64 step
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Outcome of a successful synthesis, after any regenerations.
+#[derive(Debug, Clone)]
+pub struct SynthesisReport {
+    /// Quality measurements of the accepted audio
+    pub quality: QualityMetrics,
+    /// Seed that produced the accepted audio
+    pub seed: u64,
 }
 
 /// TTS backend trait - all TTS engines implement this.
 #[async_trait]
 pub trait TtsBackend: Send + Sync {
-    /// Synthesize text to audio file.
+    /// Synthesize text to audio file, returning quality measurements of the output.
     async fn synthesize(
         &self,
         text: &str,
         output_path: &Path,
         options: &TtsOptions,
-    ) -> Result<()>;
+    ) -> Result<QualityMetrics>;
 
     /// Synthesize with retry logic for error handling.
+    ///
+    /// Audio that fails the quality checks in `options.quality` is regenerated
+    /// up to `max_regenerations` times before giving up, each time with the
+    /// next seed after `options.seed`.
     async fn synthesize_with_retry(
         &self,
         text: &str,
         output_path: &Path,
         options: &TtsOptions,
         max_retries: u32,
-    ) -> Result<()>;
+    ) -> Result<SynthesisReport>;
 
     /// Device being used (mps, cuda, cpu).
     fn device(&self) -> &str;
@@ -138,4 +208,14 @@ mod tests {
         assert_eq!(opts.cfg, 0.0);
         assert_eq!(opts.temperature, 5.0);
     }
+
+    #[test]
+    fn test_chunk_seeds() {
+        let seed = derive_chunk_seed(42, 3, 7);
+        assert_eq!(seed, derive_chunk_seed(42, 3, 7));
+        assert_ne!(seed, derive_chunk_seed(43, 3, 7));
+        assert_ne!(seed, derive_chunk_seed(42, 7, 3));
+        assert_ne!(seed, next_seed(seed));
+        assert_eq!(next_seed(seed), next_seed(seed));
+    }
 }
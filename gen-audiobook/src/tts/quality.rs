@@ -0,0 +1,274 @@
+//! Quality checks for synthesized audio.
+//!
+//! Chatterbox occasionally produces truncated audio, long silences, or babble
+//! that runs well past the end of the text. These checks measure each chunk
+//! so bad output can be regenerated instead of ending up in the audiobook.
+
+use serde::{Deserialize, Serialize};
+
+/// Window size for silence detection in milliseconds.
+const SILENCE_WINDOW_MS: u64 = 10;
+
+/// Expected durations are never assumed shorter than this when checking for
+/// over-long output, so very short texts get some slack.
+const MIN_EXPECTED_DURATION_MS: u64 = 1500;
+
+/// Measurements taken from a synthesized chunk.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QualityMetrics {
+    /// Audio duration in milliseconds.
+    pub duration_ms: u64,
+    /// Expected duration for the text at the configured speaking rate.
+    pub expected_duration_ms: u64,
+    /// Peak absolute sample value (1.0 = full scale).
+    pub peak: f32,
+    /// Fraction of samples at or above the clipping level.
+    pub clipped_ratio: f32,
+    /// Longest run of near-silent audio in milliseconds.
+    pub longest_silence_ms: u64,
+    /// Number of NaN or infinite samples.
+    pub non_finite_samples: usize,
+}
+
+/// Thresholds for accepting synthesized audio.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QualityThresholds {
+    /// Whether to check chunks at all.
+    pub enabled: bool,
+    /// Expected speaking rate in characters per second.
+    pub chars_per_second: f32,
+    /// Shortest acceptable duration, as a fraction of the expected duration.
+    pub min_duration_ratio: f32,
+    /// Longest acceptable duration, as a multiple of the expected duration.
+    pub max_duration_ratio: f32,
+    /// Absolute sample value counted as clipped.
+    pub clip_level: f32,
+    /// Largest acceptable fraction of clipped samples.
+    pub max_clipped_ratio: f32,
+    /// RMS level below which a window counts as silent.
+    pub silence_level: f32,
+    /// Longest acceptable run of silence in milliseconds.
+    pub max_silence_ms: u64,
+    /// How many times a failing chunk is regenerated before giving up.
+    pub max_regenerations: u32,
+}
+
+impl Default for QualityThresholds {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            chars_per_second: 15.0,
+            min_duration_ratio: 0.4,
+            max_duration_ratio: 2.5,
+            clip_level: 0.999,
+            max_clipped_ratio: 0.001,
+            silence_level: 0.01,
+            max_silence_ms: 2000,
+            max_regenerations: 2,
+        }
+    }
+}
+
+impl QualityThresholds {
+    /// Expected speaking duration for a text in milliseconds.
+    pub fn expected_duration_ms(&self, text: &str) -> u64 {
+        let chars = text.chars().filter(|c| !c.is_whitespace()).count();
+        if self.chars_per_second <= 0.0 {
+            return 0;
+        }
+        (chars as f32 / self.chars_per_second * 1000.0) as u64
+    }
+}
+
+/// Measure synthesized audio samples against the text they should speak.
+pub fn analyze(
+    samples: &[f32],
+    sample_rate: u32,
+    text: &str,
+    thresholds: &QualityThresholds,
+) -> QualityMetrics {
+    let non_finite_samples = samples.iter().filter(|s| !s.is_finite()).count();
+    let finite = || samples.iter().copied().filter(|s| s.is_finite());
+
+    let peak = finite().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    let clipped = finite().filter(|s| s.abs() >= thresholds.clip_level).count();
+    let clipped_ratio = if samples.is_empty() {
+        0.0
+    } else {
+        clipped as f32 / samples.len() as f32
+    };
+
+    let duration_ms = if sample_rate == 0 {
+        0
+    } else {
+        samples.len() as u64 * 1000 / sample_rate as u64
+    };
+
+    QualityMetrics {
+        duration_ms,
+        expected_duration_ms: thresholds.expected_duration_ms(text),
+        peak,
+        clipped_ratio,
+        longest_silence_ms: longest_silence_ms(samples, sample_rate, thresholds.silence_level),
+        non_finite_samples,
+    }
+}
+
+/// Find the longest run of windows whose RMS is below `level`.
+fn longest_silence_ms(samples: &[f32], sample_rate: u32, level: f32) -> u64 {
+    let window = (sample_rate as u64 * SILENCE_WINDOW_MS / 1000) as usize;
+    if window == 0 {
+        return 0;
+    }
+
+    let mut longest = 0u64;
+    let mut current = 0u64;
+
+    for chunk in samples.chunks(window) {
+        let sum_sq: f32 = chunk
+            .iter()
+            .filter(|s| s.is_finite())
+            .map(|s| s * s)
+            .sum();
+        let rms = (sum_sq / chunk.len() as f32).sqrt();
+
+        if rms < level {
+            current += chunk.len() as u64 * 1000 / sample_rate as u64;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+
+    longest
+}
+
+impl QualityMetrics {
+    /// Describe every threshold this chunk violates (empty if it passes).
+    pub fn issues(&self, thresholds: &QualityThresholds) -> Vec<String> {
+        if !thresholds.enabled {
+            return Vec::new();
+        }
+
+        let mut issues = Vec::new();
+
+        if self.duration_ms == 0 {
+            issues.push("empty audio".to_string());
+            return issues;
+        }
+
+        if self.non_finite_samples > 0 {
+            issues.push(format!("{} NaN/infinite samples", self.non_finite_samples));
+        }
+
+        let min_ms = (self.expected_duration_ms as f32 * thresholds.min_duration_ratio) as u64;
+        if self.duration_ms < min_ms {
+            issues.push(format!(
+                "too short: {:.1}s for ~{:.1}s of text",
+                self.duration_ms as f32 / 1000.0,
+                self.expected_duration_ms as f32 / 1000.0
+            ));
+        }
+
+        let max_ms = (self.expected_duration_ms.max(MIN_EXPECTED_DURATION_MS) as f32
+            * thresholds.max_duration_ratio) as u64;
+        if self.duration_ms > max_ms {
+            issues.push(format!(
+                "too long: {:.1}s for ~{:.1}s of text",
+                self.duration_ms as f32 / 1000.0,
+                self.expected_duration_ms as f32 / 1000.0
+            ));
+        }
+
+        if self.clipped_ratio > thresholds.max_clipped_ratio {
+            issues.push(format!(
+                "clipping: {:.2}% of samples",
+                self.clipped_ratio * 100.0
+            ));
+        }
+
+        if self.longest_silence_ms > thresholds.max_silence_ms {
+            issues.push(format!(
+                "silence of {:.1}s",
+                self.longest_silence_ms as f32 / 1000.0
+            ));
+        }
+
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 24000;
+
+    fn tone(ms: u64, amplitude: f32) -> Vec<f32> {
+        let n = (RATE as u64 * ms / 1000) as usize;
+        (0..n)
+            .map(|i| amplitude * (i as f32 * 440.0 * std::f32::consts::TAU / RATE as f32).sin())
+            .collect()
+    }
+
+    fn silence(ms: u64) -> Vec<f32> {
+        vec![0.0; (RATE as u64 * ms / 1000) as usize]
+    }
+
+    #[test]
+    fn test_good_audio_passes() {
+        let thresholds = QualityThresholds::default();
+        // 30 non-space chars at 15 cps = 2s expected
+        let text = "abcde abcde abcde abcde abcde abcde";
+        let samples = tone(2000, 0.5);
+
+        let metrics = analyze(&samples, RATE, text, &thresholds);
+        assert_eq!(metrics.duration_ms, 2000);
+        assert_eq!(metrics.expected_duration_ms, 2000);
+        assert!(metrics.issues(&thresholds).is_empty());
+    }
+
+    #[test]
+    fn test_detects_truncation_and_babble() {
+        let thresholds = QualityThresholds::default();
+        let text = "a".repeat(150); // 10s expected
+
+        let short = analyze(&tone(1000, 0.5), RATE, &text, &thresholds);
+        assert!(short.issues(&thresholds)[0].starts_with("too short"));
+
+        let long = analyze(&tone(30000, 0.5), RATE, &text, &thresholds);
+        assert!(long.issues(&thresholds)[0].starts_with("too long"));
+    }
+
+    #[test]
+    fn test_detects_silence_clipping_and_nan() {
+        let thresholds = QualityThresholds::default();
+        let text = "a".repeat(90); // 6s expected
+
+        let mut samples = tone(2000, 0.5);
+        samples.extend(silence(3000));
+        samples.extend(tone(1000, 1.0));
+        samples[10] = f32::NAN;
+
+        let metrics = analyze(&samples, RATE, &text, &thresholds);
+        assert_eq!(metrics.non_finite_samples, 1);
+        assert!(metrics.longest_silence_ms >= 2990);
+        assert!(metrics.clipped_ratio > 0.0);
+
+        let issues = metrics.issues(&thresholds);
+        assert!(issues.iter().any(|i| i.contains("NaN")));
+        assert!(issues.iter().any(|i| i.starts_with("silence")));
+        assert!(issues.iter().any(|i| i.starts_with("clipping")));
+    }
+
+    #[test]
+    fn test_empty_and_disabled() {
+        let mut thresholds = QualityThresholds::default();
+        let metrics = analyze(&[], RATE, "Hello", &thresholds);
+        assert_eq!(metrics.issues(&thresholds), vec!["empty audio".to_string()]);
+
+        thresholds.enabled = false;
+        assert!(metrics.issues(&thresholds).is_empty());
+    }
+}
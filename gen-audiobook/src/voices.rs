@@ -0,0 +1,356 @@
+//! Named voice library.
+//!
+//! `gen-audio voices add narrator clip.mp3` prepares a clip once (see
+//! [`crate::audio::voice`]) and keeps it under the data directory with its
+//! metadata, so `--voice narrator` and `config set-voice narrator` can refer
+//! to it by name. Workers get library voices under their full SHA-256, so
+//! replacing a voice never reuses a stale copy.
+
+use crate::audio::voice::{self, Prepared, Segment};
+use crate::bootstrap::versions::get_data_dir;
+use crate::coordinator::compute_file_sha256;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Longest voice name accepted.
+const MAX_NAME_LEN: usize = 64;
+
+/// A voice in the library.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Voice {
+    pub name: String,
+    /// File the clip was prepared from.
+    pub source: PathBuf,
+    /// Part of the source that was used.
+    #[serde(default, skip_serializing_if = "Segment::is_whole")]
+    pub segment: Segment,
+    /// Length of the prepared clip.
+    pub duration_ms: u64,
+    pub sample_rate: u32,
+    /// SHA-256 of the prepared clip.
+    pub sha256: String,
+    pub added_at: DateTime<Utc>,
+    /// Prepared clip in the library.
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+/// Directory holding the library, `<name>.wav` with `<name>.json` beside it.
+pub fn library_dir() -> Result<PathBuf> {
+    let dir = get_data_dir()?.join("voices");
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    Ok(dir)
+}
+
+/// Check a voice name is usable as a file name and can't be taken for a path.
+pub fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && !name.starts_with(['-', '.'])
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        anyhow::bail!(
+            "Invalid voice name '{}': use up to {} letters, digits, '-', '_' or '.'",
+            name,
+            MAX_NAME_LEN
+        );
+    }
+    Ok(())
+}
+
+/// Prepare `source` and add it to the library as `name`.
+pub fn add(name: &str, source: &Path, segment: Segment, force: bool) -> Result<(Voice, Prepared)> {
+    add_in(&library_dir()?, name, source, segment, force)
+}
+
+fn add_in(
+    dir: &Path,
+    name: &str,
+    source: &Path,
+    segment: Segment,
+    force: bool,
+) -> Result<(Voice, Prepared)> {
+    validate_name(name)?;
+    if !source.exists() {
+        anyhow::bail!("Voice clip not found: {}", source.display());
+    }
+    if !force && get_in(dir, name)?.is_some() {
+        anyhow::bail!("Voice '{}' already exists; use --force to replace it", name);
+    }
+
+    // Prepare next to the library so a failure leaves the old voice alone
+    let partial = dir.join(format!(".{}.partial.wav", name));
+    let prepared = match voice::prepare(source, &partial, segment) {
+        Ok(prepared) => prepared,
+        Err(e) => {
+            let _ = fs::remove_file(&partial);
+            return Err(e);
+        }
+    };
+
+    // Record the part that was kept when a long clip was trimmed
+    let segment = match prepared.trimmed_from_ms {
+        Some(from_ms) => Segment {
+            start: Some(segment.start.unwrap_or(0.0) + from_ms as f32 / 1000.0),
+            length: Some(prepared.duration_ms as f32 / 1000.0),
+        },
+        None => segment,
+    };
+
+    let voice = Voice {
+        name: name.to_string(),
+        source: source.canonicalize().unwrap_or_else(|_| source.to_path_buf()),
+        segment,
+        duration_ms: prepared.duration_ms,
+        sample_rate: voice::SAMPLE_RATE,
+        sha256: compute_file_sha256(&partial)?,
+        added_at: Utc::now(),
+        path: dir.join(format!("{}.wav", name)),
+    };
+    fs::rename(&partial, &voice.path)
+        .with_context(|| format!("Failed to write {}", voice.path.display()))?;
+    save_in(dir, &voice)?;
+    Ok((voice, prepared))
+}
+
+fn save_in(dir: &Path, voice: &Voice) -> Result<()> {
+    let path = dir.join(format!("{}.json", voice.name));
+    fs::write(&path, serde_json::to_string_pretty(voice)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// All voices in the library, by name.
+pub fn list() -> Result<Vec<Voice>> {
+    list_in(&library_dir()?)
+}
+
+fn list_in(dir: &Path) -> Result<Vec<Voice>> {
+    let mut voices = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .filter(|_| path.extension().is_some_and(|e| e == "json"));
+        if let Some(voice) = name.map(|name| get_in(dir, &name)).transpose()?.flatten() {
+            voices.push(voice);
+        }
+    }
+    voices.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(voices)
+}
+
+/// Look up a voice by name in the library at `dir`.
+fn get_in(dir: &Path, name: &str) -> Result<Option<Voice>> {
+    validate_name(name)?;
+    let metadata = dir.join(format!("{}.json", name));
+    let path = dir.join(format!("{}.wav", name));
+    if !metadata.exists() || !path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(&metadata)
+        .with_context(|| format!("Failed to read {}", metadata.display()))?;
+    let voice: Voice = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", metadata.display()))?;
+    Ok(Some(Voice { path, ..voice }))
+}
+
+/// Remove a voice from the library.
+pub fn remove(name: &str) -> Result<Voice> {
+    remove_in(&library_dir()?, name)
+}
+
+fn remove_in(dir: &Path, name: &str) -> Result<Voice> {
+    let voice = get_in(dir, name)?.ok_or_else(|| not_found(name))?;
+    fs::remove_file(&voice.path)
+        .with_context(|| format!("Failed to remove {}", voice.path.display()))?;
+    fs::remove_file(dir.join(format!("{}.json", name)))?;
+    Ok(voice)
+}
+
+/// Copy a voice's prepared clip to `dest`, a file or directory.
+pub fn export(name: &str, dest: &Path) -> Result<PathBuf> {
+    export_in(&library_dir()?, name, dest)
+}
+
+fn export_in(dir: &Path, name: &str, dest: &Path) -> Result<PathBuf> {
+    let voice = get_in(dir, name)?.ok_or_else(|| not_found(name))?;
+    let dest = if dest.is_dir() {
+        dest.join(format!("{}.wav", name))
+    } else {
+        dest.to_path_buf()
+    };
+    fs::copy(&voice.path, &dest)
+        .with_context(|| format!("Failed to write {}", dest.display()))?;
+    Ok(dest)
+}
+
+/// Resolve a `--voice` or `voice_ref` value: an existing file is used as
+/// is, anything else is looked up in the library by name.
+pub fn resolve(voice: &Path) -> Result<PathBuf> {
+    if voice.exists() {
+        return Ok(voice.to_path_buf());
+    }
+    resolve_in(&library_dir()?, voice)
+}
+
+fn resolve_in(dir: &Path, voice: &Path) -> Result<PathBuf> {
+    if voice.exists() {
+        return Ok(voice.to_path_buf());
+    }
+    match voice.to_str().filter(|name| validate_name(name).is_ok()) {
+        Some(name) => get_in(dir, name)?
+            .map(|v| v.path)
+            .ok_or_else(|| not_found(name)),
+        None => anyhow::bail!("Voice reference not found: {}", voice.display()),
+    }
+}
+
+/// Whether `path` is a clip in the library, as returned by [`resolve`].
+pub fn is_library_voice(path: &Path) -> bool {
+    library_dir().is_ok_and(|dir| path.parent() == Some(dir.as_path()))
+}
+
+/// A prepared clip for a resolved voice reference.
+///
+/// Library voices were prepared when they were added and are used as they
+/// are. Any other file is prepared into `dir`, reporting what was trimmed
+/// and any problems with the recording.
+pub fn prepare_reference(path: &Path, dir: &Path) -> Result<PathBuf> {
+    if is_library_voice(path) {
+        return Ok(path.to_path_buf());
+    }
+
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "voice".to_string());
+    let output = dir.join(format!("{}.voice.wav", stem));
+    let prepared = voice::prepare(path, &output, Segment::default())
+        .with_context(|| format!("Failed to prepare voice reference {}", path.display()))?;
+
+    report(path, &prepared);
+    Ok(output)
+}
+
+/// Print what preparing `source` changed and any problems with it.
+pub fn report(source: &Path, prepared: &Prepared) {
+    if let Some(from_ms) = prepared.trimmed_from_ms {
+        eprintln!(
+            "Voice reference {}: using the clearest {:.1}s, from {:.1}s in",
+            source.display(),
+            prepared.duration_ms as f32 / 1000.0,
+            from_ms as f32 / 1000.0
+        );
+    }
+    for warning in &prepared.warnings {
+        eprintln!("Warning: voice reference {}: {}", source.display(), warning);
+    }
+}
+
+fn not_found(name: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "No voice file or library voice named '{}' (see 'gen-audio voices list')",
+        name
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::wav::test_wav;
+    use tempfile::TempDir;
+
+    /// Put a voice in the library the way `add` leaves it.
+    fn add_test_voice(dir: &Path, name: &str) -> Voice {
+        let path = dir.join(format!("{}.wav", name));
+        fs::write(&path, test_wav(&[0; 24000 * 6])).unwrap();
+        let voice = Voice {
+            name: name.to_string(),
+            source: PathBuf::from("/clips/source.mp3"),
+            segment: Segment::default(),
+            duration_ms: 6000,
+            sample_rate: voice::SAMPLE_RATE,
+            sha256: compute_file_sha256(&path).unwrap(),
+            added_at: Utc::now(),
+            path,
+        };
+        save_in(dir, &voice).unwrap();
+        voice
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("narrator").is_ok());
+        assert!(validate_name("dr_who-2.0").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("-narrator").is_err());
+        assert!(validate_name(".hidden").is_err());
+        assert!(validate_name("voices/narrator").is_err());
+        assert!(validate_name("my voice").is_err());
+        assert!(validate_name(&"a".repeat(MAX_NAME_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn test_list_get_remove() {
+        let dir = TempDir::new().unwrap();
+        let narrator = add_test_voice(dir.path(), "narrator");
+        add_test_voice(dir.path(), "villain");
+        // A clip without metadata isn't a library voice
+        fs::write(dir.path().join("stray.wav"), test_wav(&[0; 10])).unwrap();
+
+        let names: Vec<String> = list_in(dir.path()).unwrap().into_iter().map(|v| v.name).collect();
+        assert_eq!(names, vec!["narrator", "villain"]);
+        assert_eq!(get_in(dir.path(), "narrator").unwrap(), Some(narrator));
+        assert_eq!(get_in(dir.path(), "stray").unwrap(), None);
+
+        remove_in(dir.path(), "villain").unwrap();
+        assert!(!dir.path().join("villain.wav").exists());
+        assert!(!dir.path().join("villain.json").exists());
+        assert!(remove_in(dir.path(), "villain").is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let dir = TempDir::new().unwrap();
+        let narrator = add_test_voice(dir.path(), "narrator");
+
+        assert_eq!(resolve_in(dir.path(), Path::new("narrator")).unwrap(), narrator.path);
+        // An existing file wins over the library
+        assert_eq!(resolve_in(dir.path(), &narrator.path).unwrap(), narrator.path);
+        assert!(resolve_in(dir.path(), Path::new("villain")).is_err());
+        assert!(resolve_in(dir.path(), Path::new("/missing/clip.wav")).is_err());
+    }
+
+    #[test]
+    fn test_export() {
+        let dir = TempDir::new().unwrap();
+        let out = TempDir::new().unwrap();
+        let narrator = add_test_voice(dir.path(), "narrator");
+
+        let exported = export_in(dir.path(), "narrator", out.path()).unwrap();
+        assert_eq!(exported, out.path().join("narrator.wav"));
+        assert_eq!(fs::read(&exported).unwrap(), fs::read(&narrator.path).unwrap());
+
+        let named = out.path().join("copy.wav");
+        assert_eq!(export_in(dir.path(), "narrator", &named).unwrap(), named);
+        assert!(export_in(dir.path(), "villain", out.path()).is_err());
+    }
+
+    #[test]
+    fn test_add_rejects_existing_and_missing() {
+        let dir = TempDir::new().unwrap();
+        let narrator = add_test_voice(dir.path(), "narrator");
+
+        let err = add_in(dir.path(), "narrator", &narrator.path, Segment::default(), false).unwrap_err();
+        assert!(err.to_string().contains("--force"));
+        assert!(add_in(dir.path(), "other", Path::new("/missing.wav"), Segment::default(), false).is_err());
+        assert!(add_in(dir.path(), "bad name", &narrator.path, Segment::default(), false).is_err());
+    }
+}
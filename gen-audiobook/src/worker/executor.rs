@@ -1,12 +1,17 @@
 //! Job execution logic for worker mode.
 
-use super::protocol::{TtsJob, TtsResult, WorkerStatus};
+use super::protocol::{
+    INLINE_AUDIO_VERSION, JobStatus, PROTOCOL_VERSION, TtsJob, TtsResult, WorkerStatus,
+};
 use crate::setup;
-use crate::tts::{self, TtsOptions};
+use crate::tts::{self, TtsBackend, TtsOptions};
 use anyhow::{Context, Result};
+use serde::Serialize;
 use std::io::{self, Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
+use tokio::io::{AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
 
 /// Base directory for worker data.
 fn worker_data_dir() -> PathBuf {
@@ -60,6 +65,90 @@ pub async fn execute_job_from_stdin() -> Result<()> {
     Ok(())
 }
 
+/// Serve jobs from stdin until it closes, keeping the model loaded.
+///
+/// The first stdout line is the worker status. After that, each stdin line is
+/// a `TtsJob` and each stdout line a `TtsResult`. Jobs run concurrently and
+/// results are written as they finish, so they may arrive out of order.
+/// Chatterbox generates one chunk at a time per process to keep each chunk
+/// reproducible from its seed, so the coordinator sends one job at a time.
+pub async fn serve() -> Result<()> {
+    let mut stdout = tokio::io::stdout();
+
+    // Handshake, so the coordinator knows the session is up
+    let status = get_worker_status();
+    write_line(&mut stdout, &status).await?;
+
+    // Load the model before reading jobs so the first chunk doesn't pay for it
+    if status.ready {
+        let preloaded = tokio::task::spawn_blocking(|| {
+            tts::chatterbox::ChatterboxBackend::new(None, None)?.preload()
+        })
+        .await
+        .context("Task join error")?;
+        if let Err(e) = preloaded {
+            eprintln!("Warning: Failed to preload model: {}", e);
+        }
+    }
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<TtsResult>();
+
+    let writer = tokio::spawn(async move {
+        while let Some(result) = rx.recv().await {
+            if let Err(e) = write_line(&mut stdout, &result).await {
+                eprintln!("Failed to write result: {}", e);
+                break;
+            }
+        }
+    });
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    while let Some(line) = lines.next_line().await.context("Failed to read job")? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let tx = tx.clone();
+        match serde_json::from_str::<TtsJob>(&line) {
+            Ok(job) => {
+                tokio::spawn(async move {
+                    let _ = tx.send(execute_job(&job).await);
+                });
+            }
+            Err(e) => {
+                // Answer if the job ID is readable, so the coordinator doesn't wait
+                let job_id = serde_json::from_str::<serde_json::Value>(&line)
+                    .ok()
+                    .and_then(|v| v["job_id"].as_str().map(str::to_string));
+                match job_id {
+                    Some(job_id) => {
+                        let _ = tx.send(TtsResult::failure(job_id, format!("Invalid job: {}", e)));
+                    }
+                    None => eprintln!("Ignoring invalid job: {}", e),
+                }
+            }
+        }
+    }
+
+    // Stdin closed: finish in-flight jobs, then exit
+    drop(tx);
+    writer.await.context("Result writer failed")?;
+    Ok(())
+}
+
+/// Write a value as a single JSON line and flush.
+async fn write_line<W, T>(out: &mut W, value: &T) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let mut line = serde_json::to_string(value).context("Failed to serialize")?;
+    line.push('\n');
+    out.write_all(line.as_bytes()).await?;
+    out.flush().await?;
+    Ok(())
+}
+
 /// Execute a TTS job and return the result.
 pub async fn execute_job(job: &TtsJob) -> TtsResult {
     let start = Instant::now();
@@ -83,13 +172,7 @@ pub async fn execute_job(job: &TtsJob) -> TtsResult {
         );
     }
 
-    // Create TTS options
-    let options = TtsOptions {
-        voice_ref,
-        exaggeration: job.options.exaggeration,
-        cfg: job.options.cfg,
-        temperature: job.options.temperature,
-    };
+    let options = job_tts_options(job, voice_ref);
 
     // Ensure output directory exists
     let output_path = get_output_path(&job.job_id);
@@ -113,14 +196,62 @@ pub async fn execute_job(job: &TtsJob) -> TtsResult {
         }
     };
 
-    // Synthesize with retry
+    let mut result = synthesize_job(backend.as_ref(), job, &options, &output_path, start).await;
+    if result.status != JobStatus::Completed {
+        return result;
+    }
+    result.version = job.version.min(PROTOCOL_VERSION);
+
+    if result.version < INLINE_AUDIO_VERSION {
+        // Older coordinators fetch the file themselves
+        return result;
+    }
+
+    match std::fs::read(&output_path) {
+        Ok(audio) => {
+            let _ = std::fs::remove_file(&output_path);
+            result.with_audio(&audio)
+        }
+        Err(e) => TtsResult::failure(
+            &job.job_id,
+            format!("Failed to read generated audio: {}", e),
+        ),
+    }
+}
+
+/// Synthesis options for a job, with its voice reference already resolved.
+pub fn job_tts_options(job: &TtsJob, voice_ref: Option<PathBuf>) -> TtsOptions {
+    TtsOptions {
+        voice_ref,
+        exaggeration: job.options.exaggeration,
+        cfg: job.options.cfg,
+        temperature: job.options.temperature,
+        quality: job.options.quality.clone(),
+        seed: job
+            .options
+            .seed
+            .map(|seed| tts::derive_chunk_seed(seed, job.chapter_id, job.chunk_id)),
+    }
+}
+
+/// Synthesize a job's text to `output_path` and describe the outcome.
+///
+/// A successful result points at `output_path`; `start` is when the job
+/// started, for its reported duration.
+pub async fn synthesize_job(
+    backend: &dyn TtsBackend,
+    job: &TtsJob,
+    options: &TtsOptions,
+    output_path: &Path,
+    start: Instant,
+) -> TtsResult {
     match backend
-        .synthesize_with_retry(&job.text, &output_path, &options, 3)
+        .synthesize_with_retry(&job.text, output_path, options, 3)
         .await
     {
-        Ok(()) => {
+        Ok(report) => {
             let duration_ms = start.elapsed().as_millis() as u64;
-            let audio_size = std::fs::metadata(&output_path)
+            let audio_size = std::fs::metadata(output_path)
                 .map(|m| m.len())
                 .unwrap_or(0);
 
@@ -130,6 +261,8 @@ pub async fn execute_job(job: &TtsJob) -> TtsResult {
                 audio_size,
                 output_path.to_string_lossy(),
             )
+            .with_quality(report.quality)
+            .with_seed(report.seed)
         }
         Err(e) => TtsResult::failure(&job.job_id, e.to_string()),
     }
@@ -142,7 +275,7 @@ pub fn get_worker_status() -> WorkerStatus {
     let chatterbox_installed = setup::is_chatterbox_installed().unwrap_or(false);
 
     if !venv_ready || !chatterbox_installed {
-        return WorkerStatus::not_ready("Setup incomplete");
+        return WorkerStatus::not_ready("Setup incomplete").with_data_dirs(&voices_dir(), &output_dir());
     }
 
     // Detect device
@@ -152,6 +285,8 @@ pub fn get_worker_status() -> WorkerStatus {
     let available_disk_mb = get_available_disk_mb().unwrap_or(0);
 
     WorkerStatus::ready(device, available_disk_mb)
+        .with_data_dirs(&voices_dir(), &output_dir())
+        .with_packages(crate::bootstrap::installed_packages())
 }
 
 /// Detect the best available device.
@@ -181,16 +316,14 @@ fn detect_device() -> Result<String> {
 /// Get available disk space in MB.
 fn get_available_disk_mb() -> Result<u64> {
     let output_dir = output_dir();
-    let _dir = if output_dir.exists() {
+    let dir = if output_dir.exists() {
         output_dir
     } else {
         // Use home directory as fallback
         dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"))
     };
 
-    // TODO: Use statvfs for accurate disk space on Unix
-    // For now, return a reasonable default
-    Ok(10000) // 10GB default
+    crate::doctor::available_space_mb(&dir)
 }
 
 /// Clean up old output files.
@@ -12,6 +12,9 @@
 //! # Execute a job (coordinator sends job via SSH)
 //! ssh worker "gen-audio worker run" < job.json
 //!
+//! # Serve jobs as JSON lines with the model kept loaded
+//! ssh worker "gen-audio worker serve"
+//!
 //! # Self-install on a new machine
 //! gen-audio worker install
 //! ```
@@ -21,7 +24,7 @@ pub mod protocol;
 
 pub use executor::{
     execute_job_from_stdin,
-    get_worker_status, output_dir, voices_dir,
+    get_worker_status, output_dir, serve, voices_dir,
 };
 
 use anyhow::{Context, Result};
@@ -36,11 +39,18 @@ pub enum WorkerCommand {
     /// Execute a single job from stdin, output result to stdout.
     Run,
 
+    /// Serve jobs from stdin as JSON lines until it closes, keeping the model loaded.
+    Serve,
+
     /// Self-install gen-audio on this machine (download dependencies).
     Install {
         /// Force reinstall even if already installed.
         #[arg(long)]
         force: bool,
+
+        /// Don't download the model weights; the first job will.
+        #[arg(long)]
+        no_models: bool,
     },
 
     /// Clean up worker data (output files, voice cache).
@@ -66,8 +76,12 @@ pub async fn handle_worker_command(cmd: &WorkerCommand) -> Result<()> {
             execute_job_from_stdin().await
         }
 
-        WorkerCommand::Install { force } => {
-            handle_install(*force).await
+        WorkerCommand::Serve => {
+            serve().await
+        }
+
+        WorkerCommand::Install { force, no_models } => {
+            handle_install(*force, !*no_models).await
         }
 
         WorkerCommand::Clean { include_voices } => {
@@ -77,36 +91,53 @@ pub async fn handle_worker_command(cmd: &WorkerCommand) -> Result<()> {
 }
 
 /// Handle worker install command.
-async fn handle_install(force: bool) -> Result<()> {
-    use crate::bootstrap;
+async fn handle_install(force: bool, models: bool) -> Result<()> {
+    let result = install(force, models).await;
+    crate::bootstrap::progress::finish(&result);
+    result
+}
+
+async fn install(force: bool, models: bool) -> Result<()> {
+    use crate::bootstrap::{self, progress};
 
-    println!("Installing gen-audio worker dependencies...");
+    progress::message("Installing gen-audio worker dependencies...");
 
     // Check current status
     let status = bootstrap::check_status()?;
 
-    if status == bootstrap::BootstrapStatus::Ready && !force {
-        println!("Worker is already set up. Use --force to reinstall.");
+    if status.is_ready() && !force {
+        progress::message("Worker is already set up. Use --force to reinstall.");
+        // Workers set up before weights were pre-fetched still need them
+        if models {
+            bootstrap::models::ensure_fetched().await?;
+        }
         return Ok(());
     }
 
     // Run bootstrap
     bootstrap::ensure_bootstrapped().await?;
 
+    // Download the weights now, so the first job doesn't time out doing it
+    if models {
+        bootstrap::models::ensure_fetched().await?;
+    }
+
     // Create worker directories
     std::fs::create_dir_all(voices_dir())
         .context("Failed to create voices directory")?;
     std::fs::create_dir_all(output_dir())
         .context("Failed to create output directory")?;
 
-    println!("Worker installation complete!");
-    println!();
+    progress::message("Worker installation complete!");
 
     // Show status
     let status = get_worker_status();
-    println!("Device: {}", status.device);
-    println!("Chatterbox: {}", if status.chatterbox_installed { "installed" } else { "not installed" });
-    println!("Ready: {}", if status.ready { "yes" } else { "no" });
+    progress::message(format!("Device: {}", status.device));
+    progress::message(format!(
+        "Chatterbox: {}",
+        if status.chatterbox_installed { "installed" } else { "not installed" }
+    ));
+    progress::message(format!("Ready: {}", if status.ready { "yes" } else { "no" }));
 
     Ok(())
 }
@@ -1,12 +1,34 @@
 //! Protocol types for worker communication.
 //!
 //! Jobs are sent as JSON over stdin, results returned via stdout.
+//!
+//! Version history:
+//! - 1: audio is left on the worker and fetched over SFTP.
+//! - 2: audio is returned inline in the result, base64 encoded with a SHA-256.
+//!
+//! The coordinator sends each job with the highest version both sides
+//! support, and the worker answers in that version.
 
+use crate::tts::{QualityMetrics, QualityThresholds};
+use anyhow::Result;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::Path;
 
 /// Current protocol version.
-pub const PROTOCOL_VERSION: u32 = 1;
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// First protocol version that returns audio inline.
+pub const INLINE_AUDIO_VERSION: u32 = 2;
+
+/// Protocol version assumed for workers that don't report one.
+fn legacy_protocol_version() -> u32 {
+    1
+}
 
 /// A TTS job to be executed by a worker.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -68,6 +90,13 @@ pub struct TtsJobOptions {
     /// SHA256 hash of voice reference file (if using voice cloning).
     /// Worker uses this to locate the pre-uploaded voice file.
     pub voice_ref_hash: Option<String>,
+    /// Thresholds for the worker's quality checks.
+    #[serde(default)]
+    pub quality: QualityThresholds,
+    /// Book seed. The worker derives each chunk's seed from this and the
+    /// job's chapter and chunk IDs, so results match local generation.
+    #[serde(default)]
+    pub seed: Option<u64>,
 }
 
 impl Default for TtsJobOptions {
@@ -77,6 +106,8 @@ impl Default for TtsJobOptions {
             cfg: 0.5,
             temperature: 0.8,
             voice_ref_hash: None,
+            quality: QualityThresholds::default(),
+            seed: None,
         }
     }
 }
@@ -94,10 +125,22 @@ pub struct TtsResult {
     pub duration_ms: Option<u64>,
     /// Size of generated audio file (bytes).
     pub audio_size_bytes: Option<u64>,
-    /// Path to generated audio on worker filesystem.
+    /// Path to generated audio on worker filesystem (if not sent inline).
     pub audio_path: Option<String>,
+    /// Base64-encoded audio (protocol version 2).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_data: Option<String>,
+    /// Hex SHA-256 of the decoded audio.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_sha256: Option<String>,
     /// Error message if job failed.
     pub error: Option<String>,
+    /// Quality measurements of the generated audio.
+    #[serde(default)]
+    pub quality: Option<QualityMetrics>,
+    /// Seed that produced the generated audio.
+    #[serde(default)]
+    pub seed: Option<u64>,
     /// When this job completed.
     pub completed_at: DateTime<Utc>,
 }
@@ -117,11 +160,60 @@ impl TtsResult {
             duration_ms: Some(duration_ms),
             audio_size_bytes: Some(audio_size_bytes),
             audio_path: Some(audio_path.into()),
+            audio_data: None,
+            audio_sha256: None,
             error: None,
+            quality: None,
+            seed: None,
             completed_at: Utc::now(),
         }
     }
 
+    /// Attach quality measurements to this result.
+    pub fn with_quality(mut self, quality: QualityMetrics) -> Self {
+        self.quality = Some(quality);
+        self
+    }
+
+    /// Record the seed that produced this result.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Carry the audio inline instead of leaving it on the worker.
+    pub fn with_audio(mut self, audio: &[u8]) -> Self {
+        self.audio_size_bytes = Some(audio.len() as u64);
+        self.audio_data = Some(BASE64.encode(audio));
+        self.audio_sha256 = Some(sha256_hex(audio));
+        self.audio_path = None;
+        self
+    }
+
+    /// Decode inline audio and verify its checksum.
+    ///
+    /// Returns `None` if the audio was not sent inline.
+    pub fn decode_audio(&self) -> Result<Option<Vec<u8>>> {
+        let Some(ref data) = self.audio_data else {
+            return Ok(None);
+        };
+
+        let audio = BASE64
+            .decode(data)
+            .map_err(|e| anyhow::anyhow!("Invalid audio encoding: {}", e))?;
+
+        let expected = self
+            .audio_sha256
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("Inline audio has no checksum"))?;
+        let actual = sha256_hex(&audio);
+        if actual != expected {
+            anyhow::bail!("Audio checksum mismatch (expected {}, got {})", expected, actual);
+        }
+
+        Ok(Some(audio))
+    }
+
     /// Create a failed result.
     pub fn failure(job_id: impl Into<String>, error: impl Into<String>) -> Self {
         Self {
@@ -131,13 +223,16 @@ impl TtsResult {
             duration_ms: None,
             audio_size_bytes: None,
             audio_path: None,
+            audio_data: None,
+            audio_sha256: None,
             error: Some(error.into()),
+            quality: None,
+            seed: None,
             completed_at: Utc::now(),
         }
     }
 
     /// Create a timeout result.
-    #[allow(dead_code)]
     pub fn timeout(job_id: impl Into<String>) -> Self {
         Self {
             version: PROTOCOL_VERSION,
@@ -146,7 +241,11 @@ impl TtsResult {
             duration_ms: None,
             audio_size_bytes: None,
             audio_path: None,
+            audio_data: None,
+            audio_sha256: None,
             error: Some("Job timed out".to_string()),
+            quality: None,
+            seed: None,
             completed_at: Utc::now(),
         }
     }
@@ -179,6 +278,31 @@ pub struct WorkerStatus {
     pub jobs_in_progress: usize,
     /// Available disk space in MB.
     pub available_disk_mb: u64,
+    /// Highest protocol version the worker supports.
+    #[serde(default = "legacy_protocol_version")]
+    pub protocol_version: u32,
+    /// Directory the worker reads voice references from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voices_dir: Option<String>,
+    /// Directory the worker writes job output to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_dir: Option<String>,
+    /// Installed versions of the Python packages that affect the audio
+    /// (torch, chatterbox-tts, ...), by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub packages: BTreeMap<String, String>,
+}
+
+impl WorkerStatus {
+    /// Protocol version to use with this worker.
+    pub fn negotiated_version(&self) -> u32 {
+        self.protocol_version.min(PROTOCOL_VERSION)
+    }
+}
+
+/// Hex-encoded SHA-256 of some bytes.
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
 }
 
 impl WorkerStatus {
@@ -191,6 +315,10 @@ impl WorkerStatus {
             chatterbox_installed: true,
             jobs_in_progress: 0,
             available_disk_mb,
+            protocol_version: PROTOCOL_VERSION,
+            voices_dir: None,
+            output_dir: None,
+            packages: BTreeMap::new(),
         }
     }
 
@@ -203,8 +331,25 @@ impl WorkerStatus {
             chatterbox_installed: false,
             jobs_in_progress: 0,
             available_disk_mb: 0,
+            protocol_version: PROTOCOL_VERSION,
+            voices_dir: None,
+            output_dir: None,
+            packages: BTreeMap::new(),
         }
     }
+
+    /// Report the worker's installed Python packages.
+    pub fn with_packages(mut self, packages: BTreeMap<String, String>) -> Self {
+        self.packages = packages;
+        self
+    }
+
+    /// Report the worker's data directories.
+    pub fn with_data_dirs(mut self, voices_dir: &Path, output_dir: &Path) -> Self {
+        self.voices_dir = Some(voices_dir.to_string_lossy().to_string());
+        self.output_dir = Some(output_dir.to_string_lossy().to_string());
+        self
+    }
 }
 
 #[cfg(test)]
@@ -241,4 +386,37 @@ mod tests {
         assert!(status.ready);
         assert_eq!(status.device, "cuda");
     }
+
+    #[test]
+    fn test_inline_audio_round_trip() {
+        let audio = b"RIFF fake wav data";
+        let result = TtsResult::success("job", 1, 0, "/tmp/job.wav").with_audio(audio);
+        assert!(result.audio_path.is_none());
+        assert_eq!(result.audio_size_bytes, Some(audio.len() as u64));
+
+        let json = serde_json::to_string(&result).unwrap();
+        let parsed: TtsResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.decode_audio().unwrap().unwrap(), audio);
+
+        let mut corrupted = parsed.clone();
+        corrupted.audio_sha256 = Some(sha256_hex(b"something else"));
+        assert!(corrupted.decode_audio().is_err());
+
+        let no_inline = TtsResult::success("job", 1, 0, "/tmp/job.wav");
+        assert!(no_inline.decode_audio().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_version_negotiation() {
+        // A worker from before version negotiation doesn't report a version
+        let json = r#"{"ready":true,"device":"cuda","gen_audio_version":"0.2.0",
+            "chatterbox_installed":true,"jobs_in_progress":0,"available_disk_mb":1}"#;
+        let old: WorkerStatus = serde_json::from_str(json).unwrap();
+        assert_eq!(old.protocol_version, 1);
+        assert_eq!(old.negotiated_version(), 1);
+
+        let mut newer = WorkerStatus::ready("cuda", 1);
+        newer.protocol_version = PROTOCOL_VERSION + 1;
+        assert_eq!(newer.negotiated_version(), PROTOCOL_VERSION);
+    }
 }